@@ -0,0 +1,299 @@
+//! Grafana and Alertmanager alert notifications.
+//!
+//! Add a webhook contact point in Grafana (or a `webhook_configs` receiver in Alertmanager)
+//! pointing at `POST /webhook/alerts` with `Authorization: Bearer <ALERT_WEBHOOK_TOKEN>`. Both
+//! send the same payload: a group of alerts, each firing or resolved, with labels and
+//! annotations. Every alert becomes an embed colored by its `severity` label, and firing alerts
+//! mention the roles whose label selector they match. Repeats of the same alert are dropped
+//! by [`crate::notify::dedup`] unless its severity went up.
+//!
+//! Environment Variables:
+//! - `ALERT_WEBHOOK_TOKEN`: Bearer token required on every request
+//! - `ALERT_CHANNEL_ID`: Channel for alerts (default: `DISCORD_STATUS_CHANNEL_ID`)
+//! - `ALERT_ROLE_MENTIONS`: Comma-separated `label=value:role_id` rules, e.g.
+//!   `severity=critical:123,team=backend:456`
+
+use axum::{
+    extract::{Json, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use serde::Deserialize;
+use serenity::{builder::CreateEmbed, utils::Colour};
+use std::{collections::BTreeMap, env};
+
+use crate::github::WebhookOutcome;
+use crate::notify::{self, dedup::{self, Severity}, Destination, Outgoing, Priority};
+use crate::ops_events::{self, EventKind};
+use crate::secrets;
+use crate::AppState;
+
+const HANDLER: &str = "alerts";
+/// Discord's limit on embeds per message.
+const EMBEDS_PER_MESSAGE: usize = 10;
+
+pub fn routes(shared_state: AppState) -> Router {
+    Router::new()
+        .route("/alerts", post(handle_alert_webhook))
+        .with_state(shared_state)
+}
+
+/// Alertmanager webhook payload (Grafana's contact point sends a superset of it).
+#[derive(Debug, Deserialize)]
+pub struct AlertGroup {
+    #[serde(default)]
+    pub alerts: Vec<Alert>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Alert {
+    /// `firing` or `resolved`.
+    pub status: String,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+    pub starts_at: Option<String>,
+    pub generator_url: Option<String>,
+    /// Grafana only.
+    pub dashboard_url: Option<String>,
+    /// Grafana only, e.g. `[ var='A' labels={} value=97 ]`.
+    pub value_string: Option<String>,
+}
+
+impl Alert {
+    fn firing(&self) -> bool {
+        self.status.eq_ignore_ascii_case("firing")
+    }
+
+    fn name(&self) -> &str {
+        self.labels.get("alertname").map(String::as_str).unwrap_or("Alert")
+    }
+
+    fn severity(&self) -> Option<&str> {
+        self.labels.get("severity").map(String::as_str)
+    }
+
+    fn summary(&self) -> Option<&str> {
+        ["summary", "description", "message"]
+            .iter()
+            .find_map(|key| self.annotations.get(*key))
+            .map(String::as_str)
+    }
+
+    /// Identifies the alert across notifications; severity is left out so an escalation
+    /// keeps the same key.
+    fn dedup_key(&self) -> String {
+        let labels = self
+            .labels
+            .iter()
+            .filter(|(key, _)| !matches!(key.as_str(), "alertname" | "severity"))
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("grafana: {} {{{}}}", self.name(), labels)
+    }
+
+    fn dedup_severity(&self) -> Severity {
+        match self.severity().map(str::to_lowercase).as_deref() {
+            Some("critical") | Some("page") | Some("error") => Severity::Critical,
+            _ => Severity::Warning,
+        }
+    }
+
+    fn title(&self) -> String {
+        let icon = if self.firing() { "🔥" } else { "✅" };
+        let state = if self.firing() { "Firing" } else { "Resolved" };
+        format!("{} {}: {}", icon, state, self.name())
+    }
+
+    /// Red, orange or blue by severity while firing; green once resolved.
+    fn colour(&self) -> Colour {
+        if !self.firing() {
+            return Colour::DARK_GREEN;
+        }
+        match self.severity().map(str::to_lowercase).as_deref() {
+            Some("critical") | Some("page") | Some("error") => Colour::RED,
+            Some("warning") | Some("warn") => Colour::ORANGE,
+            Some("info") | Some("none") => Colour::BLUE,
+            _ => Colour::GOLD,
+        }
+    }
+
+    /// Labels other than the ones already shown in the title and severity field.
+    fn extra_labels(&self) -> String {
+        self.labels
+            .iter()
+            .filter(|(key, _)| !matches!(key.as_str(), "alertname" | "severity" | "__alert_rule_uid__"))
+            .map(|(key, value)| format!("`{}={}`", key, value))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn link(&self) -> Option<&str> {
+        self.dashboard_url
+            .as_deref()
+            .or(self.generator_url.as_deref())
+            .filter(|url| !url.is_empty())
+    }
+
+    fn embed(&self) -> CreateEmbed {
+        let mut e = CreateEmbed::default();
+        e.title(self.title()).colour(self.colour());
+        if let Some(summary) = self.summary() {
+            e.description(summary);
+        }
+        if let Some(link) = self.link() {
+            e.url(link);
+        }
+        if let Some(severity) = self.severity() {
+            e.field("Severity", severity, true);
+        }
+        if let Some(value) = self.value_string.as_deref().filter(|v| !v.is_empty()) {
+            e.field("Value", value, true);
+        }
+        let labels = self.extra_labels();
+        if !labels.is_empty() {
+            e.field("Labels", labels, false);
+        }
+        if let Some(started) = &self.starts_at {
+            e.footer(|f| f.text(format!("Since {}", started)));
+        }
+        e
+    }
+
+    /// Plain-text rendering, used when embeds can't be posted.
+    fn to_text(&self) -> String {
+        let mut text = format!("**{}**", self.title());
+        if let Some(severity) = self.severity() {
+            text.push_str(&format!(" ({})", severity));
+        }
+        if let Some(summary) = self.summary() {
+            text.push_str(&format!("\n{}", summary));
+        }
+        if let Some(link) = self.link() {
+            text.push_str(&format!("\n{}", link));
+        }
+        text
+    }
+}
+
+/// Role IDs to mention for `alert`, from the `ALERT_ROLE_MENTIONS` rules it matches.
+fn roles_for(alert: &Alert) -> Vec<u64> {
+    env::var("ALERT_ROLE_MENTIONS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|rule| {
+            let (selector, role) = rule.trim().rsplit_once(':')?;
+            let (label, value) = selector.split_once('=')?;
+            let matches = alert
+                .labels
+                .get(label.trim())
+                .is_some_and(|v| v.eq_ignore_ascii_case(value.trim()));
+            if matches {
+                role.trim().parse().ok()
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn channel() -> Option<u64> {
+    ["ALERT_CHANNEL_ID", "DISCORD_STATUS_CHANNEL_ID"]
+        .iter()
+        .find_map(|key| env::var(key).ok().and_then(|v| v.trim().parse().ok()))
+}
+
+async fn handle_alert_webhook(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Json(payload): Json<AlertGroup>,
+) -> Response {
+    let token = match secrets::current("ALERT_WEBHOOK_TOKEN") {
+        Some(token) => token,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let authorized = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|t| secrets::accepts("ALERT_WEBHOOK_TOKEN", &token, t));
+    if !authorized {
+        eprintln!("Rejected alert webhook: missing or invalid token");
+        return WebhookOutcome::unauthorized("missing or invalid bearer token").into_response();
+    }
+
+    handle_alerts(&state, payload.alerts).await.into_response()
+}
+
+async fn handle_alerts(state: &AppState, alerts: Vec<Alert>) -> WebhookOutcome {
+    if alerts.is_empty() {
+        return WebhookOutcome::ignored(HANDLER, "no alerts in payload");
+    }
+    let channel_id = match channel() {
+        Some(id) => id,
+        None => return WebhookOutcome::ignored(HANDLER, "no alert or status channel configured"),
+    };
+
+    for alert in alerts.iter().filter(|a| a.firing()) {
+        ops_events::record(EventKind::Alert, format!("{}: {}", alert.name(), alert.summary().unwrap_or_default()));
+    }
+
+    let alerts: Vec<Alert> = alerts
+        .into_iter()
+        .filter(|alert| {
+            if alert.firing() {
+                dedup::fire(&alert.dedup_key(), alert.dedup_severity())
+            } else {
+                dedup::resolve(&alert.dedup_key())
+            }
+        })
+        .collect();
+    if alerts.is_empty() {
+        return WebhookOutcome::ignored(HANDLER, "duplicate alerts suppressed");
+    }
+
+    let mut roles: Vec<u64> = alerts.iter().filter(|a| a.firing()).flat_map(roles_for).collect();
+    roles.sort_unstable();
+    roles.dedup();
+    let mentions: String = roles.iter().map(|r| format!("<@&{}> ", r)).collect();
+
+    // Embeds need the gateway; otherwise the text version goes through the notify service so
+    // it is still queued or sent through the fallback webhook.
+    let ctx = match notify::discord_ctx(state) {
+        Some(ctx) => ctx,
+        None => {
+            let text = alerts.iter().map(Alert::to_text).collect::<Vec<_>>().join("\n\n");
+            let message = format!("{}{}", mentions, text);
+            let delivery = notify::send(state, HANDLER, Destination::Channel(channel_id), message).await;
+            return WebhookOutcome::from_delivery(HANDLER, delivery);
+        }
+    };
+
+    for (i, chunk) in alerts.chunks(EMBEDS_PER_MESSAGE).enumerate() {
+        let mut message = if i == 0 && !mentions.is_empty() {
+            Outgoing::text(mentions.trim_end())
+        } else {
+            Outgoing::default()
+        };
+        for alert in chunk {
+            message = message.embed(alert.embed());
+        }
+        // Firing critical alerts jump ahead of queued routine messages.
+        let priority = if chunk.iter().any(|a| a.firing() && a.dedup_severity() == Severity::Critical) {
+            Priority::Critical
+        } else {
+            notify::priority_for(HANDLER)
+        };
+        if let Err(e) = notify::post(&ctx, channel_id, priority, message).await {
+            eprintln!("Failed to post alert notification: {}", e);
+            return WebhookOutcome::failed(HANDLER, format!("Discord send failed: {}", e));
+        }
+    }
+
+    WebhookOutcome::handled(HANDLER)
+}
@@ -0,0 +1,190 @@
+//! `/alerts`: state and control of the bot's alerts (see [`crate::notify::dedup`]).
+//!
+//! - `/alerts list`: alerts currently firing or held back, with their severity, when they
+//!   started, when they were last posted, and how many repeats were suppressed since; plus the
+//!   active silences.
+//! - `/alerts silence <key> <duration>`: stops posting an alert (or every alert whose key starts
+//!   with `key`) for a while. Admin-only.
+//! - `/alerts test <key>`: posts a test alert to the channel the real alert goes to, without
+//!   pinging anyone, to check routing end to end. Admin-only.
+
+use chrono::Utc;
+use serenity::{
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    prelude::*,
+};
+use std::{env, time::Duration};
+
+use super::command_spec::{CommandSpec, OptionSpec};
+use super::options::{reply_error, OptionError, Options};
+use super::paginator::Paginator;
+use crate::duration::{format_duration, parse_duration};
+use crate::notify::{self, dedup::{self, Entry}, Priority};
+use crate::ops_events::{self, EventKind};
+
+/// Channel setting of each alert source, by key prefix. Every source falls back to
+/// `DISCORD_STATUS_CHANNEL_ID`.
+const ROUTES: &[(&str, &str)] = &[
+    ("api:", "API_METRICS_CHANNEL_ID"),
+    ("clock:", "CLOCK_CHANNEL_ID"),
+    ("grafana:", "ALERT_CHANNEL_ID"),
+    ("kernel:", "KERNEL_EVENTS_CHANNEL_ID"),
+    ("memory leak:", "MEMORY_LEAK_CHANNEL_ID"),
+    ("permissions:", "PERMISSION_ALERT_CHANNEL_ID"),
+    ("probe:", "SYNTHETIC_PROBE_CHANNEL_ID"),
+    ("proxy:", "PROXY_CHANNEL_ID"),
+];
+
+/// Registers `/alerts list|silence|test`.
+pub async fn register_alerts_command(ctx: &Context) {
+    CommandSpec::new("alerts", "Show, silence, and test the bot's alerts")
+        .option(OptionSpec::sub("list", "List firing alerts, suppressed duplicates, and silences"))
+        .option(
+            OptionSpec::sub("silence", "Stop posting an alert for a while")
+                .option(OptionSpec::string("key", "Alert key or key prefix, e.g. kernel:").required())
+                .option(OptionSpec::string("duration", "How long, e.g. 30m, 6h, 2d").required()),
+        )
+        .option(
+            OptionSpec::sub("test", "Post a test alert to check where it is delivered")
+                .option(OptionSpec::string("key", "Alert key, e.g. api: error rate").required()),
+        )
+        .register(ctx)
+        .await;
+}
+
+/// Slash command handler for `/alerts`.
+pub async fn handle_alerts(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let (name, sub) = match Options::of(command).subcommand() {
+        Some(sub) => sub,
+        None => return,
+    };
+    let result = match name {
+        "list" => return list(ctx, command).await,
+        "silence" => silence(command, sub),
+        "test" => test(ctx, command, sub).await,
+        _ => return,
+    };
+    match result {
+        Ok(content) => reply(ctx, command, content).await,
+        Err(e) => reply_error(ctx, command, &e).await,
+    }
+}
+
+async fn reply(ctx: &Context, command: &ApplicationCommandInteraction, content: String) {
+    let _ = command
+        .create_interaction_response(&ctx.http, |res| {
+            res.interaction_response_data(|msg| msg.content(content).ephemeral(true))
+        })
+        .await;
+}
+
+fn describe(key: &str, entry: &Entry) -> String {
+    let emoji = if entry.active { "🔴" } else { "⚪" };
+    let posted = if entry.last_posted == 0 {
+        "never posted".to_string()
+    } else {
+        format!("last posted <t:{}:R>", entry.last_posted)
+    };
+    let mut line = format!(
+        "{} `{}` ({}): since <t:{}:R>, {}",
+        emoji,
+        key,
+        entry.severity.label(),
+        entry.since,
+        posted
+    );
+    if entry.suppressed > 0 {
+        line.push_str(&format!(", {} suppressed", entry.suppressed));
+    }
+    line
+}
+
+/// Replies with the alerts and silences, paginated.
+async fn list(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let active = dedup::active();
+    let silences = dedup::silences();
+
+    let header = if active.is_empty() {
+        "✅ No alerts are firing."
+    } else {
+        "🚨 **Alerts:**"
+    };
+    let mut lines: Vec<String> = active.iter().map(|(key, entry)| describe(key, entry)).collect();
+    if !silences.is_empty() {
+        lines.push(String::new());
+        lines.push("**Silenced:**".to_string());
+        lines.extend(
+            silences
+                .iter()
+                .map(|(key, until)| format!("🔇 `{}` until <t:{}:f> (<t:{}:R>)", key, until, until)),
+        );
+    }
+
+    let window = dedup::window_secs();
+    let footer = if window == 0 {
+        "Duplicate suppression is off.".to_string()
+    } else {
+        let window = format_duration(Duration::from_secs(window as u64));
+        format!("Repeats are posted at most once every {}, unless they escalate.", window)
+    };
+    Paginator::new(header, lines).footer(footer).ephemeral(true).send(ctx, command).await;
+}
+
+fn silence(command: &ApplicationCommandInteraction, sub: Options) -> Result<String, OptionError> {
+    let key = sub.required_str("key")?;
+    let duration = sub
+        .trimmed("duration")
+        .and_then(parse_duration)
+        .filter(|d| d.as_secs() > 0)
+        .ok_or_else(|| OptionError::invalid("duration", "Try a duration like 30m, 6h or 2d."))?;
+
+    let until = Utc::now().timestamp() + duration.as_secs() as i64;
+    dedup::silence(key, until);
+    ops_events::record(
+        EventKind::Alert,
+        format!("Alert `{}` silenced by {} for {}", key, command.user.name, format_duration(duration)),
+    );
+
+    Ok(format!(
+        "🔇 Alerts matching `{}` are silenced until <t:{}:f> (<t:{}:R>). `/alerts list` still shows them.",
+        key, until, until
+    ))
+}
+
+/// The channel alerts with `key` are posted to.
+fn channel_for(key: &str) -> Option<u64> {
+    let setting = ROUTES
+        .iter()
+        .find(|(prefix, _)| key.starts_with(prefix))
+        .map(|(_, setting)| *setting);
+    setting
+        .into_iter()
+        .chain(["DISCORD_STATUS_CHANNEL_ID"])
+        .find_map(|setting| env::var(setting).ok().and_then(|v| v.trim().parse::<u64>().ok()))
+}
+
+async fn test(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    sub: Options<'_>,
+) -> Result<String, OptionError> {
+    let key = sub.required_str("key")?;
+    let channel = channel_for(key)
+        .ok_or_else(|| OptionError::invalid("key", "no channel is configured for this alert."))?;
+
+    // Sent straight to the queue rather than through dedup, so the test neither counts as the
+    // real alert nor is held back by it.
+    let message = format!(
+        "🧪 **Test alert** `{}`, fired by <@{}> with `/alerts test`. The real alert is posted here.",
+        key, command.user.id.0
+    );
+    if let Err(e) = notify::queue::send(ctx.http.clone(), channel, Priority::Critical, message).await {
+        return Ok(format!("❌ Could not post the test alert to <#{}>: {}", channel, e));
+    }
+
+    let mut content = format!("✅ Test alert posted to <#{}>.", channel);
+    if let Some(until) = dedup::silenced_until(key) {
+        content.push_str(&format!("\n🔇 The real alert is silenced until <t:{}:f>.", until));
+    }
+    Ok(content)
+}
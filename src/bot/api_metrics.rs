@@ -0,0 +1,328 @@
+//! FitchFork API error-rate and latency monitor.
+//!
+//! Polls the API's Prometheus metrics endpoint and compares each scrape with the previous one,
+//! giving the share of 5xx responses and the p50/p95/p99 latency over the last interval. The
+//! latest numbers are shown on the status dashboard (`/status section:api`). When the 5xx rate
+//! stays above the threshold for several polls in a row, the dev role is alerted, and again
+//! once it recovers.
+//!
+//! Environment Variables:
+//! - `API_METRICS_URL`: The API's metrics endpoint to poll (required)
+//! - `API_METRICS_INTERVAL_SECS`: How often to poll (default: 60)
+//! - `API_METRICS_REQUESTS_METRIC`: Request counter with a `status` label
+//!   (default: `http_requests_total`)
+//! - `API_METRICS_LATENCY_METRIC`: Request duration histogram in seconds
+//!   (default: `http_request_duration_seconds`)
+//! - `API_ERROR_RATE_THRESHOLD`: 5xx percentage that counts as a spike (default: 5)
+//! - `API_ERROR_RATE_SUSTAIN`: Consecutive polls above the threshold before alerting (default: 3)
+//! - `API_ERROR_RATE_MIN_REQUESTS`: Polls with fewer requests are not judged (default: 20)
+//! - `API_METRICS_CHANNEL_ID`: Where to alert (default: `DISCORD_STATUS_CHANNEL_ID`)
+
+use chrono::{Local, TimeZone};
+use once_cell::sync::Lazy;
+use serenity::prelude::*;
+use std::{collections::BTreeMap, env, sync::Mutex, time::Duration};
+use tokio::time::sleep;
+
+use crate::impact;
+use crate::notify::{self, dedup::{self, Severity}, Priority};
+use crate::ops_events::{self, EventKind};
+use crate::tasks::Tasks;
+
+const DEFAULT_INTERVAL_SECS: u64 = 60;
+const DEFAULT_THRESHOLD: f64 = 5.0;
+const DEFAULT_SUSTAIN: u32 = 3;
+const DEFAULT_MIN_REQUESTS: f64 = 20.0;
+const ALERT_KEY: &str = "api: error rate";
+
+/// The most recent interval, shown on the status dashboard.
+static LATEST: Lazy<Mutex<Option<Interval>>> = Lazy::new(|| Mutex::new(None));
+
+/// Cumulative counters from one scrape.
+#[derive(Debug, Clone, Default)]
+struct Scrape {
+    requests: f64,
+    errors: f64,
+    /// Cumulative histogram counts by upper bound, ascending.
+    buckets: Vec<(f64, f64)>,
+}
+
+/// Traffic between two scrapes.
+#[derive(Debug, Clone)]
+struct Interval {
+    /// Unix timestamp of the later scrape.
+    at: i64,
+    requests: f64,
+    errors: f64,
+    /// p50, p95 and p99 in seconds, if the histogram had data.
+    percentiles: Option<[f64; 3]>,
+}
+
+impl Interval {
+    fn error_rate(&self) -> f64 {
+        if self.requests > 0.0 {
+            self.errors / self.requests * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env::var(key).ok().and_then(|v| v.trim().parse().ok()).unwrap_or(default)
+}
+
+fn metrics_url() -> Option<String> {
+    env::var("API_METRICS_URL").ok().map(|u| u.trim().to_string()).filter(|u| !u.is_empty())
+}
+
+/// Splits `name{a="x",b="y"} 12 [timestamp]` into its name, labels and value.
+fn parse_line(line: &str) -> Option<(&str, BTreeMap<&str, &str>, f64)> {
+    let (series, rest) = if line.contains('{') {
+        let close = line.rfind('}')?;
+        (&line[..close + 1], &line[close + 1..])
+    } else {
+        line.split_once(' ')?
+    };
+    let value = rest.split_whitespace().next()?.parse().ok()?;
+
+    let (name, labels) = match series.split_once('{') {
+        Some((name, labels)) => (name, labels.trim_end_matches('}')),
+        None => (series, ""),
+    };
+    let labels = labels
+        .split(',')
+        .filter_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            Some((key.trim(), value.trim().trim_matches('"')))
+        })
+        .collect();
+    Some((name, labels, value))
+}
+
+/// Sums the request counter and latency histogram across all other labels.
+fn parse_scrape(text: &str, requests_metric: &str, latency_metric: &str) -> Scrape {
+    let bucket_metric = format!("{}_bucket", latency_metric);
+    let mut scrape = Scrape::default();
+    let mut buckets: Vec<(f64, f64)> = Vec::new();
+
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        let (name, labels, value) = match parse_line(line) {
+            Some(parsed) => parsed,
+            None => continue,
+        };
+        if name == requests_metric {
+            scrape.requests += value;
+            if labels.get("status").is_some_and(|s| s.starts_with('5')) {
+                scrape.errors += value;
+            }
+        } else if name == bucket_metric {
+            let le = match labels.get("le").and_then(|le| le.parse::<f64>().ok()) {
+                Some(le) => le,
+                None => continue,
+            };
+            match buckets.iter_mut().find(|(bound, _)| *bound == le) {
+                Some((_, count)) => *count += value,
+                None => buckets.push((le, value)),
+            }
+        }
+    }
+
+    buckets.sort_by(|a, b| a.0.total_cmp(&b.0));
+    scrape.buckets = buckets;
+    scrape
+}
+
+/// Estimates a quantile from cumulative bucket counts, interpolating within the bucket.
+fn quantile(buckets: &[(f64, f64)], q: f64) -> Option<f64> {
+    let total = buckets.last()?.1;
+    if total <= 0.0 {
+        return None;
+    }
+    let rank = total * q;
+    let (mut lower_bound, mut lower_count) = (0.0, 0.0);
+    for &(bound, count) in buckets {
+        if count >= rank {
+            // The `+Inf` bucket has no width; report the last finite bound.
+            if bound.is_infinite() {
+                return Some(lower_bound);
+            }
+            let share = if count > lower_count { (rank - lower_count) / (count - lower_count) } else { 0.0 };
+            return Some(lower_bound + (bound - lower_bound) * share);
+        }
+        lower_bound = bound;
+        lower_count = count;
+    }
+    Some(lower_bound)
+}
+
+/// Traffic between two scrapes, or `None` if the counters reset (the API restarted).
+fn diff(previous: &Scrape, current: &Scrape, at: i64) -> Option<Interval> {
+    if current.requests < previous.requests || current.errors < previous.errors {
+        return None;
+    }
+    let buckets: Vec<(f64, f64)> = current
+        .buckets
+        .iter()
+        .map(|&(bound, count)| {
+            let before = previous.buckets.iter().find(|(b, _)| *b == bound).map_or(0.0, |(_, c)| *c);
+            (bound, (count - before).max(0.0))
+        })
+        .collect();
+    let percentiles = match (quantile(&buckets, 0.5), quantile(&buckets, 0.95), quantile(&buckets, 0.99)) {
+        (Some(p50), Some(p95), Some(p99)) => Some([p50, p95, p99]),
+        _ => None,
+    };
+
+    Some(Interval {
+        at,
+        requests: current.requests - previous.requests,
+        errors: current.errors - previous.errors,
+        percentiles,
+    })
+}
+
+async fn fetch_scrape(url: &str) -> Result<Scrape, String> {
+    let res = crate::http::client().get(url).send().await.map_err(|e| e.to_string())?;
+    if !res.status().is_success() {
+        return Err(format!("metrics endpoint returned {}", res.status()));
+    }
+    let text = res.text().await.map_err(|e| e.to_string())?;
+    Ok(parse_scrape(
+        &text,
+        &env::var("API_METRICS_REQUESTS_METRIC").unwrap_or_else(|_| "http_requests_total".to_string()),
+        &env::var("API_METRICS_LATENCY_METRIC").unwrap_or_else(|_| "http_request_duration_seconds".to_string()),
+    ))
+}
+
+/// The `API:` block of the status dashboard, if the monitor is configured.
+pub fn api_section() -> Option<String> {
+    metrics_url()?;
+    let latest = LATEST.lock().unwrap().clone();
+    let interval = match latest {
+        Some(interval) => interval,
+        None => return Some("API:\n(waiting for the first two metrics scrapes)".to_string()),
+    };
+
+    let latency = match interval.percentiles {
+        Some([p50, p95, p99]) => format!(
+            "Latency:    p50 {:.0}ms, p95 {:.0}ms, p99 {:.0}ms",
+            p50 * 1000.0,
+            p95 * 1000.0,
+            p99 * 1000.0
+        ),
+        None => "Latency:    no requests".to_string(),
+    };
+    let at = Local
+        .timestamp_opt(interval.at, 0)
+        .single()
+        .map(|t| t.format("%H:%M:%S").to_string())
+        .unwrap_or_default();
+
+    Some(format!(
+        "API (last interval, {}):\nRequests:   {:.0}\n5xx Rate:   {:.1}% ({:.0} errors)\n{}",
+        at,
+        interval.requests,
+        interval.error_rate(),
+        interval.errors,
+        latency
+    ))
+}
+
+async fn alert(ctx: &Context, message: String) {
+    let channel = ["API_METRICS_CHANNEL_ID", "DISCORD_STATUS_CHANNEL_ID"]
+        .iter()
+        .find_map(|key| env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok()));
+    let channel = match channel {
+        Some(channel) => channel,
+        None => {
+            eprintln!("API error-rate alert not posted, no channel configured: {}", message);
+            return;
+        }
+    };
+    if let Err(e) = notify::queue::send(ctx.http.clone(), channel, Priority::Critical, message).await {
+        eprintln!("Failed to post API error-rate alert: {}", e);
+    }
+}
+
+fn dev_mention() -> String {
+    env::var("DISCORD_DEV_ROLE_ID")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(|id| format!("<@&{}> ", id))
+        .unwrap_or_default()
+}
+
+/// Spawns the background task that polls the metrics endpoint, if configured.
+pub async fn start_api_metrics_loop(ctx: Context, tasks: &Tasks) {
+    let url = match metrics_url() {
+        Some(url) => url,
+        None => return,
+    };
+    if tasks.is_running("api_metrics") {
+        println!("API metrics loop already running, reusing it.");
+        return;
+    }
+
+    let interval = env_or("API_METRICS_INTERVAL_SECS", DEFAULT_INTERVAL_SECS);
+    let threshold = env_or("API_ERROR_RATE_THRESHOLD", DEFAULT_THRESHOLD);
+    let sustain = env_or("API_ERROR_RATE_SUSTAIN", DEFAULT_SUSTAIN).max(1);
+    let min_requests = env_or("API_ERROR_RATE_MIN_REQUESTS", DEFAULT_MIN_REQUESTS);
+
+    tasks.spawn("api_metrics", move |beat| {
+        let (ctx, url) = (ctx.clone(), url.clone());
+        async move {
+            let mut previous: Option<Scrape> = None;
+            let mut above = 0u32;
+            let mut alerting = false;
+
+            loop {
+                beat.tick();
+                let current = match fetch_scrape(&url).await {
+                    Ok(scrape) => scrape,
+                    Err(e) => {
+                        eprintln!("Failed to scrape API metrics: {}", e);
+                        sleep(Duration::from_secs(interval)).await;
+                        continue;
+                    }
+                };
+
+                let at = Local::now().timestamp();
+                if let Some(window) = previous.as_ref().and_then(|p| diff(p, &current, at)) {
+                    *LATEST.lock().unwrap() = Some(window.clone());
+
+                    // Quiet intervals say nothing either way about a spike.
+                    if window.requests >= min_requests {
+                        let rate = window.error_rate();
+                        above = if rate > threshold { above + 1 } else { 0 };
+
+                        if above >= sustain && !alerting {
+                            alerting = true;
+                            ops_events::record(EventKind::Alert, format!("API 5xx rate at {:.1}%", rate));
+                            if dedup::fire(ALERT_KEY, Severity::Critical) {
+                                let message = format!(
+                                    "{}📈 **API error rate spike:** {:.1}% of {:.0} requests returned 5xx \
+                                     (threshold {}%, {} polls in a row)",
+                                    dev_mention(),
+                                    rate,
+                                    window.requests,
+                                    threshold,
+                                    above
+                                );
+                                alert(&ctx, impact::append(message).await).await;
+                            }
+                        } else if above == 0 && alerting {
+                            alerting = false;
+                            if dedup::resolve(ALERT_KEY) {
+                                alert(&ctx, format!("✅ **API error rate back to normal:** {:.1}%", rate)).await;
+                            }
+                        }
+                    }
+                }
+                previous = Some(current);
+
+                sleep(Duration::from_secs(interval)).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,204 @@
+//! Authorization helpers for privileged slash commands.
+//!
+//! A member is an admin if they hold one of the configured admin roles or have the
+//! Discord `ADMINISTRATOR` permission in the guild. Guilds with admin roles in their
+//! `guilds.json` record use those instead of the global ones.
+//!
+//! Commands that act on the host itself (services, builds, logs, files, secrets, tasks,
+//! replays) are for operators only: members of the operators' guild (`DISCORD_GUILD_ID`)
+//! holding one of the global admin roles. Being an admin of any other guild the bot is in
+//! is not enough. Commands sent in DMs are refused outright.
+//!
+//! Who may run each slash command is declared once in [`COMMAND_ACCESS`], checked by
+//! [`allowed`] before a command is dispatched, and listed by `/permissions export`.
+//!
+//! Environment Variables:
+//! - `DISCORD_ADMIN_ROLE_ID`: Comma-separated role IDs allowed to run admin commands (and,
+//!   in `DISCORD_GUILD_ID`, operator commands)
+
+use serenity::{
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    model::application::interaction::InteractionResponseType,
+    model::prelude::*,
+    prelude::*,
+};
+use std::env;
+
+use super::options::Options;
+use crate::guilds;
+
+/// Who may run a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Anyone Discord lets use the command.
+    Everyone,
+    /// Admins only.
+    Admin,
+    /// Operators only, for commands that act on the host rather than one guild.
+    Operator,
+}
+
+/// Access to every slash command, as `(command, subcommand, access)`. A subcommand's row
+/// overrides its command's; commands missing from the table are admin-only.
+pub const COMMAND_ACCESS: &[(&str, Option<&str>, Access)] = &[
+    ("alerts", None, Access::Admin),
+    ("alerts", Some("list"), Access::Everyone),
+    ("botstats", None, Access::Everyone),
+    ("clean", None, Access::Operator),
+    ("fetch-file", None, Access::Operator),
+    ("find", None, Access::Everyone),
+    ("freeze", None, Access::Admin),
+    ("fresh", None, Access::Operator),
+    ("guild-config", None, Access::Admin),
+    ("guild-config", Some("show"), Access::Everyone),
+    ("health", None, Access::Everyone),
+    // Reassigning a username linked to someone else is checked by the handler.
+    ("link_github", None, Access::Everyone),
+    ("migrate", None, Access::Operator),
+    ("permissions", None, Access::Admin),
+    ("provision-module", None, Access::Admin),
+    ("purge", None, Access::Admin),
+    ("reboot", None, Access::Operator),
+    ("restart", None, Access::Operator),
+    ("restart_api", None, Access::Operator),
+    ("rotate-secret", None, Access::Operator),
+    ("schedule", None, Access::Everyone),
+    ("selftest", None, Access::Admin),
+    ("show-file", None, Access::Operator),
+    ("smoke-test", None, Access::Everyone),
+    ("start_api", None, Access::Operator),
+    ("status", None, Access::Everyone),
+    ("stop_api", None, Access::Operator),
+    ("tail_logs", None, Access::Operator),
+    ("tasks", None, Access::Operator),
+    ("unfreeze", None, Access::Admin),
+    ("unlink_github", None, Access::Everyone),
+    ("uptime", None, Access::Everyone),
+    ("usage", None, Access::Admin),
+    ("watch", None, Access::Operator),
+    ("watch", Some("list"), Access::Everyone),
+    ("webhook_replay", None, Access::Operator),
+];
+
+/// Who may run `command`, or its `subcommand`.
+pub fn access(command: &str, subcommand: Option<&str>) -> Access {
+    let row = |sub: Option<&str>| {
+        COMMAND_ACCESS
+            .iter()
+            .find(|(name, s, _)| *name == command && *s == sub)
+            .map(|(_, _, access)| *access)
+    };
+    subcommand.and_then(|sub| row(Some(sub))).or_else(|| row(None)).unwrap_or(Access::Admin)
+}
+
+/// Returns the configured admin role IDs.
+pub fn admin_role_ids() -> Vec<RoleId> {
+    env::var("DISCORD_ADMIN_ROLE_ID")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|id| id.trim().parse::<u64>().ok())
+        .map(RoleId)
+        .collect()
+}
+
+/// Returns the admin role IDs for `guild_id`: the guild's own, if it configured any.
+pub fn admin_role_ids_for(guild_id: Option<GuildId>) -> Vec<RoleId> {
+    let guild_roles: Vec<RoleId> = guild_id
+        .and_then(|id| guilds::for_guild(id.0))
+        .map(|config| config.admin_role_ids.into_iter().map(RoleId).collect())
+        .unwrap_or_default();
+
+    if guild_roles.is_empty() {
+        admin_role_ids()
+    } else {
+        guild_roles
+    }
+}
+
+/// Returns `true` if the invoking member may run admin-only commands.
+pub fn is_admin(command: &ApplicationCommandInteraction) -> bool {
+    is_admin_member(command.member.as_ref(), command.guild_id)
+}
+
+/// Returns `true` if `member` is an admin in `guild_id` (e.g. for button presses).
+pub fn is_admin_member(member: Option<&Member>, guild_id: Option<GuildId>) -> bool {
+    let member = match member {
+        Some(member) => member,
+        None => return false,
+    };
+
+    if member.permissions.is_some_and(|p| p.administrator()) {
+        return true;
+    }
+
+    let admin_roles = admin_role_ids_for(guild_id);
+    member.roles.iter().any(|role| admin_roles.contains(role))
+}
+
+/// Returns `true` if `member` is an operator: they hold a global admin role in the
+/// operators' guild.
+pub fn is_operator_member(member: Option<&Member>, guild_id: Option<GuildId>) -> bool {
+    let in_home_guild = guild_id.is_some_and(|id| Some(id.0) == guilds::home_guild());
+    let admin_roles = admin_role_ids();
+    in_home_guild && member.is_some_and(|m| m.roles.iter().any(|role| admin_roles.contains(role)))
+}
+
+/// Returns `true` if the invoking member may run the command, per [`COMMAND_ACCESS`].
+/// Commands sent in DMs are always refused.
+pub fn allowed(command: &ApplicationCommandInteraction) -> bool {
+    if command.guild_id.is_none() {
+        return false;
+    }
+    let options = Options::of(command);
+    let subcommand = options.subcommand().map(|(name, _)| name);
+    match access(&command.data.name, subcommand) {
+        Access::Everyone => true,
+        Access::Admin => is_admin(command),
+        Access::Operator => is_operator_member(command.member.as_ref(), command.guild_id),
+    }
+}
+
+/// Replies ephemerally that the invoker is not allowed to run the command.
+pub async fn deny(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let _ = command
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|msg| {
+                    msg.content(format!("⛔ You are not allowed to use `/{}`.", command.data.name))
+                        .ephemeral(true)
+                })
+        })
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Access, COMMAND_ACCESS};
+
+    /// Commands that act on the host rather than one guild.
+    const HOST_COMMANDS: &[&str] = &[
+        "clean",
+        "fetch-file",
+        "fresh",
+        "migrate",
+        "reboot",
+        "restart",
+        "restart_api",
+        "rotate-secret",
+        "show-file",
+        "start_api",
+        "stop_api",
+        "tail_logs",
+        "tasks",
+        "watch",
+        "webhook_replay",
+    ];
+
+    #[test]
+    fn host_commands_are_operator_only() {
+        for command in HOST_COMMANDS {
+            let row = COMMAND_ACCESS.iter().find(|(name, sub, _)| name == command && sub.is_none());
+            assert_eq!(row.map(|(_, _, access)| *access), Some(Access::Operator), "/{}", command);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! `/botstats`: process and gateway health.
+//!
+//! Reports the bot version, process uptime, how often the gateway sessions were
+//! (re)established or resumed, and each shard's connection stage and heartbeat latency.
+
+use chrono::Utc;
+use serenity::{
+    client::bridge::gateway::ShardManager,
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    prelude::*,
+};
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    sync::Arc,
+    time::Duration,
+};
+
+use super::command_spec::CommandSpec;
+use crate::duration::format_duration;
+use crate::lifecycle;
+
+/// `ready` events received (first connect, and new sessions after failed resumes).
+pub static READY_COUNT: AtomicU64 = AtomicU64::new(0);
+/// Gateway sessions resumed without a new `ready`.
+pub static RESUME_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// Type-map key giving command handlers access to the shard manager.
+pub struct ShardManagerContainer;
+
+impl TypeMapKey for ShardManagerContainer {
+    type Value = Arc<Mutex<ShardManager>>;
+}
+
+/// Registers `/botstats`.
+pub async fn register_botstats_command(ctx: &Context) {
+    CommandSpec::new("botstats", "Show bot uptime, version, and shard health")
+        .register(ctx)
+        .await;
+}
+
+/// Slash command handler for `/botstats`.
+pub async fn handle_botstats(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let uptime_secs = (Utc::now().timestamp() - lifecycle::started_at()).max(0) as u64;
+
+    let mut lines = vec![
+        "**Bot stats**".to_string(),
+        format!("Version: `{}`", lifecycle::version()),
+        format!("Uptime: {}", format_duration(Duration::from_secs(uptime_secs))),
+        format!(
+            "Sessions: {} ready, {} resumed",
+            READY_COUNT.load(Ordering::SeqCst),
+            RESUME_COUNT.load(Ordering::SeqCst)
+        ),
+        format!("This interaction: shard {}", ctx.shard_id),
+    ];
+    lines.extend(shard_lines(ctx).await);
+
+    let _ = command
+        .create_interaction_response(&ctx.http, |res| {
+            res.interaction_response_data(|msg| msg.content(lines.join("\n")))
+        })
+        .await;
+}
+
+/// One line per shard with its connection stage and last heartbeat latency.
+async fn shard_lines(ctx: &Context) -> Vec<String> {
+    let manager = {
+        let data = ctx.data.read().await;
+        match data.get::<ShardManagerContainer>() {
+            Some(manager) => manager.clone(),
+            None => return vec!["Shards: unavailable".to_string()],
+        }
+    };
+
+    let manager = manager.lock().await;
+    let runners = manager.runners.lock().await;
+
+    let mut shards: Vec<_> = runners.iter().collect();
+    shards.sort_by_key(|(id, _)| id.0);
+
+    let mut lines = vec![format!("Shards ({}):", shards.len())];
+    for (id, info) in shards {
+        let latency = info
+            .latency
+            .map(|l| format!("{} ms", l.as_millis()))
+            .unwrap_or_else(|| "no heartbeat yet".into());
+        lines.push(format!("- Shard {}: {} ({})", id.0, info.stage, latency));
+    }
+    lines
+}
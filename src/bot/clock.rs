@@ -0,0 +1,211 @@
+//! Clock synchronization check.
+//!
+//! Clock skew breaks submission-deadline enforcement and TLS, so on an interval the system
+//! clock's NTP state is read from chrony (`chronyc tracking`) or, without chrony,
+//! systemd-timesyncd (`timedatectl`). The dev role is alerted when the clock is no longer
+//! synchronized or its offset exceeds the threshold, and a recovery message follows once it
+//! is back in sync.
+//!
+//! Environment Variables:
+//! - `CLOCK_DRIFT_MAX_MS`: Offset from NTP time that triggers an alert (default: 500)
+//! - `CLOCK_CHECK_INTERVAL_SECS`: How often to check; set to 0 to disable (default: 600)
+//! - `CLOCK_CHANNEL_ID`: Where to alert (default: `DISCORD_STATUS_CHANNEL_ID`)
+
+use serenity::prelude::*;
+use std::{env, process::Command, time::Duration};
+use tokio::time::sleep;
+
+use crate::notify::{self, dedup::{self, Severity}, Priority};
+use crate::ops_events::{self, EventKind};
+use crate::tasks::Tasks;
+
+const DEFAULT_MAX_DRIFT_MS: f64 = 500.0;
+const DEFAULT_INTERVAL_SECS: u64 = 600;
+const ALERT_KEY: &str = "clock: drift";
+
+/// The clock's synchronization state, as reported by the time daemon.
+#[derive(Debug)]
+struct ClockState {
+    source: &'static str,
+    synchronized: bool,
+    /// Offset from NTP time in milliseconds (positive = fast), if the daemon reports it.
+    offset_ms: Option<f64>,
+}
+
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Value of a `Key : value` line.
+fn value_of<'a>(output: &'a str, key: &str) -> Option<&'a str> {
+    output.lines().find_map(|line| {
+        let (k, v) = line.split_once(':')?;
+        (k.trim() == key).then(|| v.trim())
+    })
+}
+
+/// Parses `chronyc tracking`, e.g. `System time : 0.000012 seconds fast of NTP time`.
+fn chrony_state(output: &str) -> Option<ClockState> {
+    let system_time = value_of(output, "System time")?;
+    let mut words = system_time.split_whitespace();
+    let seconds: f64 = words.next()?.parse().ok()?;
+    let sign = if system_time.contains("slow") { -1.0 } else { 1.0 };
+    let leap = value_of(output, "Leap status").unwrap_or_default();
+
+    Some(ClockState {
+        source: "chrony",
+        synchronized: !leap.eq_ignore_ascii_case("Not synchronised"),
+        offset_ms: Some(sign * seconds * 1000.0),
+    })
+}
+
+/// Parses a timesyncd offset such as `+1.234ms`, `-56us` or `+1.5s` into milliseconds.
+fn parse_offset_ms(offset: &str) -> Option<f64> {
+    let offset = offset.trim();
+    let (number, scale) = if let Some(n) = offset.strip_suffix("us") {
+        (n, 0.001)
+    } else if let Some(n) = offset.strip_suffix("ms") {
+        (n, 1.0)
+    } else if let Some(n) = offset.strip_suffix("min") {
+        (n, 60_000.0)
+    } else if let Some(n) = offset.strip_suffix('s') {
+        (n, 1000.0)
+    } else {
+        return None;
+    };
+    number.trim_start_matches('+').parse::<f64>().ok().map(|n| n * scale)
+}
+
+/// Reads `timedatectl`'s synchronized flag and, with timesyncd, its last measured offset.
+fn timedatectl_state() -> Option<ClockState> {
+    let synchronized = run("timedatectl", &["show", "--property", "NTPSynchronized", "--value"])?;
+    let offset_ms = run("timedatectl", &["timesync-status"])
+        .and_then(|status| value_of(&status, "Offset").and_then(parse_offset_ms));
+
+    Some(ClockState {
+        source: "timedatectl",
+        synchronized: synchronized.trim() == "yes",
+        offset_ms,
+    })
+}
+
+fn clock_state() -> Option<ClockState> {
+    run("chronyc", &["tracking"])
+        .and_then(|output| chrony_state(&output))
+        .or_else(timedatectl_state)
+}
+
+/// What's wrong with the clock, if anything.
+fn problem(state: &ClockState, max_drift_ms: f64) -> Option<String> {
+    if !state.synchronized {
+        return Some(format!("the clock is not synchronized with NTP ({})", state.source));
+    }
+    match state.offset_ms {
+        Some(offset) if offset.abs() > max_drift_ms => Some(format!(
+            "the clock is {:.0} ms {} NTP time (threshold {:.0} ms, {})",
+            offset.abs(),
+            if offset > 0.0 { "ahead of" } else { "behind" },
+            max_drift_ms,
+            state.source
+        )),
+        _ => None,
+    }
+}
+
+/// Posts to the clock alert channel.
+async fn alert(ctx: &Context, message: String) {
+    let channel = ["CLOCK_CHANNEL_ID", "DISCORD_STATUS_CHANNEL_ID"]
+        .iter()
+        .find_map(|key| env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok()));
+    let channel = match channel {
+        Some(channel) => channel,
+        None => {
+            eprintln!("Clock alert not posted, no channel configured: {}", message);
+            return;
+        }
+    };
+    if let Err(e) = notify::queue::send(ctx.http.clone(), channel, Priority::Critical, message).await {
+        eprintln!("Failed to post clock alert: {}", e);
+    }
+}
+
+fn dev_mention() -> String {
+    env::var("DISCORD_DEV_ROLE_ID")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(|id| format!("<@&{}> ", id))
+        .unwrap_or_default()
+}
+
+/// Spawns the background task that checks clock synchronization.
+pub async fn start_clock_check_loop(ctx: Context, tasks: &Tasks) {
+    let interval = env::var("CLOCK_CHECK_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+    if interval == 0 {
+        return;
+    }
+    if tasks.is_running("clock_check") {
+        println!("Clock check loop already running, reusing it.");
+        return;
+    }
+
+    let max_drift_ms = env::var("CLOCK_DRIFT_MAX_MS")
+        .ok()
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|ms| *ms > 0.0)
+        .unwrap_or(DEFAULT_MAX_DRIFT_MS);
+
+    tasks.spawn("clock_check", move |beat| {
+        let ctx = ctx.clone();
+        async move {
+            let mut failing = false;
+            let mut warned_unavailable = false;
+
+            loop {
+                beat.tick();
+                let state = tokio::task::spawn_blocking(clock_state).await.ok().flatten();
+
+                match state.as_ref().map(|state| problem(state, max_drift_ms)) {
+                    // Neither chrony nor timedatectl is available; nothing to check.
+                    None => {
+                        if !warned_unavailable {
+                            warned_unavailable = true;
+                            eprintln!("Clock check skipped: neither chronyc nor timedatectl answered");
+                        }
+                    }
+                    Some(Some(problem)) if !failing => {
+                        failing = true;
+                        ops_events::record(EventKind::Alert, format!("Clock drift: {}", problem));
+                        if dedup::fire(ALERT_KEY, Severity::Warning) {
+                            alert(
+                                &ctx,
+                                format!(
+                                    "{}🕰️ **Clock drift:** {}. Deadlines and TLS may misbehave until it is fixed.",
+                                    dev_mention(),
+                                    problem
+                                ),
+                            )
+                            .await;
+                        }
+                    }
+                    Some(Some(problem)) => eprintln!("Clock still drifting: {}", problem),
+                    Some(None) if failing => {
+                        failing = false;
+                        if dedup::resolve(ALERT_KEY) {
+                            alert(&ctx, "✅ **Clock is back in sync with NTP.**".to_string()).await;
+                        }
+                    }
+                    Some(None) => {}
+                }
+
+                sleep(Duration::from_secs(interval)).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,112 @@
+//! Reporting for slash command handlers that panic.
+//!
+//! Each command runs in its own task (see `interaction_create`), so a panicking handler no
+//! longer takes the event handler down with it. The panic is posted to the error channel with
+//! the command that caused it, and the user gets a friendly error instead of a timeout.
+//!
+//! Environment Variables:
+//! - `DISCORD_ERROR_CHANNEL_ID`: Channel for command errors (default: `PERMISSION_ALERT_CHANNEL_ID`,
+//!   then `DISCORD_STATUS_CHANNEL_ID`); the fallback webhook is used if none can be posted to
+
+use serenity::{
+    model::application::interaction::application_command::{
+        ApplicationCommandInteraction, CommandDataOption,
+    },
+    model::application::interaction::InteractionResponseType,
+    prelude::*,
+};
+use std::env;
+
+use crate::fallback;
+use crate::notify::{self, Priority};
+
+const FRIENDLY_ERROR: &str =
+    "⚠️ Something went wrong while running this command. The error has been reported.";
+
+/// Reports a panic raised while handling `command` and tells the user it failed.
+pub async fn report_command_panic(ctx: &Context, command: &ApplicationCommandInteraction, panic: &str) {
+    let invocation = describe_invocation(command);
+    eprintln!("Command `{}` panicked: {}", invocation, panic);
+
+    let location = match command.guild_id {
+        Some(guild_id) => format!("guild `{}`, <#{}>", guild_id.0, command.channel_id.0),
+        None => "a direct message".to_string(),
+    };
+    let report = format!(
+        "💥 **Command panicked**: `{}`\nRun by <@{}> in {}\n```\n{}\n```",
+        invocation,
+        command.user.id.0,
+        location,
+        truncate(panic, 1500)
+    );
+    post_report(ctx, &report).await;
+
+    reply_with_error(ctx, command).await;
+}
+
+/// Posts `report` to the error channel, falling back to the fallback webhook.
+async fn post_report(ctx: &Context, report: &str) {
+    let channel = ["DISCORD_ERROR_CHANNEL_ID", "PERMISSION_ALERT_CHANNEL_ID", "DISCORD_STATUS_CHANNEL_ID"]
+        .iter()
+        .find_map(|key| env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok()));
+
+    if let Some(channel) = channel {
+        match notify::queue::send(ctx.http.clone(), channel, Priority::Critical, report.to_string()).await {
+            Ok(()) => return,
+            Err(e) => eprintln!("Failed to post command error report: {}", e),
+        }
+    }
+
+    if let Err(e) = fallback::post(report).await {
+        eprintln!("Fallback delivery of command error report failed: {}", e);
+    }
+}
+
+/// Answers the interaction with an error, or follows up if the handler already responded.
+async fn reply_with_error(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let responded = command
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|msg| msg.content(FRIENDLY_ERROR).ephemeral(true))
+        })
+        .await;
+
+    if responded.is_err() {
+        if let Err(e) = command
+            .create_followup_message(&ctx.http, |msg| msg.content(FRIENDLY_ERROR).ephemeral(true))
+            .await
+        {
+            eprintln!("Failed to tell the user their command failed: {e:?}");
+        }
+    }
+}
+
+/// Formats the command as typed, e.g. `/guild-config channel kind:pr`.
+fn describe_invocation(command: &ApplicationCommandInteraction) -> String {
+    let mut parts = vec![format!("/{}", command.data.name)];
+    describe_options(&command.data.options, &mut parts);
+    parts.join(" ")
+}
+
+fn describe_options(options: &[CommandDataOption], parts: &mut Vec<String>) {
+    for option in options {
+        match &option.value {
+            Some(value) => {
+                let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+                parts.push(format!("{}:{}", option.name, value));
+            }
+            None => {
+                parts.push(option.name.clone());
+                describe_options(&option.options, parts);
+            }
+        }
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        format!("{}…", text.chars().take(max_chars).collect::<String>())
+    }
+}
@@ -0,0 +1,177 @@
+//! Declarative slash command definitions.
+//!
+//! [`CommandSpec`] describes a command's options and subcommands as plain data and registers
+//! it globally, so commands with nested options don't each hand-write
+//! serenity builder closures:
+//!
+//! ```ignore
+//! CommandSpec::new("service", "Manage systemd services")
+//!     .option(
+//!         OptionSpec::sub("restart", "Restart a service")
+//!             .option(OptionSpec::string("name", "Service name").required())
+//!             .option(OptionSpec::boolean("force", "Skip the health check")),
+//!     )
+//!     .register(ctx)
+//!     .await;
+//! ```
+
+use serenity::{
+    builder::CreateApplicationCommandOption,
+    model::application::command::{Command, CommandOptionType},
+    prelude::*,
+};
+
+/// The most choices Discord accepts on one option.
+const MAX_CHOICES: usize = 25;
+
+/// An option, subcommand, or subcommand group.
+#[derive(Debug, Clone)]
+pub struct OptionSpec {
+    name: String,
+    description: String,
+    kind: CommandOptionType,
+    required: bool,
+    /// String choices as `(name, value)`.
+    choices: Vec<(String, String)>,
+    min: Option<i64>,
+    max: Option<i64>,
+    /// Options of a subcommand.
+    options: Vec<OptionSpec>,
+}
+
+impl OptionSpec {
+    fn new(kind: CommandOptionType, name: &str, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            kind,
+            required: false,
+            choices: Vec::new(),
+            min: None,
+            max: None,
+            options: Vec::new(),
+        }
+    }
+
+    pub fn string(name: &str, description: &str) -> Self {
+        Self::new(CommandOptionType::String, name, description)
+    }
+
+    pub fn integer(name: &str, description: &str) -> Self {
+        Self::new(CommandOptionType::Integer, name, description)
+    }
+
+    pub fn boolean(name: &str, description: &str) -> Self {
+        Self::new(CommandOptionType::Boolean, name, description)
+    }
+
+    pub fn channel(name: &str, description: &str) -> Self {
+        Self::new(CommandOptionType::Channel, name, description)
+    }
+
+    pub fn role(name: &str, description: &str) -> Self {
+        Self::new(CommandOptionType::Role, name, description)
+    }
+
+    /// A subcommand; add its parameters with [`OptionSpec::option`].
+    pub fn sub(name: &str, description: &str) -> Self {
+        Self::new(CommandOptionType::SubCommand, name, description)
+    }
+
+    /// Marks the option as required (options are optional by default).
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Adds a choice to a string option; the user picks `name` and the handler sees `value`.
+    /// Discord allows at most 25 choices, so any past that are dropped.
+    pub fn choice(mut self, name: &str, value: &str) -> Self {
+        if self.choices.len() < MAX_CHOICES {
+            self.choices.push((name.to_string(), value.to_string()));
+        }
+        self
+    }
+
+    /// Bounds an integer option (inclusive).
+    pub fn range(mut self, min: i64, max: i64) -> Self {
+        self.min = Some(min);
+        self.max = Some(max);
+        self
+    }
+
+    /// Sets a lower bound on an integer option (inclusive).
+    pub fn min(mut self, min: i64) -> Self {
+        self.min = Some(min);
+        self
+    }
+
+    /// Adds a parameter to a subcommand.
+    pub fn option(mut self, option: OptionSpec) -> Self {
+        self.options.push(option);
+        self
+    }
+
+    fn build(&self) -> CreateApplicationCommandOption {
+        let mut opt = CreateApplicationCommandOption::default();
+        opt.name(&self.name).description(&self.description).kind(self.kind);
+
+        // Discord rejects `required` on subcommands.
+        if self.kind != CommandOptionType::SubCommand {
+            opt.required(self.required);
+        }
+        for (name, value) in &self.choices {
+            opt.add_string_choice(name, value);
+        }
+        if let Some(min) = self.min {
+            opt.min_int_value(min);
+        }
+        if let Some(max) = self.max {
+            opt.max_int_value(max);
+        }
+        for sub in &self.options {
+            opt.add_sub_option(sub.build());
+        }
+        opt
+    }
+}
+
+/// A top-level slash command.
+#[derive(Debug, Clone)]
+pub struct CommandSpec {
+    name: String,
+    description: String,
+    options: Vec<OptionSpec>,
+}
+
+impl CommandSpec {
+    pub fn new(name: &str, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            options: Vec::new(),
+        }
+    }
+
+    /// Adds a parameter or subcommand.
+    pub fn option(mut self, option: OptionSpec) -> Self {
+        self.options.push(option);
+        self
+    }
+
+    /// Registers (or updates) the command globally.
+    pub async fn register(&self, ctx: &Context) {
+        let result = Command::create_global_application_command(&ctx.http, |cmd| {
+            cmd.name(&self.name).description(&self.description);
+            for option in &self.options {
+                cmd.add_option(option.build());
+            }
+            cmd
+        })
+        .await;
+
+        if let Err(e) = result {
+            eprintln!("Failed to register /{}: {e:?}", self.name);
+        }
+    }
+}
@@ -0,0 +1,458 @@
+//! Buttons attached to notifications, and their interaction handlers.
+//!
+//! Buttons carry everything their handler needs in the custom ID, so they keep working across
+//! restarts without any stored state.
+//!
+//! - **Re-run failed jobs** (`rerun_workflow:<owner/repo>#<run id>`): on workflow failure
+//!   messages; re-runs the run's failed jobs through the GitHub API. Only admins and holders of
+//!   the repository's dev role may press it, and re-runs are recorded in the operations journal.
+//! - **Approve** / **Merge when green** (`pr_approve:` / `pr_merge:<owner/repo>#<number>`):
+//!   posted under PR notifications; approve the PR, or merge it (right away if its checks
+//!   passed, otherwise through GitHub auto-merge once they do). Only admins and maintainers of
+//!   the operators' guild or the guild that claims the repository may press them, each press
+//!   asks for confirmation first, and merging is refused during a
+//!   deploy freeze. Every action is logged, recorded in the operations journal (see
+//!   [`crate::ops_events`]) and announced in the channel.
+//!
+//! Environment Variables:
+//! - `WORKFLOW_RERUN_BUTTON`: Set to `false` to leave the button off failure messages
+//!   (default: on whenever GitHub API credentials are set)
+//! - `PR_ACTION_BUTTONS`: Set to `true` to post the approve/merge buttons (default: off)
+//! - `PR_MAINTAINER_ROLE_IDS`: Comma-separated role IDs allowed to approve and merge, besides
+//!   admins
+//! - `PR_MERGE_METHOD`: `merge`, `squash` or `rebase` (default: `merge`)
+//! - `GITHUB_TOKEN` or a GitHub App (see [`crate::github::client`]): needs Actions and pull
+//!   request write access; reviews and merges are made as the token's owner or the App
+
+use reqwest::Method;
+use serde_json::{json, Value};
+use serenity::{
+    builder::CreateComponents,
+    model::application::component::ButtonStyle,
+    model::application::interaction::message_component::MessageComponentInteraction,
+    model::application::interaction::InteractionResponseType,
+    model::prelude::*,
+    prelude::*,
+};
+use std::env;
+
+use super::auth;
+use crate::freeze;
+use crate::github::client;
+use crate::guilds;
+use crate::notify::{self, Delivery, Outgoing, Priority};
+use crate::observer;
+use crate::ops_events::{self, EventKind};
+
+const RERUN_PREFIX: &str = "rerun_workflow:";
+const APPROVE_PREFIX: &str = "pr_approve:";
+const MERGE_PREFIX: &str = "pr_merge:";
+const APPROVE_CONFIRM_PREFIX: &str = "pr_approve_confirm:";
+const MERGE_CONFIRM_PREFIX: &str = "pr_merge_confirm:";
+const CANCEL_ID: &str = "pr_cancel";
+/// Discord rejects custom IDs longer than this.
+const MAX_CUSTOM_ID_LEN: usize = 100;
+
+/// An action on a pull request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PrAction {
+    Approve,
+    Merge,
+}
+
+impl PrAction {
+    fn prefix(self) -> &'static str {
+        match self {
+            PrAction::Approve => APPROVE_PREFIX,
+            PrAction::Merge => MERGE_PREFIX,
+        }
+    }
+
+    fn confirm_prefix(self) -> &'static str {
+        match self {
+            PrAction::Approve => APPROVE_CONFIRM_PREFIX,
+            PrAction::Merge => MERGE_CONFIRM_PREFIX,
+        }
+    }
+
+    fn verb(self) -> &'static str {
+        match self {
+            PrAction::Approve => "approve",
+            PrAction::Merge => "merge",
+        }
+    }
+}
+
+/// Whether workflow failure messages get a re-run button.
+pub fn rerun_enabled() -> bool {
+    let on = env::var("WORKFLOW_RERUN_BUTTON")
+        .map(|v| !v.trim().eq_ignore_ascii_case("false"))
+        .unwrap_or(true);
+    on && client::authenticated()
+}
+
+/// Whether PR notifications get approve/merge buttons.
+pub fn pr_actions_enabled() -> bool {
+    let on = env::var("PR_ACTION_BUTTONS")
+        .map(|v| v.trim().eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+    on && client::authenticated()
+}
+
+/// Custom ID of the re-run button for a run, if it fits Discord's limit.
+pub fn rerun_custom_id(repo: &str, run_id: u64) -> Option<String> {
+    let id = format!("{}{}#{}", RERUN_PREFIX, repo, run_id);
+    (id.len() <= MAX_CUSTOM_ID_LEN).then_some(id)
+}
+
+/// Custom ID of a button acting on a PR, if it fits Discord's limit.
+fn pr_custom_id(prefix: &str, repo: &str, number: u64) -> Option<String> {
+    let id = format!("{}{}#{}", prefix, repo, number);
+    (id.len() <= MAX_CUSTOM_ID_LEN).then_some(id)
+}
+
+/// Adds the re-run button with `custom_id` to a message's components.
+pub fn add_rerun_button<'a>(components: &'a mut CreateComponents, custom_id: &str) -> &'a mut CreateComponents {
+    components.create_action_row(|row| {
+        row.create_button(|button| {
+            button
+                .custom_id(custom_id)
+                .label("Re-run failed jobs")
+                .emoji(ReactionType::Unicode("🔁".to_string()))
+                .style(ButtonStyle::Secondary)
+        })
+    })
+}
+
+/// Posts the approve/merge buttons for a PR to `channel_id`.
+pub async fn post_pr_actions(ctx: &Context, channel_id: u64, repo: &str, number: u64, title: &str, url: &str) {
+    let (approve, merge) = match (
+        pr_custom_id(APPROVE_PREFIX, repo, number),
+        pr_custom_id(MERGE_PREFIX, repo, number),
+    ) {
+        (Some(approve), Some(merge)) => (approve, merge),
+        _ => return,
+    };
+
+    let mut buttons = CreateComponents::default();
+    buttons.create_action_row(|row| {
+        row.create_button(|b| {
+            b.custom_id(&approve)
+                .label("Approve")
+                .emoji(ReactionType::Unicode("✅".to_string()))
+                .style(ButtonStyle::Success)
+        })
+        .create_button(|b| {
+            b.custom_id(&merge)
+                .label("Merge when green")
+                .emoji(ReactionType::Unicode("🔀".to_string()))
+                .style(ButtonStyle::Primary)
+        })
+    });
+    let message = Outgoing::text(format!("🛠️ Maintainer actions for [#{} {}](<{}>):", number, title, url))
+        .components(buttons);
+    if let Err(e) = notify::post(ctx, channel_id, notify::priority_for("pull_request"), message).await {
+        eprintln!("Failed to post PR actions for {}#{}: {}", repo, number, e);
+    }
+}
+
+/// Whether `custom_id` belongs to one of the buttons handled here.
+pub fn is_component(custom_id: &str) -> bool {
+    [
+        RERUN_PREFIX,
+        APPROVE_PREFIX,
+        MERGE_PREFIX,
+        APPROVE_CONFIRM_PREFIX,
+        MERGE_CONFIRM_PREFIX,
+        CANCEL_ID,
+    ]
+    .iter()
+    .any(|prefix| custom_id.starts_with(prefix))
+}
+
+/// Handles a press on one of the buttons handled here.
+pub async fn handle_component(ctx: &Context, component: &MessageComponentInteraction) {
+    let id = component.data.custom_id.as_str();
+    if let Some(target) = id.strip_prefix(RERUN_PREFIX) {
+        handle_rerun(ctx, component, target).await;
+    } else if id == CANCEL_ID {
+        update(ctx, component, "Cancelled.".to_string()).await;
+    } else {
+        for action in [PrAction::Approve, PrAction::Merge] {
+            if let Some(target) = id.strip_prefix(action.prefix()) {
+                ask_confirmation(ctx, component, action, target).await;
+            } else if let Some(target) = id.strip_prefix(action.confirm_prefix()) {
+                handle_pr_action(ctx, component, action, target).await;
+            }
+        }
+    }
+}
+
+async fn reply(ctx: &Context, component: &MessageComponentInteraction, content: String, ephemeral: bool) {
+    let _ = component
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|msg| msg.content(content).ephemeral(ephemeral))
+        })
+        .await;
+}
+
+/// Replaces the (ephemeral) message the button is on, removing its buttons.
+async fn update(ctx: &Context, component: &MessageComponentInteraction, content: String) {
+    let _ = component
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|msg| msg.content(content).components(|c| c))
+        })
+        .await;
+}
+
+/// Splits a `<owner/repo>#<number>` target.
+fn parse_target(target: &str) -> Option<(&str, u64)> {
+    target
+        .rsplit_once('#')
+        .and_then(|(repo, id)| Some((repo, id.parse::<u64>().ok()?)))
+}
+
+/// Admins, and members with the repository's dev role, may re-run its workflows.
+fn can_rerun(component: &MessageComponentInteraction, repo: &str) -> bool {
+    if auth::is_admin_member(component.member.as_ref(), component.guild_id) {
+        return true;
+    }
+    let dev_role = match guilds::github_dev_role(repo) {
+        Some(role) => RoleId(role),
+        None => return false,
+    };
+    component.member.as_ref().is_some_and(|m| m.roles.contains(&dev_role))
+}
+
+/// Roles allowed to approve and merge PRs besides admins (`PR_MAINTAINER_ROLE_IDS`).
+pub(crate) fn maintainer_role_ids() -> Vec<RoleId> {
+    env::var("PR_MAINTAINER_ROLE_IDS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|id| id.trim().parse::<u64>().ok())
+        .map(RoleId)
+        .collect()
+}
+
+/// Admins, and members with a maintainer role, may approve and merge `repo`'s PRs, but only in
+/// the operators' guild or the guild that claims `repo`: being an admin elsewhere isn't enough.
+fn is_maintainer(component: &MessageComponentInteraction, repo: &str) -> bool {
+    let guild_id = component.guild_id.map(|id| id.0);
+    let trusted_guild =
+        guild_id.is_some() && (guild_id == guilds::home_guild() || guild_id == guilds::owning_guild(repo));
+    if !trusted_guild {
+        return false;
+    }
+    if auth::is_admin_member(component.member.as_ref(), component.guild_id) {
+        return true;
+    }
+    let maintainer_roles = maintainer_role_ids();
+    component
+        .member
+        .as_ref()
+        .is_some_and(|m| m.roles.iter().any(|role| maintainer_roles.contains(role)))
+}
+
+/// Re-runs the failed jobs of a run through the GitHub API.
+async fn rerun_failed_jobs(repo: &str, run_id: u64) -> Result<(), String> {
+    let path = format!("/repos/{}/actions/runs/{}/rerun-failed-jobs", repo, run_id);
+    client::request_json(Method::POST, &path, None).await.map(|_| ())
+}
+
+async fn handle_rerun(ctx: &Context, component: &MessageComponentInteraction, target: &str) {
+    let (repo, run_id) = match parse_target(target) {
+        Some(parsed) => parsed,
+        None => return,
+    };
+
+    if observer::enabled() {
+        reply(ctx, component, observer::refusal("re-run workflows"), true).await;
+        return;
+    }
+    if !can_rerun(component, repo) {
+        let denied = "⛔ Only admins and the repository's dev role can re-run workflows.";
+        reply(ctx, component, denied.to_string(), true).await;
+        return;
+    }
+
+    match rerun_failed_jobs(repo, run_id).await {
+        Ok(()) => {
+            let by = component.user.tag();
+            println!("{} re-ran failed jobs of run {} in {}", by, run_id, repo);
+            ops_events::record_with_link(
+                EventKind::MaintainerAction,
+                format!("{} re-ran the failed jobs of run {} in {}", by, run_id, repo),
+                Some(format!("https://github.com/{}/actions/runs/{}", repo, run_id)),
+            );
+            reply(
+                ctx,
+                component,
+                format!(
+                    "🔁 <@{}> re-ran the failed jobs of [run {}](<https://github.com/{}/actions/runs/{}>).",
+                    component.user.id.0, run_id, repo, run_id
+                ),
+                false,
+            )
+            .await;
+        }
+        Err(e) => reply(ctx, component, format!("❌ Could not re-run the workflow: {}", e), true).await,
+    }
+}
+
+/// Why `action` can't be taken right now, if it can't.
+fn refusal(component: &MessageComponentInteraction, action: PrAction, repo: &str) -> Option<String> {
+    if !is_maintainer(component, repo) {
+        return Some("⛔ Only admins and maintainers can approve or merge pull requests.".to_string());
+    }
+    if observer::enabled() {
+        return Some(observer::refusal(&format!("{} pull requests", action.verb())));
+    }
+    if action == PrAction::Merge {
+        if let Some(frozen) = freeze::active() {
+            return Some(format!("{}\nMerging is disabled until the freeze is lifted.", frozen.describe()));
+        }
+    }
+    None
+}
+
+/// Answers a press on an approve/merge button with an ephemeral confirmation prompt.
+async fn ask_confirmation(ctx: &Context, component: &MessageComponentInteraction, action: PrAction, target: &str) {
+    let (repo, number) = match parse_target(target) {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    if let Some(refusal) = refusal(component, action, repo) {
+        reply(ctx, component, refusal, true).await;
+        return;
+    }
+    let confirm = match pr_custom_id(action.confirm_prefix(), repo, number) {
+        Some(id) => id,
+        None => return,
+    };
+
+    let question = match action {
+        PrAction::Approve => format!(
+            "Approve **{}#{}**? The review is submitted as the bot's GitHub account, naming you.",
+            repo, number
+        ),
+        PrAction::Merge => format!(
+            "Merge **{}#{}**? It is merged now if its checks passed, otherwise once they do.",
+            repo, number
+        ),
+    };
+    let _ = component
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|msg| {
+                    msg.content(question).ephemeral(true).components(|c| {
+                        c.create_action_row(|row| {
+                            row.create_button(|b| {
+                                b.custom_id(&confirm)
+                                    .label(format!("Yes, {}", action.verb()))
+                                    .style(ButtonStyle::Danger)
+                            })
+                            .create_button(|b| b.custom_id(CANCEL_ID).label("Cancel").style(ButtonStyle::Secondary))
+                        })
+                    })
+                })
+        })
+        .await;
+}
+
+/// Approves a PR, crediting the Discord user in the review body. Returns what was done.
+async fn approve(repo: &str, number: u64, by: &str) -> Result<&'static str, String> {
+    let path = format!("/repos/{}/pulls/{}/reviews", repo, number);
+    let body = json!({ "event": "APPROVE", "body": format!("Approved from Discord by {}.", by) });
+    client::request_json(Method::POST, &path, Some(body)).await?;
+    Ok("approved")
+}
+
+fn merge_method() -> String {
+    env::var("PR_MERGE_METHOD")
+        .map(|v| v.trim().to_lowercase())
+        .ok()
+        .filter(|m| matches!(m.as_str(), "merge" | "squash" | "rebase"))
+        .unwrap_or_else(|| "merge".to_string())
+}
+
+/// Merges a PR whose checks passed, or enables auto-merge so GitHub merges it once they do.
+/// Returns what was done.
+async fn merge_when_green(repo: &str, number: u64) -> Result<&'static str, String> {
+    let path = format!("/repos/{}/pulls/{}", repo, number);
+    let pr: Value = client::get_json(&path).await?;
+    if pr.get("merged").and_then(Value::as_bool).unwrap_or(false) {
+        return Err("it is already merged".to_string());
+    }
+    if pr.get("state").and_then(Value::as_str) != Some("open") {
+        return Err("it is closed".to_string());
+    }
+
+    let method = merge_method();
+    // `clean`: mergeable, with every required check and review passing.
+    if pr.get("mergeable_state").and_then(Value::as_str) == Some("clean") {
+        let sha = pr.pointer("/head/sha").and_then(Value::as_str).unwrap_or_default();
+        let body = json!({ "merge_method": method, "sha": sha });
+        client::request_json(Method::PUT, &format!("{}/merge", path), Some(body)).await?;
+        return Ok("merged");
+    }
+
+    // Auto-merge is only exposed through GraphQL.
+    let node_id = pr.get("node_id").and_then(Value::as_str).ok_or("the PR has no node ID")?;
+    let mutation = "mutation($id: ID!, $method: PullRequestMergeMethod!) { \
+                    enablePullRequestAutoMerge(input: {pullRequestId: $id, mergeMethod: $method}) { clientMutationId } }";
+    client::graphql(mutation, json!({ "id": node_id, "method": method.to_uppercase() })).await?;
+    Ok("enabled auto-merge for")
+}
+
+/// Takes a confirmed approve/merge action and announces it in the channel.
+async fn handle_pr_action(ctx: &Context, component: &MessageComponentInteraction, action: PrAction, target: &str) {
+    let (repo, number) = match parse_target(target) {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    // Checked again: roles or the freeze may have changed since the prompt.
+    if let Some(refusal) = refusal(component, action, repo) {
+        update(ctx, component, refusal).await;
+        return;
+    }
+
+    let by = component.user.tag();
+    let result = match action {
+        PrAction::Approve => approve(repo, number, &by).await,
+        PrAction::Merge => merge_when_green(repo, number).await,
+    };
+    println!(
+        "Audit: {} ({}) asked to {} {}#{}: {:?}",
+        by,
+        component.user.id.0,
+        action.verb(),
+        repo,
+        number,
+        result
+    );
+    if let Ok(done) = &result {
+        ops_events::record_with_link(
+            EventKind::MaintainerAction,
+            format!("{} {} {}#{}", by, done, repo, number),
+            Some(format!("https://github.com/{}/pull/{}", repo, number)),
+        );
+    }
+
+    match result {
+        Ok(done) => {
+            update(ctx, component, format!("✅ Done, {} {}#{}.", done, repo, number)).await;
+            let announcement = format!(
+                "🛠️ <@{}> {} [{}#{}](<https://github.com/{}/pull/{}>) from Discord.",
+                component.user.id.0, done, repo, number, repo, number
+            );
+            if let Delivery::Failed(e) =
+                notify::post_text(ctx, component.channel_id.0, Priority::Normal, announcement).await
+            {
+                eprintln!("Failed to announce PR action: {}", e);
+            }
+        }
+        Err(e) => update(ctx, component, format!("❌ Could not {} {}#{}: {}", action.verb(), repo, number, e)).await,
+    }
+}
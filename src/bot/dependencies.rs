@@ -0,0 +1,213 @@
+//! Reachability of external dependencies (GitHub, Discord, LDAP, SMTP, registries, ...).
+//!
+//! On an interval, each configured dependency is resolved through DNS and then reached over
+//! HTTP(S) or plain TCP. The latest results are shown on the status dashboard
+//! (`/status section:dependencies`), so during an incident it is clear at a glance whether the
+//! problem is on our side or theirs.
+//!
+//! Environment Variables:
+//! - `STATUS_DEPENDENCIES`: Comma-separated `name=target` pairs; the checks are disabled when
+//!   unset. A target is an `http(s)://` URL (any HTTP response counts as reachable), a
+//!   `host:port` reached over TCP, or a bare host that only needs to resolve.
+//! - `DEPENDENCY_CHECK_INTERVAL_SECS`: How often to check (default: 60)
+
+use chrono::{Local, TimeZone};
+use once_cell::sync::Lazy;
+use std::{
+    env,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::{
+    net::{lookup_host, TcpStream},
+    time::{sleep, timeout},
+};
+
+use crate::tasks::Tasks;
+
+const DEFAULT_INTERVAL_SECS: u64 = 60;
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Results of the most recent round of checks, shown on the status dashboard.
+static LATEST: Lazy<Mutex<Option<Round>>> = Lazy::new(|| Mutex::new(None));
+
+/// How a dependency is reached.
+#[derive(Debug, Clone)]
+enum Target {
+    Http(String),
+    Tcp(String, u16),
+    Dns(String),
+}
+
+#[derive(Debug, Clone)]
+struct Dependency {
+    name: String,
+    target: Target,
+}
+
+/// One round of checks.
+#[derive(Debug, Clone)]
+struct Round {
+    /// Unix timestamp the round finished at.
+    at: i64,
+    results: Vec<(String, Result<String, String>)>,
+}
+
+impl Target {
+    fn parse(target: &str) -> Option<Self> {
+        let target = target.trim();
+        if target.starts_with("http://") || target.starts_with("https://") {
+            return Some(Target::Http(target.to_string()));
+        }
+        let target = target.strip_prefix("tcp://").unwrap_or(target);
+        match target.rsplit_once(':') {
+            Some((host, port)) => Some(Target::Tcp(host.to_string(), port.parse().ok()?)),
+            None if !target.is_empty() => Some(Target::Dns(target.to_string())),
+            None => None,
+        }
+    }
+
+    /// Host and port to resolve.
+    fn address(&self) -> Option<(String, u16)> {
+        match self {
+            Target::Http(url) => {
+                let url = reqwest::Url::parse(url).ok()?;
+                Some((url.host_str()?.to_string(), url.port_or_known_default()?))
+            }
+            Target::Tcp(host, port) => Some((host.clone(), *port)),
+            Target::Dns(host) => Some((host.clone(), 0)),
+        }
+    }
+}
+
+/// Configured dependencies from `STATUS_DEPENDENCIES`.
+fn configured_dependencies() -> Vec<Dependency> {
+    env::var("STATUS_DEPENDENCIES")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|entry| {
+            let (name, target) = entry.split_once('=')?;
+            let target = match Target::parse(target) {
+                Some(target) => target,
+                None => {
+                    eprintln!("Ignoring dependency `{}`: cannot parse target `{}`", name.trim(), target.trim());
+                    return None;
+                }
+            };
+            Some(Dependency { name: name.trim().to_string(), target })
+        })
+        .collect()
+}
+
+/// Checks one dependency, returning a short description of the result either way.
+async fn check(target: &Target) -> Result<String, String> {
+    let (host, port) = target.address().ok_or("invalid target")?;
+
+    let started = Instant::now();
+    let mut addrs = match timeout(CHECK_TIMEOUT, lookup_host((host.as_str(), port))).await {
+        Ok(Ok(addrs)) => addrs,
+        Ok(Err(e)) => return Err(format!("DNS lookup failed ({})", e)),
+        Err(_) => return Err("DNS lookup timed out".to_string()),
+    };
+    let addr = addrs.next().ok_or("DNS lookup returned no addresses")?;
+    let dns = started.elapsed();
+
+    match target {
+        Target::Dns(_) => Ok(format!("resolves to {} in {} ms", addr.ip(), dns.as_millis())),
+        Target::Tcp(..) => match timeout(CHECK_TIMEOUT, TcpStream::connect(addr)).await {
+            Ok(Ok(_)) => Ok(format!("{} ms (TCP)", started.elapsed().as_millis())),
+            Ok(Err(e)) => Err(format!("connection failed ({})", e)),
+            Err(_) => Err("connection timed out".to_string()),
+        },
+        Target::Http(url) => {
+            let response = crate::http::client().get(url).timeout(CHECK_TIMEOUT).send().await;
+            match response {
+                Ok(res) => Ok(format!("{} ms (HTTP {})", started.elapsed().as_millis(), res.status().as_u16())),
+                Err(e) if e.is_timeout() => Err("request timed out".to_string()),
+                Err(e) => Err(format!("request failed ({})", e)),
+            }
+        }
+    }
+}
+
+/// Checks every dependency concurrently.
+async fn check_all(dependencies: &[Dependency]) -> Round {
+    let handles: Vec<_> = dependencies
+        .iter()
+        .map(|dep| {
+            let target = dep.target.clone();
+            tokio::spawn(async move { check(&target).await })
+        })
+        .collect();
+
+    let mut results = Vec::new();
+    for (dep, handle) in dependencies.iter().zip(handles) {
+        let result = handle.await.unwrap_or_else(|e| Err(format!("check panicked ({})", e)));
+        results.push((dep.name.clone(), result));
+    }
+    Round { at: Local::now().timestamp(), results }
+}
+
+/// Checks every configured dependency right away (for `/selftest`).
+pub async fn check_now() -> Vec<(String, Result<String, String>)> {
+    check_all(&configured_dependencies()).await.results
+}
+
+/// The latest results as a dashboard section, or `None` if no dependencies are configured.
+pub fn dependencies_section() -> Option<String> {
+    if configured_dependencies().is_empty() {
+        return None;
+    }
+    let round = match LATEST.lock().unwrap().clone() {
+        Some(round) => round,
+        None => return Some("Dependencies:\n(waiting for the first check)".to_string()),
+    };
+
+    let at = Local
+        .timestamp_opt(round.at, 0)
+        .single()
+        .map(|t| t.format("%H:%M:%S").to_string())
+        .unwrap_or_default();
+    let lines = round
+        .results
+        .iter()
+        .map(|(name, result)| match result {
+            Ok(detail) => format!("- ✅ {}: {}", name, detail),
+            Err(problem) => format!("- ❌ {}: {}", name, problem),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Some(format!("Dependencies (checked {}):\n{}", at, lines))
+}
+
+/// Spawns the background task that checks dependencies, if any are configured.
+pub async fn start_dependency_check_loop(tasks: &Tasks) {
+    if configured_dependencies().is_empty() {
+        return;
+    }
+    if tasks.is_running("dependency_checks") {
+        println!("Dependency check loop already running, reusing it.");
+        return;
+    }
+
+    let interval = env::var("DEPENDENCY_CHECK_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+
+    tasks.spawn("dependency_checks", move |beat| async move {
+        loop {
+            beat.tick();
+            let round = check_all(&configured_dependencies()).await;
+            for (name, result) in &round.results {
+                if let Err(problem) = result {
+                    eprintln!("Dependency `{}` unreachable: {}", name, problem);
+                }
+            }
+            *LATEST.lock().unwrap() = Some(round);
+
+            sleep(Duration::from_secs(interval)).await;
+        }
+    });
+}
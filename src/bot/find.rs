@@ -0,0 +1,177 @@
+//! `/find`: fuzzy search across what the bot knows.
+//!
+//! Searches the operational journal (deployments, incidents, alerts, CI results and maintainer
+//! actions, see [`crate::ops_events`]) and the tracked PR threads, and lists the best matches
+//! with links: PR threads are linked directly, journal entries link to their details when one
+//! was recorded (e.g. a deployment's logs). Every word of the query has to match, either as written
+//! or with its letters in order (`dply api` finds `Deployment ... api`), so a rough recollection
+//! is enough. Results can be narrowed to one source with the `in` option.
+
+use serenity::{
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    prelude::*,
+};
+
+use super::command_spec::{CommandSpec, OptionSpec};
+use super::options::{reply_error, OptionError, Options};
+use super::paginator::Paginator;
+use crate::github::threads;
+use crate::ops_events::{self, EventKind};
+
+/// Most matches listed.
+const MAX_RESULTS: usize = 50;
+const MIN_QUERY_CHARS: usize = 2;
+/// How far apart the letters of a query word may be spread, relative to its length.
+const MAX_SPREAD: usize = 3;
+
+/// Sources selectable with the `in` option, as `(value, label)`.
+const SOURCES: &[(&str, &str)] = &[
+    ("deployments", "Deployments"),
+    ("incidents", "Incidents"),
+    ("alerts", "Alerts"),
+    ("ci", "CI results"),
+    ("actions", "Maintainer actions"),
+    ("prs", "PR threads"),
+];
+
+struct Hit {
+    score: u32,
+    /// When the entry happened, if known; newer entries win ties.
+    at: Option<i64>,
+    line: String,
+}
+
+/// The `in` value, emoji and label of a journal entry of `kind`.
+fn describe_kind(kind: EventKind) -> (&'static str, &'static str, &'static str) {
+    match kind {
+        EventKind::Deployment => ("deployments", "🚀", "Deployment"),
+        EventKind::Incident => ("incidents", "🔥", "Incident"),
+        EventKind::Alert => ("alerts", "🚨", "Alert"),
+        EventKind::CiSuccess => ("ci", "✅", "CI passed"),
+        EventKind::CiFailure => ("ci", "❌", "CI failed"),
+        EventKind::MaintainerAction => ("actions", "🛠️", "Maintainer action"),
+    }
+}
+
+/// How well `word` matches `haystack` (both lowercase), if at all. Found as written scores
+/// highest, more so at the start of a word; otherwise the tighter its letters, the better.
+fn word_score(word: &str, haystack: &str) -> Option<u32> {
+    if let Some(pos) = haystack.find(word) {
+        let at_word_start = haystack[..pos].chars().last().is_none_or(|c| !c.is_alphanumeric());
+        return Some(if at_word_start { 30 } else { 20 });
+    }
+
+    let word: Vec<char> = word.chars().collect();
+    let text: Vec<char> = haystack.chars().collect();
+    let shortest = (0..text.len())
+        .filter(|&start| text[start] == word[0])
+        .filter_map(|start| {
+            let mut matched = 0;
+            for (i, c) in text[start..].iter().take(word.len() * MAX_SPREAD).enumerate() {
+                if *c == word[matched] {
+                    matched += 1;
+                    if matched == word.len() {
+                        return Some(i + 1);
+                    }
+                }
+            }
+            None
+        })
+        .min()?;
+    Some((10 * word.len() / shortest) as u32)
+}
+
+/// How well `haystack` matches the query, or `None` unless every word of it matches.
+fn match_score(words: &[String], haystack: &str) -> Option<u32> {
+    let haystack = haystack.to_lowercase();
+    words.iter().map(|word| word_score(word, &haystack)).sum()
+}
+
+async fn search(words: &[String], source: Option<&str>) -> Vec<Hit> {
+    let mut hits = Vec::new();
+
+    for event in ops_events::all() {
+        let (key, emoji, label) = describe_kind(event.kind);
+        if source.is_some_and(|s| s != key) {
+            continue;
+        }
+        let Some(score) = match_score(words, &format!("{} {}", label, event.summary)) else {
+            continue;
+        };
+        let mut line = format!("{} {} <t:{}:R>: {}", emoji, label, event.timestamp, event.summary);
+        if let Some(link) = &event.link {
+            line.push_str(&format!(" · [details](<{}>)", link));
+        }
+        hits.push(Hit { score, at: Some(event.timestamp), line });
+    }
+
+    if source.is_none_or(|s| s == "prs") {
+        for thread in threads::tracked_threads().await {
+            let Some(score) = match_score(words, &format!("pr {} {}", thread.key, thread.title)) else {
+                continue;
+            };
+            let line = format!(
+                "🧵 {} **{}** {}: <#{}>",
+                thread.state.emoji(),
+                thread.key,
+                thread.title,
+                thread.thread_id
+            );
+            hits.push(Hit { score, at: None, line });
+        }
+    }
+
+    hits.sort_by(|a, b| b.score.cmp(&a.score).then(b.at.cmp(&a.at)));
+    hits
+}
+
+pub async fn register_find_command(ctx: &Context) {
+    let mut source = OptionSpec::string("in", "Only search one kind of entry");
+    for (value, label) in SOURCES {
+        source = source.choice(label, value);
+    }
+
+    CommandSpec::new("find", "Search deployments, incidents, alerts, CI results and PR threads")
+        .option(OptionSpec::string("query", "What to look for; rough spellings match too").required())
+        .option(source)
+        .register(ctx)
+        .await;
+}
+
+/// Slash command handler for `/find`.
+pub async fn handle_find(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let options = Options::of(command);
+    let query = match options.required_str("query") {
+        Ok(query) => query.trim(),
+        Err(e) => return reply_error(ctx, command, &e).await,
+    };
+    if query.chars().count() < MIN_QUERY_CHARS {
+        let error = OptionError::invalid("query", format!("Use at least {} characters.", MIN_QUERY_CHARS));
+        return reply_error(ctx, command, &error).await;
+    }
+
+    let words: Vec<String> = query.to_lowercase().split_whitespace().map(str::to_string).collect();
+    let hits = search(&words, options.trimmed("in")).await;
+    if hits.is_empty() {
+        let _ = command
+            .create_interaction_response(&ctx.http, |res| {
+                res.interaction_response_data(|msg| {
+                    msg.content(format!("🔍 Nothing matches `{}`.", query)).ephemeral(true)
+                })
+            })
+            .await;
+        return;
+    }
+
+    let total = hits.len();
+    let lines = hits.into_iter().take(MAX_RESULTS).map(|hit| hit.line).collect();
+    let mut paginator =
+        Paginator::new(format!("🔍 **{} match(es) for `{}`:**", total, query), lines).ephemeral(true);
+    if total > MAX_RESULTS {
+        paginator = paginator.footer(format!(
+            "Showing the best {} matches. Add words to the query to narrow it down.",
+            MAX_RESULTS
+        ));
+    }
+    paginator.send(ctx, command).await;
+}
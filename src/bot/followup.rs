@@ -0,0 +1,116 @@
+//! Deferred interaction responses that survive token expiry.
+//!
+//! Interaction tokens are only valid for 15 minutes, so a long job (migrations, fresh
+//! builds, large purges) that finishes later can no longer edit its deferred response.
+//! [`Followup`] edits the original response while the token is fresh and otherwise posts
+//! the result as a normal message: a reply in the invoking channel with a jump link to
+//! the original invocation, or a DM for ephemeral invocations. Results are never lost.
+
+use serenity::{
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    model::application::interaction::InteractionResponseType,
+    model::prelude::*,
+    prelude::*,
+};
+use std::time::{Duration, Instant};
+
+/// Interaction tokens expire after 15 minutes; stop trying to use them a minute early.
+const TOKEN_LIFETIME: Duration = Duration::from_secs(14 * 60);
+
+/// A deferred response to a slash command, to be completed with [`Followup::finish`].
+pub struct Followup {
+    started: Instant,
+    ephemeral: bool,
+    /// The deferred response message, for replying to and linking once the token expires.
+    response: Option<Message>,
+}
+
+impl Followup {
+    /// Defers the response ("Bot is thinking…") and remembers when the token was issued.
+    pub async fn defer(ctx: &Context, command: &ApplicationCommandInteraction, ephemeral: bool) -> Self {
+        let started = Instant::now();
+
+        let _ = command
+            .create_interaction_response(&ctx.http, |res| {
+                res.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                    .interaction_response_data(|msg| msg.ephemeral(ephemeral))
+            })
+            .await;
+
+        // Ephemeral responses can't be replied to or linked, so there is nothing to look up.
+        let response = if ephemeral {
+            None
+        } else {
+            command.get_interaction_response(&ctx.http).await.ok()
+        };
+
+        Self { started, ephemeral, response }
+    }
+
+    /// Completes the response with `content`, falling back to a normal message if the
+    /// interaction token has expired or the edit fails.
+    pub async fn finish(&self, ctx: &Context, command: &ApplicationCommandInteraction, content: String) {
+        if self.started.elapsed() < TOKEN_LIFETIME {
+            match command
+                .edit_original_interaction_response(&ctx.http, |res| res.content(&content))
+                .await
+            {
+                Ok(_) => return,
+                Err(e) => eprintln!("Failed to edit response to /{}: {e:?}", command.data.name),
+            }
+        }
+
+        if let Err(e) = self.post_fallback(ctx, command, &content).await {
+            eprintln!("Failed to deliver result of /{}: {e:?}", command.data.name);
+        }
+    }
+
+    async fn post_fallback(
+        &self,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        content: &str,
+    ) -> serenity::Result<()> {
+        let name = &command.data.name;
+
+        if self.ephemeral {
+            let dm = command.user.create_dm_channel(&ctx.http).await?;
+            dm.send_message(&ctx.http, |m| {
+                m.content(format!(
+                    "Result of your `/{}` in <#{}>:\n{}",
+                    name, command.channel_id.0, content
+                ))
+            })
+            .await?;
+            return Ok(());
+        }
+
+        let link = self
+            .response
+            .as_ref()
+            .map(|r| format!(" ({})", jump_link(command.guild_id, r.channel_id, r.id)))
+            .unwrap_or_default();
+
+        command
+            .channel_id
+            .send_message(&ctx.http, |m| {
+                m.content(format!(
+                    "<@{}> result of `/{}`{}:\n{}",
+                    command.user.id.0, name, link, content
+                ));
+                if let Some(response) = &self.response {
+                    m.reference_message(response);
+                }
+                m
+            })
+            .await?;
+        Ok(())
+    }
+}
+
+fn jump_link(guild_id: Option<GuildId>, channel_id: ChannelId, message_id: MessageId) -> String {
+    let guild = guild_id
+        .map(|g| g.0.to_string())
+        .unwrap_or_else(|| "@me".to_string());
+    format!("https://discord.com/channels/{}/{}/{}", guild, channel_id.0, message_id.0)
+}
@@ -0,0 +1,88 @@
+//! `/freeze` and `/unfreeze`: toggle the deploy freeze (see [`crate::freeze`]).
+//!
+//! Both are admin-only. The freeze is announced in the channel the command was run in, so
+//! everyone sees why deploys are refusing to run.
+
+use chrono::Utc;
+use serenity::{
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    prelude::*,
+};
+
+use super::command_spec::{CommandSpec, OptionSpec};
+use super::options::{reply_error, OptionError, Options};
+use crate::duration::{format_duration, parse_duration};
+use crate::freeze::{self, Freeze};
+use crate::ops_events::{self, EventKind};
+
+/// Registers `/freeze` and `/unfreeze`.
+pub async fn register_freeze_commands(ctx: &Context) {
+    CommandSpec::new("freeze", "Freeze deploys, e.g. during an assessment period")
+        .option(OptionSpec::string("reason", "Why deploys are frozen").required())
+        .option(OptionSpec::string("duration", "How long the freeze lasts, e.g. 6h, 3d (default: until /unfreeze)"))
+        .register(ctx)
+        .await;
+    CommandSpec::new("unfreeze", "Lift the deploy freeze").register(ctx).await;
+}
+
+/// Slash command handler for `/freeze`.
+pub async fn handle_freeze(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let options = Options::of(command);
+    let reason = match options.required_str("reason") {
+        Ok(reason) => reason.trim().to_string(),
+        Err(e) => return reply_error(ctx, command, &e).await,
+    };
+    let duration = match options.trimmed("duration") {
+        Some(input) => match parse_duration(input).filter(|d| d.as_secs() > 0) {
+            Some(duration) => Some(duration),
+            None => {
+                let err = OptionError::invalid("duration", "Try a duration like 6h or 3d.");
+                return reply_error(ctx, command, &err).await;
+            }
+        },
+        None => None,
+    };
+
+    let now = Utc::now().timestamp();
+    let frozen = Freeze {
+        reason,
+        set_by: command.user.name.clone(),
+        set_at: now,
+        expires_at: duration.map(|d| now + d.as_secs() as i64),
+    };
+    let content = match duration {
+        Some(d) => format!("{} ({})", frozen.describe(), format_duration(d)),
+        None => format!("{}\nRun `/unfreeze` to lift it.", frozen.describe()),
+    };
+    ops_events::record(
+        EventKind::Alert,
+        format!("Deploys frozen by {}: {}", frozen.set_by, frozen.reason),
+    );
+    freeze::set(frozen);
+
+    reply(ctx, command, content).await;
+}
+
+/// Slash command handler for `/unfreeze`.
+pub async fn handle_unfreeze(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let content = match freeze::clear() {
+        Some(lifted) => {
+            ops_events::record(
+                EventKind::Alert,
+                format!("Deploy freeze lifted by {} ({})", command.user.name, lifted.reason),
+            );
+            format!("🔥 Deploy freeze lifted by `{}` (was: {}).", command.user.name, lifted.reason)
+        }
+        None => "Deploys are not frozen.".to_string(),
+    };
+
+    reply(ctx, command, content).await;
+}
+
+async fn reply(ctx: &Context, command: &ApplicationCommandInteraction, content: String) {
+    let _ = command
+        .create_interaction_response(&ctx.http, |res| {
+            res.interaction_response_data(|msg| msg.content(content))
+        })
+        .await;
+}
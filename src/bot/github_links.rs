@@ -0,0 +1,94 @@
+//! Slash commands for linking GitHub usernames to Discord accounts.
+//!
+//! - `/link_github <username>`: Link your GitHub username so PR reviews and `@mentions` ping you
+//! - `/unlink_github`: Remove your links
+//!
+//! A username already linked to someone else can only be reassigned by an admin.
+
+use serenity::{
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    prelude::*,
+};
+
+use super::auth;
+use super::command_spec::{CommandSpec, OptionSpec};
+use super::options::{self, FromOptions, OptionError, Options};
+use crate::github::mentions;
+
+/// Registers `/link_github` and `/unlink_github`.
+pub async fn register_github_link_commands(ctx: &Context) {
+    CommandSpec::new("link_github", "Link your GitHub username so GitHub notifications mention you")
+        .option(OptionSpec::string("username", "Your GitHub username").required())
+        .register(ctx)
+        .await;
+
+    CommandSpec::new("unlink_github", "Remove the GitHub usernames linked to your Discord account")
+        .register(ctx)
+        .await;
+}
+
+/// Arguments of `/link_github`.
+struct LinkArgs {
+    login: String,
+}
+
+impl FromOptions for LinkArgs {
+    fn from_options(options: Options<'_>) -> Result<Self, OptionError> {
+        let login = options.required_str("username")?.trim_start_matches('@');
+        if !mentions::is_valid_login(login) {
+            return Err(OptionError::invalid(
+                "username",
+                format!("`{}` is not a valid GitHub username.", login),
+            ));
+        }
+        Ok(Self { login: login.to_string() })
+    }
+}
+
+/// Slash command handler for `/link_github`.
+pub async fn handle_link_github(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let login = match options::parse::<LinkArgs>(ctx, command).await {
+        Some(args) => args.login,
+        None => return,
+    };
+    let login = login.as_str();
+    let user_id = command.user.id.0;
+
+    let content = match mentions::linked_user(login) {
+        Some(id) if id == user_id => format!("ℹ️ `{}` is already linked to you.", login),
+        Some(id) if !auth::is_admin(command) => format!(
+            "⛔ `{}` is already linked to <@{}>. Ask an admin to reassign it.",
+            login, id
+        ),
+        _ => {
+            mentions::link(login, user_id);
+            format!("✅ Linked GitHub user `{}` to <@{}>.", login, user_id)
+        }
+    };
+
+    reply(ctx, command, content).await;
+}
+
+/// Slash command handler for `/unlink_github`.
+pub async fn handle_unlink_github(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let removed = mentions::unlink(command.user.id.0);
+
+    let content = if removed.is_empty() {
+        "ℹ️ No GitHub username is linked to your account.".to_string()
+    } else {
+        format!(
+            "✅ Unlinked {}.",
+            removed.iter().map(|l| format!("`{}`", l)).collect::<Vec<_>>().join(", ")
+        )
+    };
+
+    reply(ctx, command, content).await;
+}
+
+async fn reply(ctx: &Context, command: &ApplicationCommandInteraction, content: String) {
+    let _ = command
+        .create_interaction_response(&ctx.http, |res| {
+            res.interaction_response_data(|msg| msg.content(content).ephemeral(true))
+        })
+        .await;
+}
@@ -0,0 +1,201 @@
+//! Slash command for viewing and editing this guild's configuration.
+//!
+//! `/guild-config` edits the guild's record in `guilds.json` (see [`crate::guilds`]):
+//! - `show`: Print the current settings
+//! - `channel <kind> [channel]`: Set (or clear) a notification channel
+//! - `dev-role [role]`: Set (or clear) the role mentioned in PR and CI failure notifications
+//! - `repos [patterns]`: GitHub repositories routed to this guild (`owner/name` or `owner/*`);
+//!   outside the operators' guild, only those in `DISCORD_CLAIMABLE_REPOS`
+//! - `allowlist <files|watch> [aliases]`: The aliases this guild may use (omit to allow none,
+//!   or every alias in the operators' guild)
+//!
+//! All subcommands except `show` are admin-only.
+
+use serenity::{
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    prelude::*,
+};
+
+use super::command_spec::{CommandSpec, OptionSpec};
+use super::options::Options;
+use crate::guilds::{self, ChannelKind, GuildConfig, GuildConfigs};
+
+/// Registers `/guild-config show|channel|dev-role|repos|allowlist`.
+pub async fn register_guild_config_command(ctx: &Context) {
+    let mut kind = OptionSpec::string("kind", "Which notifications").required();
+    for channel_kind in ChannelKind::ALL {
+        kind = kind.choice(channel_kind.key(), channel_kind.key());
+    }
+
+    CommandSpec::new("guild-config", "View or change this server's bot configuration")
+        .option(OptionSpec::sub("show", "Show this server's configuration"))
+        .option(
+            OptionSpec::sub("channel", "Set or clear a notification channel")
+                .option(kind)
+                .option(OptionSpec::channel("channel", "Channel to use (omit to clear)")),
+        )
+        .option(
+            OptionSpec::sub("dev-role", "Set or clear the role mentioned in PR and CI failure notifications")
+                .option(OptionSpec::role("role", "Role to mention (omit to clear)")),
+        )
+        .option(
+            OptionSpec::sub("repos", "GitHub repositories routed to this server").option(OptionSpec::string(
+                "patterns",
+                "Comma-separated owner/name or owner/* (omit to clear)",
+            )),
+        )
+        .option(
+            OptionSpec::sub("allowlist", "Restrict which file or watch aliases this server may use")
+                .option(
+                    OptionSpec::string("list", "Which allowlist")
+                        .required()
+                        .choice("files", "files")
+                        .choice("watch", "watch"),
+                )
+                .option(OptionSpec::string("aliases", "Comma-separated aliases (omit to clear)")),
+        )
+        .register(ctx)
+        .await;
+}
+
+/// Slash command handler for `/guild-config`.
+pub async fn handle_guild_config(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let ((name, sub), guild_id) = match (Options::of(command).subcommand(), command.guild_id) {
+        (Some(sub), Some(guild_id)) => (sub, guild_id.0),
+        _ => return,
+    };
+
+    let content = if name == "show" {
+        let config = guilds::for_guild(guild_id).unwrap_or_default();
+        describe(guild_id, &config)
+    } else {
+        let applied = GuildConfigs::update(|configs| {
+            apply(guild_id, configs.guilds.entry(guild_id).or_default(), name, &sub)
+        });
+        match applied {
+            Some(content) => content,
+            None => return,
+        }
+    };
+
+    let _ = command
+        .create_interaction_response(&ctx.http, |res| {
+            res.interaction_response_data(|msg| msg.content(content).ephemeral(true))
+        })
+        .await;
+}
+
+/// Applies subcommand `name` to `config`, returning the reply, or `None` if it isn't one.
+fn apply(guild_id: u64, config: &mut GuildConfig, name: &str, sub: &Options<'_>) -> Option<String> {
+    let home = Some(guild_id) == guilds::home_guild();
+    let content = match name {
+        "channel" => {
+            let kind = sub.str("kind").and_then(ChannelKind::from_key)?;
+            let channel = sub.id("channel");
+            config.set_channel(kind, channel);
+            match channel {
+                Some(id) => format!("✅ `{}` notifications will be posted in <#{}>.", kind.key(), id),
+                None => format!("✅ Cleared the `{}` channel.", kind.key()),
+            }
+        }
+        "dev-role" => {
+            config.dev_role_id = sub.id("role");
+            match config.dev_role_id {
+                Some(id) => format!("✅ <@&{}> will be mentioned in PR and CI failure notifications.", id),
+                None => "✅ Cleared the dev role.".to_string(),
+            }
+        }
+        "repos" => {
+            let repos = split_list(sub.str("patterns"));
+            let refused: Vec<String> = repos
+                .iter()
+                .filter(|pattern| !home && !guilds::pattern_claimable(pattern))
+                .cloned()
+                .collect();
+            if !refused.is_empty() {
+                return Some(format!(
+                    "❌ {} can't be routed to this server: only repositories in `DISCORD_CLAIMABLE_REPOS` can.",
+                    code_list(&refused)
+                ));
+            }
+            config.repos = repos;
+            if config.repos.is_empty() {
+                "✅ No repositories are routed to this server.".to_string()
+            } else {
+                format!("✅ Routing GitHub events for {} to this server.", code_list(&config.repos))
+            }
+        }
+        "allowlist" => {
+            let aliases = sub.str("aliases").map(|v| split_list(Some(v)));
+            let list = sub.str("list").unwrap_or_default();
+            let message = match &aliases {
+                Some(aliases) => format!("✅ `{}` allowlist restricted to {}.", list, code_list(aliases)),
+                None if home => format!("✅ Every `{}` alias is allowed.", list),
+                None => format!("✅ No `{}` aliases are allowed.", list),
+            };
+            match list {
+                "files" => config.file_aliases = aliases,
+                "watch" => config.watch_aliases = aliases,
+                _ => return None,
+            }
+            message
+        }
+        _ => return None,
+    };
+    Some(content)
+}
+
+fn split_list(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(|v| v.trim().to_lowercase())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+fn code_list(items: &[String]) -> String {
+    if items.is_empty() {
+        return "nothing".to_string();
+    }
+    items.iter().map(|i| format!("`{}`", i)).collect::<Vec<_>>().join(", ")
+}
+
+fn describe(guild_id: u64, config: &GuildConfig) -> String {
+    let mut lines = vec!["**Server configuration**".to_string()];
+
+    for kind in ChannelKind::ALL {
+        let channel = config
+            .channel(*kind)
+            .map(|id| format!("<#{}>", id))
+            .unwrap_or_else(|| "not set".into());
+        lines.push(format!("- `{}` channel: {}", kind.key(), channel));
+    }
+
+    let admins = config
+        .admin_role_ids
+        .iter()
+        .map(|r| format!("<@&{}>", r))
+        .collect::<Vec<_>>()
+        .join(", ");
+    lines.push(format!(
+        "- Admin roles: {}",
+        if admins.is_empty() { "global (`DISCORD_ADMIN_ROLE_ID`)".to_string() } else { admins }
+    ));
+    lines.push(format!(
+        "- Dev role: {}",
+        config.dev_role_id.map(|id| format!("<@&{}>", id)).unwrap_or_else(|| "not set".into())
+    ));
+    lines.push(format!("- Repositories: {}", code_list(&config.repos)));
+
+    let home = Some(guild_id) == guilds::home_guild();
+    let allowlist = |aliases: &Option<Vec<String>>| match aliases {
+        Some(aliases) => code_list(aliases),
+        None if home => "all".to_string(),
+        None => "none".to_string(),
+    };
+    lines.push(format!("- File aliases: {}", allowlist(&config.file_aliases)));
+    lines.push(format!("- Watch aliases: {}", allowlist(&config.watch_aliases)));
+
+    lines.join("\n")
+}
@@ -0,0 +1,48 @@
+//! Optional heartbeat to an external dead-man's-switch monitor (healthchecks.io style).
+//!
+//! The status loop pings the configured URL on every tick. If the bot, or the whole host,
+//! stops running, the pings stop and the external monitor alerts the team through its own
+//! channels, independently of Discord.
+//!
+//! Environment Variables:
+//! - `HEARTBEAT_URL`: Ping URL. Failed ticks are reported to `<HEARTBEAT_URL>/fail`.
+
+use chrono::Local;
+use std::env;
+
+/// Returns the configured heartbeat URL, if any.
+fn heartbeat_url() -> Option<String> {
+    env::var("HEARTBEAT_URL")
+        .ok()
+        .map(|u| u.trim().trim_end_matches('/').to_string())
+        .filter(|u| !u.is_empty())
+}
+
+/// Sends a heartbeat in the background.
+///
+/// `success` reports whether this tick managed to update Discord; failures are sent to the
+/// monitor's `/fail` endpoint so a broken Discord connection is flagged even while the
+/// process itself is still alive.
+pub fn ping(success: bool) {
+    let url = match heartbeat_url() {
+        Some(url) if success => url,
+        Some(url) => format!("{}/fail", url),
+        None => return,
+    };
+
+    let body = format!(
+        "fitchfork-discord-bot {} at {}",
+        if success { "ok" } else { "status update failed" },
+        Local::now().format("%Y-%m-%d %H:%M:%S")
+    );
+
+    tokio::spawn(async move {
+        match crate::http::client().post(&url).body(body).send().await {
+            Ok(res) if !res.status().is_success() => {
+                eprintln!("Heartbeat ping returned {}", res.status());
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Heartbeat ping failed: {e:?}"),
+        }
+    });
+}
@@ -0,0 +1,312 @@
+//! Alerts for kernel events that otherwise go unnoticed until something breaks.
+//!
+//! On an interval, new kernel messages are read from the journal (`journalctl -k`) and
+//! scanned for:
+//! - OOM-killer kills, with the killed process and its memory at the time
+//! - segfaults and traps of watched processes
+//! - filesystem and block device errors (ext4, XFS, Btrfs, I/O errors, read-only remounts)
+//!
+//! The journal cursor is persisted, so messages logged while the bot was down are still
+//! reported after a restart, and nothing is reported twice.
+//!
+//! The bot's user needs to read the kernel journal (e.g. the `systemd-journal` or `adm` group).
+//!
+//! Environment Variables:
+//! - `KERNEL_EVENTS`: Set to `true` to enable the alerts
+//! - `KERNEL_EVENTS_PROCESSES`: Comma-separated process names whose segfaults are reported
+//!   (default: the units in `STATUS_SERVICES`)
+//! - `KERNEL_EVENTS_INTERVAL_SECS`: How often the journal is read (default: 30)
+//! - `KERNEL_EVENTS_CHANNEL_ID`: Where to alert (default: `DISCORD_STATUS_CHANNEL_ID`)
+
+use chrono::Utc;
+use serde_json::Value;
+use serenity::prelude::*;
+use std::{env, process::Command, time::Duration};
+use tokio::time::sleep;
+
+use super::status::configured_services;
+use crate::notify::{self, dedup::{self, Severity}, Priority};
+use crate::ops_events::{self, EventKind};
+use crate::store;
+use crate::tasks::Tasks;
+
+const CURSOR_FILE: &str = "kernel_journal_cursor.json";
+const DEFAULT_INTERVAL_SECS: u64 = 30;
+/// Events of one kind listed per alert; the rest are counted.
+const MAX_LISTED: usize = 5;
+/// The kernel truncates process names (`comm`) to this many characters.
+const COMM_LEN: usize = 15;
+
+/// Kernel messages that indicate filesystem or block device trouble.
+const FS_MARKERS: &[&str] = &[
+    "EXT4-fs error",
+    "BTRFS error",
+    "BTRFS critical",
+    "Buffer I/O error",
+    "I/O error, dev",
+    "journal commit I/O error",
+    "Remounting filesystem read-only",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KernelEventKind {
+    OomKill,
+    Segfault,
+    Filesystem,
+}
+
+impl KernelEventKind {
+    const ALL: [KernelEventKind; 3] = [
+        KernelEventKind::OomKill,
+        KernelEventKind::Segfault,
+        KernelEventKind::Filesystem,
+    ];
+
+    fn heading(self) -> &'static str {
+        match self {
+            KernelEventKind::OomKill => "💀 **OOM killer** killed a process",
+            KernelEventKind::Segfault => "💥 **Watched process crashed**",
+            KernelEventKind::Filesystem => "🗄️ **Filesystem errors** in the kernel log",
+        }
+    }
+
+    fn severity(self) -> Severity {
+        match self {
+            KernelEventKind::Segfault => Severity::Warning,
+            KernelEventKind::OomKill | KernelEventKind::Filesystem => Severity::Critical,
+        }
+    }
+
+    /// Summary recorded in the ops event log.
+    fn summary(self) -> &'static str {
+        match self {
+            KernelEventKind::OomKill => "OOM killer killed a process",
+            KernelEventKind::Segfault => "Watched process crashed",
+            KernelEventKind::Filesystem => "Filesystem errors in the kernel log",
+        }
+    }
+}
+
+/// A kernel message worth alerting on, summarized.
+#[derive(Debug)]
+struct KernelEvent {
+    kind: KernelEventKind,
+    summary: String,
+}
+
+fn enabled() -> bool {
+    env::var("KERNEL_EVENTS")
+        .map(|v| v.trim().eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Process names whose segfaults are reported.
+fn watched_processes() -> Vec<String> {
+    let configured: Vec<String> = env::var("KERNEL_EVENTS_PROCESSES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect();
+    if !configured.is_empty() {
+        return configured;
+    }
+    configured_services()
+        .into_iter()
+        .map(|s| s.trim_end_matches(".service").to_string())
+        .collect()
+}
+
+/// Whether `comm` (possibly truncated by the kernel) is one of `watched`.
+fn is_watched(comm: &str, watched: &[String]) -> bool {
+    watched
+        .iter()
+        .any(|name| name == comm || (comm.chars().count() == COMM_LEN && name.starts_with(comm)))
+}
+
+/// The killed process from an OOM message:
+/// `... Killed process 1234 (java) total-vm:...kB, anon-rss:...kB, ...`.
+fn parse_oom(message: &str) -> Option<String> {
+    let rest = message.split_once("Killed process ")?.1;
+    let (pid, rest) = rest.split_once(" (")?;
+    let (name, rest) = rest.split_once(')')?;
+    let rss = rest
+        .split(',')
+        .find_map(|field| field.trim().strip_prefix("anon-rss:"))
+        .and_then(|kb| kb.trim_end_matches("kB").parse::<u64>().ok())
+        .map(|kb| format!(", {} MB resident", kb / 1024))
+        .unwrap_or_default();
+    Some(format!("`{}` (PID {}{})", name, pid, rss))
+}
+
+/// `comm` and `pid` from `comm[pid]: segfault at ...` or `traps: comm[pid] trap ...`.
+fn parse_crash(message: &str) -> Option<(String, String)> {
+    let message = message.strip_prefix("traps: ").unwrap_or(message);
+    let (comm, rest) = message.split_once('[')?;
+    let (pid, _) = rest.split_once(']')?;
+    Some((comm.trim().to_string(), pid.to_string()))
+}
+
+/// Classifies one kernel message.
+fn classify(message: &str, watched: &[String]) -> Option<KernelEvent> {
+    if message.contains("Killed process ") {
+        let summary = parse_oom(message).unwrap_or_else(|| message.to_string());
+        return Some(KernelEvent { kind: KernelEventKind::OomKill, summary });
+    }
+
+    if message.contains("segfault at") || message.starts_with("traps: ") {
+        let (comm, pid) = parse_crash(message)?;
+        if !is_watched(&comm, watched) {
+            return None;
+        }
+        let what = if message.contains("segfault at") { "segfault" } else { "trap" };
+        return Some(KernelEvent {
+            kind: KernelEventKind::Segfault,
+            summary: format!("`{}` (PID {}) {}: `{}`", comm, pid, what, message),
+        });
+    }
+
+    let xfs_error = message.starts_with("XFS (")
+        && ["error", "Corruption", "Shutting down"].iter().any(|m| message.contains(m));
+    if xfs_error || FS_MARKERS.iter().any(|m| message.contains(m)) {
+        return Some(KernelEvent {
+            kind: KernelEventKind::Filesystem,
+            summary: format!("`{}`", message),
+        });
+    }
+    None
+}
+
+/// Kernel messages logged after `cursor` (or since `since`, a unix timestamp, without one),
+/// and the cursor of the last.
+fn read_journal(cursor: Option<&str>, since: i64) -> Result<(Vec<String>, Option<String>), String> {
+    let mut command = Command::new("journalctl");
+    command.args(["-k", "--no-pager", "-o", "json"]);
+    match cursor {
+        Some(cursor) => command.arg(format!("--after-cursor={}", cursor)),
+        None => command.arg(format!("--since=@{}", since)),
+    };
+    let output = command.output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    let mut messages = Vec::new();
+    let mut last_cursor = None;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let entry: Value = match serde_json::from_str(line) {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        if let Some(c) = entry.get("__CURSOR").and_then(Value::as_str) {
+            last_cursor = Some(c.to_string());
+        }
+        // Messages that aren't valid UTF-8 are logged as byte arrays; skip those.
+        if let Some(message) = entry.get("MESSAGE").and_then(Value::as_str) {
+            messages.push(message.trim().to_string());
+        }
+    }
+    Ok((messages, last_cursor))
+}
+
+/// One alert per kind of event found.
+fn alert_messages(events: &[KernelEvent]) -> Vec<(KernelEventKind, String)> {
+    KernelEventKind::ALL
+        .iter()
+        .filter_map(|kind| {
+            let summaries: Vec<&str> = events
+                .iter()
+                .filter(|e| e.kind == *kind)
+                .map(|e| e.summary.as_str())
+                .collect();
+            if summaries.is_empty() {
+                return None;
+            }
+            let mut message = format!("{}{}", dev_mention(), kind.heading());
+            for summary in summaries.iter().take(MAX_LISTED) {
+                message.push_str(&format!("\n- {}", summary));
+            }
+            if summaries.len() > MAX_LISTED {
+                message.push_str(&format!("\n…and {} more", summaries.len() - MAX_LISTED));
+            }
+            Some((*kind, message))
+        })
+        .collect()
+}
+
+/// Posts to the kernel event alert channel.
+async fn alert(ctx: &Context, message: String) {
+    let channel = ["KERNEL_EVENTS_CHANNEL_ID", "DISCORD_STATUS_CHANNEL_ID"]
+        .iter()
+        .find_map(|key| env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok()));
+    let channel = match channel {
+        Some(channel) => channel,
+        None => {
+            eprintln!("Kernel event alert not posted, no channel configured: {}", message);
+            return;
+        }
+    };
+    if let Err(e) = notify::queue::send(ctx.http.clone(), channel, Priority::Critical, message).await {
+        eprintln!("Failed to post kernel event alert: {}", e);
+    }
+}
+
+fn dev_mention() -> String {
+    env::var("DISCORD_DEV_ROLE_ID")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(|id| format!("<@&{}> ", id))
+        .unwrap_or_default()
+}
+
+/// Spawns the background task that watches the kernel log, if enabled.
+pub async fn start_kernel_events_loop(ctx: Context, tasks: &Tasks) {
+    if !enabled() {
+        return;
+    }
+    if tasks.is_running("kernel_events") {
+        println!("Kernel events loop already running, reusing it.");
+        return;
+    }
+
+    let interval = env::var("KERNEL_EVENTS_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+
+    tasks.spawn("kernel_events", move |beat| {
+        let ctx = ctx.clone();
+        async move {
+            // Without a saved cursor, start from now rather than replaying the whole boot.
+            let started = Utc::now().timestamp();
+
+            loop {
+                beat.tick();
+                let cursor: Option<String> = store::load(CURSOR_FILE);
+                let read = tokio::task::spawn_blocking(move || read_journal(cursor.as_deref(), started)).await;
+
+                match read {
+                    Ok(Ok((messages, last_cursor))) => {
+                        if let Some(last_cursor) = last_cursor {
+                            store::save(CURSOR_FILE, &Some(last_cursor));
+                        }
+                        let watched = watched_processes();
+                        let events: Vec<KernelEvent> =
+                            messages.iter().filter_map(|m| classify(m, &watched)).collect();
+
+                        for (kind, message) in alert_messages(&events) {
+                            ops_events::record(EventKind::Alert, kind.summary().to_string());
+                            if dedup::event(&format!("kernel: {}", kind.summary()), kind.severity()) {
+                                alert(&ctx, message).await;
+                            }
+                        }
+                    }
+                    Ok(Err(e)) => eprintln!("Failed to read the kernel journal: {}", e),
+                    Err(e) => eprintln!("Kernel journal reader panicked: {}", e),
+                }
+
+                sleep(Duration::from_secs(interval)).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,249 @@
+//! Memory leak detection for the services in `STATUS_SERVICES`.
+//!
+//! On an interval, the resident memory (RSS) of each service's main process is read from
+//! procfs. When a service's memory has only grown over the whole window, faster than the
+//! configured slope, the dev role is told to restart it before the OOM killer picks a victim.
+//! A restart (new main PID) starts the service's history over.
+//!
+//! Environment Variables:
+//! - `MEMORY_LEAK_SLOPE_MB_PER_HOUR`: Growth rate that counts as a leak (no detection without it)
+//! - `MEMORY_LEAK_WINDOW_HOURS`: How long growth must be sustained (default: 6)
+//! - `MEMORY_LEAK_INTERVAL_SECS`: How often RSS is sampled (default: 300)
+//! - `MEMORY_LEAK_CHANNEL_ID`: Where to alert (default: `DISCORD_STATUS_CHANNEL_ID`)
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serenity::prelude::*;
+use std::{collections::BTreeMap, env, fs, process::Command, time::Duration};
+use sysinfo::{System, SystemExt};
+use tokio::time::sleep;
+
+use super::status::configured_services;
+use crate::duration::format_duration;
+use crate::notify::{self, dedup::{self, Severity}, Priority};
+use crate::ops_events::{self, EventKind};
+use crate::store;
+use crate::tasks::Tasks;
+
+const HISTORY_FILE: &str = "service_rss.json";
+const DEFAULT_WINDOW_HOURS: f64 = 6.0;
+const DEFAULT_INTERVAL_SECS: u64 = 300;
+/// A trend needs this many samples before it is judged.
+const MIN_SAMPLES: usize = 6;
+/// Dips smaller than this (allocator noise) don't break a monotonic trend.
+const NOISE_KB: u64 = 1024;
+
+/// RSS history of one service's current main process.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ServiceHistory {
+    pid: u32,
+    /// `(unix timestamp, RSS in KiB)`, oldest first.
+    samples: Vec<(i64, u64)>,
+    /// Whether this process has already been reported.
+    #[serde(default)]
+    alerted: bool,
+}
+
+/// A detected leak.
+struct Leak {
+    mb_per_hour: f64,
+    rss_mb: f64,
+    /// How long until available memory runs out at this rate.
+    until_oom: Option<Duration>,
+}
+
+/// Main PID of a systemd unit, if it is running.
+fn main_pid(service: &str) -> Option<u32> {
+    let output = Command::new("systemctl")
+        .args(["show", "--property", "MainPID", "--value", service])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .ok()
+        .filter(|pid| *pid != 0)
+}
+
+/// Resident memory of `pid` in KiB (`VmRSS` in `/proc/<pid>/status`).
+fn rss_kb(pid: u32) -> Option<u64> {
+    let status = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+}
+
+/// Whether `history` shows sustained growth over the window, and how fast.
+fn detect_leak(history: &ServiceHistory, window_secs: i64, slope_mb_per_hour: f64) -> Option<Leak> {
+    let samples = &history.samples;
+    if samples.len() < MIN_SAMPLES {
+        return None;
+    }
+    let (first_at, first_kb) = samples[0];
+    let (last_at, last_kb) = samples[samples.len() - 1];
+    // The process must have been watched for (nearly) the whole window.
+    if (last_at - first_at) * 10 < window_secs * 9 {
+        return None;
+    }
+    if samples.windows(2).any(|w| w[1].1 + NOISE_KB < w[0].1) {
+        return None;
+    }
+
+    let hours = (last_at - first_at) as f64 / 3600.0;
+    let mb_per_hour = last_kb.saturating_sub(first_kb) as f64 / 1024.0 / hours;
+    if mb_per_hour < slope_mb_per_hour {
+        return None;
+    }
+
+    let mut sys = System::new();
+    sys.refresh_memory();
+    let available_mb = sys.available_memory() as f64 / 1024.0 / 1024.0;
+    let until_oom = (available_mb > 0.0).then(|| Duration::from_secs_f64(available_mb / mb_per_hour * 3600.0));
+
+    Some(Leak {
+        mb_per_hour,
+        rss_mb: last_kb as f64 / 1024.0,
+        until_oom,
+    })
+}
+
+/// Samples every service and returns the leaks found since the last alert.
+fn check_services(window_secs: i64, slope_mb_per_hour: f64) -> Vec<(String, Leak)> {
+    let mut histories: BTreeMap<String, ServiceHistory> = store::load(HISTORY_FILE);
+    let services = configured_services();
+    histories.retain(|service, _| services.contains(service));
+
+    let now = Utc::now().timestamp();
+    let mut leaks = Vec::new();
+    for service in services {
+        let sample = main_pid(&service).and_then(|pid| rss_kb(pid).map(|kb| (pid, kb)));
+        let (pid, kb) = match sample {
+            Some(sample) => sample,
+            None => {
+                histories.remove(&service);
+                continue;
+            }
+        };
+
+        let history = histories.entry(service.clone()).or_default();
+        if history.pid != pid {
+            *history = ServiceHistory { pid, ..Default::default() };
+        }
+        history.samples.push((now, kb));
+        history.samples.retain(|(at, _)| now - at <= window_secs);
+
+        if history.alerted {
+            continue;
+        }
+        if let Some(leak) = detect_leak(history, window_secs, slope_mb_per_hour) {
+            history.alerted = true;
+            leaks.push((service, leak));
+        }
+    }
+
+    store::save(HISTORY_FILE, &histories);
+    leaks
+}
+
+fn leak_message(service: &str, leak: &Leak, window_hours: f64) -> String {
+    let mut message = format!(
+        "{}🧠 **Possible memory leak in `{}`:** RSS has grown steadily for {}h at ~{:.0} MB/h (now {:.0} MB).",
+        dev_mention(),
+        service,
+        window_hours,
+        leak.mb_per_hour,
+        leak.rss_mb
+    );
+    if let Some(until_oom) = leak.until_oom {
+        message.push_str(&format!(
+            "\nAvailable memory runs out in ~{} at this rate.",
+            format_duration(until_oom)
+        ));
+    }
+    message.push_str(&format!(
+        "\nConsider `/restart service:{}` before the OOM killer steps in.",
+        service
+    ));
+    message
+}
+
+/// Posts to the leak alert channel.
+async fn alert(ctx: &Context, message: String) {
+    let channel = ["MEMORY_LEAK_CHANNEL_ID", "DISCORD_STATUS_CHANNEL_ID"]
+        .iter()
+        .find_map(|key| env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok()));
+    let channel = match channel {
+        Some(channel) => channel,
+        None => {
+            eprintln!("Memory leak alert not posted, no channel configured: {}", message);
+            return;
+        }
+    };
+    if let Err(e) = notify::queue::send(ctx.http.clone(), channel, Priority::Critical, message).await {
+        eprintln!("Failed to post memory leak alert: {}", e);
+    }
+}
+
+fn dev_mention() -> String {
+    env::var("DISCORD_DEV_ROLE_ID")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(|id| format!("<@&{}> ", id))
+        .unwrap_or_default()
+}
+
+/// Spawns the background task that samples service memory, if a leak slope is configured.
+pub async fn start_memory_leak_loop(ctx: Context, tasks: &Tasks) {
+    let slope = match env::var("MEMORY_LEAK_SLOPE_MB_PER_HOUR")
+        .ok()
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|s| *s > 0.0)
+    {
+        Some(slope) => slope,
+        None => return,
+    };
+    if configured_services().is_empty() {
+        eprintln!("MEMORY_LEAK_SLOPE_MB_PER_HOUR is set but STATUS_SERVICES is empty.");
+        return;
+    }
+    if tasks.is_running("memory_leak") {
+        println!("Memory leak loop already running, reusing it.");
+        return;
+    }
+
+    let window_hours = env::var("MEMORY_LEAK_WINDOW_HOURS")
+        .ok()
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|h| *h > 0.0)
+        .unwrap_or(DEFAULT_WINDOW_HOURS);
+    let interval = env::var("MEMORY_LEAK_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+
+    tasks.spawn("memory_leak", move |beat| {
+        let ctx = ctx.clone();
+        async move {
+            loop {
+                beat.tick();
+                let window_secs = (window_hours * 3600.0) as i64;
+                let leaks = tokio::task::spawn_blocking(move || check_services(window_secs, slope))
+                    .await
+                    .unwrap_or_default();
+
+                for (service, leak) in leaks {
+                    ops_events::record(
+                        EventKind::Alert,
+                        format!("Memory leak suspected in {} ({:.0} MB/h)", service, leak.mb_per_hour),
+                    );
+                    if dedup::event(&format!("memory leak: {}", service), Severity::Warning) {
+                        alert(&ctx, leak_message(&service, &leak, window_hours)).await;
+                    }
+                }
+
+                sleep(Duration::from_secs(interval)).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,173 @@
+//! Periodic resource samples (CPU, RAM, disks) kept for trend reporting.
+//!
+//! The status loop records one sample per tick into `metrics.json`; samples older than
+//! the retention window are dropped.
+//!
+//! Disk samples also drive a growth forecast: a straight line fitted through each mount's
+//! recent usage tells how long until it is full, shown in the status message and weekly report.
+//!
+//! Environment Variables:
+//! - `DISK_FORECAST_WINDOW_DAYS`: How much history the trend is fitted to (default: 7)
+//! - `DISK_FORECAST_HORIZON_DAYS`: Only mounts filling up within this many days are reported
+//!   (default: 30)
+
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, env, sync::Mutex};
+use sysinfo::{CpuExt, DiskExt, SystemExt};
+
+use super::status::sample_system;
+use crate::store;
+
+const METRICS_FILE: &str = "metrics.json";
+const RETENTION_SECS: i64 = 35 * 86_400;
+const DEFAULT_FORECAST_WINDOW_DAYS: i64 = 7;
+const DEFAULT_FORECAST_HORIZON_DAYS: f64 = 30.0;
+/// A trend needs this many samples spread over at least this long to be worth reporting.
+const FORECAST_MIN_SAMPLES: usize = 6;
+const FORECAST_MIN_SPAN_SECS: i64 = 6 * 3600;
+
+static SAMPLES: Lazy<Mutex<Vec<MetricSample>>> = Lazy::new(|| Mutex::new(store::load(METRICS_FILE)));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskSample {
+    pub mount: String,
+    pub used_bytes: u64,
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricSample {
+    /// Unix timestamp (seconds).
+    pub timestamp: i64,
+    pub cpu_percent: f32,
+    pub ram_percent: f32,
+    pub disks: Vec<DiskSample>,
+}
+
+/// Takes a sample of the current system and appends it to the store. Blocks for ~0.5s.
+pub fn record_sample() {
+    let sys = sample_system();
+    let cpu_count = sys.cpus().len().max(1);
+
+    let sample = MetricSample {
+        timestamp: Utc::now().timestamp(),
+        cpu_percent: sys.cpus().iter().map(|c| c.cpu_usage()).sum::<f32>() / cpu_count as f32,
+        ram_percent: sys.used_memory() as f32 / sys.total_memory().max(1) as f32 * 100.0,
+        disks: sys
+            .disks()
+            .iter()
+            .map(|d| DiskSample {
+                mount: d.mount_point().display().to_string(),
+                used_bytes: d.total_space() - d.available_space(),
+                total_bytes: d.total_space(),
+            })
+            .collect(),
+    };
+
+    let mut samples = SAMPLES.lock().unwrap();
+    samples.retain(|s| sample.timestamp - s.timestamp < RETENTION_SECS);
+    samples.push(sample);
+    store::save(METRICS_FILE, &*samples);
+}
+
+/// Returns every sample recorded in `[from, to)`, oldest first.
+pub fn samples_between(from: i64, to: i64) -> Vec<MetricSample> {
+    SAMPLES
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|s| s.timestamp >= from && s.timestamp < to)
+        .cloned()
+        .collect()
+}
+
+/// One mount's usage over the forecast window.
+#[derive(Debug, Default)]
+struct MountSeries {
+    /// (timestamp, used bytes) per sample.
+    points: Vec<(f64, f64)>,
+    /// The latest sample's usage and size.
+    used: u64,
+    total: u64,
+}
+
+/// When a mount will be full if it keeps growing at its recent rate.
+#[derive(Debug, Clone)]
+pub struct DiskForecast {
+    pub mount: String,
+    pub used_percent: f64,
+    pub days_until_full: f64,
+}
+
+impl DiskForecast {
+    /// e.g. "/ will be full in ~9 days at current rate (81.2% used)".
+    pub fn describe(&self) -> String {
+        let eta = if self.days_until_full < 1.0 {
+            format!("~{:.0} hours", (self.days_until_full * 24.0).max(1.0))
+        } else {
+            format!("~{:.0} days", self.days_until_full)
+        };
+        format!(
+            "{} will be full in {} at current rate ({:.1}% used)",
+            self.mount, eta, self.used_percent
+        )
+    }
+}
+
+/// Mounts that will fill up within the forecast horizon, soonest first.
+///
+/// Fits a least-squares line through each mount's usage over the forecast window ending at
+/// `now`; shrinking or flat mounts are never reported.
+pub fn disk_forecasts(now: i64) -> Vec<DiskForecast> {
+    let window_days = env::var("DISK_FORECAST_WINDOW_DAYS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_FORECAST_WINDOW_DAYS);
+    let horizon_days = env::var("DISK_FORECAST_HORIZON_DAYS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_FORECAST_HORIZON_DAYS);
+
+    let mut series: BTreeMap<String, MountSeries> = BTreeMap::new();
+    for sample in samples_between(now - window_days * 86_400, now + 1) {
+        for disk in sample.disks {
+            let entry = series.entry(disk.mount).or_default();
+            entry.points.push((sample.timestamp as f64, disk.used_bytes as f64));
+            entry.used = disk.used_bytes;
+            entry.total = disk.total_bytes;
+        }
+    }
+
+    let mut forecasts: Vec<DiskForecast> = series
+        .into_iter()
+        .filter_map(|(mount, MountSeries { points, used, total })| {
+            let span = points.last()?.0 - points.first()?.0;
+            if points.len() < FORECAST_MIN_SAMPLES || span < FORECAST_MIN_SPAN_SECS as f64 || total == 0 {
+                return None;
+            }
+
+            let n = points.len() as f64;
+            let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / n;
+            let mean_u = points.iter().map(|(_, u)| u).sum::<f64>() / n;
+            let (covariance, variance) = points.iter().fold((0.0, 0.0), |(cov, var), (t, u)| {
+                (cov + (t - mean_t) * (u - mean_u), var + (t - mean_t).powi(2))
+            });
+            // Bytes per second.
+            let slope = covariance / variance;
+            if !slope.is_finite() || slope <= 0.0 {
+                return None;
+            }
+
+            let days_until_full = total.saturating_sub(used) as f64 / slope / 86_400.0;
+            (days_until_full <= horizon_days).then(|| DiskForecast {
+                mount,
+                used_percent: used as f64 / total as f64 * 100.0,
+                days_until_full,
+            })
+        })
+        .collect();
+    forecasts.sort_by(|a, b| a.days_until_full.total_cmp(&b.days_until_full));
+    forecasts
+}
@@ -6,14 +6,28 @@
 
 use serenity::{
     async_trait,
+    client::bridge::gateway::event::ShardStageUpdateEvent,
+    gateway::ConnectionStage,
     model::prelude::*,
-    model::application::interaction::{Interaction},
-    model::application::command::Command,
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    model::application::interaction::Interaction,
     prelude::*,
     Client,
 };
+use std::{
+    env,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
+use crate::files;
+use crate::github;
+use crate::lifecycle;
+use crate::notify;
+use crate::observer;
+use crate::tasks;
+use crate::ops_events::{self, EventKind};
 use crate::AppState;
+use options::Options;
 use crate::commands::{
     clean, fresh, migrate, reboot,
     restart_api, restart_service,
@@ -21,8 +35,89 @@ use crate::commands::{
     tail_logs, uptime,
 };
 
+mod alerts;
+mod api_metrics;
+pub(crate) mod auth;
+mod botstats;
+mod clock;
+mod command_errors;
+mod command_spec;
+pub(crate) mod components;
+mod dependencies;
+mod fetch_file;
+mod find;
+mod freeze;
+pub(crate) mod followup;
+mod github_links;
+mod guild_config;
+mod heartbeat;
+mod kernel_events;
+mod memory_leak;
+mod metrics;
+mod onboarding;
+pub(crate) mod options;
+mod paginator;
+mod permission_export;
+mod permissions;
+mod probe;
+mod provision;
+mod proxy;
+mod purge;
+mod rotate_secret;
+mod schedule;
+mod selftest;
+mod show_file;
+pub(crate) mod smoke_test;
+mod startup;
 mod status;
-use status::{handle_health, handle_status, start_status_loop};
+mod status_history;
+mod status_hosts;
+mod task_status;
+mod usage;
+mod watch;
+mod webhook_replay;
+mod weekly_report;
+use alerts::{handle_alerts, register_alerts_command};
+use api_metrics::start_api_metrics_loop;
+use botstats::{
+    handle_botstats, register_botstats_command, ShardManagerContainer, READY_COUNT, RESUME_COUNT,
+};
+use clock::start_clock_check_loop;
+use command_errors::report_command_panic;
+use command_spec::{CommandSpec, OptionSpec};
+use dependencies::start_dependency_check_loop;
+use fetch_file::{handle_fetch_file, register_fetch_file_command};
+use find::{handle_find, register_find_command};
+use freeze::{handle_freeze, handle_unfreeze, register_freeze_commands};
+use github_links::{handle_link_github, handle_unlink_github, register_github_link_commands};
+use guild_config::{handle_guild_config, register_guild_config_command};
+use kernel_events::start_kernel_events_loop;
+use memory_leak::start_memory_leak_loop;
+use onboarding::{handle_guild_create, handle_wizard_component, is_wizard_component};
+use permission_export::{handle_permissions, register_permissions_command};
+use permissions::start_permission_check_loop;
+use probe::start_probe_loop;
+use provision::{handle_provision_module, register_provision_command};
+use proxy::start_proxy_status_loop;
+use purge::{handle_purge, register_purge_command};
+use rotate_secret::{handle_rotate_secret, register_rotate_secret_command};
+use schedule::{handle_schedule, register_schedule_command};
+use selftest::{handle_selftest, register_selftest_command};
+use show_file::{handle_show_file, register_show_file_command};
+use smoke_test::{handle_smoke_test, register_smoke_test_command};
+use startup::announce_startup;
+use status::{handle_health, handle_status, register_status_command, start_status_loop};
+use task_status::{handle_tasks, register_tasks_command};
+use usage::{handle_usage, register_usage_command, start_usage_report_loop};
+use watch::{handle_watch, register_watch_command, start_watch_loop};
+use webhook_replay::{handle_webhook_replay, register_webhook_replay_command};
+use weekly_report::start_weekly_report_loop;
+
+pub use schedule::routes as schedule_routes;
+pub use status_hosts::routes as status_routes;
+
+/// Slash commands are registered on the first `ready` only; later ones are reconnects.
+static COMMANDS_REGISTERED: AtomicBool = AtomicBool::new(false);
 
 /// Starts the Discord bot client.
 ///
@@ -30,11 +125,14 @@ use status::{handle_health, handle_status, start_status_loop};
 /// sets up the event handler, and connects to the Discord gateway.
 /// Any runtime errors will be logged to stderr.
 ///
+/// `DISCORD_SHARDS` selects sharding: unset runs a single shard, `auto` uses the shard
+/// count Discord recommends, and a number runs that many shards.
+///
 /// # Arguments
 /// - `token`: Discord bot token.
 /// - `state`: Shared application state used across modules.
 pub async fn start(token: String, state: AppState) {
-    let intents = GatewayIntents::GUILD_MESSAGES | GatewayIntents::MESSAGE_CONTENT;
+    let intents = GatewayIntents::GUILDS | GatewayIntents::GUILD_MESSAGES | GatewayIntents::MESSAGE_CONTENT;
 
     let handler = Handler {
         shared_state: state.clone(),
@@ -45,7 +143,34 @@ pub async fn start(token: String, state: AppState) {
         .await
         .expect("Error creating Discord client");
 
-    if let Err(why) = client.start().await {
+    {
+        let mut data = client.data.write().await;
+        data.insert::<ShardManagerContainer>(client.shard_manager.clone());
+    }
+
+    // Close the gateway connection cleanly once the process is asked to shut down.
+    let shard_manager = client.shard_manager.clone();
+    let shutdown = state.shutdown.clone();
+    tokio::spawn(async move {
+        shutdown.wait().await;
+        println!("Shutting down Discord shards...");
+        shard_manager.lock().await.shutdown_all().await;
+    });
+
+    let shards = env::var("DISCORD_SHARDS").unwrap_or_default();
+    let result = match shards.trim() {
+        "" => client.start().await,
+        "auto" => client.start_autosharded().await,
+        count => match count.parse::<u64>() {
+            Ok(count) if count > 0 => client.start_shards(count).await,
+            _ => {
+                eprintln!("Invalid DISCORD_SHARDS `{}`, starting a single shard.", count);
+                client.start().await
+            }
+        },
+    };
+
+    if let Err(why) = result {
         eprintln!("Client error: {:?}", why);
     }
 }
@@ -59,48 +184,115 @@ struct Handler {
 
 #[async_trait]
 impl EventHandler for Handler {
-    /// Handles all incoming application command interactions (slash commands).
+    /// Handles all incoming interactions (slash commands and message components).
     ///
     /// Routes commands to the appropriate async handler function.
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
-        if let Interaction::ApplicationCommand(command) = interaction {
-            match command.data.name.as_str() {
-                "status" => handle_status(&ctx, &command).await,
-                "health" => handle_health(&ctx, &command).await,
-                "uptime" => uptime(&ctx, &command).await,
-                "restart" => restart_service(&ctx, &command).await,
-                "clean" => clean(&ctx, &command).await,
-                "fresh" => fresh(&ctx, &command).await,
-                "migrate" => migrate(&ctx, &command).await,
-                "restart_api" => restart_api(&ctx, &command).await,
-                "start_api" => start_api(&ctx, &command).await,
-                "stop_api" => stop_api(&ctx, &command).await,
-                "tail_logs" => tail_logs(&ctx, &command).await,
-                "reboot" => reboot(&ctx, &command).await,
-                _ => {}
+        if let Interaction::MessageComponent(component) = interaction {
+            if is_wizard_component(&component.data.custom_id) {
+                handle_wizard_component(&ctx, &component).await;
+            } else if components::is_component(&component.data.custom_id) {
+                components::handle_component(&ctx, &component).await;
+            } else if paginator::is_paginator_component(&component.data.custom_id) {
+                paginator::handle_paginator_component(&ctx, &component).await;
+            }
+        } else if let Interaction::ApplicationCommand(command) = interaction {
+            // Run the handler in its own task so a panic is caught instead of killing this one.
+            let task = tokio::spawn(dispatch_command(ctx.clone(), command.clone(), self.shared_state.clone()));
+            if let Err(e) = task.await {
+                if e.is_panic() {
+                    report_command_panic(&ctx, &command, &tasks::panic_message(e.into_panic())).await;
+                }
             }
         }
     }
 
-    /// Called when the bot is fully connected and ready.
+    /// Called when a shard is fully connected and ready.
     ///
+    /// Fires once per shard, and again whenever a session couldn't be resumed.
     /// - Stores the Discord context globally so other modules (like system commands) can access it.
     /// - Launches a background status update loop that periodically posts system metrics.
-    /// - Registers all slash commands globally with Discord.
+    /// - Registers all slash commands globally with Discord, on the first `ready` only.
     async fn ready(&self, ctx: Context, ready: Ready) {
-        println!("{} is connected!", ready.user.name);
+        READY_COUNT.fetch_add(1, Ordering::SeqCst);
+        match ready.shard {
+            Some([shard, total]) => {
+                println!("{} is connected on shard {}/{}!", ready.user.name, shard, total)
+            }
+            None => println!("{} is connected!", ready.user.name),
+        }
 
         // Store context for later use in background tasks or manual command sending.
         {
             let mut lock = self.shared_state.discord_ctx.lock().unwrap();
             *lock = Some(ctx.clone());
         }
+        self.shared_state.gateway_connected.store(true, Ordering::SeqCst);
+
+        // Post GitHub notifications that arrived before the gateway was up.
+        notify::flush_pending(&ctx).await;
+
+        // Announce the (re)start once per process.
+        announce_startup(&ctx).await;
 
         // Start the repeating system status updater task in a separate async thread.
-        start_status_loop(ctx.clone()).await;
+        start_status_loop(ctx.clone(), &self.shared_state.tasks).await;
+
+        // Start the scheduled command watcher.
+        start_watch_loop(ctx.clone(), &self.shared_state.tasks).await;
+
+        // Start the periodic channel permission health check.
+        start_permission_check_loop(ctx.clone(), &self.shared_state.tasks).await;
+
+        // Start the weekly operations report scheduler (if configured).
+        start_weekly_report_loop(ctx.clone(), &self.shared_state.tasks).await;
+        start_usage_report_loop(ctx.clone(), &self.shared_state.tasks).await;
+
+        // Start the synthetic submission probe (if configured).
+        start_probe_loop(ctx.clone(), &self.shared_state.tasks).await;
+
+        // Start the API error-rate monitor (if configured).
+        start_api_metrics_loop(ctx.clone(), &self.shared_state.tasks).await;
+
+        // Start the reverse-proxy status poller (if configured).
+        start_proxy_status_loop(ctx.clone(), &self.shared_state.tasks).await;
+
+        // Start the external dependency reachability checks (if configured).
+        start_dependency_check_loop(&self.shared_state.tasks).await;
+
+        // Start the sweep of expired download links (if links are configured).
+        files::start_prune_loop(&self.shared_state.tasks).await;
+
+        // Start memory leak detection for STATUS_SERVICES (if configured).
+        start_memory_leak_loop(ctx.clone(), &self.shared_state.tasks).await;
+
+        // Start OOM-kill, crash and filesystem error alerts from the kernel log (if enabled).
+        start_kernel_events_loop(ctx.clone(), &self.shared_state.tasks).await;
+
+        // Start the NTP/clock drift check.
+        start_clock_check_loop(ctx.clone(), &self.shared_state.tasks).await;
+
+        // Start the daily star/fork digest (if configured).
+        github::start_community_digest_loop(self.shared_state.clone()).await;
+        github::start_stale_review_loop(self.shared_state.clone()).await;
+        github::start_review_queue_loop(self.shared_state.clone()).await;
+
+        // Start the low-priority event digest (if any category is held).
+        notify::start_low_priority_digest_loop(self.shared_state.clone()).await;
+
+        // Commands are already registered globally; skip re-registering while crash-looping.
+        if lifecycle::crash_looping() {
+            eprintln!("Crash loop detected, skipping slash command registration.");
+            return;
+        }
+
+        // Global commands only need registering once per process, not per shard or reconnect.
+        if COMMANDS_REGISTERED.swap(true, Ordering::SeqCst) {
+            return;
+        }
 
         // Register slash commands available to users
-        register_command(&ctx, "status", "Show system status (CPU, RAM, Disk)").await;
+        register_status_command(&ctx).await;
         register_command(&ctx, "health", "Simple health check to see if the bot is responsive").await;
         register_command(&ctx, "uptime", "Show system uptime").await;
 
@@ -112,19 +304,141 @@ impl EventHandler for Handler {
             "The name of the systemd service to restart"
         ).await;
 
+        register_provision_command(&ctx).await;
+        register_purge_command(&ctx).await;
+        register_fetch_file_command(&ctx).await;
+        register_show_file_command(&ctx).await;
+        register_schedule_command(&ctx).await;
+        register_watch_command(&ctx).await;
+        register_guild_config_command(&ctx).await;
+        register_github_link_commands(&ctx).await;
+        register_botstats_command(&ctx).await;
+        register_tasks_command(&ctx).await;
+        register_webhook_replay_command(&ctx).await;
+        register_freeze_commands(&ctx).await;
+        register_smoke_test_command(&ctx).await;
+        register_alerts_command(&ctx).await;
+        register_selftest_command(&ctx).await;
+        register_permissions_command(&ctx).await;
+        register_rotate_secret_command(&ctx).await;
+        register_usage_command(&ctx).await;
+        register_find_command(&ctx).await;
+
         // Register additional predefined bot actions
         for (name, description) in &[
             ("clean", "Run cargo make clean"),
-            ("fresh", "Run cargo make fresh"),
-            ("migrate", "Run cargo make migrate"),
-            ("restart_api", "Restart the FitchFork API"),
-            ("start_api", "Start the FitchFork API"),
             ("stop_api", "Stop the FitchFork API"),
             ("tail_logs", "Tail the FitchFork log file"),
             ("reboot", "Reboot the server"),
         ] {
             register_command(&ctx, name, description).await;
         }
+
+        // Deployments refuse to run during a deploy freeze unless overridden.
+        for (name, description) in &[
+            ("fresh", "Run cargo make fresh"),
+            ("migrate", "Run cargo make migrate"),
+            ("restart_api", "Restart the FitchFork API"),
+            ("start_api", "Start the FitchFork API"),
+        ] {
+            CommandSpec::new(name, description)
+                .option(OptionSpec::boolean("override", "Run even though deploys are frozen"))
+                .register(&ctx)
+                .await;
+        }
+    }
+
+    /// Called when a dropped gateway session is resumed. Nothing was missed, so unlike
+    /// `ready` this restarts no loops and registers no commands.
+    async fn resume(&self, ctx: Context, _: ResumedEvent) {
+        RESUME_COUNT.fetch_add(1, Ordering::SeqCst);
+        println!("Resumed gateway session on shard {}.", ctx.shard_id);
+        self.shared_state.gateway_connected.store(true, Ordering::SeqCst);
+        notify::flush_pending(&ctx).await;
+    }
+
+    /// Posts the setup wizard when the bot is added to a guild it has no configuration for.
+    async fn guild_create(&self, ctx: Context, guild: Guild) {
+        handle_guild_create(&ctx, &guild).await;
+    }
+
+    /// Tracks gateway connectivity so notifications can fall back to the
+    /// Discord webhook URL while the connection is down.
+    async fn shard_stage_update(&self, _ctx: Context, event: ShardStageUpdateEvent) {
+        let connected = event.new == ConnectionStage::Connected;
+        let was_connected = self.shared_state.gateway_connected.swap(connected, Ordering::SeqCst);
+
+        if was_connected && !connected {
+            eprintln!("Gateway connection lost ({:?}), notifications will use the fallback webhook.", event.new);
+            ops_events::record(EventKind::Incident, format!("Discord gateway connection lost ({:?})", event.new));
+        } else if !was_connected && connected {
+            println!("Gateway connection established.");
+        }
+    }
+}
+
+/// Replies ephemerally that observer mode keeps the command from running.
+async fn observer_refusal(ctx: &Context, command: &ApplicationCommandInteraction) {
+    println!("Observer mode: refused /{} from {}", command.data.name, command.user.tag());
+    let content = observer::refusal(&format!("run `/{}`", command.data.name));
+    let _ = command
+        .create_interaction_response(&ctx.http, |res| {
+            res.interaction_response_data(|msg| msg.content(content).ephemeral(true))
+        })
+        .await;
+}
+
+/// Routes a slash command to its handler, once [`auth::allowed`] lets the invoker run it and
+/// observer mode doesn't block it.
+async fn dispatch_command(ctx: Context, command: ApplicationCommandInteraction, state: AppState) {
+    let (ctx, command) = (&ctx, &command);
+    if !auth::allowed(command) {
+        auth::deny(ctx, command).await;
+        return;
+    }
+    if observer::enabled() {
+        let subcommand = Options::of(command).subcommand().map(|(name, _)| name);
+        if !observer::allows(&command.data.name, subcommand) {
+            observer_refusal(ctx, command).await;
+            return;
+        }
+    }
+    usage::record(command);
+    match command.data.name.as_str() {
+        "status" => handle_status(ctx, command).await,
+        "health" => handle_health(ctx, command).await,
+        "uptime" => uptime(ctx, command).await,
+        "restart" => restart_service(ctx, command).await,
+        "clean" => clean(ctx, command).await,
+        "fresh" => fresh(ctx, command).await,
+        "migrate" => migrate(ctx, command).await,
+        "restart_api" => restart_api(ctx, command).await,
+        "start_api" => start_api(ctx, command).await,
+        "stop_api" => stop_api(ctx, command).await,
+        "tail_logs" => tail_logs(ctx, command).await,
+        "reboot" => reboot(ctx, command).await,
+        "provision-module" => handle_provision_module(ctx, command).await,
+        "purge" => handle_purge(ctx, command).await,
+        "fetch-file" => handle_fetch_file(ctx, command).await,
+        "show-file" => handle_show_file(ctx, command).await,
+        "schedule" => handle_schedule(ctx, command).await,
+        "watch" => handle_watch(ctx, command).await,
+        "guild-config" => handle_guild_config(ctx, command).await,
+        "botstats" => handle_botstats(ctx, command).await,
+        "tasks" => handle_tasks(ctx, command, &state.tasks).await,
+        "link_github" => handle_link_github(ctx, command).await,
+        "unlink_github" => handle_unlink_github(ctx, command).await,
+        "webhook_replay" => handle_webhook_replay(ctx, command, &state).await,
+        "freeze" => handle_freeze(ctx, command).await,
+        "unfreeze" => handle_unfreeze(ctx, command).await,
+        "smoke-test" => handle_smoke_test(ctx, command).await,
+        "alerts" => handle_alerts(ctx, command).await,
+        "selftest" => handle_selftest(ctx, command).await,
+        "permissions" => handle_permissions(ctx, command).await,
+        "rotate-secret" => handle_rotate_secret(ctx, command).await,
+        "usage" => handle_usage(ctx, command).await,
+        "find" => handle_find(ctx, command).await,
+        _ => {}
     }
 }
 
@@ -135,15 +449,13 @@ impl EventHandler for Handler {
 /// - `name`: Name of the command (e.g., "health").
 /// - `description`: Description shown in the Discord UI.
 async fn register_command(ctx: &Context, name: &str, description: &str) {
-    let _ = Command::create_global_application_command(&ctx.http, |cmd| {
-        cmd.name(name).description(description)
-    })
-    .await;
+    CommandSpec::new(name, description).register(ctx).await;
 }
 
 /// Registers a slash command that requires a string parameter.
 ///
-/// Useful for commands like `/restart` that accept a service name.
+/// Useful for commands like `/restart` that accept a service name. Commands with more options
+/// or subcommands build a [`CommandSpec`] directly.
 ///
 /// # Arguments
 /// - `ctx`: Discord context.
@@ -158,15 +470,8 @@ async fn register_command_with_option(
     option: &str,
     option_desc: &str,
 ) {
-    let _ = Command::create_global_application_command(&ctx.http, |cmd| {
-        cmd.name(name)
-            .description(description)
-            .create_option(|opt| {
-                opt.name(option)
-                    .description(option_desc)
-                    .kind(serenity::model::application::command::CommandOptionType::String)
-                    .required(true)
-            })
-    })
-    .await;
+    CommandSpec::new(name, description)
+        .option(OptionSpec::string(option, option_desc).required())
+        .register(ctx)
+        .await;
 }
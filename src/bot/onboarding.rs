@@ -0,0 +1,273 @@
+//! Setup wizard for guilds the bot is newly added to.
+//!
+//! When the bot joins a guild it has no configuration for, it posts a wizard in the
+//! guild's system channel with select menus for the status, PR, and alerts channels and
+//! the admin roles. Each selection is saved to the guild's record in `guilds.json`;
+//! "Finish setup" marks the guild as onboarded and removes the menus.
+//!
+//! Guilds the bot was already in before this existed are recorded as onboarded without a
+//! wizard, so restarts never re-post it. In observer mode nothing is recorded or posted.
+
+use chrono::Utc;
+use serenity::{
+    builder::CreateComponents,
+    model::application::component::ButtonStyle,
+    model::application::interaction::message_component::MessageComponentInteraction,
+    model::application::interaction::InteractionResponseType,
+    model::prelude::*,
+    prelude::*,
+};
+use std::collections::btree_map::Entry;
+
+use crate::guilds::{ChannelKind, GuildConfig, GuildConfigs};
+use crate::notify::{self, Outgoing, Priority};
+use crate::observer;
+
+const CUSTOM_ID_PREFIX: &str = "setup:";
+/// A guild joined within this many seconds of `guild_create` counts as newly added.
+const NEW_GUILD_GRACE_SECS: i64 = 600;
+/// Discord allows at most 25 options per select menu.
+const MAX_OPTIONS: usize = 25;
+
+/// Channel settings offered by the wizard as `(custom id suffix, label)`.
+const CHANNEL_SETTINGS: &[(&str, &str)] = &[
+    ("status", "Status channel"),
+    ("pr", "Pull request channel"),
+    ("alerts", "Alerts channel"),
+];
+
+/// Called on `guild_create`: records pre-existing guilds and posts the wizard for new ones.
+pub async fn handle_guild_create(ctx: &Context, guild: &Guild) {
+    // Observer mode changes no configuration, so it neither records guilds nor offers setup.
+    if observer::enabled() {
+        return;
+    }
+
+    if GuildConfigs::read(|configs| configs.guilds.contains_key(&guild.id.0)) {
+        return;
+    }
+
+    let is_new = Utc::now().timestamp() - guild.joined_at.unix_timestamp() < NEW_GUILD_GRACE_SECS;
+    let recorded = GuildConfigs::update(|configs| match configs.guilds.entry(guild.id.0) {
+        Entry::Vacant(entry) => {
+            entry.insert(GuildConfig {
+                onboarded: !is_new,
+                ..Default::default()
+            });
+            true
+        }
+        Entry::Occupied(_) => false,
+    });
+
+    if !recorded || !is_new {
+        return;
+    }
+
+    let channel = match guild.system_channel_id {
+        Some(channel) => channel,
+        None => {
+            eprintln!("Guild {} has no system channel, skipping setup wizard.", guild.id);
+            return;
+        }
+    };
+
+    let mut components = CreateComponents::default();
+    wizard_components(&mut components, guild);
+    let message = Outgoing::text(
+        "👋 **Thanks for adding the FitchFork bot!**\n\
+         An admin can pick the channels and roles to use below, then press **Finish setup**.",
+    )
+    .components(components);
+    if let Err(e) = notify::post(ctx, channel.0, Priority::Normal, message).await {
+        eprintln!("Failed to post setup wizard in guild {}: {}", guild.id, e);
+    }
+}
+
+fn wizard_components<'a>(c: &'a mut CreateComponents, guild: &Guild) -> &'a mut CreateComponents {
+    let mut text_channels: Vec<&GuildChannel> = guild
+        .channels
+        .values()
+        .filter_map(|ch| match ch {
+            Channel::Guild(gc) if gc.kind == ChannelType::Text => Some(gc),
+            _ => None,
+        })
+        .collect();
+    text_channels.sort_by_key(|gc| gc.position);
+
+    let mut roles: Vec<&Role> = guild
+        .roles
+        .values()
+        .filter(|r| r.id.0 != guild.id.0 && !r.managed)
+        .collect();
+    roles.sort_by_key(|r| std::cmp::Reverse(r.position));
+
+    for (setting, label) in CHANNEL_SETTINGS {
+        c.create_action_row(|row| {
+            row.create_select_menu(|menu| {
+                menu.custom_id(format!("{}{}", CUSTOM_ID_PREFIX, setting))
+                    .placeholder(*label)
+                    .options(|opts| {
+                        for gc in text_channels.iter().take(MAX_OPTIONS) {
+                            opts.create_option(|o| o.label(format!("#{}", gc.name)).value(gc.id.0));
+                        }
+                        opts
+                    })
+            })
+        });
+    }
+
+    if !roles.is_empty() {
+        let role_count = roles.len().min(MAX_OPTIONS);
+        c.create_action_row(|row| {
+            row.create_select_menu(|menu| {
+                menu.custom_id(format!("{}admin_roles", CUSTOM_ID_PREFIX))
+                    .placeholder("Admin roles")
+                    .min_values(1)
+                    .max_values(role_count as u64)
+                    .options(|opts| {
+                        for role in roles.iter().take(MAX_OPTIONS) {
+                            opts.create_option(|o| o.label(&role.name).value(role.id.0));
+                        }
+                        opts
+                    })
+            })
+        });
+    }
+
+    c.create_action_row(|row| {
+        row.create_button(|b| {
+            b.custom_id(format!("{}finish", CUSTOM_ID_PREFIX))
+                .label("Finish setup")
+                .style(ButtonStyle::Success)
+        })
+    })
+}
+
+/// Returns `true` if `custom_id` belongs to the setup wizard.
+pub fn is_wizard_component(custom_id: &str) -> bool {
+    custom_id.starts_with(CUSTOM_ID_PREFIX)
+}
+
+fn can_configure(component: &MessageComponentInteraction) -> bool {
+    component
+        .member
+        .as_ref()
+        .and_then(|m| m.permissions)
+        .is_some_and(|p| p.administrator() || p.manage_guild())
+}
+
+/// Handles a selection or button press on the setup wizard.
+pub async fn handle_wizard_component(ctx: &Context, component: &MessageComponentInteraction) {
+    let guild_id = match component.guild_id {
+        Some(id) => id.0,
+        None => return,
+    };
+
+    if !can_configure(component) {
+        reply(ctx, component, "⛔ Only members with Manage Server can configure the bot.").await;
+        return;
+    }
+    if observer::enabled() {
+        reply(ctx, component, &observer::refusal("change the bot's configuration")).await;
+        return;
+    }
+
+    let setting = component.data.custom_id.trim_start_matches(CUSTOM_ID_PREFIX);
+    let first: Option<u64> = component.data.values.first().and_then(|v| v.parse().ok());
+
+    let values = &component.data.values;
+    let result = GuildConfigs::update(|configs| {
+        apply_setting(configs.guilds.entry(guild_id).or_default(), setting, first, values)
+    });
+
+    match result {
+        Some(Applied::Confirmed(confirmation)) => reply(ctx, component, &confirmation).await,
+        Some(Applied::Finished(summary)) => {
+            let _ = component
+                .create_interaction_response(&ctx.http, |res| {
+                    res.kind(InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|d| d.content(summary).components(|c| c))
+                })
+                .await;
+        }
+        None => {}
+    }
+}
+
+/// What a wizard selection did.
+enum Applied {
+    /// A setting changed; the confirmation to reply with.
+    Confirmed(String),
+    /// Setup finished; the summary replacing the wizard.
+    Finished(String),
+}
+
+/// Applies the wizard's `setting` to `config`, or `None` if it isn't one.
+fn apply_setting(
+    config: &mut GuildConfig,
+    setting: &str,
+    first: Option<u64>,
+    values: &[String],
+) -> Option<Applied> {
+    let confirmation = match setting {
+        "status" | "pr" | "alerts" => {
+            let kind = ChannelKind::from_key(setting)?;
+            config.set_channel(kind, first);
+            let label = CHANNEL_SETTINGS
+                .iter()
+                .find(|(key, _)| *key == setting)
+                .map(|(_, label)| *label)
+                .unwrap_or(setting);
+            format!("✅ {} set to <#{}>.", label, first.unwrap_or_default())
+        }
+        "admin_roles" => {
+            config.admin_role_ids = values.iter().filter_map(|v| v.parse().ok()).collect();
+            format!(
+                "✅ Admin roles: {}",
+                config
+                    .admin_role_ids
+                    .iter()
+                    .map(|r| format!("<@&{}>", r))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+        "finish" => {
+            config.onboarded = true;
+            return Some(Applied::Finished(summary(config)));
+        }
+        _ => return None,
+    };
+    Some(Applied::Confirmed(confirmation))
+}
+
+fn summary(config: &GuildConfig) -> String {
+    let channel = |id: Option<u64>| id.map(|id| format!("<#{}>", id)).unwrap_or_else(|| "not set".into());
+    let roles = if config.admin_role_ids.is_empty() {
+        "not set".to_string()
+    } else {
+        config
+            .admin_role_ids
+            .iter()
+            .map(|r| format!("<@&{}>", r))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    format!(
+        "✅ **Setup complete.**\nStatus: {}\nPull requests: {}\nAlerts: {}\nAdmin roles: {}",
+        channel(config.channel(ChannelKind::Status)),
+        channel(config.channel(ChannelKind::PullRequests)),
+        channel(config.channel(ChannelKind::Alerts)),
+        roles
+    )
+}
+
+async fn reply(ctx: &Context, component: &MessageComponentInteraction, content: &str) {
+    let _ = component
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| d.content(content).ephemeral(true))
+        })
+        .await;
+}
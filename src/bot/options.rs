@@ -0,0 +1,141 @@
+//! Typed access to slash command options.
+//!
+//! Discord delivers option values as untyped JSON. [`Options`] looks them up by name with the
+//! expected type, and commands with validated arguments implement [`FromOptions`] so
+//! [`parse`] can build the argument struct and reply with an ephemeral error when an option is
+//! missing or invalid, instead of each handler unwrapping values itself.
+
+use serde_json::Value;
+use serenity::{
+    model::application::interaction::application_command::{
+        ApplicationCommandInteraction, CommandDataOption,
+    },
+    model::application::command::CommandOptionType,
+    prelude::*,
+};
+use std::fmt;
+
+/// Why an option could not be used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionError {
+    Missing(String),
+    Invalid { name: String, reason: String },
+}
+
+impl OptionError {
+    pub fn invalid(name: &str, reason: impl Into<String>) -> Self {
+        OptionError::Invalid { name: name.to_string(), reason: reason.into() }
+    }
+}
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionError::Missing(name) => write!(f, "Missing required option `{}`.", name),
+            OptionError::Invalid { name, reason } => write!(f, "Invalid `{}`: {}", name, reason),
+        }
+    }
+}
+
+/// The options of a command or subcommand.
+#[derive(Clone, Copy)]
+pub struct Options<'a> {
+    options: &'a [CommandDataOption],
+}
+
+impl<'a> Options<'a> {
+    /// Top-level options of `command`.
+    pub fn of(command: &'a ApplicationCommandInteraction) -> Self {
+        Self { options: &command.data.options }
+    }
+
+    /// Options nested under a subcommand (or subcommand group) option.
+    pub fn of_sub(sub: &'a CommandDataOption) -> Self {
+        Self { options: &sub.options }
+    }
+
+    /// The invoked subcommand (or group) and its options, if this level has one.
+    pub fn subcommand(&self) -> Option<(&'a str, Options<'a>)> {
+        self.options
+            .iter()
+            .find(|o| {
+                matches!(o.kind, CommandOptionType::SubCommand | CommandOptionType::SubCommandGroup)
+            })
+            .map(|sub| (sub.name.as_str(), Options::of_sub(sub)))
+    }
+
+    fn value(&self, name: &str) -> Option<&'a Value> {
+        self.options
+            .iter()
+            .find(|o| o.name == name)
+            .and_then(|o| o.value.as_ref())
+    }
+
+    /// A string option.
+    pub fn str(&self, name: &str) -> Option<&'a str> {
+        self.value(name).and_then(Value::as_str)
+    }
+
+    /// A string option, trimmed, treating blank input as absent.
+    pub fn trimmed(&self, name: &str) -> Option<&'a str> {
+        self.str(name).map(str::trim).filter(|v| !v.is_empty())
+    }
+
+    pub fn i64(&self, name: &str) -> Option<i64> {
+        self.value(name).and_then(Value::as_i64)
+    }
+
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        self.value(name).and_then(Value::as_bool)
+    }
+
+    /// A user, channel, or role option; these arrive as snowflake strings.
+    pub fn id(&self, name: &str) -> Option<u64> {
+        self.str(name).and_then(|v| v.parse().ok())
+    }
+
+    /// A string option that must be present and non-blank.
+    pub fn required_str(&self, name: &str) -> Result<&'a str, OptionError> {
+        self.trimmed(name).ok_or_else(|| OptionError::Missing(name.to_string()))
+    }
+
+    /// An optional string option run through `parse`, failing with `expected` if it doesn't parse.
+    pub fn parsed<T>(
+        &self,
+        name: &str,
+        parse: impl FnOnce(&str) -> Option<T>,
+        expected: &str,
+    ) -> Result<Option<T>, OptionError> {
+        match self.trimmed(name) {
+            Some(input) => parse(input)
+                .map(Some)
+                .ok_or_else(|| OptionError::invalid(name, format!("`{}` is not {}.", input, expected))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Arguments of a command, parsed and validated from its options.
+pub trait FromOptions: Sized {
+    fn from_options(options: Options<'_>) -> Result<Self, OptionError>;
+}
+
+/// Parses `command`'s options into `T`, replying with the error if they are missing or invalid.
+pub async fn parse<T: FromOptions>(ctx: &Context, command: &ApplicationCommandInteraction) -> Option<T> {
+    match T::from_options(Options::of(command)) {
+        Ok(args) => Some(args),
+        Err(e) => {
+            reply_error(ctx, command, &e).await;
+            None
+        }
+    }
+}
+
+/// Tells the user an option was missing or invalid.
+pub async fn reply_error(ctx: &Context, command: &ApplicationCommandInteraction, error: &OptionError) {
+    let _ = command
+        .create_interaction_response(&ctx.http, |res| {
+            res.interaction_response_data(|msg| msg.content(format!("❌ {}", error)).ephemeral(true))
+        })
+        .await;
+}
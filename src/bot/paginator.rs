@@ -0,0 +1,271 @@
+//! Paginated replies for long listings.
+//!
+//! A [`Paginator`] splits a listing into pages of at most [`PAGE_LINES`] entries (and well
+//! under Discord's message limit) and answers with the first page and ◀ / ▶ buttons, instead of
+//! truncating the listing or posting several messages. Listings that fit on one page are sent
+//! as a plain reply.
+//!
+//! Each listing is a session owned by whoever ran the command: only they can turn its pages.
+//! Anyone else pressing a button gets their own ephemeral copy to page through. Sessions are
+//! kept in memory and expire [`TIMEOUT`] after their last use, when the buttons are removed.
+
+use once_cell::sync::Lazy;
+use serde_json::json;
+use serenity::{
+    builder::CreateComponents,
+    http::Http,
+    model::application::component::ButtonStyle,
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    model::application::interaction::message_component::MessageComponentInteraction,
+    model::application::interaction::InteractionResponseType,
+    model::prelude::*,
+    prelude::*,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+const CUSTOM_ID_PREFIX: &str = "page:";
+/// Most entries on one page.
+pub const PAGE_LINES: usize = 15;
+/// Most characters on one page, leaving room below Discord's 2000.
+const PAGE_CHARS: usize = 1800;
+/// How long a listing can be paged after its last use.
+pub const TIMEOUT: Duration = Duration::from_secs(600);
+
+static SESSIONS: Lazy<Mutex<HashMap<u64, Session>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+struct Session {
+    owner: UserId,
+    pages: Arc<Vec<String>>,
+    last_used: Instant,
+}
+
+/// What a button press does.
+enum Press {
+    Expired,
+    /// Show the owner another page.
+    Turn { content: String, page: usize, total: usize },
+    /// Give someone else their own copy of the listing at a page.
+    Copy { pages: Arc<Vec<String>>, page: usize },
+}
+
+/// A listing to send in pages.
+pub struct Paginator {
+    header: String,
+    lines: Vec<String>,
+    footer: Option<String>,
+    ephemeral: bool,
+}
+
+impl Paginator {
+    /// A listing of `lines` (one entry each) under `header`, e.g. `**Background tasks:**`.
+    pub fn new(header: impl Into<String>, lines: Vec<String>) -> Self {
+        Self {
+            header: header.into(),
+            lines,
+            footer: None,
+            ephemeral: false,
+        }
+    }
+
+    /// Text shown below the lines on every page.
+    pub fn footer(mut self, footer: impl Into<String>) -> Self {
+        self.footer = Some(footer.into());
+        self
+    }
+
+    /// Makes the reply visible to the invoker only.
+    pub fn ephemeral(mut self, ephemeral: bool) -> Self {
+        self.ephemeral = ephemeral;
+        self
+    }
+
+    fn render(&self, lines: &[String]) -> String {
+        let mut page = format!("{}\n{}", self.header, lines.join("\n"));
+        if let Some(footer) = &self.footer {
+            page.push_str("\n\n");
+            page.push_str(footer);
+        }
+        page
+    }
+
+    /// Splits the listing into pages. A line too long for a page on its own is cut short.
+    fn pages(&self) -> Vec<String> {
+        let budget = PAGE_CHARS.saturating_sub(self.render(&[]).chars().count());
+        let mut pages = Vec::new();
+        let mut current: Vec<String> = Vec::new();
+        let mut chars = 0;
+
+        for line in &self.lines {
+            let mut line = line.clone();
+            if line.chars().count() > budget {
+                line = line.chars().take(budget.saturating_sub(1)).collect::<String>() + "…";
+            }
+            let len = line.chars().count() + 1;
+            if !current.is_empty() && (current.len() == PAGE_LINES || chars + len > budget) {
+                pages.push(self.render(&current));
+                current.clear();
+                chars = 0;
+            }
+            chars += len;
+            current.push(line);
+        }
+        pages.push(self.render(&current));
+        pages
+    }
+
+    /// Replies to `command` with the first page, adding buttons if there is more than one.
+    pub async fn send(self, ctx: &Context, command: &ApplicationCommandInteraction) {
+        let pages = self.pages();
+        let first = pages[0].clone();
+        if pages.len() == 1 {
+            let _ = command
+                .create_interaction_response(&ctx.http, |res| {
+                    res.interaction_response_data(|msg| msg.content(first).ephemeral(self.ephemeral))
+                })
+                .await;
+            return;
+        }
+
+        let id = command.id.0;
+        let total = pages.len();
+        start_session(ctx, id, command.user.id, Arc::new(pages), command.token.clone());
+        let sent = command
+            .create_interaction_response(&ctx.http, |res| {
+                res.interaction_response_data(|msg| {
+                    msg.content(first)
+                        .ephemeral(self.ephemeral)
+                        .components(|c| buttons(c, id, 0, total))
+                })
+            })
+            .await;
+        if let Err(e) = sent {
+            eprintln!("Failed to send paginated reply to /{}: {e:?}", command.data.name);
+            SESSIONS.lock().unwrap().remove(&id);
+        }
+    }
+}
+
+fn start_session(ctx: &Context, id: u64, owner: UserId, pages: Arc<Vec<String>>, token: String) {
+    let mut sessions = SESSIONS.lock().unwrap();
+    sessions.retain(|_, s| s.last_used.elapsed() < TIMEOUT);
+    sessions.insert(
+        id,
+        Session {
+            owner,
+            pages,
+            last_used: Instant::now(),
+        },
+    );
+    drop(sessions);
+    tokio::spawn(expire(ctx.http.clone(), id, token));
+}
+
+/// Removes the buttons once the session has been idle for [`TIMEOUT`].
+async fn expire(http: Arc<Http>, id: u64, token: String) {
+    let mut wait = TIMEOUT;
+    loop {
+        tokio::time::sleep(wait).await;
+        let idle = match SESSIONS.lock().unwrap().get(&id) {
+            Some(session) => session.last_used.elapsed(),
+            None => return,
+        };
+        if idle >= TIMEOUT {
+            break;
+        }
+        wait = TIMEOUT - idle;
+    }
+    SESSIONS.lock().unwrap().remove(&id);
+    // Fails once the interaction token has expired (15 minutes); the buttons then just stop
+    // responding.
+    let _ = http.edit_original_interaction_response(&token, &json!({ "components": [] })).await;
+}
+
+fn buttons(c: &mut CreateComponents, id: u64, page: usize, total: usize) -> &mut CreateComponents {
+    c.create_action_row(|row| {
+        row.create_button(|b| {
+            b.custom_id(format!("{}{}:{}", CUSTOM_ID_PREFIX, id, page.saturating_sub(1)))
+                .label("◀ Previous")
+                .style(ButtonStyle::Secondary)
+                .disabled(page == 0)
+        })
+        .create_button(|b| {
+            b.custom_id(format!("{}{}:current", CUSTOM_ID_PREFIX, id))
+                .label(format!("Page {}/{}", page + 1, total))
+                .style(ButtonStyle::Secondary)
+                .disabled(true)
+        })
+        .create_button(|b| {
+            b.custom_id(format!("{}{}:{}", CUSTOM_ID_PREFIX, id, page + 1))
+                .label("Next ▶")
+                .style(ButtonStyle::Secondary)
+                .disabled(page + 1 >= total)
+        })
+    })
+}
+
+/// Returns `true` if `custom_id` is a paginator button.
+pub fn is_paginator_component(custom_id: &str) -> bool {
+    custom_id.starts_with(CUSTOM_ID_PREFIX)
+}
+
+fn press(id: u64, page: usize, user: UserId) -> Press {
+    let mut sessions = SESSIONS.lock().unwrap();
+    let session = match sessions.get_mut(&id).filter(|s| s.last_used.elapsed() < TIMEOUT) {
+        Some(session) => session,
+        None => return Press::Expired,
+    };
+    let total = session.pages.len();
+    let page = page.min(total - 1);
+    if session.owner != user {
+        return Press::Copy { pages: session.pages.clone(), page };
+    }
+    session.last_used = Instant::now();
+    Press::Turn { content: session.pages[page].clone(), page, total }
+}
+
+/// Turns the page of the listing the pressed button belongs to.
+pub async fn handle_paginator_component(ctx: &Context, component: &MessageComponentInteraction) {
+    let target = component.data.custom_id.trim_start_matches(CUSTOM_ID_PREFIX);
+    let (id, page) = match target.split_once(':').and_then(|(id, page)| Some((id.parse().ok()?, page.parse().ok()?))) {
+        Some(parsed) => parsed,
+        None => return,
+    };
+
+    match press(id, page, component.user.id) {
+        Press::Expired => {
+            let expired = "⌛ This listing has expired. Run the command again to page through it.";
+            let _ = component
+                .create_interaction_response(&ctx.http, |res| {
+                    res.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|msg| msg.content(expired).ephemeral(true))
+                })
+                .await;
+        }
+        Press::Turn { content, page, total } => {
+            let _ = component
+                .create_interaction_response(&ctx.http, |res| {
+                    res.kind(InteractionResponseType::UpdateMessage).interaction_response_data(|msg| {
+                        msg.content(content).components(|c| buttons(c, id, page, total))
+                    })
+                })
+                .await;
+        }
+        Press::Copy { pages, page } => {
+            let copy = component.id.0;
+            let (content, total) = (pages[page].clone(), pages.len());
+            start_session(ctx, copy, component.user.id, pages, component.token.clone());
+            let _ = component
+                .create_interaction_response(&ctx.http, |res| {
+                    res.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|msg| {
+                            msg.content(content).ephemeral(true).components(|c| buttons(c, copy, page, total))
+                        })
+                })
+                .await;
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! `/permissions export`: a matrix of who may run every command, for periodic security review.
+//!
+//! Admin-only. Attaches a CSV with one row per command (and per subcommand with its own
+//! rule) and per button action, built from [`auth::COMMAND_ACCESS`] and the checks the
+//! buttons make, so it lists exactly what the bot enforces:
+//! - `bot_check`: the bot's own rule (`everyone`, `admin`, ...)
+//! - `allowed_roles`: the roles in this server that satisfy it
+//! - `discord_overrides`: the role, user and channel overrides set for the command under
+//!   Server Settings → Integrations, which Discord applies before the bot sees the command
+//!   (`default` when the command has none and falls back to the bot's app-wide settings)
+
+use serenity::{
+    model::application::command::{Command, CommandPermission, CommandPermissionType},
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    model::channel::AttachmentType,
+    model::guild::Role,
+    model::id::{GuildId, RoleId, UserId},
+    prelude::*,
+};
+use std::{borrow::Cow, collections::{hash_map::Entry, HashMap}, env};
+
+use super::auth::{self, Access};
+use super::command_spec::{CommandSpec, OptionSpec};
+use super::components;
+use super::followup::Followup;
+use super::options::{reply_error, OptionError};
+use crate::guilds;
+
+/// One row of the matrix.
+struct Row {
+    command: String,
+    bot_check: &'static str,
+    roles: Vec<RoleId>,
+    /// Overrides of the slash command this row belongs to; buttons have none.
+    command_name: Option<&'static str>,
+}
+
+/// Registers `/permissions export`.
+pub async fn register_permissions_command(ctx: &Context) {
+    CommandSpec::new("permissions", "Review who may run the bot's commands")
+        .option(OptionSpec::sub("export", "Export a matrix of every command and who may run it"))
+        .register(ctx)
+        .await;
+}
+
+/// Slash command handler for `/permissions`.
+pub async fn handle_permissions(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let guild_id = match command.guild_id {
+        Some(guild_id) => guild_id,
+        None => {
+            let error = OptionError::invalid("export", "run this in a server to see its roles.");
+            return reply_error(ctx, command, &error).await;
+        }
+    };
+
+    let followup = Followup::defer(ctx, command, true).await;
+    let rows = rows(guild_id);
+    let csv = match render(ctx, guild_id, &rows).await {
+        Ok(csv) => csv,
+        Err(e) => {
+            followup.finish(ctx, command, format!("❌ Could not build the matrix: {}", e)).await;
+            return;
+        }
+    };
+
+    let admin_only = rows.iter().filter(|row| row.bot_check == "admin").count();
+    let guild_name = match guild_id.to_partial_guild(&ctx.http).await {
+        Ok(guild) => guild.name,
+        Err(_) => guild_id.0.to_string(),
+    };
+    println!("{} exported the permissions matrix of {}", command.user.tag(), guild_name);
+    followup
+        .finish(
+            ctx,
+            command,
+            format!(
+                "📋 Permissions matrix for **{}**: {} entries, {} admin-only.",
+                guild_name,
+                rows.len(),
+                admin_only
+            ),
+        )
+        .await;
+
+    let filename = format!("permissions-{}-{}.csv", guild_id.0, chrono::Utc::now().format("%Y-%m-%d"));
+    let sent = command
+        .create_followup_message(&ctx.http, |msg| {
+            msg.add_file(AttachmentType::Bytes { data: Cow::from(csv.into_bytes()), filename: filename.clone() })
+                .ephemeral(true)
+        })
+        .await;
+    if let Err(e) = sent {
+        eprintln!("Failed to upload {} for /permissions: {e:?}", filename);
+    }
+}
+
+fn dev_role(guild_id: GuildId) -> Option<RoleId> {
+    guilds::for_guild(guild_id.0)
+        .and_then(|config| config.dev_role_id)
+        .or_else(|| env::var("DISCORD_DEV_ROLE_ID").ok().and_then(|v| v.trim().parse().ok()))
+        .map(RoleId)
+}
+
+/// Every command and button, with the roles that pass the bot's check.
+fn rows(guild_id: GuildId) -> Vec<Row> {
+    let admins = auth::admin_role_ids_for(Some(guild_id));
+    let operators = if Some(guild_id.0) == guilds::home_guild() { auth::admin_role_ids() } else { Vec::new() };
+    let everyone = vec![RoleId(guild_id.0)];
+
+    let mut rows: Vec<Row> = auth::COMMAND_ACCESS
+        .iter()
+        .map(|(name, sub, access)| Row {
+            command: match sub {
+                Some(sub) => format!("/{} {}", name, sub),
+                None => format!("/{}", name),
+            },
+            bot_check: match access {
+                Access::Everyone => "everyone",
+                Access::Admin => "admin",
+                Access::Operator => "operator",
+            },
+            roles: match access {
+                Access::Everyone => everyone.clone(),
+                Access::Admin => admins.clone(),
+                Access::Operator => operators.clone(),
+            },
+            command_name: Some(*name),
+        })
+        .collect();
+
+    let with = |extra: Option<RoleId>, more: Vec<RoleId>| {
+        admins.iter().copied().chain(extra).chain(more).collect::<Vec<_>>()
+    };
+    rows.push(Row {
+        command: "[button] Re-run failed jobs".to_string(),
+        bot_check: "admin or dev role",
+        roles: with(dev_role(guild_id), Vec::new()),
+        command_name: None,
+    });
+    rows.push(Row {
+        command: "[button] Approve / Merge when green".to_string(),
+        bot_check: "admin or maintainer, in the operators' or owning guild",
+        roles: with(None, components::maintainer_role_ids()),
+        command_name: None,
+    });
+    rows
+}
+
+fn role_name(roles: &HashMap<RoleId, Role>, id: RoleId) -> String {
+    match roles.get(&id) {
+        Some(role) => format!("{} ({})", role.name.trim_start_matches('@'), id.0),
+        None => format!("deleted role ({})", id.0),
+    }
+}
+
+/// Quotes a CSV field if it needs it.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Describes a command's Discord overrides, e.g. `allow role Staff (123); deny user bob (456)`.
+async fn describe_overrides(
+    ctx: &Context,
+    permission: &CommandPermission,
+    roles: &HashMap<RoleId, Role>,
+    users: &mut HashMap<u64, String>,
+) -> String {
+    let mut parts = Vec::new();
+    for entry in &permission.permissions {
+        let verdict = if entry.permission { "allow" } else { "deny" };
+        let id = entry.id.0;
+        let target = match entry.kind {
+            CommandPermissionType::Role => format!("role {}", role_name(roles, RoleId(id))),
+            CommandPermissionType::User => {
+                let name = match users.entry(id) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        let name = UserId(id).to_user(ctx).await.map_or_else(|_| "unknown".to_string(), |u| u.tag());
+                        entry.insert(name)
+                    }
+                };
+                format!("user {} ({})", name, id)
+            }
+            CommandPermissionType::Channel => format!("channel {}", id),
+            _ => format!("{:?} {}", entry.kind, id),
+        };
+        parts.push(format!("{} {}", verdict, target));
+    }
+    parts.join("; ")
+}
+
+/// Renders the rows as CSV, resolving role names and the server's command overrides.
+async fn render(ctx: &Context, guild_id: GuildId, rows: &[Row]) -> Result<String, String> {
+    let roles = guild_id.roles(&ctx.http).await.map_err(|e| e.to_string())?;
+
+    let command_ids: HashMap<String, u64> = Command::get_global_application_commands(&ctx.http)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|c| (c.name, c.id.0))
+        .collect();
+    let permissions = guild_id
+        .get_application_commands_permissions(&ctx.http)
+        .await
+        .map_err(|e| e.to_string())?;
+    // The bot's app-wide overrides are stored under its application ID.
+    let app_id = ctx.http.application_id();
+
+    let mut users = HashMap::new();
+    let mut app_wide = "default".to_string();
+    if let Some(permission) = permissions.iter().find(|p| Some(p.id.0) == app_id) {
+        app_wide = format!("app-wide: {}", describe_overrides(ctx, permission, &roles, &mut users).await);
+    }
+
+    let mut csv = String::from("command,bot_check,allowed_roles,discord_overrides\n");
+    for row in rows {
+        let allowed = row
+            .roles
+            .iter()
+            .map(|id| role_name(&roles, *id))
+            .chain((row.bot_check != "everyone").then(|| "Administrator permission".to_string()))
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        let overrides = match row.command_name {
+            Some(name) => {
+                let own = command_ids
+                    .get(name)
+                    .and_then(|id| permissions.iter().find(|p| p.id.0 == *id));
+                match own {
+                    Some(permission) => describe_overrides(ctx, permission, &roles, &mut users).await,
+                    None => app_wide.clone(),
+                }
+            }
+            None => "n/a".to_string(),
+        };
+
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            csv_field(&row.command),
+            csv_field(row.bot_check),
+            csv_field(&allowed),
+            csv_field(&overrides)
+        ));
+    }
+    Ok(csv)
+}
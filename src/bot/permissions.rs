@@ -0,0 +1,288 @@
+//! Periodic permission health checks for the channels the bot posts into.
+//!
+//! Sends elsewhere in the bot are fire-and-forget (`let _ =`), so a role or overwrite change
+//! that removes the bot's access would otherwise fail silently. This loop resolves the bot's
+//! effective permissions in every configured channel (global and per guild), provisioned
+//! module channel, watch channel and PR thread, and alerts admins when permissions regress
+//! (and again once fixed).
+//!
+//! Environment Variables:
+//! - `PERMISSION_CHECK_INTERVAL_SECS`: How often to check (default: 3600)
+//! - `PERMISSION_ALERT_CHANNEL_ID`: Where to alert (default: `DISCORD_STATUS_CHANNEL_ID`);
+//!   the fallback webhook is used if that channel itself is affected
+
+use serenity::{model::prelude::*, prelude::*};
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap},
+    env,
+    time::Duration,
+};
+use tokio::time::sleep;
+
+use super::{auth, watch};
+use crate::fallback;
+use crate::github::threads;
+use crate::guilds::{ChannelKind, GuildConfigs};
+use crate::notify::{self, dedup::{self, Severity}, Priority};
+use crate::ops_events::{self, EventKind};
+use crate::routing::RoutingConfig;
+use crate::tasks::Tasks;
+
+const DEFAULT_INTERVAL_SECS: u64 = 3600;
+
+
+/// A channel the bot must be able to use, with the permissions that use needs.
+struct Target {
+    channel_id: u64,
+    purpose: String,
+    required: Permissions,
+}
+
+fn basic() -> Permissions {
+    Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES | Permissions::EMBED_LINKS
+}
+
+fn env_channel(key: &str) -> Option<u64> {
+    env::var(key).ok().and_then(|v| v.trim().parse().ok())
+}
+
+/// Collects every channel and thread the bot is configured to post into.
+async fn targets() -> Vec<Target> {
+    let mut targets: Vec<Target> = Vec::new();
+    let mut add = |channel_id: u64, purpose: String, required: Permissions| {
+        match targets.iter_mut().find(|t| t.channel_id == channel_id) {
+            Some(existing) => existing.required |= required,
+            None => targets.push(Target { channel_id, purpose, required }),
+        }
+    };
+
+    // Every `DISCORD_*_CHANNEL_ID` the bot posts notifications into.
+    for (key, value) in env::vars() {
+        if key.starts_with("DISCORD_") && key.ends_with("_CHANNEL_ID") {
+            if let Ok(id) = value.trim().parse() {
+                add(id, format!("`{}`", key), basic());
+            }
+        }
+    }
+
+    // The status channel is pinned to and purged.
+    if let Some(id) = env_channel("DISCORD_STATUS_CHANNEL_ID") {
+        add(
+            id,
+            "`DISCORD_STATUS_CHANNEL_ID`".into(),
+            Permissions::MANAGE_MESSAGES | Permissions::READ_MESSAGE_HISTORY,
+        );
+    }
+
+    // PR threads are created and renamed under the PR channel.
+    if threads::enabled() {
+        if let Some(id) = env_channel("DISCORD_PR_CHANNEL_ID") {
+            add(
+                id,
+                "`DISCORD_PR_CHANNEL_ID`".into(),
+                Permissions::CREATE_PUBLIC_THREADS
+                    | Permissions::SEND_MESSAGES_IN_THREADS
+                    | Permissions::MANAGE_THREADS,
+            );
+        }
+        for id in threads::thread_ids().await {
+            add(
+                id,
+                "PR thread".into(),
+                Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES_IN_THREADS,
+            );
+        }
+    }
+
+    for (code, module) in RoutingConfig::read(|routing| routing.modules.clone()) {
+        add(module.announcements_id, format!("`{}` announcements", code), basic());
+        add(module.ci_id, format!("`{}` CI", code), basic());
+        add(module.alerts_id, format!("`{}` alerts", code), basic());
+    }
+
+    for (guild_id, config) in GuildConfigs::read(|configs| configs.guilds.clone()) {
+        for (kind, id) in config.channels {
+            let required = if kind == ChannelKind::Status.key() {
+                basic() | Permissions::MANAGE_MESSAGES | Permissions::READ_MESSAGE_HISTORY
+            } else {
+                basic()
+            };
+            add(id, format!("guild {} `{}` channel", guild_id, kind), required);
+        }
+    }
+
+    for id in watch::channel_ids() {
+        add(id, "watch channel".into(), basic());
+    }
+
+    targets
+}
+
+/// Resolves the bot's effective permissions in a channel. Threads inherit from their parent.
+async fn effective_permissions(
+    ctx: &Context,
+    bot_id: UserId,
+    channel_id: u64,
+    guilds: &mut HashMap<GuildId, (PartialGuild, Member)>,
+) -> Result<Permissions, String> {
+    let mut channel = ChannelId(channel_id)
+        .to_channel(&ctx.http)
+        .await
+        .map_err(|e| format!("cannot access channel: {}", e))?
+        .guild()
+        .ok_or("not a guild channel")?;
+
+    if matches!(channel.kind, ChannelType::PublicThread | ChannelType::PrivateThread) {
+        if let Some(parent) = channel.parent_id {
+            channel = parent
+                .to_channel(&ctx.http)
+                .await
+                .map_err(|e| format!("cannot access parent channel: {}", e))?
+                .guild()
+                .ok_or("parent is not a guild channel")?;
+        }
+    }
+
+    if let Entry::Vacant(entry) = guilds.entry(channel.guild_id) {
+        let guild = channel
+            .guild_id
+            .to_partial_guild(&ctx.http)
+            .await
+            .map_err(|e| format!("cannot fetch guild: {}", e))?;
+        let member = channel
+            .guild_id
+            .member(&ctx.http, bot_id)
+            .await
+            .map_err(|e| format!("cannot fetch bot member: {}", e))?;
+        entry.insert((guild, member));
+    }
+    let (guild, member) = &guilds[&channel.guild_id];
+
+    guild
+        .user_permissions_in(&channel, member)
+        .map_err(|e| format!("cannot compute permissions: {}", e))
+}
+
+/// Checks every target, returning `channel_id → problem` for each one that is not usable.
+async fn check_all(ctx: &Context) -> BTreeMap<u64, String> {
+    let mut problems = BTreeMap::new();
+    let bot_id = match ctx.http.get_current_user().await {
+        Ok(user) => user.id,
+        Err(e) => {
+            eprintln!("Permission check: failed to fetch bot user: {e:?}");
+            return problems;
+        }
+    };
+
+    let mut guilds = HashMap::new();
+    for target in targets().await {
+        let problem = match effective_permissions(ctx, bot_id, target.channel_id, &mut guilds).await {
+            Ok(perms) if perms.contains(target.required) => continue,
+            Ok(perms) => format!(
+                "missing {}",
+                (target.required - perms).get_permission_names().join(", ")
+            ),
+            Err(e) => e,
+        };
+        problems.insert(
+            target.channel_id,
+            format!("<#{}> ({}): {}", target.channel_id, target.purpose, problem),
+        );
+    }
+
+    problems
+}
+
+/// Posts an alert to the alert channel, using the fallback webhook if that fails.
+async fn alert(ctx: &Context, message: String) {
+    let channel = env_channel("PERMISSION_ALERT_CHANNEL_ID")
+        .or_else(|| env_channel("DISCORD_STATUS_CHANNEL_ID"));
+
+    if let Some(channel) = channel {
+        match notify::queue::send(ctx.http.clone(), channel, Priority::Critical, message.clone()).await {
+            Ok(()) => return,
+            Err(e) => eprintln!("Failed to post permission alert: {}", e),
+        }
+    }
+
+    if let Err(e) = fallback::post(&message).await {
+        eprintln!("Fallback delivery of permission alert failed: {}", e);
+    }
+}
+
+fn alert_key(channel_id: u64) -> String {
+    format!("permissions: channel {}", channel_id)
+}
+
+fn admin_mentions() -> String {
+    auth::admin_role_ids()
+        .iter()
+        .map(|r| format!("<@&{}> ", r.0))
+        .collect()
+}
+
+/// Spawns the background task that checks permissions and alerts on regressions.
+pub async fn start_permission_check_loop(ctx: Context, tasks: &Tasks) {
+    if tasks.is_running("permissions") {
+        println!("Permission check loop already running, reusing it.");
+        return;
+    }
+
+    let interval = env::var("PERMISSION_CHECK_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+
+    tasks.spawn("permissions", move |beat| {
+        let ctx = ctx.clone();
+        async move {
+            let mut known: BTreeMap<u64, String> = BTreeMap::new();
+
+            loop {
+                beat.tick();
+                let problems = check_all(&ctx).await;
+
+                let regressed: Vec<&String> = problems
+                    .iter()
+                    .filter(|(id, problem)| known.get(*id) != Some(*problem))
+                    .filter(|(id, _)| dedup::fire(&alert_key(**id), Severity::Warning))
+                    .map(|(_, problem)| problem)
+                    .collect();
+                let recovered: Vec<u64> = known
+                    .keys()
+                    .filter(|id| !problems.contains_key(*id))
+                    .filter(|id| dedup::resolve(&alert_key(**id)))
+                    .copied()
+                    .collect();
+
+                if !regressed.is_empty() {
+                    let lines = regressed
+                        .iter()
+                        .map(|p| format!("- {}", p))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    ops_events::record(
+                        EventKind::Alert,
+                        format!("Bot permissions regressed in {} channel(s)", regressed.len()),
+                    );
+                    alert(
+                        &ctx,
+                        format!("{}⚠️ **Bot permission problems detected:**\n{}", admin_mentions(), lines),
+                    )
+                    .await;
+                }
+                if !recovered.is_empty() {
+                    let lines = recovered
+                        .iter()
+                        .map(|id| format!("- <#{}>", id))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    alert(&ctx, format!("✅ **Bot permissions restored:**\n{}", lines)).await;
+                }
+
+                known = problems;
+                sleep(Duration::from_secs(interval)).await;
+            }
+        }
+    });
+}
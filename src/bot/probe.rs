@@ -0,0 +1,281 @@
+//! Synthetic end-to-end submission probe.
+//!
+//! On an interval, the probe logs in to the FitchFork API as a test account, submits a known
+//! archive to a test assignment, and polls the submission until it is marked. If the round
+//! trip takes longer than the threshold, or the submission never gets marked, the dev role is
+//! alerted, so a broken marking pipeline is noticed before students run into it. A recovery
+//! message is posted once a probe succeeds again.
+//!
+//! Environment Variables:
+//! - `SYNTHETIC_PROBE_API_URL`: Base URL of the FitchFork API (required)
+//! - `SYNTHETIC_PROBE_USERNAME` / `SYNTHETIC_PROBE_PASSWORD`: Test account credentials
+//! - `SYNTHETIC_PROBE_MODULE_ID` / `SYNTHETIC_PROBE_ASSIGNMENT_ID`: The test assignment
+//! - `SYNTHETIC_PROBE_FILE`: Path of the archive to submit
+//! - `SYNTHETIC_PROBE_INTERVAL_SECS`: How often to probe (default: 1800)
+//! - `SYNTHETIC_PROBE_MAX_SECS`: Round trip that triggers an alert (default: 300)
+//! - `SYNTHETIC_PROBE_TIMEOUT_SECS`: When to give up on marking (default: 900)
+//! - `SYNTHETIC_PROBE_CHANNEL_ID`: Where to alert (default: `DISCORD_STATUS_CHANNEL_ID`)
+
+use serde_json::Value;
+use serenity::prelude::*;
+use std::{
+    env,
+    path::Path,
+    time::{Duration, Instant},
+};
+use tokio::time::sleep;
+
+use crate::duration::format_duration;
+use crate::impact;
+use crate::notify::{self, dedup::{self, Severity}, Priority};
+use crate::ops_events::{self, EventKind};
+use crate::tasks::Tasks;
+
+const DEFAULT_INTERVAL_SECS: u64 = 1800;
+const ALERT_KEY: &str = "probe: submission round trip";
+const DEFAULT_MAX_SECS: u64 = 300;
+const DEFAULT_TIMEOUT_SECS: u64 = 900;
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+const BOUNDARY: &str = "fitchfork-discord-bot-probe";
+
+/// Probe settings, read once when the loop starts.
+#[derive(Debug, Clone)]
+struct ProbeConfig {
+    api_url: String,
+    username: String,
+    password: String,
+    module_id: String,
+    assignment_id: String,
+    file: String,
+    max: Duration,
+    timeout: Duration,
+}
+
+impl ProbeConfig {
+    fn from_env() -> Option<Self> {
+        let var = |key: &str| env::var(key).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let secs = |key: &str, default: u64| {
+            Duration::from_secs(env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default))
+        };
+
+        let api_url = var("SYNTHETIC_PROBE_API_URL")?.trim_end_matches('/').to_string();
+        let config = (|| {
+            Some(Self {
+                username: var("SYNTHETIC_PROBE_USERNAME")?,
+                password: var("SYNTHETIC_PROBE_PASSWORD")?,
+                module_id: var("SYNTHETIC_PROBE_MODULE_ID")?,
+                assignment_id: var("SYNTHETIC_PROBE_ASSIGNMENT_ID")?,
+                file: var("SYNTHETIC_PROBE_FILE")?,
+                max: secs("SYNTHETIC_PROBE_MAX_SECS", DEFAULT_MAX_SECS),
+                timeout: secs("SYNTHETIC_PROBE_TIMEOUT_SECS", DEFAULT_TIMEOUT_SECS),
+                api_url: api_url.clone(),
+            })
+        })();
+        if config.is_none() {
+            eprintln!("SYNTHETIC_PROBE_API_URL is set but the probe's account, assignment or file is missing.");
+        }
+        config
+    }
+
+    fn submissions_url(&self) -> String {
+        format!(
+            "{}/api/modules/{}/assignments/{}/submissions",
+            self.api_url, self.module_id, self.assignment_id
+        )
+    }
+}
+
+/// Logs in and returns the bearer token.
+async fn login(config: &ProbeConfig) -> Result<String, String> {
+    let res = crate::http::client()
+        .post(format!("{}/api/auth/login", config.api_url))
+        .json(&serde_json::json!({ "username": config.username, "password": config.password }))
+        .send()
+        .await
+        .map_err(|e| format!("login failed: {}", e))?;
+    if !res.status().is_success() {
+        return Err(format!("login returned {}", res.status()));
+    }
+    let body: Value = res.json().await.map_err(|e| format!("login response: {}", e))?;
+    body.pointer("/data/token")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| "login response has no token".to_string())
+}
+
+/// Uploads the probe archive and returns the new submission's ID.
+async fn submit(config: &ProbeConfig, token: &str) -> Result<String, String> {
+    let contents = tokio::fs::read(&config.file)
+        .await
+        .map_err(|e| format!("reading {}: {}", config.file, e))?;
+    let filename = Path::new(&config.file)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "probe.zip".to_string());
+
+    // reqwest is built without its multipart feature, so the form is encoded by hand.
+    let mut body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{f}\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\n",
+        b = BOUNDARY,
+        f = filename
+    )
+    .into_bytes();
+    body.extend_from_slice(&contents);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+
+    let res = crate::http::client()
+        .post(config.submissions_url())
+        .bearer_auth(token)
+        .header("Content-Type", format!("multipart/form-data; boundary={}", BOUNDARY))
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| format!("submission failed: {}", e))?;
+    if !res.status().is_success() {
+        return Err(format!("submission returned {}", res.status()));
+    }
+    let body: Value = res.json().await.map_err(|e| format!("submission response: {}", e))?;
+    match body.pointer("/data/id") {
+        Some(Value::Number(id)) => Ok(id.to_string()),
+        Some(Value::String(id)) => Ok(id.clone()),
+        _ => Err("submission response has no ID".to_string()),
+    }
+}
+
+/// Polls the submission until it has a mark, fails, or the probe times out.
+async fn wait_for_mark(config: &ProbeConfig, token: &str, id: &str, started: Instant) -> Result<(), String> {
+    let url = format!("{}/{}", config.submissions_url(), id);
+    loop {
+        if started.elapsed() > config.timeout {
+            return Err(format!(
+                "submission {} was not marked within {}",
+                id,
+                format_duration(config.timeout)
+            ));
+        }
+        sleep(POLL_INTERVAL).await;
+
+        // A single failed poll is not an outage; keep trying until the timeout.
+        let res = match crate::http::client().get(&url).bearer_auth(token).send().await {
+            Ok(res) if res.status().is_success() => res,
+            Ok(res) => {
+                eprintln!("Synthetic probe poll of submission {} returned {}", id, res.status());
+                continue;
+            }
+            Err(e) => {
+                eprintln!("Synthetic probe poll of submission {} failed: {}", id, e);
+                continue;
+            }
+        };
+        let body: Value = match res.json().await {
+            Ok(body) => body,
+            Err(_) => continue,
+        };
+
+        let status = body.pointer("/data/status").and_then(Value::as_str).unwrap_or_default();
+        if status.contains("fail") || status.contains("error") {
+            return Err(format!("submission {} ended with status `{}`", id, status));
+        }
+        if body.pointer("/data/mark").is_some_and(|m| !m.is_null()) {
+            return Ok(());
+        }
+    }
+}
+
+/// Runs one probe, returning the round-trip time or what went wrong.
+async fn run_probe(config: &ProbeConfig) -> Result<Duration, String> {
+    let started = Instant::now();
+    let token = login(config).await?;
+    let id = submit(config, &token).await?;
+    wait_for_mark(config, &token, &id, started).await?;
+    Ok(started.elapsed())
+}
+
+/// Posts to the probe's alert channel.
+async fn alert(ctx: &Context, message: String) {
+    let channel = ["SYNTHETIC_PROBE_CHANNEL_ID", "DISCORD_STATUS_CHANNEL_ID"]
+        .iter()
+        .find_map(|key| env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok()));
+    let channel = match channel {
+        Some(channel) => channel,
+        None => {
+            eprintln!("Synthetic probe alert not posted, no channel configured: {}", message);
+            return;
+        }
+    };
+    if let Err(e) = notify::queue::send(ctx.http.clone(), channel, Priority::Critical, message).await {
+        eprintln!("Failed to post synthetic probe alert: {}", e);
+    }
+}
+
+fn dev_mention() -> String {
+    env::var("DISCORD_DEV_ROLE_ID")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(|id| format!("<@&{}> ", id))
+        .unwrap_or_default()
+}
+
+/// Spawns the background task that runs the probe on an interval, if configured.
+pub async fn start_probe_loop(ctx: Context, tasks: &Tasks) {
+    let config = match ProbeConfig::from_env() {
+        Some(config) => config,
+        None => return,
+    };
+    if tasks.is_running("synthetic_probe") {
+        println!("Synthetic probe loop already running, reusing it.");
+        return;
+    }
+
+    let interval = env::var("SYNTHETIC_PROBE_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+
+    tasks.spawn("synthetic_probe", move |beat| {
+        let (ctx, config) = (ctx.clone(), config.clone());
+        async move {
+            let mut failing = false;
+
+            loop {
+                beat.tick();
+                let problem = match run_probe(&config).await {
+                    Ok(elapsed) if elapsed > config.max => Some(format!(
+                        "round trip took {} (threshold {})",
+                        format_duration(elapsed),
+                        format_duration(config.max)
+                    )),
+                    Ok(elapsed) => {
+                        println!("Synthetic probe marked in {}.", format_duration(elapsed));
+                        None
+                    }
+                    Err(e) => Some(e),
+                };
+
+                match problem {
+                    // Only the first failure alerts; a recovery message closes it.
+                    Some(problem) if !failing => {
+                        failing = true;
+                        ops_events::record(EventKind::Alert, format!("Synthetic probe failed: {}", problem));
+                        if dedup::fire(ALERT_KEY, Severity::Critical) {
+                            let message =
+                                format!("{}🧪 **Synthetic submission probe failed:** {}", dev_mention(), problem);
+                            alert(&ctx, impact::append(message).await).await;
+                        }
+                    }
+                    Some(problem) => eprintln!("Synthetic probe still failing: {}", problem),
+                    None if failing => {
+                        failing = false;
+                        if dedup::resolve(ALERT_KEY) {
+                            alert(&ctx, "✅ **Synthetic submission probe recovered.**".to_string()).await;
+                        }
+                    }
+                    None => {}
+                }
+
+                sleep(Duration::from_secs(interval)).await;
+            }
+        }
+    });
+}
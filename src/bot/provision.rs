@@ -0,0 +1,192 @@
+//! Slash command for onboarding a new FitchFork module into the Discord server.
+//!
+//! `/provision-module <code> [staff_role] [repos]` creates a category named after the module
+//! with `announcements`, `ci`, and `alerts` channels, applies permission overwrites, and stores
+//! the resulting channel IDs in the routing config (see [`crate::routing`]), along with the
+//! GitHub repositories whose notifications the module's channels receive. If a channel can't
+//! be created, the ones already created are deleted again.
+
+use serenity::{
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    model::prelude::*,
+    prelude::*,
+};
+
+use super::command_spec::{CommandSpec, OptionSpec};
+use super::followup::Followup;
+use super::options::{self, FromOptions, OptionError, Options};
+use crate::guilds;
+use crate::routing::{ModuleChannels, RoutingConfig};
+
+/// Registers `/provision-module`.
+pub async fn register_provision_command(ctx: &Context) {
+    CommandSpec::new("provision-module", "Create the standard channel structure for a new FitchFork module")
+        .option(OptionSpec::string("code", "Module code, e.g. cos301").required())
+        .option(OptionSpec::role("staff_role", "Role allowed to post module announcements"))
+        .option(OptionSpec::string("repos", "Comma-separated owner/name or owner/* routed to the module"))
+        .register(ctx)
+        .await;
+}
+
+/// Arguments of `/provision-module`.
+struct ProvisionArgs {
+    code: String,
+    staff_role: Option<RoleId>,
+    repos: Vec<String>,
+}
+
+impl FromOptions for ProvisionArgs {
+    fn from_options(options: Options<'_>) -> Result<Self, OptionError> {
+        let code = options.required_str("code")?.to_lowercase();
+        if !code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(OptionError::invalid(
+                "code",
+                format!("`{}` is not a valid module code (letters, digits and `-` only).", code),
+            ));
+        }
+        let repos = options
+            .str("repos")
+            .unwrap_or_default()
+            .split(',')
+            .map(|r| r.trim().to_lowercase())
+            .filter(|r| !r.is_empty())
+            .collect();
+        Ok(Self { code, staff_role: options.id("staff_role").map(RoleId), repos })
+    }
+}
+
+/// Slash command handler for `/provision-module`.
+pub async fn handle_provision_module(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let args = match options::parse::<ProvisionArgs>(ctx, command).await {
+        Some(args) => args,
+        None => return,
+    };
+
+    let followup = Followup::defer(ctx, command, false).await;
+
+    let content = match provision(ctx, command, args).await {
+        Ok(channels) => format!(
+            "✅ Provisioned module channels: <#{}> <#{}> <#{}>",
+            channels.announcements_id, channels.ci_id, channels.alerts_id
+        ),
+        Err(e) => format!("❌ Provisioning failed: {}", e),
+    };
+
+    followup.finish(ctx, command, content).await;
+}
+
+/// Creates the channels and persists their IDs.
+async fn provision(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    args: ProvisionArgs,
+) -> Result<ModuleChannels, String> {
+    let guild_id = command
+        .guild_id
+        .ok_or("this command can only be used in a server")?;
+
+    let ProvisionArgs { code, staff_role, repos } = args;
+
+    if RoutingConfig::read(|routing| routing.module(guild_id.0, &code).is_some()) {
+        return Err(format!("module `{}` is already provisioned", code));
+    }
+    if Some(guild_id.0) != guilds::home_guild() {
+        if let Some(repo) = repos.iter().find(|pattern| !guilds::pattern_claimable(pattern)) {
+            return Err(format!("`{}` can't be routed here: it is not in `DISCORD_CLAIMABLE_REPOS`", repo));
+        }
+    }
+
+    let bot_id = ctx
+        .http
+        .get_current_user()
+        .await
+        .map_err(|e| format!("could not fetch bot user: {}", e))?
+        .id;
+
+    // Everyone can read; only the bot (and staff, for announcements) can post.
+    let everyone = RoleId(guild_id.0);
+    let read_only = vec![
+        PermissionOverwrite {
+            allow: Permissions::VIEW_CHANNEL | Permissions::READ_MESSAGE_HISTORY,
+            deny: Permissions::SEND_MESSAGES,
+            kind: PermissionOverwriteType::Role(everyone),
+        },
+        PermissionOverwrite {
+            allow: Permissions::VIEW_CHANNEL
+                | Permissions::SEND_MESSAGES
+                | Permissions::MANAGE_MESSAGES
+                | Permissions::EMBED_LINKS
+                | Permissions::ATTACH_FILES,
+            deny: Permissions::empty(),
+            kind: PermissionOverwriteType::Member(bot_id),
+        },
+    ];
+    let mut announcements = read_only.clone();
+    if let Some(role) = staff_role {
+        announcements.push(PermissionOverwrite {
+            allow: Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES | Permissions::MENTION_EVERYONE,
+            deny: Permissions::empty(),
+            kind: PermissionOverwriteType::Role(role),
+        });
+    }
+
+    let category = guild_id
+        .create_channel(&ctx.http, |c| c.name(&code).kind(ChannelType::Category))
+        .await
+        .map_err(|e| format!("could not create category: {}", e))?;
+
+    let mut ids = Vec::new();
+    for (suffix, overwrites) in [
+        ("announcements", announcements),
+        ("ci", read_only.clone()),
+        ("alerts", read_only),
+    ] {
+        let created = guild_id
+            .create_channel(&ctx.http, |c| {
+                c.name(format!("{}-{}", code, suffix))
+                    .kind(ChannelType::Text)
+                    .category(category.id)
+                    .permissions(overwrites)
+            })
+            .await;
+        match created {
+            Ok(channel) => ids.push(channel.id.0),
+            Err(e) => {
+                roll_back(ctx, &ids, category.id).await;
+                return Err(format!("could not create `{}-{}`: {}", code, suffix, e));
+            }
+        }
+    }
+
+    let channels = ModuleChannels {
+        guild_id: guild_id.0,
+        category_id: category.id.0,
+        announcements_id: ids[0],
+        ci_id: ids[1],
+        alerts_id: ids[2],
+        repos,
+    };
+    // Another `/provision-module` for the same code may have finished in the meantime.
+    let recorded = RoutingConfig::update(|routing| {
+        if routing.module(guild_id.0, &code).is_some() {
+            return false;
+        }
+        routing.modules.insert(RoutingConfig::key(guild_id.0, &code), channels.clone());
+        true
+    });
+    if !recorded {
+        roll_back(ctx, &ids, category.id).await;
+        return Err(format!("module `{}` is already provisioned", code));
+    }
+
+    Ok(channels)
+}
+
+/// Deletes the channels and category of a partly provisioned module.
+async fn roll_back(ctx: &Context, channel_ids: &[u64], category_id: ChannelId) {
+    for id in channel_ids.iter().copied().map(ChannelId).chain([category_id]) {
+        if let Err(e) = id.delete(&ctx.http).await {
+            eprintln!("Failed to delete channel {} after a failed /provision-module: {}", id, e);
+        }
+    }
+}
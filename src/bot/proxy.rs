@@ -0,0 +1,342 @@
+//! Reverse-proxy status: connections, request rate, and 502s from the API upstream.
+//!
+//! Polls nginx's `stub_status` page or HAProxy's stats CSV and shows active connections and
+//! the request rate over the last interval on the status dashboard (`/status section:proxy`).
+//!
+//! 502s for the API upstream are counted per interval and alert the dev role when they reach
+//! the threshold, with a recovery message once they stop:
+//! - nginx: `stub_status` has no status codes, so new lines of the access log (combined
+//!   format) for requests under the API path prefix that got a 502 are counted
+//! - HAProxy: the API backend's connection and response errors (`econ` + `eresp`), which
+//!   HAProxy answers with 502/503
+//!
+//! Environment Variables:
+//! - `PROXY_STATUS_URL`: nginx `stub_status` URL, or HAProxy stats URL ending in `;csv`
+//!   (required)
+//! - `PROXY_STATUS_INTERVAL_SECS`: How often to poll (default: 60)
+//! - `PROXY_ACCESS_LOG`: nginx access log to count 502s in
+//! - `PROXY_API_PREFIX`: Path prefix of API requests in the access log (default: `/api`)
+//! - `PROXY_API_BACKEND`: HAProxy backend serving the API
+//! - `PROXY_502_THRESHOLD`: 502s per interval that trigger an alert (default: 10)
+//! - `PROXY_CHANNEL_ID`: Where to alert (default: `DISCORD_STATUS_CHANNEL_ID`)
+
+use chrono::{Local, TimeZone};
+use once_cell::sync::Lazy;
+use serenity::prelude::*;
+use std::{
+    env,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    sync::Mutex,
+    time::Duration,
+};
+use tokio::time::sleep;
+
+use crate::impact;
+use crate::notify::{self, dedup::{self, Severity}, Priority};
+use crate::ops_events::{self, EventKind};
+use crate::tasks::Tasks;
+
+const DEFAULT_INTERVAL_SECS: u64 = 60;
+const DEFAULT_THRESHOLD: u64 = 10;
+const DEFAULT_API_PREFIX: &str = "/api";
+const ALERT_KEY: &str = "proxy: API 502s";
+
+/// The most recent interval, shown on the status dashboard.
+static LATEST: Lazy<Mutex<Option<ProxyInterval>>> = Lazy::new(|| Mutex::new(None));
+
+/// Readings from one poll of the status page.
+#[derive(Debug, Clone)]
+struct ProxyStatus {
+    kind: &'static str,
+    active: u64,
+    /// Breakdown of the active connections, if the proxy reports one.
+    detail: Option<String>,
+    /// Cumulative requests served.
+    requests: u64,
+    /// Cumulative API backend errors (HAProxy only).
+    backend_errors: Option<u64>,
+}
+
+/// What happened between two polls.
+#[derive(Debug, Clone)]
+struct ProxyInterval {
+    /// Unix timestamp of the later poll.
+    at: i64,
+    status: ProxyStatus,
+    requests_per_sec: f64,
+    /// API 502s in the interval, if counted.
+    bad_gateways: Option<u64>,
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env::var(key).ok().and_then(|v| v.trim().parse().ok()).unwrap_or(default)
+}
+
+fn status_url() -> Option<String> {
+    env::var("PROXY_STATUS_URL").ok().map(|u| u.trim().to_string()).filter(|u| !u.is_empty())
+}
+
+/// Parses nginx's `stub_status` page.
+fn parse_stub_status(body: &str) -> Option<ProxyStatus> {
+    let mut lines = body.lines();
+    let active = lines.next()?.strip_prefix("Active connections:")?.trim().parse().ok()?;
+    lines.next()?; // "server accepts handled requests"
+    let requests = lines.next()?.split_whitespace().nth(2)?.parse().ok()?;
+    let detail = lines.next().map(|l| l.trim().to_lowercase());
+    Some(ProxyStatus {
+        kind: "nginx",
+        active,
+        detail,
+        requests,
+        backend_errors: None,
+    })
+}
+
+/// Parses HAProxy's stats CSV, summing the frontends and reading the API backend's errors.
+fn parse_haproxy_csv(body: &str, api_backend: Option<&str>) -> Option<ProxyStatus> {
+    let mut lines = body.lines();
+    let header: Vec<&str> = lines.next()?.trim_start_matches("# ").split(',').collect();
+    let column = |name: &str| header.iter().position(|h| *h == name);
+    let (pxname, svname) = (column("pxname")?, column("svname")?);
+    let (scur, req_tot) = (column("scur")?, column("req_tot")?);
+    let (econ, eresp) = (column("econ"), column("eresp"));
+
+    let mut status = ProxyStatus {
+        kind: "HAProxy",
+        active: 0,
+        detail: None,
+        requests: 0,
+        backend_errors: None,
+    };
+    for line in lines {
+        let fields: Vec<&str> = line.split(',').collect();
+        let field = |i: usize| fields.get(i).and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+        match fields.get(svname).copied() {
+            Some("FRONTEND") => {
+                status.active += field(scur);
+                status.requests += field(req_tot);
+            }
+            Some("BACKEND") if api_backend == fields.get(pxname).copied() => {
+                let errors = econ.map_or(0, field) + eresp.map_or(0, field);
+                status.backend_errors = Some(errors);
+            }
+            _ => {}
+        }
+    }
+    Some(status)
+}
+
+async fn fetch_status(url: &str) -> Result<ProxyStatus, String> {
+    let res = crate::http::client().get(url).send().await.map_err(|e| e.to_string())?;
+    if !res.status().is_success() {
+        return Err(format!("status page returned {}", res.status()));
+    }
+    let body = res.text().await.map_err(|e| e.to_string())?;
+
+    let api_backend = env::var("PROXY_API_BACKEND").ok();
+    if body.starts_with("Active connections:") {
+        parse_stub_status(&body).ok_or_else(|| "could not parse stub_status".to_string())
+    } else if body.starts_with("# pxname") {
+        parse_haproxy_csv(&body, api_backend.as_deref()).ok_or_else(|| "could not parse HAProxy stats".to_string())
+    } else {
+        Err("unrecognized status page (expected nginx stub_status or HAProxy CSV)".to_string())
+    }
+}
+
+/// Follows the nginx access log, counting API 502s in lines appended since the last read.
+struct AccessLog {
+    path: String,
+    prefix: String,
+    /// Bytes already read; `None` until the first read, which starts at the end.
+    offset: Option<u64>,
+}
+
+impl AccessLog {
+    fn from_env() -> Option<Self> {
+        let path = env::var("PROXY_ACCESS_LOG").ok().filter(|p| !p.trim().is_empty())?;
+        Some(Self {
+            path,
+            prefix: env::var("PROXY_API_PREFIX").unwrap_or_else(|_| DEFAULT_API_PREFIX.to_string()),
+            offset: None,
+        })
+    }
+
+    /// Whether a combined-format line is an API request answered with 502:
+    /// `1.2.3.4 - - [date] "GET /api/x HTTP/1.1" 502 157 "-" "agent"`.
+    fn is_api_bad_gateway(&self, line: &str) -> bool {
+        let mut quoted = line.splitn(3, '"');
+        let request = quoted.nth(1).unwrap_or_default();
+        let status = quoted.next().and_then(|rest| rest.split_whitespace().next());
+        let path = request.split_whitespace().nth(1).unwrap_or_default();
+        status == Some("502") && path.starts_with(&self.prefix)
+    }
+
+    fn count_new(&mut self) -> Result<u64, String> {
+        let mut file = File::open(&self.path).map_err(|e| e.to_string())?;
+        let len = file.metadata().map_err(|e| e.to_string())?.len();
+        let offset = match self.offset {
+            // Rotated or truncated: start over from the top of the new file.
+            Some(offset) if offset <= len => offset,
+            Some(_) => 0,
+            None => {
+                self.offset = Some(len);
+                return Ok(0);
+            }
+        };
+
+        file.seek(SeekFrom::Start(offset)).map_err(|e| e.to_string())?;
+        let mut appended = Vec::new();
+        file.read_to_end(&mut appended).map_err(|e| e.to_string())?;
+        // Leave a partially written last line for the next read.
+        let complete = appended.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+        self.offset = Some(offset + complete as u64);
+
+        Ok(String::from_utf8_lossy(&appended[..complete])
+            .lines()
+            .filter(|line| self.is_api_bad_gateway(line))
+            .count() as u64)
+    }
+}
+
+/// The latest interval as a dashboard section, or `None` if the proxy isn't monitored.
+pub fn proxy_section() -> Option<String> {
+    status_url()?;
+    let latest = match LATEST.lock().unwrap().clone() {
+        Some(latest) => latest,
+        None => return Some("Proxy:\n(waiting for the first two polls)".to_string()),
+    };
+
+    let at = Local
+        .timestamp_opt(latest.at, 0)
+        .single()
+        .map(|t| t.format("%H:%M:%S").to_string())
+        .unwrap_or_default();
+    let detail = latest.status.detail.map(|d| format!(" ({})", d)).unwrap_or_default();
+    let mut section = format!(
+        "Proxy ({}, {}):\nConnections: {} active{}\nRequests:    {:.1}/s",
+        latest.status.kind, at, latest.status.active, detail, latest.requests_per_sec
+    );
+    if let Some(bad_gateways) = latest.bad_gateways {
+        section.push_str(&format!("\nAPI 502s:    {}", bad_gateways));
+    }
+    Some(section)
+}
+
+async fn alert(ctx: &Context, message: String) {
+    let channel = ["PROXY_CHANNEL_ID", "DISCORD_STATUS_CHANNEL_ID"]
+        .iter()
+        .find_map(|key| env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok()));
+    let channel = match channel {
+        Some(channel) => channel,
+        None => {
+            eprintln!("Proxy alert not posted, no channel configured: {}", message);
+            return;
+        }
+    };
+    if let Err(e) = notify::queue::send(ctx.http.clone(), channel, Priority::Critical, message).await {
+        eprintln!("Failed to post proxy alert: {}", e);
+    }
+}
+
+fn dev_mention() -> String {
+    env::var("DISCORD_DEV_ROLE_ID")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(|id| format!("<@&{}> ", id))
+        .unwrap_or_default()
+}
+
+/// Spawns the background task that polls the proxy, if configured.
+pub async fn start_proxy_status_loop(ctx: Context, tasks: &Tasks) {
+    let url = match status_url() {
+        Some(url) => url,
+        None => return,
+    };
+    if tasks.is_running("proxy_status") {
+        println!("Proxy status loop already running, reusing it.");
+        return;
+    }
+
+    let interval = env_or("PROXY_STATUS_INTERVAL_SECS", DEFAULT_INTERVAL_SECS).max(1);
+    let threshold = env_or("PROXY_502_THRESHOLD", DEFAULT_THRESHOLD).max(1);
+
+    tasks.spawn("proxy_status", move |beat| {
+        let (ctx, url) = (ctx.clone(), url.clone());
+        async move {
+            let mut previous: Option<(i64, ProxyStatus)> = None;
+            let mut access_log = AccessLog::from_env();
+            let mut alerting = false;
+
+            loop {
+                beat.tick();
+                let status = match fetch_status(&url).await {
+                    Ok(status) => status,
+                    Err(e) => {
+                        eprintln!("Failed to poll proxy status: {}", e);
+                        sleep(Duration::from_secs(interval)).await;
+                        continue;
+                    }
+                };
+                let at = Local::now().timestamp();
+
+                let logged_502s = access_log.as_mut().and_then(|log| match log.count_new() {
+                    Ok(count) => Some(count),
+                    Err(e) => {
+                        eprintln!("Failed to read proxy access log: {}", e);
+                        None
+                    }
+                });
+
+                if let Some((previous_at, previous_status)) = &previous {
+                    let secs = (at - previous_at).max(1) as f64;
+                    // Counters reset when the proxy restarts; skip that interval.
+                    let requests = status.requests.checked_sub(previous_status.requests);
+                    let backend_502s = status
+                        .backend_errors
+                        .zip(previous_status.backend_errors)
+                        .and_then(|(now, before)| now.checked_sub(before));
+                    let bad_gateways = logged_502s.or(backend_502s);
+
+                    if let Some(requests) = requests {
+                        *LATEST.lock().unwrap() = Some(ProxyInterval {
+                            at,
+                            status: status.clone(),
+                            requests_per_sec: requests as f64 / secs,
+                            bad_gateways,
+                        });
+                    }
+
+                    match bad_gateways {
+                        Some(count) if count >= threshold && !alerting => {
+                            alerting = true;
+                            ops_events::record(EventKind::Alert, format!("Proxy returned {} API 502s", count));
+                            if dedup::fire(ALERT_KEY, Severity::Critical) {
+                                let message = format!(
+                                    "{}🚧 **API upstream failing:** the proxy returned {} 502s for the API in the \
+                                     last {}s (threshold {})",
+                                    dev_mention(),
+                                    count,
+                                    interval,
+                                    threshold
+                                );
+                                alert(&ctx, impact::append(message).await).await;
+                            }
+                        }
+                        Some(count) if count < threshold && alerting => {
+                            alerting = false;
+                            if dedup::resolve(ALERT_KEY) {
+                                let message = "✅ **API upstream recovered:** API 502s are back below the threshold.";
+                                alert(&ctx, message.to_string()).await;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                previous = Some((at, status));
+
+                sleep(Duration::from_secs(interval)).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,203 @@
+//! Bulk message cleanup.
+//!
+//! Provides the `/purge [author] [older_than] [limit]` admin command and the [`purge`]
+//! routine it shares with the status loop. Messages younger than Discord's 14-day
+//! bulk-delete limit are removed in batches of up to 100; older ones are deleted one by one.
+//! Pinned messages are never purged.
+
+use chrono::Utc;
+use serenity::{
+    http::Http,
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    model::prelude::*,
+    prelude::*,
+};
+use serde_json::json;
+use std::time::Duration;
+
+use super::command_spec::{CommandSpec, OptionSpec};
+use super::followup::Followup;
+use super::options::{self, FromOptions, OptionError, Options};
+use crate::duration::{format_duration, parse_duration};
+
+/// Discord refuses to bulk-delete messages older than 14 days; keep a safety margin.
+const BULK_DELETE_MAX_AGE_SECS: i64 = 14 * 86_400 - 3600;
+/// Upper bound on messages inspected per purge, to keep a single run bounded.
+const MAX_SCANNED: usize = 1000;
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: i64 = 500;
+
+/// Which messages a purge should remove.
+#[derive(Debug, Clone, Default)]
+pub struct PurgeFilter {
+    /// Only delete messages from this author.
+    pub author: Option<UserId>,
+    /// Only delete messages at least this old.
+    pub older_than: Option<Duration>,
+    /// Maximum number of messages to delete.
+    pub limit: usize,
+}
+
+/// Result of a purge run.
+#[derive(Debug, Default)]
+pub struct PurgeReport {
+    pub bulk_deleted: usize,
+    pub individually_deleted: usize,
+    pub failed: usize,
+}
+
+impl PurgeReport {
+    pub fn deleted(&self) -> usize {
+        self.bulk_deleted + self.individually_deleted
+    }
+}
+
+/// Deletes messages in `channel` matching `filter`, newest first.
+pub async fn purge(http: &Http, channel: ChannelId, filter: &PurgeFilter) -> Result<PurgeReport, String> {
+    let now = Utc::now().timestamp();
+    let min_age = filter.older_than.map(|d| d.as_secs() as i64).unwrap_or(0);
+
+    let mut recent = Vec::new();
+    let mut old = Vec::new();
+    let mut scanned = 0;
+    let mut before: Option<MessageId> = None;
+
+    while recent.len() + old.len() < filter.limit && scanned < MAX_SCANNED {
+        let page = channel
+            .messages(http, |r| match before {
+                Some(id) => r.limit(100).before(id),
+                None => r.limit(100),
+            })
+            .await
+            .map_err(|e| format!("could not fetch messages: {}", e))?;
+
+        if page.is_empty() {
+            break;
+        }
+        scanned += page.len();
+        before = page.last().map(|m| m.id);
+
+        for msg in page {
+            if recent.len() + old.len() >= filter.limit {
+                break;
+            }
+            let age = now - msg.timestamp.unix_timestamp();
+            let matches_author = filter.author.is_none_or(|author| msg.author.id == author);
+            if msg.pinned || !matches_author || age < min_age {
+                continue;
+            }
+
+            if age < BULK_DELETE_MAX_AGE_SECS {
+                recent.push(msg.id);
+            } else {
+                old.push(msg.id);
+            }
+        }
+    }
+
+    let mut report = PurgeReport::default();
+
+    for chunk in recent.chunks(100) {
+        // Bulk delete requires at least two messages.
+        let result = if chunk.len() == 1 {
+            channel.delete_message(http, chunk[0]).await
+        } else {
+            let ids: Vec<u64> = chunk.iter().map(|id| id.0).collect();
+            http.delete_messages(channel.0, &json!({ "messages": ids })).await
+        };
+        match result {
+            Ok(()) => report.bulk_deleted += chunk.len(),
+            Err(e) => {
+                eprintln!("Bulk delete failed in {}: {e:?}", channel);
+                report.failed += chunk.len();
+            }
+        }
+    }
+
+    for id in old {
+        match channel.delete_message(http, id).await {
+            Ok(()) => report.individually_deleted += 1,
+            Err(_) => report.failed += 1,
+        }
+    }
+
+    Ok(report)
+}
+
+/// Registers `/purge`.
+pub async fn register_purge_command(ctx: &Context) {
+    CommandSpec::new("purge", "Bulk-delete messages in this channel")
+        .option(
+            OptionSpec::string("author", "Whose messages to delete (default: anyone)")
+                .choice("bot", "bot")
+                .choice("anyone", "anyone"),
+        )
+        .option(OptionSpec::string("older_than", "Only delete messages older than this, e.g. 30m, 2h, 7d"))
+        .option(
+            OptionSpec::integer("limit", "Maximum number of messages to delete (default 100)").range(1, MAX_LIMIT),
+        )
+        .register(ctx)
+        .await;
+}
+
+/// Arguments of `/purge`.
+struct PurgeArgs {
+    older_than: Option<Duration>,
+    limit: usize,
+    bot_only: bool,
+}
+
+impl FromOptions for PurgeArgs {
+    fn from_options(options: Options<'_>) -> Result<Self, OptionError> {
+        Ok(Self {
+            older_than: options.parsed("older_than", parse_duration, "a duration (try 30m, 2h, 7d)")?,
+            limit: options
+                .i64("limit")
+                .map(|l| l.clamp(1, MAX_LIMIT) as usize)
+                .unwrap_or(DEFAULT_LIMIT),
+            bot_only: options.str("author") == Some("bot"),
+        })
+    }
+}
+
+/// Slash command handler for `/purge`.
+pub async fn handle_purge(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let PurgeArgs { older_than, limit, bot_only } = match options::parse(ctx, command).await {
+        Some(args) => args,
+        None => return,
+    };
+
+    let followup = Followup::defer(ctx, command, true).await;
+
+    let author = if bot_only {
+        match ctx.http.get_current_user().await {
+            Ok(user) => Some(user.id),
+            Err(e) => {
+                followup
+                    .finish(ctx, command, format!("❌ Could not resolve bot user: {}", e))
+                    .await;
+                return;
+            }
+        }
+    } else {
+        None
+    };
+
+    let filter = PurgeFilter { author, older_than, limit };
+    let content = match purge(&ctx.http, command.channel_id, &filter).await {
+        Ok(report) => format!(
+            "🧹 Deleted {} message(s){}{} ({} bulk, {} individually older than 14 days{}).",
+            report.deleted(),
+            if bot_only { " from the bot" } else { "" },
+            older_than
+                .map(|d| format!(" older than {}", format_duration(d)))
+                .unwrap_or_default(),
+            report.bulk_deleted,
+            report.individually_deleted,
+            if report.failed > 0 { format!(", {} failed", report.failed) } else { String::new() },
+        ),
+        Err(e) => format!("❌ Purge failed: {}", e),
+    };
+
+    followup.finish(ctx, command, content).await;
+}
@@ -0,0 +1,175 @@
+//! `/rotate-secret <name> [grace]`: replaces a webhook secret or endpoint token.
+//!
+//! Operators only. Generates a new value (see [`crate::secrets`]), shows it once in an ephemeral
+//! reply with the steps to update the sender (the GitHub webhook, Alertmanager, Jenkins, ...),
+//! and keeps accepting the old value for the grace period. When the grace period ends the old
+//! value is removed and the channel is told.
+
+use serenity::{
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    prelude::*,
+};
+use std::time::Duration;
+
+use super::command_spec::{CommandSpec, OptionSpec};
+use super::options::{reply_error, OptionError, Options};
+use crate::duration::{format_duration, parse_duration};
+use crate::github::signature;
+use crate::notify::{self, Priority};
+use crate::secrets::{self, Kind};
+
+/// Registers `/rotate-secret`.
+pub async fn register_rotate_secret_command(ctx: &Context) {
+    CommandSpec::new("rotate-secret", "Replace a webhook secret or endpoint token")
+        .option(OptionSpec::string("name", "Variable to rotate, e.g. GITHUB_WEBHOOK_SECRET").required())
+        .option(OptionSpec::string("grace", "How long the old value stays valid, e.g. 2h or 3d"))
+        .register(ctx)
+        .await;
+}
+
+/// Slash command handler for `/rotate-secret`.
+pub async fn handle_rotate_secret(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let options = Options::of(command);
+    let name = match options.required_str("name") {
+        Ok(name) => name.trim().to_uppercase(),
+        Err(e) => return reply_error(ctx, command, &e).await,
+    };
+    let grace = match options.trimmed("grace") {
+        Some(grace) => match parse_duration(grace) {
+            Some(grace) => grace,
+            None => {
+                let error = OptionError::invalid("grace", "Try a duration like 2h or 3d, or 0 to drop it now.");
+                return reply_error(ctx, command, &error).await;
+            }
+        },
+        None => Duration::from_secs(secrets::default_grace_secs() as u64),
+    };
+
+    let kind = match secrets::kind(&name) {
+        Some(kind) if secrets::current(&name).is_some() => kind,
+        _ => {
+            let rotatable = secrets::rotatable();
+            let known = if rotatable.is_empty() {
+                "No rotatable secret is configured.".to_string()
+            } else {
+                format!("Configured: `{}`.", rotatable.join("`, `"))
+            };
+            let error = OptionError::invalid("name", format!("`{}` can't be rotated here. {}", name, known));
+            return reply_error(ctx, command, &error).await;
+        }
+    };
+
+    let value = match secrets::rotate(&name, grace.as_secs() as i64, &command.user.tag()) {
+        Ok(value) => value,
+        Err(e) => return reply_error(ctx, command, &OptionError::invalid("name", e)).await,
+    };
+    println!(
+        "Audit: {} rotated {} (old value valid for {})",
+        command.user.tag(),
+        name,
+        format_duration(grace)
+    );
+
+    let old_value = if grace.is_zero() {
+        "The old value no longer works.".to_string()
+    } else {
+        format!(
+            "The old value keeps working for {} (until <t:{}:f>).",
+            format_duration(grace),
+            chrono::Utc::now().timestamp() + grace.as_secs() as i64
+        )
+    };
+    let content = format!(
+        "🔐 **`{}` rotated.** New value (shown once):\n```\n{}\n```\n{}\n\n**Next steps:**\n{}",
+        name,
+        value,
+        old_value,
+        steps(&name, kind)
+    );
+    let _ = command
+        .create_interaction_response(&ctx.http, |res| {
+            res.interaction_response_data(|msg| msg.content(content).ephemeral(true))
+        })
+        .await;
+
+    if !grace.is_zero() {
+        schedule_removal(ctx, command, name, grace);
+    }
+}
+
+/// The steps that switch the sender over to the new value.
+fn steps(name: &str, kind: Kind) -> String {
+    let mut steps = match kind {
+        Kind::WebhookSecret => {
+            let repos = signature::label_for(name)
+                .map(|label| signature::repos_for_label(&label))
+                .unwrap_or_default();
+            let targets = if repos.is_empty() {
+                "every repository or organization whose webhook uses this secret".to_string()
+            } else {
+                format!("`{}`", repos.join("`, `"))
+            };
+            vec![
+                format!("On GitHub, open **Settings → Webhooks** of {} and edit the bot's webhook.", targets),
+                "Paste the new value into **Secret** and click **Update webhook**.".to_string(),
+                "Click **Redeliver** on a recent delivery and check that it succeeds.".to_string(),
+            ]
+        }
+        Kind::EndpointToken => vec![sender_step(name)],
+    };
+    steps.push(format!(
+        "Replace `{}` in `.env` too. Until then the new value is kept in `rotated_secrets.json` in \
+         the data directory, which takes precedence.",
+        name
+    ));
+    steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| format!("{}. {}", i + 1, step))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn sender_step(name: &str) -> String {
+    match name {
+        "ALERT_WEBHOOK_TOKEN" => {
+            "Update the bearer token of the Alertmanager receiver or Grafana contact point that posts \
+             to `/webhook/alerts`."
+                .to_string()
+        }
+        "JENKINS_WEBHOOK_TOKEN" => {
+            "Update the `token` query parameter of Jenkins' notification URL (`/webhook/jenkins`).".to_string()
+        }
+        "SCHEDULE_FEED_TOKEN" => {
+            "Re-subscribe calendars to `/schedule.ics?token=<new value>`.".to_string()
+        }
+        "STATUS_API_TOKEN" => {
+            "Set the new `STATUS_API_TOKEN` on every bot instance that queries this host's `/status`."
+                .to_string()
+        }
+        "UPTIME_WEBHOOK_TOKEN" => {
+            "Update the `token` query parameter of the uptime monitor's webhook URL (`/webhook/uptime`)."
+                .to_string()
+        }
+        "WEBHOOK_REPLAY_TOKEN" => {
+            "Update the bearer token of scripts calling `POST /webhook/replay/<delivery_id>`.".to_string()
+        }
+        _ => "Update the `Authorization: Bearer` header of every sender of `/webhook/custom/<name>` \
+              using this token."
+            .to_string(),
+    }
+}
+
+/// Drops the old value once the grace period ends and says so in the channel.
+fn schedule_removal(ctx: &Context, command: &ApplicationCommandInteraction, name: String, grace: Duration) {
+    let ctx = ctx.clone();
+    let channel = command.channel_id;
+    tokio::spawn(async move {
+        tokio::time::sleep(grace).await;
+        if secrets::remove_expired(&name) {
+            println!("Removed the old value of {} after its grace period", name);
+            let notice = format!("🔐 The old value of `{}` has been removed and no longer works.", name);
+            let _ = notify::post_text(&ctx, channel.0, Priority::Normal, notice).await;
+        }
+    });
+}
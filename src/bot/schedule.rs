@@ -0,0 +1,366 @@
+//! Ops calendar of scheduled jobs (backups, reboots, maintenance windows).
+//!
+//! Jobs are configured as `SCHEDULE_JOB_<NAME>=<when> [duration]`, where `<when>` is one of
+//! `daily HH:MM`, `weekly <weekday> HH:MM`, or `once YYYY-MM-DD HH:MM` in server local time,
+//! e.g. `SCHEDULE_JOB_DB_BACKUP=daily 02:00 30m` or
+//! `SCHEDULE_JOB_MAINTENANCE=once 2026-11-02 22:00 2h`.
+//!
+//! `/schedule upcoming [days]` lists the next occurrences, and `GET /schedule.ics?token=...`
+//! serves them as an iCalendar feed the team can subscribe to.
+//!
+//! Environment Variables:
+//! - `SCHEDULE_JOB_<NAME>`: A scheduled job, as above
+//! - `SCHEDULE_FEED_TOKEN`: Token required by `GET /schedule.ics`
+
+use axum::{
+    extract::Query,
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDate, NaiveTime, TimeZone, Utc, Weekday,
+};
+use serde::Deserialize;
+use serenity::{
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    prelude::*,
+};
+use std::{env, time::Duration};
+
+use super::command_spec::{CommandSpec, OptionSpec};
+use super::options::Options;
+use super::paginator::Paginator;
+use crate::duration::{format_duration, parse_duration};
+use crate::secrets;
+
+const JOB_PREFIX: &str = "SCHEDULE_JOB_";
+const DEFAULT_DAYS: i64 = 7;
+const MAX_DAYS: i64 = 60;
+/// How far ahead the iCalendar feed is expanded.
+const FEED_DAYS: i64 = 60;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Recurrence {
+    Daily(NaiveTime),
+    Weekly(Weekday, NaiveTime),
+    Once(NaiveDate, NaiveTime),
+}
+
+/// A configured job.
+#[derive(Debug, Clone)]
+struct Job {
+    name: String,
+    recurrence: Recurrence,
+    duration: Option<Duration>,
+}
+
+/// One upcoming run of a job.
+#[derive(Debug, Clone)]
+struct Occurrence {
+    name: String,
+    start: DateTime<Local>,
+    duration: Option<Duration>,
+}
+
+fn parse_time(input: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(input, "%H:%M").ok()
+}
+
+/// Parses `daily HH:MM`, `weekly <weekday> HH:MM`, or `once YYYY-MM-DD HH:MM`, with an
+/// optional trailing duration.
+fn parse_job(name: &str, spec: &str) -> Option<Job> {
+    let words: Vec<&str> = spec.split_whitespace().collect();
+    let (recurrence, rest) = match words.as_slice() {
+        ["daily", time, rest @ ..] => (Recurrence::Daily(parse_time(time)?), rest),
+        ["weekly", day, time, rest @ ..] => {
+            (Recurrence::Weekly(day.parse().ok()?, parse_time(time)?), rest)
+        }
+        ["once", date, time, rest @ ..] => (
+            Recurrence::Once(NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?, parse_time(time)?),
+            rest,
+        ),
+        _ => return None,
+    };
+
+    let duration = match rest {
+        [] => None,
+        [d] => Some(parse_duration(d)?),
+        _ => return None,
+    };
+
+    Some(Job {
+        name: name.to_string(),
+        recurrence,
+        duration,
+    })
+}
+
+/// Returns every valid configured job, sorted by name. Invalid specs are logged and skipped.
+fn jobs() -> Vec<Job> {
+    let mut jobs: Vec<Job> = env::vars()
+        .filter_map(|(key, spec)| {
+            let name = key.strip_prefix(JOB_PREFIX)?.to_lowercase().replace('_', " ");
+            if name.is_empty() {
+                return None;
+            }
+            let job = parse_job(&name, &spec.to_lowercase());
+            if job.is_none() {
+                eprintln!("Ignoring invalid schedule `{}`: {}", key, spec);
+            }
+            job
+        })
+        .collect();
+    jobs.sort_by(|a, b| a.name.cmp(&b.name));
+    jobs
+}
+
+fn local(date: NaiveDate, time: NaiveTime) -> Option<DateTime<Local>> {
+    Local.from_local_datetime(&date.and_time(time)).earliest()
+}
+
+/// All occurrences of `jobs` starting in `[from, to)`, in chronological order.
+fn occurrences(jobs: &[Job], from: DateTime<Local>, to: DateTime<Local>) -> Vec<Occurrence> {
+    let mut out = Vec::new();
+
+    for job in jobs {
+        let mut starts = Vec::new();
+        match &job.recurrence {
+            Recurrence::Once(date, time) => starts.extend(local(*date, *time)),
+            Recurrence::Daily(time) | Recurrence::Weekly(_, time) => {
+                let mut date = from.date_naive();
+                while date <= to.date_naive() {
+                    let matches_day = match &job.recurrence {
+                        Recurrence::Weekly(day, _) => date.weekday() == *day,
+                        _ => true,
+                    };
+                    if matches_day {
+                        starts.extend(local(date, *time));
+                    }
+                    date = match date.succ_opt() {
+                        Some(next) => next,
+                        None => break,
+                    };
+                }
+            }
+        }
+
+        out.extend(
+            starts
+                .into_iter()
+                .filter(|start| *start >= from && *start < to)
+                .map(|start| Occurrence {
+                    name: job.name.clone(),
+                    start,
+                    duration: job.duration,
+                }),
+        );
+    }
+
+    out.sort_by_key(|o| o.start);
+    out
+}
+
+/// Registers `/schedule upcoming [days]`.
+pub async fn register_schedule_command(ctx: &Context) {
+    CommandSpec::new("schedule", "Scheduled backups, reboots, and maintenance windows")
+        .option(
+            OptionSpec::sub("upcoming", "List upcoming scheduled jobs").option(
+                OptionSpec::integer(
+                    "days",
+                    &format!("How many days ahead to look (default {})", DEFAULT_DAYS),
+                )
+                .range(1, MAX_DAYS),
+            ),
+        )
+        .register(ctx)
+        .await;
+}
+
+/// Slash command handler for `/schedule`.
+pub async fn handle_schedule(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let sub = match Options::of(command).subcommand() {
+        Some(("upcoming", sub)) => sub,
+        _ => return,
+    };
+
+    let days = sub.i64("days").unwrap_or(DEFAULT_DAYS).clamp(1, MAX_DAYS);
+
+    let now = Local::now();
+    let upcoming = occurrences(&jobs(), now, now + ChronoDuration::days(days));
+    if upcoming.is_empty() {
+        let content = format!("📅 Nothing scheduled in the next {} day(s).", days);
+        let _ = command
+            .create_interaction_response(&ctx.http, |res| {
+                res.interaction_response_data(|msg| msg.content(content))
+            })
+            .await;
+        return;
+    }
+
+    let lines = upcoming
+        .iter()
+        .map(|o| {
+            format!(
+                "- <t:{}:F> (<t:{}:R>) **{}**{}",
+                o.start.timestamp(),
+                o.start.timestamp(),
+                o.name,
+                o.duration
+                    .map(|d| format!(" for {}", format_duration(d)))
+                    .unwrap_or_default()
+            )
+        })
+        .collect();
+    Paginator::new(format!("📅 **Upcoming in the next {} day(s):**", days), lines)
+        .send(ctx, command)
+        .await;
+}
+
+#[derive(Debug, Deserialize)]
+struct FeedQuery {
+    token: Option<String>,
+}
+
+/// Feed routes: `GET /schedule.ics`.
+pub fn routes() -> Router {
+    Router::new().route("/schedule.ics", get(feed))
+}
+
+fn ics_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escapes text per RFC 5545.
+fn ics_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Renders upcoming occurrences as an iCalendar document.
+fn render_ics(occurrences: &[Occurrence]) -> String {
+    let stamp = ics_time(Utc::now());
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//FitchFork//Discord Bot Ops Calendar//EN".to_string(),
+        "X-WR-CALNAME:FitchFork Ops".to_string(),
+    ];
+
+    for o in occurrences {
+        let start = o.start.with_timezone(&Utc);
+        // Point-in-time jobs get a nominal 15 minute slot so calendars display them.
+        let length = o.duration.unwrap_or(Duration::from_secs(15 * 60));
+        let end = start + ChronoDuration::seconds(length.as_secs() as i64);
+
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}-{}@fitchfork-discord-bot", o.name.replace(' ', "-"), start.timestamp()),
+            format!("DTSTAMP:{}", stamp),
+            format!("DTSTART:{}", ics_time(start)),
+            format!("DTEND:{}", ics_time(end)),
+            format!("SUMMARY:{}", ics_escape(&o.name)),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+    // RFC 5545 requires CRLF line endings.
+    lines.join("\r\n") + "\r\n"
+}
+
+/// `GET /schedule.ics?token=<SCHEDULE_FEED_TOKEN>`: the ops calendar feed.
+async fn feed(Query(query): Query<FeedQuery>) -> Response {
+    let token = match secrets::current("SCHEDULE_FEED_TOKEN") {
+        Some(token) => token,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    if !query.token.as_deref().is_some_and(|t| secrets::accepts("SCHEDULE_FEED_TOKEN", &token, t)) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let now = Local::now();
+    let body = render_ics(&occurrences(&jobs(), now, now + ChronoDuration::days(FEED_DAYS)));
+
+    ([(CONTENT_TYPE, "text/calendar; charset=utf-8")], body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    /// Monday, 6 July 2026 at `h`:00 local time.
+    fn monday(h: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 7, 6, h, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn parse_job_recurrences() {
+        assert_eq!(parse_job("backup", "daily 02:00").unwrap().recurrence, Recurrence::Daily(time(2, 0)));
+        assert_eq!(
+            parse_job("reboot", "weekly sun 04:30").unwrap().recurrence,
+            Recurrence::Weekly(Weekday::Sun, time(4, 30))
+        );
+        assert_eq!(
+            parse_job("maintenance", "once 2026-11-02 22:00").unwrap().recurrence,
+            Recurrence::Once(NaiveDate::from_ymd_opt(2026, 11, 2).unwrap(), time(22, 0))
+        );
+    }
+
+    #[test]
+    fn parse_job_duration() {
+        assert_eq!(parse_job("backup", "daily 02:00").unwrap().duration, None);
+        assert_eq!(
+            parse_job("backup", "daily 02:00 30m").unwrap().duration,
+            Some(Duration::from_secs(30 * 60))
+        );
+    }
+
+    #[test]
+    fn parse_job_rejects_invalid_specs() {
+        let invalid = [
+            "",
+            "hourly 02:00",
+            "daily 25:00",
+            "weekly someday 02:00",
+            "once 2026-13-01 02:00",
+            "daily 02:00 30m extra",
+        ];
+        for spec in invalid {
+            assert!(parse_job("job", spec).is_none(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn occurrences_expand_daily_and_weekly_jobs_in_order() {
+        let jobs = [
+            parse_job("backup", "daily 02:00").unwrap(),
+            parse_job("reboot", "weekly wed 01:00").unwrap(),
+        ];
+        let found = occurrences(&jobs, monday(0), monday(0) + ChronoDuration::days(3));
+        let names: Vec<(&str, u32)> = found.iter().map(|o| (o.name.as_str(), o.start.day())).collect();
+        assert_eq!(names, [("backup", 6), ("backup", 7), ("reboot", 8), ("backup", 8)]);
+    }
+
+    #[test]
+    fn occurrences_window_is_half_open() {
+        let jobs = [parse_job("backup", "daily 02:00").unwrap()];
+        let found = occurrences(&jobs, monday(2), monday(2) + ChronoDuration::days(1));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].start, monday(2));
+    }
+
+    #[test]
+    fn once_jobs_only_occur_inside_the_window() {
+        let jobs = [parse_job("maintenance", "once 2026-07-07 22:00 2h").unwrap()];
+        assert_eq!(occurrences(&jobs, monday(0), monday(0) + ChronoDuration::days(2)).len(), 1);
+        assert!(occurrences(&jobs, monday(0), monday(0) + ChronoDuration::days(1)).is_empty());
+    }
+}
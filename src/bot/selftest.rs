@@ -0,0 +1,142 @@
+//! `/selftest`: checks every configured external integration with harmless calls.
+//!
+//! Admin-only. Each integration gets one row in a pass/fail table:
+//! - GitHub API credentials (token or App): an authenticated `GET /rate_limit`, which doesn't
+//!   count against the limit
+//! - GitHub webhook secrets: placeholder values and per-repo selections naming missing secrets
+//! - Fallback webhook: looked up with `GET`, nothing is posted
+//! - Mirror webhooks (Discord or Slack-compatible): looked up, or sent an empty payload that a
+//!   live webhook rejects
+//! - Every `STATUS_DEPENDENCIES` target (database, SMTP, S3, LDAP, ...): DNS plus an HTTP
+//!   request or TCP connect
+//!
+//! Integrations that aren't configured are listed as skipped.
+
+use serde_json::Value;
+use serenity::{
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    prelude::*,
+};
+
+use super::command_spec::CommandSpec;
+use super::dependencies;
+use super::followup::Followup;
+use crate::fallback;
+use crate::github::{client, signature};
+use crate::notify;
+
+/// Result of one integration's check.
+enum Outcome {
+    Pass(String),
+    Fail(String),
+    Skipped(String),
+}
+
+impl Outcome {
+    fn from_result(result: Result<String, String>) -> Self {
+        match result {
+            Ok(detail) => Outcome::Pass(detail),
+            Err(problem) => Outcome::Fail(problem),
+        }
+    }
+}
+
+/// Registers `/selftest`.
+pub async fn register_selftest_command(ctx: &Context) {
+    CommandSpec::new("selftest", "Check that every configured integration works")
+        .register(ctx)
+        .await;
+}
+
+/// Slash command handler for `/selftest`.
+pub async fn handle_selftest(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let followup = Followup::defer(ctx, command, true).await;
+    let rows = run_checks().await;
+    followup.finish(ctx, command, render(&rows)).await;
+}
+
+async fn check_github_token() -> Outcome {
+    let kind = match client::auth_kind() {
+        Some(kind) => kind,
+        None => return Outcome::Skipped("no GITHUB_TOKEN or GitHub App set".to_string()),
+    };
+    let limits: Value = match client::get_json("/rate_limit").await {
+        Ok(limits) => limits,
+        Err(e) => return Outcome::Fail(e),
+    };
+    let core = |field: &str| limits.pointer(&format!("/resources/core/{}", field)).and_then(Value::as_u64);
+    match (core("remaining"), core("limit")) {
+        (Some(remaining), Some(limit)) => Outcome::Pass(format!("{}: {}/{} requests left", kind, remaining, limit)),
+        _ => Outcome::Pass(format!("authenticated with {}", kind)),
+    }
+}
+
+fn check_webhook_secrets() -> Outcome {
+    match signature::self_check() {
+        Ok(Some(detail)) => Outcome::Pass(detail),
+        Ok(None) => Outcome::Skipped("no secret set, deliveries are not verified".to_string()),
+        Err(problem) => Outcome::Fail(problem),
+    }
+}
+
+async fn check_fallback() -> Outcome {
+    if !fallback::is_configured() {
+        return Outcome::Skipped("DISCORD_FALLBACK_WEBHOOK_URL not set".to_string());
+    }
+    Outcome::from_result(fallback::check().await)
+}
+
+async fn run_checks() -> Vec<(String, Outcome)> {
+    let mut rows = vec![
+        ("GitHub API".to_string(), check_github_token().await),
+        ("GitHub webhook secrets".to_string(), check_webhook_secrets()),
+        ("Fallback webhook".to_string(), check_fallback().await),
+    ];
+
+    let mirrors = notify::check_mirrors().await;
+    if mirrors.is_empty() {
+        rows.push(("Mirror webhooks".to_string(), Outcome::Skipped("none configured".to_string())));
+    }
+    for (host, result) in mirrors {
+        rows.push((format!("Mirror {}", host), Outcome::from_result(result)));
+    }
+
+    let dependencies = dependencies::check_now().await;
+    if dependencies.is_empty() {
+        rows.push(("Dependencies".to_string(), Outcome::Skipped("STATUS_DEPENDENCIES not set".to_string())));
+    }
+    for (name, result) in dependencies {
+        rows.push((name, Outcome::from_result(result)));
+    }
+
+    rows
+}
+
+/// Renders the rows as an aligned table in a code block.
+fn render(rows: &[(String, Outcome)]) -> String {
+    let width = rows.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+    let failed = rows.iter().filter(|(_, o)| matches!(o, Outcome::Fail(_))).count();
+    let passed = rows.iter().filter(|(_, o)| matches!(o, Outcome::Pass(_))).count();
+
+    let lines = rows
+        .iter()
+        .map(|(name, outcome)| {
+            let (mark, detail) = match outcome {
+                Outcome::Pass(detail) => ("PASS", detail),
+                Outcome::Fail(detail) => ("FAIL", detail),
+                Outcome::Skipped(detail) => ("SKIP", detail),
+            };
+            format!("{:<width$}  {}  {}", name, mark, detail, width = width)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let verdict = if failed == 0 { "🟢" } else { "🔴" };
+    format!(
+        "{} **Self-test:** {} passed, {} failed\n```\n{}\n```",
+        verdict,
+        passed,
+        failed,
+        lines.replace("```", "'''")
+    )
+}
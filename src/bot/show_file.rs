@@ -0,0 +1,169 @@
+//! Syntax-highlighted snippets of allowlisted server files.
+//!
+//! `/show-file <alias> [start] [end]` posts a bounded range of lines from one of the
+//! `/fetch-file` allowlisted files as a highlighted code block, for discussing production
+//! configuration in a channel. Secret-looking values are redacted the same way.
+//!
+//! Environment Variables:
+//! - `FETCH_FILE_<ALIAS>`: Allowlisted files (shared with `/fetch-file`)
+
+use serenity::{
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    prelude::*,
+};
+use std::{
+    fs,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use super::command_spec::{CommandSpec, OptionSpec};
+use super::fetch_file::{allowed_files, redact_line, resolve_alias};
+use super::followup::Followup;
+use super::options::{reply_error, Options};
+
+/// Most lines shown in one snippet.
+const MAX_LINES: i64 = 60;
+/// Keep the code block comfortably under Discord's 2000 character limit.
+const MAX_SNIPPET_CHARS: usize = 1800;
+
+/// Maps a file extension to a Discord code block language.
+fn language_for(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "rs" => "rust",
+        "toml" => "toml",
+        "json" => "json",
+        "yml" | "yaml" => "yaml",
+        "sh" | "bash" => "bash",
+        "py" => "python",
+        "js" => "js",
+        "ts" => "ts",
+        "sql" => "sql",
+        "conf" | "ini" | "service" | "socket" | "timer" => "ini",
+        "md" => "md",
+        "log" => "log",
+        _ if name.starts_with(".env") || ext == "env" || ext == "example" => "bash",
+        _ if name == "dockerfile" => "dockerfile",
+        _ if name == "nginx.conf" => "nginx",
+        _ => "",
+    }
+}
+
+/// Registers `/show-file` with the `/fetch-file` aliases as choices.
+pub async fn register_show_file_command(ctx: &Context) {
+    let aliases: Vec<String> = allowed_files().into_iter().map(|(alias, _)| alias).collect();
+
+    let mut alias = OptionSpec::string("alias", "Which file to show").required();
+    for name in &aliases {
+        alias = alias.choice(name, name);
+    }
+
+    CommandSpec::new("show-file", "Show a highlighted snippet of an allowlisted server file")
+        .option(alias)
+        .option(OptionSpec::integer("start", "First line to show (default: 1)").min(1))
+        .option(
+            OptionSpec::integer("end", &format!("Last line to show (at most {} lines)", MAX_LINES)).min(1),
+        )
+        .register(ctx)
+        .await;
+}
+
+/// Slash command handler for `/show-file`.
+pub async fn handle_show_file(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let options = Options::of(command);
+    let alias = match options.required_str("alias") {
+        Ok(alias) => alias.to_string(),
+        Err(e) => return reply_error(ctx, command, &e).await,
+    };
+    let start = options.i64("start");
+    let end = options.i64("end");
+
+    // Deferred first so a slow disk doesn't miss the interaction deadline.
+    let followup = Followup::defer(ctx, command, false).await;
+
+    let guild_id = command.guild_id.map(|id| id.0);
+    let content = match tokio::task::spawn_blocking(move || snippet(guild_id, &alias, start, end)).await {
+        Ok(Ok(content)) => content,
+        Ok(Err(e)) => format!("❌ {}", e),
+        Err(e) => format!("❌ Could not read the file: {}", e),
+    };
+
+    followup.finish(ctx, command, content).await;
+}
+
+/// Renders lines `start..=end` (1-based) of an allowlisted file as a code block, reading only
+/// as far as `end`.
+fn snippet(
+    guild_id: Option<u64>,
+    alias: &str,
+    start: Option<i64>,
+    end: Option<i64>,
+) -> Result<String, String> {
+    let path = resolve_alias(guild_id, alias).ok_or_else(|| format!("`{}` is not an allowlisted file.", alias))?;
+    let file = fs::File::open(&path).map_err(|e| format!("Could not read `{}`: {}", alias, e))?;
+    let mut reader = BufReader::new(file);
+
+    let start = start.unwrap_or(1).max(1);
+    let end = end
+        .unwrap_or(start + MAX_LINES - 1)
+        .clamp(start, start + MAX_LINES - 1);
+
+    let width = end.to_string().len();
+    let mut body = String::new();
+    let mut shown_end = start - 1;
+    let mut line = Vec::new();
+    let mut n = 0;
+    while n < end {
+        line.clear();
+        let read = reader
+            .read_until(b'\n', &mut line)
+            .map_err(|e| format!("Could not read `{}`: {}", alias, e))?;
+        if read == 0 {
+            break;
+        }
+        n += 1;
+        if n < start {
+            continue;
+        }
+        let text = String::from_utf8_lossy(&line);
+        let numbered = format!(
+            "{:>width$} | {}\n",
+            n,
+            redact_line(text.trim_end_matches(['\r', '\n'])),
+            width = width
+        );
+        if body.chars().count() + numbered.chars().count() > MAX_SNIPPET_CHARS {
+            break;
+        }
+        body.push_str(&numbered);
+        shown_end = n;
+    }
+
+    if n == 0 {
+        return Err(format!("`{}` is empty.", alias));
+    }
+    if n < start {
+        return Err(format!("`{}` only has {} lines.", alias, n));
+    }
+    if shown_end < start {
+        return Err(format!("Line {} of `{}` is too long to display.", start, alias));
+    }
+
+    Ok(format!(
+        "📄 `{}` lines {}-{}:\n```{}\n{}```",
+        alias,
+        start,
+        shown_end,
+        language_for(&path),
+        body
+    ))
+}
@@ -0,0 +1,201 @@
+//! `/smoke-test [env]`: HTTP checks against a deployed FitchFork instance.
+//!
+//! Each check is a request against the environment's base URL and the status code it must
+//! answer with, e.g. logging in, reaching the submission endpoint, and reading the marking
+//! status. The results are reported per check. With `SMOKE_TEST_AFTER_DEPLOY=true` the suite
+//! also runs after every successful deployment command and posts to the channel it ran in.
+//!
+//! Environment Variables:
+//! - `SMOKE_TEST_URL_<ENV>`: Base URL of an environment, e.g.
+//!   `SMOKE_TEST_URL_PRODUCTION=https://fitchfork.example.com`
+//! - `SMOKE_TEST_DEFAULT_ENV`: Environment used when none is given (default: the first one)
+//! - `SMOKE_CHECK_<NAME>`: `<METHOD> <path> <expected status> [JSON body]`, e.g.
+//!   `SMOKE_CHECK_LOGIN=POST /api/auth/login 200 {"username":"smoke","password":"..."}`
+//! - `SMOKE_TEST_AFTER_DEPLOY`: Set to `true` to run the suite after deployments
+
+use serenity::{
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    model::prelude::*,
+    prelude::*,
+};
+use std::{
+    env,
+    time::{Duration, Instant},
+};
+
+use super::command_spec::{CommandSpec, OptionSpec};
+use super::followup::Followup;
+use super::options::{reply_error, OptionError, Options};
+use crate::notify::{self, Delivery, Priority};
+
+const URL_PREFIX: &str = "SMOKE_TEST_URL_";
+const CHECK_PREFIX: &str = "SMOKE_CHECK_";
+/// Give a restarted API a moment to come up before testing it.
+const AFTER_DEPLOY_DELAY: Duration = Duration::from_secs(15);
+
+/// A single configured request and the status it must return.
+#[derive(Debug, Clone)]
+struct Check {
+    name: String,
+    method: reqwest::Method,
+    path: String,
+    expected: u16,
+    body: Option<String>,
+}
+
+impl Check {
+    fn parse(name: String, spec: &str) -> Option<Self> {
+        let mut parts = spec.trim().splitn(4, ' ');
+        let method = parts.next()?.to_uppercase().parse().ok()?;
+        let path = parts.next()?.to_string();
+        let expected = parts.next()?.parse().ok()?;
+        let body = parts.next().map(str::trim).filter(|b| !b.is_empty()).map(str::to_string);
+        Some(Self { name, method, path, expected, body })
+    }
+}
+
+/// Configured environments as `(name, base URL)`, sorted by name.
+fn environments() -> Vec<(String, String)> {
+    let mut envs: Vec<(String, String)> = env::vars()
+        .filter_map(|(key, url)| {
+            let name = key.strip_prefix(URL_PREFIX)?.to_lowercase();
+            if name.is_empty() || url.trim().is_empty() {
+                return None;
+            }
+            Some((name, url.trim().trim_end_matches('/').to_string()))
+        })
+        .collect();
+    envs.sort();
+    envs
+}
+
+fn checks() -> Vec<Check> {
+    let mut checks: Vec<Check> = env::vars()
+        .filter_map(|(key, spec)| {
+            let name = key.strip_prefix(CHECK_PREFIX)?.to_lowercase();
+            match Check::parse(name.clone(), &spec) {
+                Some(check) => Some(check),
+                None => {
+                    eprintln!("Ignoring malformed smoke check `{}`: {}", name, spec);
+                    None
+                }
+            }
+        })
+        .collect();
+    checks.sort_by(|a, b| a.name.cmp(&b.name));
+    checks
+}
+
+/// The environment called `name`, or the default one.
+fn environment(name: Option<&str>) -> Option<(String, String)> {
+    let envs = environments();
+    let name = name
+        .map(str::to_lowercase)
+        .or_else(|| env::var("SMOKE_TEST_DEFAULT_ENV").ok().map(|e| e.trim().to_lowercase()));
+    match name {
+        Some(name) => envs.into_iter().find(|(env, _)| *env == name),
+        None => envs.into_iter().next(),
+    }
+}
+
+/// Runs `check` against `base_url`, returning its report line and whether it passed.
+async fn run_check(base_url: &str, check: &Check) -> (bool, String) {
+    let started = Instant::now();
+    let url = format!("{}{}", base_url, check.path);
+    let mut request = crate::http::client().request(check.method.clone(), url);
+    if let Some(body) = &check.body {
+        request = request.header("Content-Type", "application/json").body(body.clone());
+    }
+
+    match request.send().await {
+        Ok(res) if res.status().as_u16() == check.expected => (
+            true,
+            format!("✅ `{}`: {} in {}ms", check.name, check.expected, started.elapsed().as_millis()),
+        ),
+        Ok(res) => (
+            false,
+            format!("❌ `{}`: expected {}, got {}", check.name, check.expected, res.status().as_u16()),
+        ),
+        Err(e) => (false, format!("❌ `{}`: {}", check.name, e)),
+    }
+}
+
+/// Runs every check against the environment and formats the report.
+async fn run_suite(env_name: &str, base_url: &str) -> String {
+    let checks = checks();
+    if checks.is_empty() {
+        return "❌ No smoke checks configured (SMOKE_CHECK_<NAME>).".to_string();
+    }
+
+    let mut passed = 0;
+    let mut lines = Vec::new();
+    for check in &checks {
+        let (ok, line) = run_check(base_url, check).await;
+        passed += ok as usize;
+        lines.push(line);
+    }
+
+    let verdict = if passed == checks.len() { "🟢" } else { "🔴" };
+    format!(
+        "{} **Smoke test** against `{}` ({}): {}/{} passed\n{}",
+        verdict,
+        env_name,
+        base_url,
+        passed,
+        checks.len(),
+        lines.join("\n")
+    )
+}
+
+/// Registers `/smoke-test`.
+pub async fn register_smoke_test_command(ctx: &Context) {
+    let env_option = environments()
+        .iter()
+        .fold(OptionSpec::string("env", "Environment to test"), |opt, (name, _)| opt.choice(name, name));
+
+    CommandSpec::new("smoke-test", "Run HTTP smoke checks against a deployed FitchFork instance")
+        .option(env_option)
+        .register(ctx)
+        .await;
+}
+
+/// Slash command handler for `/smoke-test`.
+pub async fn handle_smoke_test(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let requested = Options::of(command).trimmed("env");
+    let (env_name, base_url) = match environment(requested) {
+        Some(env) => env,
+        None => {
+            let reason = match requested {
+                Some(name) => format!("`{}` is not a configured environment.", name),
+                None => "No environments configured (SMOKE_TEST_URL_<ENV>).".to_string(),
+            };
+            return reply_error(ctx, command, &OptionError::invalid("env", reason)).await;
+        }
+    };
+
+    let followup = Followup::defer(ctx, command, false).await;
+    let report = run_suite(&env_name, &base_url).await;
+    followup.finish(ctx, command, report).await;
+}
+
+/// Runs the default environment's suite after a deployment, if `SMOKE_TEST_AFTER_DEPLOY` is set,
+/// and posts the report to `channel_id`.
+pub fn run_after_deploy(ctx: &Context, channel_id: ChannelId, label: &str) {
+    if !env::var("SMOKE_TEST_AFTER_DEPLOY").is_ok_and(|v| v.eq_ignore_ascii_case("true")) {
+        return;
+    }
+    let (env_name, base_url) = match environment(None) {
+        Some(env) => env,
+        None => return,
+    };
+
+    let (ctx, label) = (ctx.clone(), label.to_string());
+    tokio::spawn(async move {
+        tokio::time::sleep(AFTER_DEPLOY_DELAY).await;
+        let report = run_suite(&env_name, &base_url).await;
+        let message = format!("After **{}**:\n{}", label, report);
+        if let Delivery::Failed(e) = notify::post_text(&ctx, channel_id.0, Priority::Normal, message).await {
+            eprintln!("Failed to post smoke test results after {}: {}", label, e);
+        }
+    });
+}
@@ -0,0 +1,68 @@
+//! Startup announcement.
+//!
+//! On the first `ready` of a process, posts the bot version, why it restarted (deploy,
+//! restart, or crash, from the lifecycle marker), and how long it was down.
+//!
+//! Environment Variables:
+//! - `STARTUP_ANNOUNCE_CHANNEL_ID`: Channel to announce startups in (required)
+
+use chrono::Utc;
+use serenity::prelude::*;
+use std::{
+    env,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use crate::duration::format_duration;
+use crate::lifecycle::{self, RestartReason};
+use crate::notify::{self, Delivery, Priority};
+
+static ANNOUNCED: AtomicBool = AtomicBool::new(false);
+
+fn startup_message() -> String {
+    let version = lifecycle::version();
+    let previous = lifecycle::previous_run();
+
+    let reason = match previous.map(|p| &p.reason) {
+        Some(RestartReason::Deploy { from }) => format!("🚀 Deployed `{}` → `{}`", from, version),
+        Some(RestartReason::Restart) => format!("🔄 Restarted `{}` after a clean shutdown", version),
+        Some(RestartReason::Crash) => format!(
+            "⚠️ Restarted `{}` after an unclean exit (crash, kill, or power loss)",
+            version
+        ),
+        Some(RestartReason::FirstStart) | None => format!("🟢 Started `{}` for the first time", version),
+    };
+
+    let downtime = previous
+        .and_then(|p| p.stopped_at)
+        .map(|stopped| {
+            let secs = (Utc::now().timestamp() - stopped).max(0) as u64;
+            format!(
+                "\nDown for {} since the previous shutdown.",
+                format_duration(Duration::from_secs(secs))
+            )
+        })
+        .unwrap_or_default();
+
+    format!("{}{}", reason, downtime)
+}
+
+/// Posts the startup announcement once per process (reconnects fire `ready` again).
+pub async fn announce_startup(ctx: &Context) {
+    let channel_id: u64 = match env::var("STARTUP_ANNOUNCE_CHANNEL_ID")
+        .ok()
+        .and_then(|v| v.parse().ok())
+    {
+        Some(id) => id,
+        None => return,
+    };
+
+    if ANNOUNCED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    if let Delivery::Failed(e) = notify::post_text(ctx, channel_id, Priority::Normal, startup_message()).await {
+        eprintln!("Failed to post startup announcement: {}", e);
+    }
+}
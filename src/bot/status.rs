@@ -1,8 +1,9 @@
 //! Provides system status utilities and slash command handlers for `/status` and `/health`.
 //!
 //! Includes:
-//! - A reusable function to format system metrics (RAM, CPU, disks)
-//! - Slash command handlers (`/status`, `/health`)
+//! - Reusable functions to format system metrics (RAM, CPU, disks, services, API, proxy,
+//!   dependencies), whole or per section
+//! - Slash command handlers (`/status [section] [host] [at] [public]`, `/health`)
 //! - A background task that posts or edits a pinned status message on an interval,
 //!   persisting the message ID to survive bot restarts.
 
@@ -11,32 +12,42 @@ use serenity::{
     model::prelude::*,
     prelude::*,
 };
-use std::{env, fs, time::Duration};
+use std::{collections::BTreeMap, env, fs, time::Duration};
 use tokio::time::sleep;
 use sysinfo::{CpuExt, DiskExt, System, SystemExt, ComponentExt};
-use chrono::Local;
-use std::sync::atomic::{AtomicBool, Ordering};
+use chrono::{Local, TimeZone};
+
+use super::command_spec::{CommandSpec, OptionSpec};
+use super::followup::Followup;
+use super::options::Options;
+use super::purge::{purge, PurgeFilter};
+use super::{api_metrics, dependencies, heartbeat, metrics, proxy, status_history, status_hosts};
+use crate::guilds::{self, ChannelKind};
+use crate::lifecycle;
+use crate::notify::{self, Outgoing, Priority};
+use crate::store;
+use crate::tasks::Tasks;
 
 const STATUS_MSG_PATH: &str = "status_message_id.txt";
-static STATUS_LOOP_STARTED: AtomicBool = AtomicBool::new(false);
+const GUILD_STATUS_FILE: &str = "guild_status_messages.json";
 
 
-/// Builds a formatted system status message string.
-///
-/// If `update_interval_secs` is Some, includes an "updates every Xs" line.
-///
-/// Includes:
-/// - RAM usage (percent + MiB)
-/// - Average CPU usage and per-core breakdown
-/// - Disk usage by mount point (used/total GB + percent)
-pub fn build_status_message(update_interval_secs: Option<u64>) -> String {
+/// Sections that `/status section:<name>` can render on their own.
+pub const SECTIONS: &[&str] = &["cpu", "ram", "disks", "services", "api", "proxy", "dependencies"];
+
+/// Creates a `System` with fresh readings (CPU usage needs two samples).
+pub(super) fn sample_system() -> System {
     let mut sys = System::new_all();
 
     sys.refresh_all();
     std::thread::sleep(Duration::from_millis(500));
     sys.refresh_cpu();
 
-    // === CPU Load ===
+    sys
+}
+
+/// Average CPU usage, core count, temperature, and per-core breakdown.
+fn cpu_section(sys: &System) -> String {
     let cpu_count = sys.cpus().len();
     let avg_cpu = sys
         .cpus()
@@ -53,12 +64,31 @@ pub fn build_status_message(update_interval_secs: Option<u64>) -> String {
         .collect::<Vec<_>>()
         .join("\n");
 
-    // === RAM ===
+    // First valid temperature sensor
+    let cpu_temp = sys
+        .components()
+        .iter()
+        .find(|c| c.label().to_lowercase().contains("cpu") || c.label().is_empty())
+        .map(|c| format!("{:.1}°C", c.temperature()))
+        .unwrap_or_else(|| "N/A".to_string());
+
+    format!(
+        "CPU Usage:  {:.1}% average over {} cores\nCPU Temp:   {}\n\n{}",
+        avg_cpu, cpu_count, cpu_temp, cpu_details
+    )
+}
+
+/// RAM usage (percent + MiB).
+fn ram_section(sys: &System) -> String {
     let ram_used = sys.used_memory() / 1024;
     let ram_total = sys.total_memory() / 1024;
     let ram_percent = (ram_used as f32 / ram_total as f32) * 100.0;
 
-    // === Disks ===
+    format!("RAM Usage:  {:.1}% ({} MiB / {} MiB)", ram_percent, ram_used, ram_total)
+}
+
+/// Disk usage by mount point (used/total GB + percent).
+fn disks_section(sys: &System) -> String {
     let disk_info = sys
         .disks()
         .iter()
@@ -77,13 +107,79 @@ pub fn build_status_message(update_interval_secs: Option<u64>) -> String {
         .collect::<Vec<_>>()
         .join("\n");
 
-    // === CPU Temperature (first valid sensor) ===
-    let cpu_temp = sys
-        .components()
+    format!("Disks:\n{}", disk_info)
+}
+
+/// Configured systemd services from `STATUS_SERVICES`.
+pub(super) fn configured_services() -> Vec<String> {
+    env::var("STATUS_SERVICES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// State of each configured systemd service (`systemctl is-active`).
+fn services_section() -> String {
+    let services = configured_services();
+    if services.is_empty() {
+        return "Services:\n(none configured, set STATUS_SERVICES)".to_string();
+    }
+
+    let lines = services
         .iter()
-        .find(|c| c.label().to_lowercase().contains("cpu") || c.label().is_empty())
-        .map(|c| format!("{:.1}°C", c.temperature()))
-        .unwrap_or_else(|| "N/A".to_string());
+        .map(|service| {
+            let state = std::process::Command::new("systemctl")
+                .args(["is-active", service])
+                .output()
+                .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+                .unwrap_or_else(|_| "unknown".to_string());
+            let icon = if state == "active" { "✅" } else { "❌" };
+            format!("- {} {}: {}", icon, service, state)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!("Services:\n{}", lines)
+}
+
+/// Builds a single status section (`cpu`, `ram`, `disks`, `services`, `api`, `proxy`, or
+/// `dependencies`) as a code block.
+pub fn build_section_message(section: &str) -> Option<String> {
+    let body = match section {
+        "cpu" => cpu_section(&sample_system()),
+        "ram" => ram_section(&System::new_all()),
+        "disks" => disks_section(&System::new_all()),
+        "services" => services_section(),
+        "api" => api_metrics::api_section()
+            .unwrap_or_else(|| "API:\n(not monitored, set API_METRICS_URL)".to_string()),
+        "proxy" => proxy::proxy_section()
+            .unwrap_or_else(|| "Proxy:\n(not monitored, set PROXY_STATUS_URL)".to_string()),
+        "dependencies" => dependencies::dependencies_section()
+            .unwrap_or_else(|| "Dependencies:\n(none configured, set STATUS_DEPENDENCIES)".to_string()),
+        _ => return None,
+    };
+    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S");
+
+    Some(format!("```\n{}\n\nAs of: {}\n```", body, timestamp))
+}
+
+/// Builds a formatted system status message string.
+///
+/// If `update_interval_secs` is Some, includes an "updates every Xs" line.
+///
+/// Includes:
+/// - RAM usage (percent + MiB)
+/// - Average CPU usage and per-core breakdown
+/// - Disk usage by mount point (used/total GB + percent)
+/// - Systemd service states, if `STATUS_SERVICES` is configured
+/// - API request, 5xx and latency figures, if `API_METRICS_URL` is configured
+/// - Reverse-proxy connections and request rate, if `PROXY_STATUS_URL` is configured
+/// - Reachability of external dependencies, if `STATUS_DEPENDENCIES` is configured
+/// - A warning for each disk on track to fill up soon (see [`metrics::disk_forecasts`])
+pub fn build_status_message(update_interval_secs: Option<u64>) -> String {
+    let sys = sample_system();
 
     // === Uptime ===
     let uptime_secs = sys.uptime();
@@ -99,6 +195,31 @@ pub fn build_status_message(update_interval_secs: Option<u64>) -> String {
         .map(|s| format!(" (updates every {}s)", s))
         .unwrap_or_default();
 
+    let services_str = if configured_services().is_empty() {
+        String::new()
+    } else {
+        format!("\n\n{}", services_section())
+    };
+    let api_str = api_metrics::api_section()
+        .map(|section| format!("\n\n{}", section))
+        .unwrap_or_default();
+    let proxy_str = proxy::proxy_section()
+        .map(|section| format!("\n\n{}", section))
+        .unwrap_or_default();
+    let dependencies_str = dependencies::dependencies_section()
+        .map(|section| format!("\n\n{}", section))
+        .unwrap_or_default();
+
+    let forecasts: Vec<String> = metrics::disk_forecasts(Local::now().timestamp())
+        .iter()
+        .map(|f| format!("⚠️ {}", f.describe()))
+        .collect();
+    let forecast_str = if forecasts.is_empty() {
+        String::new()
+    } else {
+        format!("\n\n{}", forecasts.join("\n"))
+    };
+
     format!(
         "```\n\
 System Status{interval_str}
@@ -106,40 +227,107 @@ System Status{interval_str}
 Last Updated: {timestamp}
 System Uptime: {uptime_str}
 
-RAM Usage:  {:.1}% ({} MiB / {} MiB)
-CPU Usage:  {:.1}% average over {} cores
-CPU Temp:   {}
-
-{}
+{ram}
+{cpu}
 
-Disks:
-{}
+{disks}{services}{api}{proxy}{dependencies}{forecast}
 ```",
-        ram_percent,
-        ram_used,
-        ram_total,
-        avg_cpu,
-        cpu_count,
-        cpu_temp,
-        cpu_details,
-        disk_info,
+        ram = ram_section(&sys),
+        cpu = cpu_section(&sys),
+        disks = disks_section(&sys),
+        services = services_str,
+        api = api_str,
+        proxy = proxy_str,
+        dependencies = dependencies_str,
+        forecast = forecast_str,
         interval_str = interval_str,
         timestamp = timestamp,
         uptime_str = uptime_str
     )
 }
 
+/// Registers `/status` with its optional parameters.
+pub async fn register_status_command(ctx: &Context) {
+    let mut hosts = vec![status_hosts::local_host_name()];
+    hosts.extend(status_hosts::remote_hosts().into_iter().map(|(name, _)| name));
+
+    let mut section = OptionSpec::string("section", "Only show one part of the dashboard");
+    for name in SECTIONS {
+        section = section.choice(name, name);
+    }
+    let mut host = OptionSpec::string("host", "Which host to query (default: this one)");
+    for name in &hosts {
+        host = host.choice(name, name);
+    }
+
+    CommandSpec::new("status", "Show system status (CPU, RAM, Disk)")
+        .option(section)
+        .option(host)
+        .option(OptionSpec::string(
+            "at",
+            "Show the archived dashboard at a past local time (YYYY-MM-DD HH:MM or HH:MM)",
+        ))
+        .option(OptionSpec::boolean(
+            "public",
+            "Post the result visibly in the channel (default: only you see it)",
+        ))
+        .register(ctx)
+        .await;
+}
+
 /// Slash command handler for `/status`.
 ///
-/// Replies to the command invoker with the current system resource usage.
+/// Replies (ephemerally unless `public:true`) with the current system resource usage,
+/// optionally restricted to one `section` and/or fetched from another `host`. With `at`,
+/// replies with the archived dashboard snapshot closest to (and not after) that time.
 pub async fn handle_status(ctx: &Context, command: &ApplicationCommandInteraction) {
-    let content = build_status_message(None);
-
-    let _ = command
-        .create_interaction_response(&ctx.http, |res| {
-            res.interaction_response_data(|msg| msg.content(content))
+    let options = Options::of(command);
+    let at = options.str("at").map(str::to_string);
+    let section = options.str("section").map(str::to_string);
+    let host = options.str("host").map(str::to_lowercase);
+    let public = options.bool("public").unwrap_or(false);
+
+    let followup = Followup::defer(ctx, command, !public).await;
+
+    let content = match (at, host) {
+        (Some(input), _) => archived_status(&input),
+        (None, Some(host)) if host != status_hosts::local_host_name() => {
+            match status_hosts::remote_hosts().into_iter().find(|(name, _)| *name == host) {
+                Some((_, url)) => match status_hosts::fetch_remote(&url, section.as_deref()).await {
+                    Ok(text) => format!("**{}**\n{}", host, text),
+                    Err(e) => format!("❌ Could not fetch status from `{}`: {}", host, e),
+                },
+                None => format!("❌ Unknown host `{}`.", host),
+            }
+        }
+        (None, _) => tokio::task::spawn_blocking(move || match section {
+            Some(section) => build_section_message(&section)
+                .unwrap_or_else(|| format!("❌ Unknown section `{}`.", section)),
+            None => build_status_message(None),
         })
-        .await;
+        .await
+        .unwrap_or_else(|e| format!("❌ Failed to collect status: {}", e)),
+    };
+
+    followup.finish(ctx, command, content).await;
+}
+
+/// Renders the archived dashboard snapshot for a user-supplied time.
+fn archived_status(input: &str) -> String {
+    match status_history::parse_time(input) {
+        Some(time) => match status_history::find_at(time) {
+            Some(snapshot) => {
+                let taken = Local
+                    .timestamp_opt(snapshot.timestamp, 0)
+                    .single()
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_else(|| snapshot.timestamp.to_string());
+                format!("Snapshot from {}:\n{}", taken, snapshot.content)
+            }
+            None => format!("❌ No archived status snapshot at or before `{}`.", input),
+        },
+        None => format!("❌ Could not parse `{}`. Use `YYYY-MM-DD HH:MM` or `HH:MM`.", input),
+    }
 }
 
 /// Slash command handler for `/health`.
@@ -166,18 +354,54 @@ fn save_status_message_id(id: MessageId) {
     let _ = fs::write(STATUS_MSG_PATH, id.0.to_string());
 }
 
+/// Mirrors the status message into each guild's own status channel, editing the previous
+/// message where possible. Message IDs are kept in `guild_status_messages.json` by channel.
+async fn update_guild_status_messages(ctx: &Context, content: &str) {
+    let channels = guilds::guild_channels(ChannelKind::Status);
+    let mut messages: BTreeMap<u64, u64> = store::load(GUILD_STATUS_FILE);
+    if channels.is_empty() && messages.is_empty() {
+        return;
+    }
+    messages.retain(|channel_id, _| channels.contains(channel_id));
+
+    for channel_id in channels {
+        if let Some(mid) = messages.get(&channel_id) {
+            let edited = notify::edit(ctx, channel_id, *mid, Priority::Normal, Outgoing::text(content)).await;
+            if edited.is_ok() {
+                continue;
+            }
+        }
+
+        match notify::post(ctx, channel_id, Priority::Normal, Outgoing::text(content)).await {
+            Ok(msg) => {
+                let _ = msg.pin(&ctx.http).await;
+                messages.insert(channel_id, msg.id.0);
+            }
+            Err(e) => eprintln!("Failed to post status in guild channel {}: {}", channel_id, e),
+        }
+    }
+
+    store::save(GUILD_STATUS_FILE, &messages);
+}
+
 /// Spawns a background task that posts or edits a pinned status message in a Discord channel.
 ///
 /// Behavior:
 /// - On first run, loads or creates the status message and pins it.
 /// - On each interval, edits the existing message (or replaces it if missing).
+/// - Before replacing a rendering, appends it to the local status history archive (if enabled).
+/// - Mirrors the rendering into every guild's own status channel from `guilds.json`.
+/// - Records a resource sample for trend reporting.
+/// - After each tick, pings the external heartbeat monitor (if configured).
 ///
 /// Environment Variables:
 /// - `DISCORD_STATUS_CHANNEL_ID`: Channel to post the status
 /// - `STATUS_UPDATE_INTERVAL_SECS`: Seconds between updates (default: 600)
-pub async fn start_status_loop(ctx: Context) {
-    if STATUS_LOOP_STARTED.swap(true, Ordering::SeqCst) {
-        println!("Status loop already started, skipping.");
+/// - `STATUS_HISTORY_LIMIT`: Snapshots to keep for `/status at` (default: 0 = disabled)
+/// - `HEARTBEAT_URL`: Optional dead-man's-switch URL pinged on every tick
+pub async fn start_status_loop(ctx: Context, tasks: &Tasks) {
+    if tasks.is_running("status") {
+        println!("Status loop already running, reusing it.");
         return;
     }
 
@@ -191,106 +415,129 @@ pub async fn start_status_loop(ctx: Context) {
         .parse()
         .unwrap_or(600);
 
-    tokio::spawn(async move {
-        let channel = ChannelId(channel_id);
-        let http = &ctx.http;
-        let mut status_message_id = load_status_message_id();
+    tasks.spawn("status", move |beat| {
+        let ctx = ctx.clone();
+        async move {
+            let channel = ChannelId(channel_id);
+            let http = &ctx.http;
+            let mut status_message_id = load_status_message_id();
+            // Last rendering posted (unix timestamp, content), archived before it is replaced.
+            let mut previous: Option<(i64, String)> = None;
 
-        // Validate saved message ID
-        if let Some(mid) = status_message_id {
-            if channel.message(http, mid).await.is_err() {
-                status_message_id = None;
-                let _ = fs::remove_file(STATUS_MSG_PATH);
+            // Validate saved message ID
+            if let Some(mid) = status_message_id {
+                match channel.message(http, mid).await {
+                    Ok(msg) => {
+                        let posted = msg.edited_timestamp.unwrap_or(msg.timestamp);
+                        previous = Some((posted.unix_timestamp(), msg.content));
+                    }
+                    Err(_) => {
+                        status_message_id = None;
+                        let _ = fs::remove_file(STATUS_MSG_PATH);
+                    }
+                }
             }
-        }
 
-        // Look for existing pinned status message
-        if status_message_id.is_none() {
-            if let Ok(bot_user) = http.get_current_user().await {
-                if let Ok(pins) = channel.pins(http).await {
-                    for msg in &pins {
-                        if msg.author.id == bot_user.id
-                            && msg.content.starts_with("```\nSystem Status")
-                        {
-                            status_message_id = Some(msg.id);
-                            save_status_message_id(msg.id);
-                            break;
+            // Look for existing pinned status message
+            if status_message_id.is_none() {
+                if let Ok(bot_user) = http.get_current_user().await {
+                    if let Ok(pins) = channel.pins(http).await {
+                        for msg in &pins {
+                            if msg.author.id == bot_user.id
+                                && msg.content.starts_with("```\nSystem Status")
+                            {
+                                status_message_id = Some(msg.id);
+                                save_status_message_id(msg.id);
+                                break;
+                            }
                         }
                     }
                 }
             }
-        }
 
-        loop {
-            let content = build_status_message(Some(interval_secs));
+            loop {
+                beat.tick();
+                let content = build_status_message(Some(interval_secs));
 
-            // Try to edit existing message
-            if let Some(mid) = status_message_id {
-                match channel.edit_message(http, mid, |m| m.content(content.clone())).await {
-                    Ok(_) => {
-                        sleep(Duration::from_secs(interval_secs)).await;
-                        continue;
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to edit status message: {e:?}");
-
-                        // Check if it's a transient server error
-                        if let serenity::Error::Http(http_err) = &e {
-                            if let serenity::http::HttpError::UnsuccessfulRequest(resp) = &**http_err {
-                                if resp.status_code.as_u16() >= 500 {
-                                    eprintln!(
-                                        "Discord server error ({}), keeping message id and retrying next loop",
-                                        resp.status_code
-                                    );
-                                    sleep(Duration::from_secs(interval_secs)).await;
-                                    continue;
-                                }
-                            }
-                        }
+                // Keep a resource sample for trend reports, and mark the bot as alive so a
+                // crash's downtime can be estimated on the next start.
+                tokio::task::spawn_blocking(metrics::record_sample);
+                lifecycle::touch();
 
-                        // Otherwise: treat as invalid and clear state
-                        eprintln!("Clearing status message state");
-                        status_message_id = None;
-                        let _ = fs::remove_file(STATUS_MSG_PATH);
-                    }
+                if let Some((timestamp, rendering)) = previous.take() {
+                    status_history::archive(timestamp, &rendering);
                 }
-            }
 
-            // Clean up all bot messages before creating a new one
-            if let Ok(bot_user) = http.get_current_user().await {
-                // Unpin & delete pinned bot messages
-                if let Ok(pins) = channel.pins(http).await {
-                    for msg in &pins {
-                        if msg.author.id == bot_user.id {
-                            let _ = msg.unpin(http).await;
-                            let _ = msg.delete(http).await;
+                update_guild_status_messages(&ctx, &content).await;
+
+                // Try to edit existing message
+                if let Some(mid) = status_message_id {
+                    let edit = Outgoing::text(&content);
+                    match notify::edit(&ctx, channel.0, mid.0, Priority::Normal, edit).await {
+                        Ok(_) => {
+                            previous = Some((Local::now().timestamp(), content));
+                            heartbeat::ping(true);
+                            sleep(Duration::from_secs(interval_secs)).await;
+                            continue;
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to edit status message: {}", e);
+
+                            // Discord server errors and network failures don't mean the message is gone
+                            if e.transient {
+                                eprintln!("Transient error, keeping message id and retrying next loop");
+                                heartbeat::ping(false);
+                                sleep(Duration::from_secs(interval_secs)).await;
+                                continue;
+                            }
+
+                            // Otherwise: treat as invalid and clear state
+                            eprintln!("Clearing status message state");
+                            status_message_id = None;
+                            let _ = fs::remove_file(STATUS_MSG_PATH);
                         }
                     }
                 }
 
-                // Also delete recent bot messages (un-pinned)
-                if let Ok(messages) = channel.messages(http, |r| r.limit(50)).await {
-                    for msg in &messages {
-                        if msg.author.id == bot_user.id {
-                            let _ = msg.delete(http).await;
+                // Clean up old bot messages before creating a new one
+                if let Ok(bot_user) = http.get_current_user().await {
+                    // Unpin old bot messages so the purge below can remove them
+                    if let Ok(pins) = channel.pins(http).await {
+                        for msg in &pins {
+                            if msg.author.id == bot_user.id {
+                                let _ = msg.unpin(http).await;
+                            }
                         }
                     }
-                }
-            }
 
-            // Send new message
-            match channel.send_message(http, |m| m.content(content.clone())).await {
-                Ok(msg) => {
-                    let _ = msg.pin(http).await;
-                    save_status_message_id(msg.id);
-                    status_message_id = Some(msg.id);
+                    // Bulk-delete recent bot messages
+                    let filter = PurgeFilter {
+                        author: Some(bot_user.id),
+                        limit: 50,
+                        ..Default::default()
+                    };
+                    if let Err(e) = purge(http, channel, &filter).await {
+                        eprintln!("Failed to clean up status channel: {}", e);
+                    }
                 }
-                Err(e) => {
-                    eprintln!("Failed to send new status message: {e:?}");
+
+                // Send new message
+                match notify::post(&ctx, channel.0, Priority::Normal, Outgoing::text(&content)).await {
+                    Ok(msg) => {
+                        let _ = msg.pin(http).await;
+                        save_status_message_id(msg.id);
+                        status_message_id = Some(msg.id);
+                        previous = Some((Local::now().timestamp(), content));
+                        heartbeat::ping(true);
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to send new status message: {}", e);
+                        heartbeat::ping(false);
+                    }
                 }
-            }
 
-            sleep(Duration::from_secs(interval_secs)).await;
+                sleep(Duration::from_secs(interval_secs)).await;
+            }
         }
     });
 }
@@ -0,0 +1,78 @@
+//! Rolling local archive of past status dashboard renderings.
+//!
+//! Before the status loop replaces the pinned message, the previous rendering is appended
+//! to `status_history.json`, so `/status at:<time>` can show what the dashboard looked like
+//! at a past moment (handy for postmortems).
+//!
+//! Environment Variables:
+//! - `STATUS_HISTORY_LIMIT`: Number of snapshots to keep (default: 0 = archiving disabled)
+
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::env;
+
+use crate::store;
+
+const HISTORY_FILE: &str = "status_history.json";
+
+/// A single archived status rendering.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Unix timestamp (seconds) of when the rendering was posted.
+    pub timestamp: i64,
+    pub content: String,
+}
+
+/// Maximum number of snapshots to keep; `0` disables archiving.
+fn history_limit() -> usize {
+    env::var("STATUS_HISTORY_LIMIT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Appends a rendering to the archive, dropping the oldest entries past the limit.
+pub fn archive(timestamp: i64, content: &str) {
+    let limit = history_limit();
+    if limit == 0 {
+        return;
+    }
+
+    let mut history: Vec<Snapshot> = store::load(HISTORY_FILE);
+    history.push(Snapshot {
+        timestamp,
+        content: content.to_string(),
+    });
+
+    if history.len() > limit {
+        let excess = history.len() - limit;
+        history.drain(..excess);
+    }
+
+    store::save(HISTORY_FILE, &history);
+}
+
+/// Returns the most recent snapshot taken at or before `at`.
+pub fn find_at(at: DateTime<Local>) -> Option<Snapshot> {
+    let history: Vec<Snapshot> = store::load(HISTORY_FILE);
+    history
+        .into_iter()
+        .filter(|s| s.timestamp <= at.timestamp())
+        .max_by_key(|s| s.timestamp)
+}
+
+/// Parses a user-supplied local time: `YYYY-MM-DD HH:MM[:SS]` or `HH:MM` (today).
+pub fn parse_time(input: &str) -> Option<DateTime<Local>> {
+    let input = input.trim();
+
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(input, fmt).ok())
+        .or_else(|| {
+            NaiveTime::parse_from_str(input, "%H:%M")
+                .ok()
+                .map(|t| Local::now().date_naive().and_time(t))
+        })?;
+
+    Local.from_local_datetime(&naive).earliest()
+}
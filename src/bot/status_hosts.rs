@@ -0,0 +1,113 @@
+//! Multi-host support for `/status host:<name>`.
+//!
+//! Every bot instance can expose its status text at `GET /status?section=<name>` (protected
+//! by a bearer token). An instance lists the other hosts it can query, so a single bot in
+//! Discord can show the dashboard of any FitchFork machine.
+//!
+//! Environment Variables:
+//! - `STATUS_HOST_NAME`: Name of this host (default: `local`)
+//! - `STATUS_REMOTE_<NAME>`: Base URL of another bot instance, e.g. `http://10.0.0.5:8080`
+//! - `STATUS_API_TOKEN`: Bearer token required by (and sent to) `GET /status`
+
+use axum::{
+    extract::Query,
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use serde::Deserialize;
+use std::env;
+
+use super::status::{build_section_message, build_status_message};
+use crate::secrets;
+
+const REMOTE_PREFIX: &str = "STATUS_REMOTE_";
+const TOKEN_VAR: &str = "STATUS_API_TOKEN";
+
+/// Name this instance answers to in `/status host:<name>`.
+pub fn local_host_name() -> String {
+    env::var("STATUS_HOST_NAME")
+        .map(|n| n.trim().to_lowercase())
+        .ok()
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| "local".to_string())
+}
+
+/// Configured remote hosts as `(name, base_url)`, sorted by name.
+pub fn remote_hosts() -> Vec<(String, String)> {
+    let mut hosts: Vec<(String, String)> = env::vars()
+        .filter_map(|(key, url)| {
+            let name = key.strip_prefix(REMOTE_PREFIX)?.to_lowercase();
+            if name.is_empty() || url.trim().is_empty() {
+                return None;
+            }
+            Some((name, url.trim().trim_end_matches('/').to_string()))
+        })
+        .collect();
+    hosts.sort();
+    hosts
+}
+
+fn api_token() -> Option<String> {
+    secrets::current(TOKEN_VAR)
+}
+
+/// Fetches the status text (optionally a single section) from a remote bot instance.
+pub async fn fetch_remote(base_url: &str, section: Option<&str>) -> Result<String, String> {
+    let mut request = crate::http::client().get(format!("{}/status", base_url));
+    if let Some(section) = section {
+        request = request.query(&[("section", section)]);
+    }
+    if let Some(token) = api_token() {
+        request = request.bearer_auth(token);
+    }
+
+    let res = request
+        .send()
+        .await
+        .map_err(|e| format!("request failed: {}", e))?;
+    if !res.status().is_success() {
+        return Err(format!("remote returned {}", res.status()));
+    }
+    res.text().await.map_err(|e| format!("invalid response: {}", e))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatusQuery {
+    pub section: Option<String>,
+}
+
+/// Routes exposing this host's status to other bot instances.
+pub fn routes() -> Router {
+    Router::new().route("/", get(status_endpoint))
+}
+
+/// `GET /status?section=<name>`: returns the rendered status text.
+async fn status_endpoint(headers: HeaderMap, Query(query): Query<StatusQuery>) -> Response {
+    let token = match api_token() {
+        Some(token) => token,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    let authorized = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|t| secrets::accepts(TOKEN_VAR, &token, t));
+    if !authorized {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let rendered = tokio::task::spawn_blocking(move || match query.section.as_deref() {
+        Some(section) => build_section_message(section),
+        None => Some(build_status_message(None)),
+    })
+    .await;
+
+    match rendered {
+        Ok(Some(text)) => text.into_response(),
+        Ok(None) => (StatusCode::BAD_REQUEST, "unknown section").into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
@@ -0,0 +1,66 @@
+//! `/tasks`: health of the supervised background loops.
+//!
+//! Operators only. Lists each task from [`crate::tasks::Tasks`] with whether it is running, when
+//! it last ran an iteration, and how often it was restarted after a panic.
+
+use serenity::{
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    prelude::*,
+};
+
+use super::command_spec::CommandSpec;
+use super::paginator::Paginator;
+use crate::tasks::{TaskStatus, Tasks};
+
+/// Longest panic message shown per task.
+const MAX_PANIC_CHARS: usize = 200;
+
+/// Registers `/tasks`.
+pub async fn register_tasks_command(ctx: &Context) {
+    CommandSpec::new("tasks", "Show the health of the bot's background tasks")
+        .register(ctx)
+        .await;
+}
+
+/// Slash command handler for `/tasks`.
+pub async fn handle_tasks(ctx: &Context, command: &ApplicationCommandInteraction, tasks: &Tasks) {
+    let statuses = tasks.statuses();
+    if statuses.is_empty() {
+        let _ = command
+            .create_interaction_response(&ctx.http, |res| {
+                res.interaction_response_data(|msg| {
+                    msg.content("No background tasks have been started.").ephemeral(true)
+                })
+            })
+            .await;
+        return;
+    }
+
+    let lines = statuses.iter().map(describe).collect();
+    Paginator::new("**Background tasks:**", lines).ephemeral(true).send(ctx, command).await;
+}
+
+fn describe(task: &TaskStatus) -> String {
+    let emoji = match (task.running, task.restarts) {
+        (false, _) => "🔴",
+        (true, 0) => "🟢",
+        (true, _) => "🟠",
+    };
+    let last_run = task
+        .last_beat
+        .map(|t| format!("last ran <t:{}:R>", t))
+        .unwrap_or_else(|| "hasn't run yet".into());
+
+    let mut line = format!(
+        "{} `{}`: {}, started <t:{}:R>, {} restart(s)",
+        emoji, task.name, last_run, task.started_at, task.restarts
+    );
+    if let Some(panic) = &task.last_panic {
+        let mut excerpt: String = panic.chars().take(MAX_PANIC_CHARS).collect();
+        if panic.chars().count() > MAX_PANIC_CHARS {
+            excerpt.push('…');
+        }
+        line.push_str(&format!("\n  Last panic: `{}`", excerpt.replace('`', "'")));
+    }
+    line
+}
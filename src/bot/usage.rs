@@ -0,0 +1,349 @@
+//! Command usage analytics and the monthly usage report.
+//!
+//! Every dispatched slash command is counted per month, by command (and subcommand) and by
+//! user, in `command_usage.json`. On the configured day each month the report for the previous
+//! month is posted: total runs, the most used commands, commands nobody ran (with when they
+//! were last used), and the most active users — to help decide what to deprecate and where to
+//! invest. `/usage report [month]` shows the same report on demand.
+//!
+//! Environment Variables:
+//! - `USAGE_REPORT_CHANNEL_ID`: Channel for the monthly report; `/usage report` works without it
+//! - `USAGE_REPORT_DAY`: Day of the month to post on, 1-28 (default: 1)
+//! - `USAGE_REPORT_HOUR`: Local hour to post at, 0-23 (default: 9)
+//! - `USAGE_RETENTION_MONTHS`: Months of usage to keep (default: 24)
+
+use chrono::{Datelike, Local, Months, NaiveDate, Timelike};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    builder::CreateEmbed,
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    model::prelude::*,
+    prelude::*,
+    utils::Colour,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    sync::Mutex,
+    time::Duration,
+};
+use tokio::time::sleep;
+
+use super::auth;
+use super::command_spec::{CommandSpec, OptionSpec};
+use super::options::{reply_error, OptionError, Options};
+use crate::notify::{self, Outgoing, Priority, SendError};
+use crate::store;
+use crate::tasks::Tasks;
+
+const USAGE_FILE: &str = "command_usage.json";
+const STATE_FILE: &str = "usage_report_state.json";
+const DEFAULT_RETENTION_MONTHS: usize = 24;
+const CHECK_INTERVAL: Duration = Duration::from_secs(600);
+/// Entries listed per ranking in the report.
+const TOP: usize = 10;
+
+static USAGE: Lazy<Mutex<Usage>> = Lazy::new(|| Mutex::new(store::load(USAGE_FILE)));
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Usage {
+    /// Usage per month, keyed `YYYY-MM` (local time).
+    months: BTreeMap<String, MonthUsage>,
+    /// Unix timestamp of the last run of every command, kept beyond the retention window.
+    last_used: BTreeMap<String, i64>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct MonthUsage {
+    /// Runs per invocation, e.g. `watch add` or `status`.
+    commands: BTreeMap<String, u64>,
+    /// Runs per user ID.
+    users: BTreeMap<u64, UserUsage>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct UserUsage {
+    /// Tag at the user's latest run.
+    tag: String,
+    runs: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ReportState {
+    /// Month (`YYYY-MM`) of the last report posted.
+    last_month: Option<String>,
+}
+
+fn month_key(date: NaiveDate) -> String {
+    format!("{:04}-{:02}", date.year(), date.month())
+}
+
+fn previous_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date) - Months::new(1)
+}
+
+fn retention_months() -> usize {
+    env::var("USAGE_RETENTION_MONTHS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|months| *months > 0)
+        .unwrap_or(DEFAULT_RETENTION_MONTHS)
+}
+
+fn report_day() -> u32 {
+    env::var("USAGE_REPORT_DAY")
+        .ok()
+        .and_then(|d| d.trim().parse().ok())
+        .filter(|d| (1..=28).contains(d))
+        .unwrap_or(1)
+}
+
+fn report_hour() -> u32 {
+    env::var("USAGE_REPORT_HOUR")
+        .ok()
+        .and_then(|h| h.trim().parse().ok())
+        .filter(|h| *h < 24)
+        .unwrap_or(9)
+}
+
+/// Counts a run of `command` by its invoker.
+pub fn record(command: &ApplicationCommandInteraction) {
+    let options = Options::of(command);
+    let invocation = match options.subcommand() {
+        Some((sub, _)) => format!("{} {}", command.data.name, sub),
+        None => command.data.name.clone(),
+    };
+    let now = Local::now();
+
+    let mut usage = USAGE.lock().unwrap();
+    let month = usage.months.entry(month_key(now.date_naive())).or_default();
+    *month.commands.entry(invocation).or_default() += 1;
+    let user = month.users.entry(command.user.id.0).or_default();
+    user.tag = command.user.tag();
+    user.runs += 1;
+    usage.last_used.insert(command.data.name.clone(), now.timestamp());
+
+    let retention = retention_months();
+    while usage.months.len() > retention {
+        let oldest = usage.months.keys().next().cloned().unwrap_or_default();
+        usage.months.remove(&oldest);
+    }
+    store::save(USAGE_FILE, &*usage);
+}
+
+/// Every slash command the bot registers.
+fn all_commands() -> BTreeSet<&'static str> {
+    auth::COMMAND_ACCESS.iter().map(|(name, _, _)| *name).collect()
+}
+
+/// The figures for one month.
+struct MonthlyReport {
+    month: String,
+    total: u64,
+    /// Change in total runs against the month before, if it was recorded.
+    previous_total: Option<u64>,
+    top_commands: Vec<(String, u64)>,
+    /// Commands nobody ran this month, with their last run if any.
+    unused: Vec<(&'static str, Option<i64>)>,
+    top_users: Vec<(u64, UserUsage)>,
+    users: usize,
+}
+
+fn compile(month: NaiveDate) -> MonthlyReport {
+    let key = month_key(month);
+    let usage = USAGE.lock().unwrap();
+    let current = usage.months.get(&key).cloned().unwrap_or_default();
+    let previous_total = usage
+        .months
+        .get(&month_key(previous_month(month)))
+        .map(|m| m.commands.values().sum());
+
+    let mut top_commands: Vec<(String, u64)> = current.commands.clone().into_iter().collect();
+    top_commands.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let used: BTreeSet<&str> = current
+        .commands
+        .keys()
+        .map(|invocation| invocation.split(' ').next().unwrap_or_default())
+        .collect();
+    let unused = all_commands()
+        .into_iter()
+        .filter(|name| !used.contains(name))
+        .map(|name| (name, usage.last_used.get(name).copied()))
+        .collect();
+
+    let mut top_users: Vec<(u64, UserUsage)> = current.users.clone().into_iter().collect();
+    top_users.sort_by(|a, b| b.1.runs.cmp(&a.1.runs).then_with(|| a.1.tag.cmp(&b.1.tag)));
+
+    MonthlyReport {
+        month: key,
+        total: current.commands.values().sum(),
+        previous_total,
+        top_commands,
+        unused,
+        users: top_users.len(),
+        top_users,
+    }
+}
+
+impl MonthlyReport {
+    fn totals(&self) -> String {
+        let change = match self.previous_total {
+            Some(previous) if previous > 0 => format!(
+                " ({:+.0}% vs. the month before)",
+                (self.total as f64 - previous as f64) / previous as f64 * 100.0
+            ),
+            _ => String::new(),
+        };
+        format!("{} runs by {} user(s){}", self.total, self.users, change)
+    }
+
+    fn top_commands(&self) -> String {
+        if self.top_commands.is_empty() {
+            return "No commands were run.".to_string();
+        }
+        self.top_commands
+            .iter()
+            .take(TOP)
+            .enumerate()
+            .map(|(i, (invocation, runs))| format!("{}. `/{}` — {}", i + 1, invocation, runs))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn unused(&self) -> String {
+        if self.unused.is_empty() {
+            return "Every command was used.".to_string();
+        }
+        self.unused
+            .iter()
+            .map(|(name, last)| match last {
+                Some(at) => format!("`/{}` (last used <t:{}:D>)", name, at),
+                None => format!("`/{}` (never used)", name),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn top_users(&self) -> String {
+        if self.top_users.is_empty() {
+            return "Nobody ran a command.".to_string();
+        }
+        self.top_users
+            .iter()
+            .take(TOP)
+            .enumerate()
+            .map(|(i, (id, user))| format!("{}. <@{}> ({}) — {}", i + 1, id, user.tag, user.runs))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Fills `embed` with the report under `title`.
+    fn fill<'e>(&self, embed: &'e mut CreateEmbed, title: &str) -> &'e mut CreateEmbed {
+        embed
+            .title(format!("📈 {} ({})", title, self.month))
+            .colour(Colour::BLURPLE)
+            .description(self.totals())
+            .field("Most used", field(self.top_commands()), false)
+            .field("Unused this month", field(self.unused()), false)
+            .field("Most active users", field(self.top_users()), false)
+    }
+}
+
+/// Truncates an embed field value to Discord's 1024-character limit.
+fn field(value: String) -> String {
+    if value.chars().count() <= 1024 {
+        return value;
+    }
+    let cut: String = value.chars().take(1000).collect();
+    match cut.rfind('\n') {
+        Some(end) => format!("{}\n…", &cut[..end]),
+        None => format!("{}…", cut),
+    }
+}
+
+async fn post_report(ctx: &Context, channel: ChannelId, report: &MonthlyReport) -> Result<Message, SendError> {
+    let mut embed = CreateEmbed::default();
+    report.fill(&mut embed, "Command Usage Report");
+    notify::post(ctx, channel.0, Priority::Normal, Outgoing::default().embed(embed)).await
+}
+
+/// Registers `/usage report`.
+pub async fn register_usage_command(ctx: &Context) {
+    CommandSpec::new("usage", "Command usage analytics")
+        .option(
+            OptionSpec::sub("report", "Show the usage report for a month").option(OptionSpec::string(
+                "month",
+                "Month as YYYY-MM (default: the current month)",
+            )),
+        )
+        .register(ctx)
+        .await;
+}
+
+/// Slash command handler for `/usage`.
+pub async fn handle_usage(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let options = Options::of(command);
+    let month = match options.subcommand().and_then(|(_, sub)| sub.trimmed("month")) {
+        Some(month) => match NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => {
+                let error = OptionError::invalid("month", "Use the YYYY-MM format, e.g. 2026-09.");
+                return reply_error(ctx, command, &error).await;
+            }
+        },
+        None => Local::now().date_naive(),
+    };
+
+    let report = compile(month);
+    let _ = command
+        .create_interaction_response(&ctx.http, |res| {
+            res.interaction_response_data(|msg| {
+                msg.ephemeral(true).embed(|e| report.fill(e, "Command Usage"))
+            })
+        })
+        .await;
+}
+
+/// Spawns the background task that posts last month's report once a month.
+pub async fn start_usage_report_loop(ctx: Context, tasks: &Tasks) {
+    let channel_id: u64 = match env::var("USAGE_REPORT_CHANNEL_ID").ok().and_then(|v| v.trim().parse().ok()) {
+        Some(id) => id,
+        None => return,
+    };
+
+    if tasks.is_running("usage_report") {
+        println!("Usage report loop already running, reusing it.");
+        return;
+    }
+
+    tasks.spawn("usage_report", move |beat| {
+        let ctx = ctx.clone();
+        async move {
+            loop {
+                beat.tick();
+                let now = Local::now();
+                let month = previous_month(now.date_naive());
+                let mut state: ReportState = store::load(STATE_FILE);
+
+                let reached = now.day() > report_day()
+                    || (now.day() == report_day() && now.hour() >= report_hour());
+                let due = reached && state.last_month.as_deref() != Some(month_key(month).as_str());
+
+                if due {
+                    let report = compile(month);
+                    match post_report(&ctx, ChannelId(channel_id), &report).await {
+                        Ok(_) => {
+                            state.last_month = Some(report.month);
+                            store::save(STATE_FILE, &state);
+                        }
+                        Err(e) => eprintln!("Failed to post usage report: {}", e),
+                    }
+                }
+
+                sleep(CHECK_INTERVAL).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,363 @@
+//! Scheduled command output watching.
+//!
+//! `/watch add <alias> <interval>` runs an allowlisted, read-only command on a schedule and
+//! posts to the channel only when its output changes, including a line diff against the
+//! previous output (e.g. watching `systemctl list-units --failed`).
+//!
+//! Each guild has its own watches, keyed by alias: adding an alias again moves it, and `remove`
+//! and `list` only see the caller's guild. Watches persist in `watches.json` and survive
+//! restarts.
+//!
+//! Environment Variables:
+//! - `WATCH_COMMAND_<ALIAS>`: Shell command for an allowlisted alias,
+//!   e.g. `WATCH_COMMAND_FAILED_UNITS=systemctl list-units --failed --no-legend`
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    model::prelude::*,
+    prelude::*,
+};
+use std::{
+    collections::HashMap,
+    env,
+    process::Command as ProcessCommand,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::time::sleep;
+
+use super::command_spec::{CommandSpec, OptionSpec};
+use super::fetch_file;
+use super::options::Options;
+use crate::duration::{format_duration, parse_duration};
+use crate::guilds;
+use crate::notify::{self, Delivery, Priority};
+use crate::store;
+use crate::tasks::Tasks;
+
+const WATCHES_FILE: &str = "watches.json";
+const COMMAND_PREFIX: &str = "WATCH_COMMAND_";
+const MIN_INTERVAL_SECS: u64 = 30;
+const TICK: Duration = Duration::from_secs(15);
+/// Keep posted diffs comfortably under Discord's 2000 character limit.
+const MAX_DIFF_CHARS: usize = 1700;
+/// Largest LCS table (old lines × new lines) diffed; bigger outputs are only reported as changed.
+const MAX_DIFF_CELLS: usize = 250_000;
+
+static WATCHES: Lazy<Mutex<Vec<Watch>>> = Lazy::new(|| Mutex::new(store::load(WATCHES_FILE)));
+
+/// A scheduled watch on an allowlisted command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Watch {
+    /// Watches from before they were per guild belong to the operators' guild.
+    #[serde(default = "legacy_guild")]
+    pub guild_id: u64,
+    pub alias: String,
+    pub interval_secs: u64,
+    pub channel_id: u64,
+    #[serde(default)]
+    pub last_output: Option<String>,
+}
+
+fn legacy_guild() -> u64 {
+    guilds::home_guild().unwrap_or_default()
+}
+
+impl Watch {
+    fn is(&self, guild_id: u64, alias: &str) -> bool {
+        self.guild_id == guild_id && self.alias == alias
+    }
+}
+
+/// Returns every allowlisted watch command as `(alias, command)`, sorted by alias.
+pub fn allowed_commands() -> Vec<(String, String)> {
+    let mut commands: Vec<(String, String)> = env::vars()
+        .filter_map(|(key, cmd)| {
+            let alias = key.strip_prefix(COMMAND_PREFIX)?.to_lowercase();
+            if alias.is_empty() || cmd.trim().is_empty() {
+                return None;
+            }
+            Some((alias, cmd))
+        })
+        .collect();
+    commands.sort();
+    commands
+}
+
+/// Channels that active watches post into.
+pub fn channel_ids() -> Vec<u64> {
+    WATCHES.lock().unwrap().iter().map(|w| w.channel_id).collect()
+}
+
+fn command_for(alias: &str) -> Option<String> {
+    allowed_commands()
+        .into_iter()
+        .find(|(a, _)| a == alias)
+        .map(|(_, cmd)| cmd)
+}
+
+/// Runs a watch command, returning its combined stdout/stderr.
+fn run_command(cmd: &str) -> String {
+    match ProcessCommand::new("bash").args(["-c", cmd]).output() {
+        Ok(out) => {
+            let stdout = String::from_utf8_lossy(&out.stdout).trim().to_string();
+            let stderr = String::from_utf8_lossy(&out.stderr).trim().to_string();
+            if stderr.is_empty() {
+                stdout
+            } else {
+                format!("{}\n{}", stdout, stderr).trim().to_string()
+            }
+        }
+        Err(e) => format!("error running command: {}", e),
+    }
+}
+
+/// Produces a unified-style line diff (`-` removed, `+` added) using the longest common subsequence.
+/// Outputs too long to diff cheaply are summarised instead.
+pub fn line_diff(old: &str, new: &str) -> String {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    if (a.len() + 1).saturating_mul(b.len() + 1) > MAX_DIFF_CELLS {
+        return format!("output changed ({} lines, too long to diff)", b.len());
+    }
+
+    // lcs[i][j] = length of the LCS of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            out.push(format!("- {}", a[i]));
+            i += 1;
+        } else {
+            out.push(format!("+ {}", b[j]));
+            j += 1;
+        }
+    }
+    out.extend(a[i..].iter().map(|l| format!("- {}", l)));
+    out.extend(b[j..].iter().map(|l| format!("+ {}", l)));
+
+    out.join("\n")
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    format!("{}\n… (truncated)", cut)
+}
+
+/// Spawns the background task that runs due watches and posts output changes.
+pub async fn start_watch_loop(ctx: Context, tasks: &Tasks) {
+    if tasks.is_running("watch") {
+        println!("Watch loop already running, reusing it.");
+        return;
+    }
+
+    tasks.spawn("watch", move |beat| {
+        let ctx = ctx.clone();
+        async move {
+            let mut last_run: HashMap<(u64, String), Instant> = HashMap::new();
+
+            loop {
+                beat.tick();
+                let due: Vec<Watch> = {
+                    let watches = WATCHES.lock().unwrap();
+                    watches
+                        .iter()
+                        .filter(|w| {
+                            last_run
+                                .get(&(w.guild_id, w.alias.clone()))
+                                .is_none_or(|t| t.elapsed() >= Duration::from_secs(w.interval_secs))
+                        })
+                        .cloned()
+                        .collect()
+                };
+
+                for watch in due {
+                    last_run.insert((watch.guild_id, watch.alias.clone()), Instant::now());
+
+                    let cmd = match command_for(&watch.alias) {
+                        Some(cmd) => cmd,
+                        None => {
+                            eprintln!("Watch `{}` is no longer allowlisted, skipping.", watch.alias);
+                            continue;
+                        }
+                    };
+
+                    let output = tokio::task::spawn_blocking(move || run_command(&cmd))
+                        .await
+                        .unwrap_or_else(|e| format!("watch task failed: {}", e));
+
+                    if let Some(previous) = &watch.last_output {
+                        if *previous != output {
+                            // Redact like /fetch-file and keep the output from closing the fence.
+                            let diff = line_diff(previous, &output)
+                                .lines()
+                                .map(|line| fetch_file::redact_line(line).replace("```", "`\u{200b}``"))
+                                .collect::<Vec<_>>()
+                                .join("\n");
+                            let diff = truncate(&diff, MAX_DIFF_CHARS);
+                            let message = format!(
+                                "👀 Output of `{}` changed:\n```diff\n{}\n```",
+                                watch.alias, diff
+                            );
+                            let delivery =
+                                notify::post_text(&ctx, watch.channel_id, Priority::Normal, message).await;
+                            if let Delivery::Failed(e) = delivery {
+                                eprintln!("Failed to post watch diff for `{}`: {}", watch.alias, e);
+                            }
+                        }
+                    }
+
+                    if watch.last_output.as_ref() != Some(&output) {
+                        let mut watches = WATCHES.lock().unwrap();
+                        if let Some(w) = watches.iter_mut().find(|w| w.is(watch.guild_id, &watch.alias)) {
+                            w.last_output = Some(output);
+                        }
+                        store::save(WATCHES_FILE, &*watches);
+                    }
+                }
+
+                last_run.retain(|(guild_id, alias), _| {
+                    WATCHES.lock().unwrap().iter().any(|w| w.is(*guild_id, alias))
+                });
+                sleep(TICK).await;
+            }
+        }
+    });
+}
+
+/// Registers `/watch add|remove|list`.
+pub async fn register_watch_command(ctx: &Context) {
+    let aliases: Vec<String> = allowed_commands().into_iter().map(|(alias, _)| alias).collect();
+
+    let alias_option = aliases
+        .iter()
+        .fold(OptionSpec::string("alias", "Allowlisted command alias").required(), |opt, alias| {
+            opt.choice(alias, alias)
+        });
+
+    CommandSpec::new("watch", "Watch an allowlisted command and post when its output changes")
+        .option(
+            OptionSpec::sub("add", "Start watching a command in this channel")
+                .option(alias_option)
+                .option(OptionSpec::string("interval", "How often to run it, e.g. 5m, 1h").required()),
+        )
+        .option(
+            OptionSpec::sub("remove", "Stop watching a command")
+                .option(OptionSpec::string("alias", "Watched command alias").required()),
+        )
+        .option(OptionSpec::sub("list", "List active watches"))
+        .register(ctx)
+        .await;
+}
+
+/// Slash command handler for `/watch`.
+pub async fn handle_watch(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let (name, sub) = match Options::of(command).subcommand() {
+        Some(sub) => sub,
+        None => return,
+    };
+
+    let guild_id = match command.guild_id {
+        Some(id) => id.0,
+        None => return,
+    };
+    let content = match name {
+        "list" => list_watches(guild_id),
+        "add" => add_watch(sub, guild_id, command.channel_id),
+        "remove" => remove_watch(sub, guild_id),
+        _ => return,
+    };
+
+    let _ = command
+        .create_interaction_response(&ctx.http, |res| {
+            res.interaction_response_data(|msg| msg.content(content))
+        })
+        .await;
+}
+
+fn add_watch(sub: Options<'_>, guild_id: u64, channel_id: ChannelId) -> String {
+    let alias = sub.str("alias").unwrap_or_default().to_lowercase();
+    if command_for(&alias).is_none() || !guilds::watch_alias_allowed(Some(guild_id), &alias) {
+        return format!("❌ `{}` is not an allowlisted watch command.", alias);
+    }
+
+    let interval = match sub.str("interval").and_then(parse_duration) {
+        Some(d) if d.as_secs() >= MIN_INTERVAL_SECS => d,
+        Some(_) => return format!("❌ Interval must be at least {}s.", MIN_INTERVAL_SECS),
+        None => return "❌ Invalid interval (try 30s, 5m, 1h).".to_string(),
+    };
+
+    let mut watches = WATCHES.lock().unwrap();
+    watches.retain(|w| !w.is(guild_id, &alias));
+    watches.push(Watch {
+        guild_id,
+        alias: alias.clone(),
+        interval_secs: interval.as_secs(),
+        channel_id: channel_id.0,
+        last_output: None,
+    });
+    store::save(WATCHES_FILE, &*watches);
+
+    format!(
+        "👀 Watching `{}` every {} in <#{}>. Changes will be posted here.",
+        alias,
+        format_duration(interval),
+        channel_id.0
+    )
+}
+
+fn remove_watch(sub: Options<'_>, guild_id: u64) -> String {
+    let alias = sub.str("alias").unwrap_or_default().to_lowercase();
+
+    let mut watches = WATCHES.lock().unwrap();
+    let before = watches.len();
+    watches.retain(|w| !w.is(guild_id, &alias));
+    if watches.len() == before {
+        return format!("❌ `{}` is not being watched.", alias);
+    }
+    store::save(WATCHES_FILE, &*watches);
+
+    format!("🛑 Stopped watching `{}`.", alias)
+}
+
+fn list_watches(guild_id: u64) -> String {
+    let watches = WATCHES.lock().unwrap();
+    let watches: Vec<&Watch> = watches.iter().filter(|w| w.guild_id == guild_id).collect();
+    if watches.is_empty() {
+        return "No active watches.".to_string();
+    }
+
+    let lines = watches
+        .iter()
+        .map(|w| {
+            format!(
+                "- `{}` every {} in <#{}>",
+                w.alias,
+                format_duration(Duration::from_secs(w.interval_secs)),
+                w.channel_id
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!("**Active watches:**\n{}", lines)
+}
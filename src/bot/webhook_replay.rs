@@ -0,0 +1,55 @@
+//! `/webhook_replay <delivery_id>`: reprocess an archived GitHub webhook delivery.
+//!
+//! Operators only. Runs the stored payload through the handlers again (see
+//! [`crate::github::replay`]) and reports what the handler did with it. Delivery IDs are shown
+//! in GitHub's "Recent Deliveries" log for the webhook.
+
+use serenity::{
+    model::application::interaction::application_command::ApplicationCommandInteraction,
+    prelude::*,
+};
+
+use super::command_spec::{CommandSpec, OptionSpec};
+use super::followup::Followup;
+use super::options::{reply_error, Options};
+use crate::github;
+use crate::AppState;
+
+/// Registers `/webhook_replay`.
+pub async fn register_webhook_replay_command(ctx: &Context) {
+    CommandSpec::new("webhook_replay", "Reprocess an archived GitHub webhook delivery")
+        .option(OptionSpec::string("delivery_id", "X-GitHub-Delivery ID from GitHub's delivery log").required())
+        .register(ctx)
+        .await;
+}
+
+/// Slash command handler for `/webhook_replay`.
+pub async fn handle_webhook_replay(ctx: &Context, command: &ApplicationCommandInteraction, state: &AppState) {
+    let delivery = match Options::of(command).required_str("delivery_id") {
+        Ok(delivery) => delivery.to_string(),
+        Err(e) => return reply_error(ctx, command, &e).await,
+    };
+
+    let followup = Followup::defer(ctx, command, true).await;
+
+    let content = match github::replay(state.clone(), &delivery).await {
+        Some((event, outcome)) => {
+            let verdict = if outcome.handled { "✅ Handled" } else { "⚠️ Not handled" };
+            let mut content = format!(
+                "{} replay of `{}` ({}) by `{}`: HTTP {}",
+                verdict,
+                delivery,
+                event,
+                outcome.handler.unwrap_or("dispatch"),
+                outcome.status.as_u16()
+            );
+            if let Some(reason) = outcome.reason {
+                content.push_str(&format!("\nReason: {}", reason));
+            }
+            content
+        }
+        None => format!("❌ Delivery `{}` is not in the webhook archive.", delivery),
+    };
+
+    followup.finish(ctx, command, content).await;
+}
@@ -0,0 +1,303 @@
+//! Scheduled weekly operations report.
+//!
+//! Once a week, compiles deployments, incidents, alert counts, uptime, CI pass rate, resource
+//! trends, and the disk growth forecast into a single embed, with a Markdown copy attached for
+//! team minutes.
+//!
+//! Environment Variables:
+//! - `WEEKLY_REPORT_CHANNEL_ID`: Channel to post the report in (required)
+//! - `WEEKLY_REPORT_DAY`: Weekday to post on, e.g. `mon` (default: `mon`)
+//! - `WEEKLY_REPORT_HOUR`: Local hour to post at, 0-23 (default: 8)
+
+use chrono::{Datelike, Local, TimeZone, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use serenity::{builder::CreateEmbed, model::prelude::*, prelude::*, utils::Colour};
+use std::{
+    collections::BTreeMap,
+    env,
+    time::Duration,
+};
+use sysinfo::{System, SystemExt};
+use tokio::time::sleep;
+
+use super::metrics::{self, MetricSample};
+use crate::duration::format_duration;
+use crate::notify::{self, Outgoing, Priority, SendError};
+use crate::ops_events::{self, EventKind, OpsEvent};
+use crate::store;
+use crate::tasks::Tasks;
+
+const STATE_FILE: &str = "weekly_report_state.json";
+const WEEK_SECS: i64 = 7 * 86_400;
+const CHECK_INTERVAL: Duration = Duration::from_secs(600);
+
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ReportState {
+    /// ISO week (e.g. `2026-W41`) of the last report sent.
+    last_week: Option<String>,
+}
+
+/// The compiled figures for one reporting window.
+pub struct WeeklyReport {
+    pub from: i64,
+    pub to: i64,
+    pub deployments: Vec<OpsEvent>,
+    pub incidents: Vec<OpsEvent>,
+    pub alerts: usize,
+    pub ci_passed: usize,
+    pub ci_failed: usize,
+    pub availability_percent: Option<f64>,
+    pub host_uptime_secs: u64,
+    pub resources: Vec<String>,
+    /// Disks on track to fill up soon, as sentences.
+    pub disk_forecast: Vec<String>,
+}
+
+fn report_weekday() -> Weekday {
+    env::var("WEEKLY_REPORT_DAY")
+        .ok()
+        .and_then(|d| d.trim().parse::<Weekday>().ok())
+        .unwrap_or(Weekday::Mon)
+}
+
+fn report_hour() -> u32 {
+    env::var("WEEKLY_REPORT_HOUR")
+        .ok()
+        .and_then(|h| h.parse().ok())
+        .filter(|h| *h < 24)
+        .unwrap_or(8)
+}
+
+/// Compiles the report for the seven days ending at `to`.
+pub fn compile(to: i64, sample_interval_secs: u64) -> WeeklyReport {
+    let from = to - WEEK_SECS;
+    let events = ops_events::between(from, to);
+    let of_kind = |kind: EventKind| -> Vec<OpsEvent> {
+        events.iter().filter(|e| e.kind == kind).cloned().collect()
+    };
+
+    let samples = metrics::samples_between(from, to);
+    let expected = (WEEK_SECS as u64 / sample_interval_secs.max(1)) as f64;
+    let availability_percent = if samples.is_empty() {
+        None
+    } else {
+        Some((samples.len() as f64 / expected * 100.0).min(100.0))
+    };
+
+    WeeklyReport {
+        from,
+        to,
+        deployments: of_kind(EventKind::Deployment),
+        incidents: of_kind(EventKind::Incident),
+        alerts: of_kind(EventKind::Alert).len(),
+        ci_passed: of_kind(EventKind::CiSuccess).len(),
+        ci_failed: of_kind(EventKind::CiFailure).len(),
+        availability_percent,
+        host_uptime_secs: System::new().uptime(),
+        resources: resource_trends(&samples),
+        disk_forecast: metrics::disk_forecasts(to).iter().map(|f| f.describe()).collect(),
+    }
+}
+
+/// Summarizes CPU/RAM averages and peaks and disk growth over the window.
+fn resource_trends(samples: &[MetricSample]) -> Vec<String> {
+    if samples.is_empty() {
+        return vec!["No resource samples recorded.".to_string()];
+    }
+
+    let n = samples.len() as f32;
+    let avg_cpu = samples.iter().map(|s| s.cpu_percent).sum::<f32>() / n;
+    let peak_cpu = samples.iter().map(|s| s.cpu_percent).fold(0.0, f32::max);
+    let avg_ram = samples.iter().map(|s| s.ram_percent).sum::<f32>() / n;
+    let peak_ram = samples.iter().map(|s| s.ram_percent).fold(0.0, f32::max);
+
+    let mut lines = vec![
+        format!("CPU: {:.1}% avg, {:.1}% peak", avg_cpu, peak_cpu),
+        format!("RAM: {:.1}% avg, {:.1}% peak", avg_ram, peak_ram),
+    ];
+
+    // First and last usage per mount point.
+    let mut disks: BTreeMap<&str, (u64, u64, u64)> = BTreeMap::new();
+    for sample in samples {
+        for disk in &sample.disks {
+            disks
+                .entry(disk.mount.as_str())
+                .and_modify(|(_, last, total)| {
+                    *last = disk.used_bytes;
+                    *total = disk.total_bytes;
+                })
+                .or_insert((disk.used_bytes, disk.used_bytes, disk.total_bytes));
+        }
+    }
+    for (mount, (first, last, total)) in disks {
+        let delta_gb = (last as f64 - first as f64) / 1e9;
+        lines.push(format!(
+            "Disk {}: {:.1}% used ({:+.2} GB this week)",
+            mount,
+            last as f64 / total.max(1) as f64 * 100.0,
+            delta_gb
+        ));
+    }
+
+    lines
+}
+
+fn format_ts(ts: i64) -> String {
+    Local
+        .timestamp_opt(ts, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| ts.to_string())
+}
+
+fn format_date(ts: i64) -> String {
+    Local
+        .timestamp_opt(ts, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| ts.to_string())
+}
+
+impl WeeklyReport {
+    fn period(&self) -> String {
+        format!("{} → {}", format_date(self.from), format_date(self.to))
+    }
+
+    fn ci_pass_rate(&self) -> String {
+        let total = self.ci_passed + self.ci_failed;
+        if total == 0 {
+            "no runs".to_string()
+        } else {
+            format!(
+                "{:.1}% ({}/{} runs)",
+                self.ci_passed as f64 / total as f64 * 100.0,
+                self.ci_passed,
+                total
+            )
+        }
+    }
+
+    fn uptime(&self) -> String {
+        let availability = self
+            .availability_percent
+            .map(|p| format!("{:.1}% bot availability", p))
+            .unwrap_or_else(|| "bot availability unknown".to_string());
+        format!(
+            "{}, host up {}",
+            availability,
+            format_duration(Duration::from_secs(self.host_uptime_secs))
+        )
+    }
+
+    /// Full report as Markdown, suitable for pasting into team minutes.
+    pub fn to_markdown(&self) -> String {
+        let list = |events: &[OpsEvent]| -> String {
+            if events.is_empty() {
+                "- None\n".to_string()
+            } else {
+                events
+                    .iter()
+                    .map(|e| format!("- {} — {}\n", format_ts(e.timestamp), e.summary))
+                    .collect()
+            }
+        };
+
+        format!(
+            "# Weekly Operations Report ({})\n\n\
+             ## Summary\n\n\
+             | Metric | Value |\n|---|---|\n\
+             | Deployments | {} |\n| Incidents | {} |\n| Alerts | {} |\n| CI pass rate | {} |\n| Uptime | {} |\n\n\
+             ## Deployments\n\n{}\n\
+             ## Incidents\n\n{}\n\
+             ## Resource trends\n\n{}\n\
+             ## Disk forecast\n\n{}\n",
+            self.period(),
+            self.deployments.len(),
+            self.incidents.len(),
+            self.alerts,
+            self.ci_pass_rate(),
+            self.uptime(),
+            list(&self.deployments),
+            list(&self.incidents),
+            self.resources
+                .iter()
+                .map(|l| format!("- {}\n", l))
+                .collect::<String>(),
+            if self.disk_forecast.is_empty() {
+                "- No disk is on track to fill up soon.\n".to_string()
+            } else {
+                self.disk_forecast.iter().map(|l| format!("- {}\n", l)).collect()
+            }
+        )
+    }
+}
+
+/// Posts `report` as an embed with the Markdown version attached.
+pub async fn post_report(ctx: &Context, channel: ChannelId, report: &WeeklyReport) -> Result<Message, SendError> {
+    let markdown = report.to_markdown();
+    let filename = format!("ops-report-{}.md", format_date(report.to));
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(format!("📊 Weekly Operations Report ({})", report.period()))
+        .colour(Colour::BLURPLE)
+        .field("Deployments", report.deployments.len(), true)
+        .field("Incidents", report.incidents.len(), true)
+        .field("Alerts", report.alerts, true)
+        .field("CI pass rate", report.ci_pass_rate(), true)
+        .field("Uptime", report.uptime(), false)
+        .field("Resource trends", report.resources.join("\n"), false);
+    if !report.disk_forecast.is_empty() {
+        embed.field("Disk forecast", report.disk_forecast.join("\n"), false);
+    }
+    let message = Outgoing::default().embed(embed).file(filename, markdown.into_bytes());
+    notify::post(ctx, channel.0, Priority::Normal, message).await
+}
+
+/// Spawns the background task that posts the report once a week.
+pub async fn start_weekly_report_loop(ctx: Context, tasks: &Tasks) {
+    let channel_id: u64 = match env::var("WEEKLY_REPORT_CHANNEL_ID").ok().and_then(|v| v.parse().ok()) {
+        Some(id) => id,
+        None => return,
+    };
+
+    if tasks.is_running("weekly_report") {
+        println!("Weekly report loop already running, reusing it.");
+        return;
+    }
+
+    let sample_interval_secs: u64 = env::var("STATUS_UPDATE_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(600);
+
+    tasks.spawn("weekly_report", move |beat| {
+        let ctx = ctx.clone();
+        async move {
+            loop {
+                beat.tick();
+                let now = Local::now();
+                let week = format!("{}-W{:02}", now.iso_week().year(), now.iso_week().week());
+                let mut state: ReportState = store::load(STATE_FILE);
+
+                let due = now.weekday() == report_weekday()
+                    && now.hour() >= report_hour()
+                    && state.last_week.as_deref() != Some(week.as_str());
+
+                if due {
+                    let report = compile(now.timestamp(), sample_interval_secs);
+                    match post_report(&ctx, ChannelId(channel_id), &report).await {
+                        Ok(_) => {
+                            state.last_week = Some(week);
+                            store::save(STATE_FILE, &state);
+                        }
+                        Err(e) => eprintln!("Failed to post weekly report: {}", e),
+                    }
+                }
+
+                sleep(CHECK_INTERVAL).await;
+            }
+        }
+    });
+}
@@ -1,7 +1,59 @@
 use std::process::Command;
+use serenity::model::application::interaction::InteractionResponseType;
 use serenity::model::prelude::application_command::ApplicationCommandInteraction;
 use serenity::prelude::Context;
 
+use crate::bot::auth;
+use crate::bot::followup::Followup;
+use crate::bot::options::{self, FromOptions, OptionError, Options};
+use crate::bot::smoke_test;
+use crate::freeze;
+use crate::ops_events::{self, EventKind};
+
+/// Records a successful deployment-type action for the weekly operations report and
+/// smoke-tests the result (see [`smoke_test::run_after_deploy`]).
+fn record_deployment(ctx: &Context, command: &ApplicationCommandInteraction, label: &str) {
+    ops_events::record(
+        EventKind::Deployment,
+        format!("{} run by {}", label, command.user.name),
+    );
+    smoke_test::run_after_deploy(ctx, command.channel_id, label);
+}
+
+/// Refuses a deployment while deploys are frozen unless an admin or operator ran it with
+/// `override:true`. Returns `true` if the deployment may go ahead.
+async fn check_freeze(ctx: &Context, command: &ApplicationCommandInteraction, label: &str) -> bool {
+    let frozen = match freeze::active() {
+        Some(frozen) => frozen,
+        None => return true,
+    };
+
+    let privileged = auth::is_admin(command) || auth::is_operator_member(command.member.as_ref(), command.guild_id);
+    if privileged && Options::of(command).bool("override").unwrap_or(false) {
+        println!("{} run by {} despite the deploy freeze.", label, command.user.name);
+        ops_events::record(
+            EventKind::Alert,
+            format!("{} run by {} during a deploy freeze ({})", label, command.user.name, frozen.reason),
+        );
+        return true;
+    }
+
+    let _ = command
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|msg| {
+                    let hint = if privileged {
+                        format!("Re-run `/{}` with `override:true` to deploy anyway.", command.data.name)
+                    } else {
+                        "Only admins can override the freeze.".to_string()
+                    };
+                    msg.content(format!("{}\n{}", frozen.describe(), hint)).ephemeral(true)
+                })
+        })
+        .await;
+    false
+}
+
 pub async fn uptime(ctx: &Context, command: &ApplicationCommandInteraction) {
     let output = Command::new("uptime")
         .output()
@@ -14,63 +66,83 @@ pub async fn uptime(ctx: &Context, command: &ApplicationCommandInteraction) {
     }).await;
 }
 
+/// Arguments of `/restart`.
+struct RestartArgs {
+    service: String,
+}
+
+impl FromOptions for RestartArgs {
+    fn from_options(options: Options<'_>) -> Result<Self, OptionError> {
+        let service = options.required_str("service")?;
+        // Passed straight to systemctl, so only accept plain unit names.
+        if !service.chars().all(|c| c.is_ascii_alphanumeric() || "-_.@".contains(c)) {
+            return Err(OptionError::invalid("service", format!("`{}` is not a valid unit name.", service)));
+        }
+        Ok(Self { service: service.to_string() })
+    }
+}
+
 pub async fn restart_service(ctx: &Context, command: &ApplicationCommandInteraction) {
-    if let Some(option) = command.data.options.get(0) {
-        let service = option.value.as_ref().unwrap().as_str().unwrap();
+    let service = match options::parse::<RestartArgs>(ctx, command).await {
+        Some(args) => args.service,
+        None => return,
+    };
 
-        let output = Command::new("systemctl")
-            .arg("restart")
-            .arg(service)
-            .output();
+    let output = Command::new("systemctl")
+        .arg("restart")
+        .arg(&service)
+        .output();
 
-        match output {
-            Ok(out) => {
-                if out.status.success() {
-                    let _ = command.create_interaction_response(&ctx.http, |res| {
-                        res.interaction_response_data(|msg| msg.content(format!("✅ Restarted `{}` successfully.", service)))
-                    }).await;
-                } else {
-                    let err = String::from_utf8_lossy(&out.stderr).trim().to_string();
-                    let _ = command.create_interaction_response(&ctx.http, |res| {
-                        res.interaction_response_data(|msg| msg.content(format!("❌ Failed to restart `{}`:\n```{}```", service, err)))
-                    }).await;
-                }
-            }
-            Err(e) => {
+    match output {
+        Ok(out) => {
+            if out.status.success() {
                 let _ = command.create_interaction_response(&ctx.http, |res| {
-                    res.interaction_response_data(|msg| msg.content(format!("❌ Error running command: {}", e)))
+                    res.interaction_response_data(|msg| msg.content(format!("✅ Restarted `{}` successfully.", service)))
+                }).await;
+            } else {
+                let err = String::from_utf8_lossy(&out.stderr).trim().to_string();
+                let _ = command.create_interaction_response(&ctx.http, |res| {
+                    res.interaction_response_data(|msg| msg.content(format!("❌ Failed to restart `{}`:\n```{}```", service, err)))
                 }).await;
             }
         }
+        Err(e) => {
+            let _ = command.create_interaction_response(&ctx.http, |res| {
+                res.interaction_response_data(|msg| msg.content(format!("❌ Error running command: {}", e)))
+            }).await;
+        }
     }
 }
 
 
+/// Runs a shell command for a slash command and reports its output.
+///
+/// The response is deferred first so long jobs (fresh builds, migrations) don't hit the
+/// 3-second interaction deadline, and finished through [`Followup`] so results outliving
+/// the interaction token are still posted. Evaluates to `true` if the command succeeded.
 macro_rules! shell_command {
     ($ctx:expr, $cmd:expr, $args:expr, $label:expr, $interaction:expr) => {{
-        let output = Command::new($cmd)
-            .args($args)
-            .output();
+        let followup = Followup::defer($ctx, $interaction, false).await;
 
-        match output {
+        let output = tokio::task::spawn_blocking(move || Command::new($cmd).args($args).output())
+            .await
+            .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+
+        let (success, content) = match output {
             Ok(out) => {
                 let stdout = String::from_utf8_lossy(&out.stdout).trim().to_string();
                 let stderr = String::from_utf8_lossy(&out.stderr).trim().to_string();
-                let content = if out.status.success() {
-                    format!("✅ **{}** executed successfully:\n```{}```", $label, stdout)
+                if out.status.success() {
+                    (true, format!("✅ **{}** executed successfully:\n```{}```", $label, stdout))
                 } else {
-                    format!("❌ **{}** failed:\n```{}```", $label, stderr)
-                };
-                let _ = $interaction.create_interaction_response(&$ctx.http, |res| {
-                    res.interaction_response_data(|msg| msg.content(content))
-                }).await;
-            }
-            Err(err) => {
-                let _ = $interaction.create_interaction_response(&$ctx.http, |res| {
-                    res.interaction_response_data(|msg| msg.content(format!("❌ Error: {}", err)))
-                }).await;
+                    (false, format!("❌ **{}** failed:\n```{}```", $label, stderr))
+                }
             }
-        }
+            Err(err) => (false, format!("❌ Error: {}", err)),
+        };
+
+        followup.finish($ctx, $interaction, content).await;
+        success
     }};
 }
 
@@ -78,16 +150,36 @@ pub async fn clean(ctx: &Context, command: &ApplicationCommandInteraction) {
     shell_command!(ctx, "bash", &["-c", "cd /home/owca/fitch-fork/backend && source /home/owca/.cargo/env && cargo make clean"], "Clean", command);
 }
 pub async fn fresh(ctx: &Context, command: &ApplicationCommandInteraction) {
-    shell_command!(ctx, "bash", &["-c", "cd /home/owca/fitch-fork/backend && source /home/owca/.cargo/env && cargo make fresh"], "Fresh", command);
+    if !check_freeze(ctx, command, "Fresh").await {
+        return;
+    }
+    if shell_command!(ctx, "bash", &["-c", "cd /home/owca/fitch-fork/backend && source /home/owca/.cargo/env && cargo make fresh"], "Fresh", command) {
+        record_deployment(ctx, command, "Fresh");
+    }
 }
 pub async fn migrate(ctx: &Context, command: &ApplicationCommandInteraction) {
-    shell_command!(ctx, "bash", &["-c", "cd /home/owca/fitch-fork/backend && source /home/owca/.cargo/env && cargo make migrate"], "Migrate", command);
+    if !check_freeze(ctx, command, "Migrate").await {
+        return;
+    }
+    if shell_command!(ctx, "bash", &["-c", "cd /home/owca/fitch-fork/backend && source /home/owca/.cargo/env && cargo make migrate"], "Migrate", command) {
+        record_deployment(ctx, command, "Migrate");
+    }
 }
 pub async fn restart_api(ctx: &Context, command: &ApplicationCommandInteraction) {
-    shell_command!(ctx, "bash", &["/home/owca/scripts/restart-api.sh"], "Restart API", command);
+    if !check_freeze(ctx, command, "Restart API").await {
+        return;
+    }
+    if shell_command!(ctx, "bash", &["/home/owca/scripts/restart-api.sh"], "Restart API", command) {
+        record_deployment(ctx, command, "Restart API");
+    }
 }
 pub async fn start_api(ctx: &Context, command: &ApplicationCommandInteraction) {
-    shell_command!(ctx, "bash", &["/home/owca/scripts/start-api.sh"], "Start API", command);
+    if !check_freeze(ctx, command, "Start API").await {
+        return;
+    }
+    if shell_command!(ctx, "bash", &["/home/owca/scripts/start-api.sh"], "Start API", command) {
+        record_deployment(ctx, command, "Start API");
+    }
 }
 pub async fn stop_api(ctx: &Context, command: &ApplicationCommandInteraction) {
     shell_command!(ctx, "bash", &["/home/owca/scripts/stop-api.sh"], "Stop API", command);
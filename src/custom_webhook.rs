@@ -0,0 +1,148 @@
+//! Generic templated webhook for internal tools.
+//!
+//! `POST /webhook/custom/<name>` accepts any JSON body and posts a message rendered from the
+//! template configured for `<name>`, so a tool can notify Discord through the bot without a
+//! dedicated handler. Placeholders are `{{ path }}` with a dot-separated path into the body
+//! (array elements by index), e.g. `{{ build.id }}` or `{{ failures.0.name }}`; missing values
+//! render as empty, `\n` becomes a newline.
+//!
+//! Requests must carry `Authorization: Bearer <token>`. A name without a template, channel,
+//! or token is answered with 404.
+//!
+//! Environment Variables:
+//! - `CUSTOM_WEBHOOK_<NAME>_TEMPLATE`: Message template for `<name>` (uppercased, `-` as `_`)
+//! - `CUSTOM_WEBHOOK_<NAME>_CHANNEL_ID`: Channel the message is posted to
+//! - `CUSTOM_WEBHOOK_<NAME>_TOKEN`: Bearer token for `<name>` (default: `CUSTOM_WEBHOOK_TOKEN`)
+//! - `CUSTOM_WEBHOOK_TOKEN`: Bearer token shared by every name without its own
+
+use axum::{
+    extract::{Json, Path, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use serde_json::Value;
+use std::env;
+
+use crate::github::WebhookOutcome;
+use crate::notify::{self, Destination};
+use crate::secrets;
+use crate::AppState;
+
+const HANDLER: &str = "custom_webhook";
+
+pub fn routes(shared_state: AppState) -> Router {
+    Router::new()
+        .route("/custom/:name", post(handle_custom_webhook))
+        .with_state(shared_state)
+}
+
+/// Configuration of one custom webhook name.
+struct CustomWebhook {
+    template: String,
+    channel_id: u64,
+    token: String,
+    /// The variable `token` came from, for accepting its old value after a rotation.
+    token_var: String,
+}
+
+impl CustomWebhook {
+    fn load(name: &str) -> Option<Self> {
+        let key = name.to_uppercase().replace('-', "_");
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return None;
+        }
+        let var = |suffix: &str| {
+            env::var(format!("CUSTOM_WEBHOOK_{}_{}", key, suffix))
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+        };
+
+        let token_var = format!("CUSTOM_WEBHOOK_{}_TOKEN", key);
+        let (token_var, token) = match secrets::current(&token_var) {
+            Some(token) => (token_var, token),
+            None => {
+                let token = secrets::current("CUSTOM_WEBHOOK_TOKEN")?;
+                ("CUSTOM_WEBHOOK_TOKEN".to_string(), token)
+            }
+        };
+
+        Some(Self {
+            template: var("TEMPLATE")?,
+            channel_id: var("CHANNEL_ID")?.trim().parse().ok()?,
+            token,
+            token_var,
+        })
+    }
+}
+
+async fn handle_custom_webhook(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(body): Json<Value>,
+) -> Response {
+    let webhook = match CustomWebhook::load(&name) {
+        Some(webhook) => webhook,
+        None => return (StatusCode::NOT_FOUND, format!("no custom webhook named `{}`", name)).into_response(),
+    };
+
+    let authorized = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|t| secrets::accepts(&webhook.token_var, &webhook.token, t));
+    if !authorized {
+        eprintln!("Rejected custom webhook `{}`: missing or invalid token", name);
+        return WebhookOutcome::unauthorized("missing or invalid bearer token").into_response();
+    }
+
+    let message = render(&webhook.template, &body);
+    if message.trim().is_empty() {
+        return WebhookOutcome::bad_request(HANDLER, "template rendered an empty message").into_response();
+    }
+
+    let delivery = notify::send(&state, HANDLER, Destination::Channel(webhook.channel_id), message).await;
+    WebhookOutcome::from_delivery(HANDLER, delivery).into_response()
+}
+
+/// Substitutes every `{{ path }}` placeholder in `template` with the value at `path` in `body`.
+fn render(template: &str, body: &Value) -> String {
+    let template = template.replace("\\n", "\n");
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template.as_str();
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                out.push_str(&lookup(body, after[..end].trim()));
+                rest = &after[end + 2..];
+            }
+            None => {
+                // Unterminated placeholder; keep it as written.
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The value at a dot-separated `path`, with strings unquoted and other values as JSON.
+fn lookup(body: &Value, path: &str) -> String {
+    let pointer = if path.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", path.replace('~', "~0").replace('/', "~1").replace('.', "/"))
+    };
+
+    match body.pointer(&pointer) {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(value) => value.to_string(),
+    }
+}
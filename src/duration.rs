@@ -0,0 +1,57 @@
+//! Parsing and formatting of short human durations like `30m`, `2h`, `7d`, or `1h30m`.
+
+use std::time::Duration;
+
+/// Parses a compact duration such as `45s`, `30m`, `2h`, `7d`, `1w`, or `1h30m`.
+///
+/// A bare number is treated as seconds.
+pub fn parse_duration(input: &str) -> Option<Duration> {
+    let input = input.trim().to_lowercase();
+    if input.is_empty() {
+        return None;
+    }
+    if let Ok(secs) = input.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let mut total = 0u64;
+    let mut number = String::new();
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86_400,
+            'w' => 604_800,
+            _ => return None,
+        };
+        let value: u64 = number.parse().ok()?;
+        total = total.checked_add(value.checked_mul(unit)?)?;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        return None;
+    }
+    Some(Duration::from_secs(total))
+}
+
+/// Formats a duration as `1d 2h 3m`, `2h 5m`, `4m 10s`, or `12s`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, minutes, seconds) = (secs / 86_400, (secs % 86_400) / 3600, (secs % 3600) / 60, secs % 60);
+
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
@@ -0,0 +1,94 @@
+//! Fallback delivery through a plain Discord webhook URL.
+//!
+//! Used for critical notifications (GitHub events, alerts) when the gateway connection is
+//! lost, so messages still reach the channel over plain HTTP during gateway outages.
+//! Notifications for another guild than the operators' only use that guild's own
+//! `fallback_webhook_url` from `guilds.json`, so they never end up in the operators' channel.
+//!
+//! Environment Variables:
+//! - `DISCORD_FALLBACK_WEBHOOK_URL`: Discord webhook URL (`https://discord.com/api/webhooks/<id>/<token>`)
+//!   for the operators' guild
+
+use serde_json::json;
+use std::env;
+
+use crate::guilds;
+use crate::notify::queue;
+
+/// Returns the configured fallback webhook URL, if any.
+fn webhook_url() -> Option<String> {
+    env::var("DISCORD_FALLBACK_WEBHOOK_URL")
+        .ok()
+        .filter(|u| !u.trim().is_empty())
+}
+
+/// The fallback webhook for notifications to a channel of `guild_id`: the guild's own, or the
+/// global one for the operators' guild and channels of no known guild.
+fn webhook_url_for(guild_id: Option<u64>) -> Option<String> {
+    if !guilds::is_foreign(guild_id) {
+        return webhook_url();
+    }
+    guild_id
+        .and_then(guilds::for_guild)
+        .and_then(|config| config.fallback_webhook_url)
+        .filter(|u| !u.trim().is_empty())
+}
+
+/// Returns `true` if the operators' fallback webhook is configured.
+pub fn is_configured() -> bool {
+    webhook_url().is_some()
+}
+
+/// Returns `true` if a fallback webhook is configured for `guild_id`'s notifications.
+pub fn is_configured_for(guild_id: Option<u64>) -> bool {
+    webhook_url_for(guild_id).is_some()
+}
+
+/// Looks the fallback webhook up without posting to it (for `/selftest`), returning its name.
+pub async fn check() -> Result<String, String> {
+    let url = webhook_url().ok_or("not configured")?;
+    let res = crate::http::client()
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("request failed: {}", e))?;
+    if !res.status().is_success() {
+        return Err(format!("webhook returned {}", res.status()));
+    }
+    let webhook: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+    Ok(format!(
+        "webhook `{}` is valid",
+        webhook.get("name").and_then(|n| n.as_str()).unwrap_or("unnamed")
+    ))
+}
+
+/// Posts `content` through the operators' fallback webhook. Like queued messages, only the
+/// users and roles it mentions explicitly are pinged.
+pub async fn post(content: &str) -> Result<(), String> {
+    post_for(None, content).await
+}
+
+/// Posts `content` through the fallback webhook for `guild_id`'s notifications.
+pub async fn post_for(guild_id: Option<u64>, content: &str) -> Result<(), String> {
+    let url = webhook_url_for(guild_id).ok_or(if guilds::is_foreign(guild_id) {
+        "no fallback webhook configured for the guild"
+    } else {
+        "no fallback webhook configured (DISCORD_FALLBACK_WEBHOOK_URL)"
+    })?;
+
+    let res = crate::http::client()
+        .post(&url)
+        .json(&json!({
+            "content": content,
+            "allowed_mentions": queue::allowed_mentions_json(content),
+        }))
+        .send()
+        .await
+        .map_err(|e| format!("fallback webhook request failed: {}", e))?;
+
+    if res.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("fallback webhook returned {}", res.status()))
+    }
+}
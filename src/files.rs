@@ -0,0 +1,236 @@
+//! Short-lived signed download links for large artifacts.
+//!
+//! Commands that produce files too large for a Discord attachment (full logs, backups,
+//! exports) store them under `BOT_DATA_DIR/files/` with [`mint`] and hand out a URL like
+//! `<FILES_PUBLIC_URL>/files/<id>.<expires>.<signature>` instead. The signature is an
+//! HMAC over the id and expiry, so links can't be forged or extended. Expired files are
+//! deleted when a link is minted or served, and by a background sweep every [`PRUNE_INTERVAL`].
+//!
+//! Environment Variables:
+//! - `FILES_PUBLIC_URL`: Externally reachable base URL of this server, e.g. `https://bot.example.com`
+//! - `FILES_SIGNING_KEY`: Secret used to sign links (links are disabled unless both are set)
+//! - `FILES_LINK_TTL_SECS`: How long a link stays valid (default: 3600)
+
+use axum::{
+    body::Body,
+    extract::Path,
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        StatusCode,
+    },
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{self, Write},
+    path::PathBuf,
+    sync::Mutex,
+    time::Duration,
+};
+use tokio::time::sleep;
+use tokio_util::io::ReaderStream;
+
+use crate::store;
+use crate::tasks::Tasks;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const LINKS_FILE: &str = "file_links.json";
+const FILES_DIR: &str = "files";
+const DEFAULT_TTL_SECS: i64 = 3600;
+const PRUNE_INTERVAL: Duration = Duration::from_secs(600);
+
+static LINKS: Lazy<Mutex<HashMap<String, StoredFile>>> =
+    Lazy::new(|| Mutex::new(store::load(LINKS_FILE)));
+
+/// A file available for download until `expires_at`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredFile {
+    filename: String,
+    expires_at: i64,
+}
+
+fn public_url() -> Option<String> {
+    env::var("FILES_PUBLIC_URL")
+        .ok()
+        .map(|u| u.trim().trim_end_matches('/').to_string())
+        .filter(|u| !u.is_empty())
+}
+
+fn signing_key() -> Option<String> {
+    env::var("FILES_SIGNING_KEY").ok().filter(|k| !k.is_empty())
+}
+
+fn ttl_secs() -> i64 {
+    env::var("FILES_LINK_TTL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|t| *t > 0)
+        .unwrap_or(DEFAULT_TTL_SECS)
+}
+
+/// Returns `true` if download links can be minted.
+pub fn is_configured() -> bool {
+    public_url().is_some() && signing_key().is_some()
+}
+
+fn file_path(id: &str) -> PathBuf {
+    store::data_path(FILES_DIR).join(id)
+}
+
+fn mac(key: &str, id: &str, expires_at: i64) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}.{}", id, expires_at).as_bytes());
+    mac
+}
+
+/// A fresh unguessable file id: 128 random bits from the OS, hex-encoded.
+fn new_id() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("cannot get random bytes: {}", e))?;
+    Ok(hex::encode(bytes))
+}
+
+/// Deletes expired files and forgets their links. Returns `true` if any expired.
+fn prune(links: &mut HashMap<String, StoredFile>, now: i64) -> bool {
+    let before = links.len();
+    links.retain(|id, file| {
+        let keep = file.expires_at > now;
+        if !keep {
+            let _ = fs::remove_file(file_path(id));
+        }
+        keep
+    });
+    links.len() != before
+}
+
+/// Prunes expired files, saving the links if any expired.
+fn prune_expired() {
+    let mut links = LINKS.lock().unwrap();
+    if prune(&mut links, Utc::now().timestamp()) {
+        store::save(LINKS_FILE, &*links);
+    }
+}
+
+/// Spawns the background task that deletes expired files even when no new links are minted.
+pub async fn start_prune_loop(tasks: &Tasks) {
+    if !is_configured() {
+        return;
+    }
+    if tasks.is_running("file_links_prune") {
+        println!("File link prune loop already running, reusing it.");
+        return;
+    }
+
+    tasks.spawn("file_links_prune", move |beat| async move {
+        loop {
+            beat.tick();
+            if let Err(e) = tokio::task::spawn_blocking(prune_expired).await {
+                eprintln!("Failed to prune expired download links: {}", e);
+            }
+            sleep(PRUNE_INTERVAL).await;
+        }
+    });
+}
+
+/// Stores `data` and returns a signed download URL that expires after `FILES_LINK_TTL_SECS`.
+pub fn mint(filename: &str, data: &[u8]) -> Result<String, String> {
+    mint_with(filename, |file| file.write_all(data))
+}
+
+/// Like [`mint`], but `write` streams the contents into the stored file, so large files don't
+/// have to be held in memory.
+pub fn mint_with(filename: &str, write: impl FnOnce(&mut fs::File) -> io::Result<()>) -> Result<String, String> {
+    let (base, key) = match (public_url(), signing_key()) {
+        (Some(base), Some(key)) => (base, key),
+        _ => return Err("download links are not configured".to_string()),
+    };
+
+    let now = Utc::now().timestamp();
+    let expires_at = now + ttl_secs();
+    let id = new_id()?;
+
+    fs::create_dir_all(store::data_path(FILES_DIR))
+        .map_err(|e| format!("cannot create files directory: {}", e))?;
+    let stored = fs::File::create(file_path(&id)).and_then(|mut file| {
+        write(&mut file)?;
+        file.flush()
+    });
+    if let Err(e) = stored {
+        let _ = fs::remove_file(file_path(&id));
+        return Err(format!("cannot store file: {}", e));
+    }
+
+    let mut links = LINKS.lock().unwrap();
+    prune(&mut links, now);
+    links.insert(
+        id.clone(),
+        StoredFile {
+            filename: filename.to_string(),
+            expires_at,
+        },
+    );
+    store::save(LINKS_FILE, &*links);
+
+    let signature = hex::encode(mac(&key, &id, expires_at).finalize().into_bytes());
+    Ok(format!("{}/files/{}.{}.{}", base, id, expires_at, signature))
+}
+
+/// Download routes, mounted at `/files`.
+pub fn routes() -> Router {
+    Router::new().route("/:token", get(download))
+}
+
+/// `GET /files/:token`: serves a stored file if the token is authentic and unexpired.
+async fn download(Path(token): Path<String>) -> Response {
+    let key = match signing_key() {
+        Some(key) => key,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    let mut parts = token.splitn(3, '.');
+    let (id, expires_at, signature) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(id), Some(expires), Some(sig)) => match (expires.parse::<i64>(), hex::decode(sig)) {
+            (Ok(expires), Ok(sig)) => (id.to_string(), expires, sig),
+            _ => return StatusCode::NOT_FOUND.into_response(),
+        },
+        _ => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    if mac(&key, &id, expires_at).verify_slice(&signature).is_err() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let _ = tokio::task::spawn_blocking(prune_expired).await;
+    if expires_at <= Utc::now().timestamp() {
+        return (StatusCode::GONE, "This download link has expired.").into_response();
+    }
+
+    let filename = match LINKS.lock().unwrap().get(&id) {
+        Some(file) => file.filename.clone(),
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    match tokio::fs::File::open(file_path(&id)).await {
+        Ok(file) => (
+            [
+                (CONTENT_TYPE, "application/octet-stream".to_string()),
+                (
+                    CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", filename.replace('"', "")),
+                ),
+            ],
+            Body::from_stream(ReaderStream::new(file)),
+        )
+            .into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
@@ -0,0 +1,70 @@
+//! Deploy freeze flag, e.g. for assessment periods.
+//!
+//! While a freeze is active, deployment commands (`/fresh`, `/migrate`, `/restart_api`,
+//! `/start_api`) refuse to run unless an admin or operator explicitly overrides it, and merge
+//! actions are disabled. The freeze persists in `deploy_freeze.json` with its reason and
+//! optional expiry, so it survives restarts and lifts itself once it expires.
+
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::store;
+
+const FREEZE_FILE: &str = "deploy_freeze.json";
+
+static FREEZE: Lazy<Mutex<Option<Freeze>>> = Lazy::new(|| Mutex::new(store::load(FREEZE_FILE)));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Freeze {
+    pub reason: String,
+    /// Username of whoever froze deploys.
+    pub set_by: String,
+    /// Unix timestamp (seconds).
+    pub set_at: i64,
+    /// Unix timestamp (seconds) the freeze lifts at, or `None` until `/unfreeze`.
+    pub expires_at: Option<i64>,
+}
+
+impl Freeze {
+    /// One-line summary, e.g. "🧊 Deploys are frozen by `alice` until <t:...:f>: exams".
+    pub fn describe(&self) -> String {
+        let until = match self.expires_at {
+            Some(at) => format!(" until <t:{}:f>", at),
+            None => String::new(),
+        };
+        format!("🧊 Deploys are frozen by `{}`{}: {}", self.set_by, until, self.reason)
+    }
+}
+
+/// The active freeze, if any. An expired freeze is cleared.
+pub fn active() -> Option<Freeze> {
+    let mut freeze = FREEZE.lock().unwrap();
+    if freeze
+        .as_ref()
+        .and_then(|f| f.expires_at)
+        .is_some_and(|at| at <= Utc::now().timestamp())
+    {
+        println!("Deploy freeze expired, lifting it.");
+        *freeze = None;
+        store::save(FREEZE_FILE, &*freeze);
+    }
+    freeze.clone()
+}
+
+/// Freezes deploys, replacing any existing freeze.
+pub fn set(freeze: Freeze) {
+    let mut current = FREEZE.lock().unwrap();
+    *current = Some(freeze);
+    store::save(FREEZE_FILE, &*current);
+}
+
+/// Lifts the freeze, returning it if one was active.
+pub fn clear() -> Option<Freeze> {
+    let lifted = active();
+    let mut current = FREEZE.lock().unwrap();
+    *current = None;
+    store::save(FREEZE_FILE, &*current);
+    lifted
+}
@@ -0,0 +1,147 @@
+//! Archive of received webhook payloads, for replaying missed or mis-handled events.
+//!
+//! Every verified delivery is stored as received, one file per delivery named
+//! `<received_at>-<delivery_id>.<event>.json` in `webhook_archive/`, so recording one never
+//! parses the payload or rewrites the others. `/webhook_replay <delivery_id>` and
+//! `POST /webhook/replay/<delivery_id>` run an archived payload through the handlers again,
+//! e.g. after fixing a handler bug or a missing channel configuration.
+//!
+//! Environment Variables:
+//! - `WEBHOOK_ARCHIVE_MAX`: Number of deliveries kept (default 100, 0 disables the archive)
+//! - `WEBHOOK_REPLAY_TOKEN`: Bearer token required by the replay endpoint
+
+use axum::body::Bytes;
+use chrono::Utc;
+use std::{env, fs, path::PathBuf};
+
+use crate::secrets;
+use crate::store;
+
+const ARCHIVE_DIR: &str = "webhook_archive";
+const DEFAULT_MAX_ARCHIVED: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct ArchivedDelivery {
+    pub delivery: String,
+    pub event: String,
+    pub received_at: i64,
+    /// The request body as received.
+    pub body: Vec<u8>,
+}
+
+/// An archive file's name split into its parts.
+struct Entry {
+    file_name: String,
+    received_at: i64,
+    delivery: String,
+    event: String,
+}
+
+impl Entry {
+    fn parse(file_name: String) -> Option<Self> {
+        let stem = file_name.strip_suffix(".json")?;
+        let (rest, event) = stem.rsplit_once('.')?;
+        let (received_at, delivery) = rest.split_once('-')?;
+        Some(Self {
+            received_at: received_at.parse().ok()?,
+            delivery: delivery.to_string(),
+            event: event.to_string(),
+            file_name,
+        })
+    }
+}
+
+fn max_archived() -> usize {
+    env::var("WEBHOOK_ARCHIVE_MAX")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_MAX_ARCHIVED)
+}
+
+fn archive_dir() -> PathBuf {
+    store::data_path(ARCHIVE_DIR)
+}
+
+/// Keeps a header value from escaping the archive directory or breaking the file name.
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Archived deliveries, oldest first.
+fn entries() -> Vec<Entry> {
+    let mut entries: Vec<Entry> = fs::read_dir(archive_dir())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter_map(Entry::parse)
+        .collect();
+    entries.sort_by_key(|e| e.received_at);
+    entries
+}
+
+/// Stores a delivery in the background, dropping the oldest ones past `WEBHOOK_ARCHIVE_MAX`.
+pub fn record(delivery: &str, event: &str, body: Bytes) {
+    let max = max_archived();
+    if max == 0 {
+        return;
+    }
+    let (delivery, event) = (sanitize(delivery), sanitize(event));
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = write(&delivery, &event, &body, max) {
+            eprintln!("Failed to archive delivery {}: {}", delivery, e);
+        }
+    });
+}
+
+fn write(delivery: &str, event: &str, body: &[u8], max: usize) -> std::io::Result<()> {
+    let dir = archive_dir();
+    fs::create_dir_all(&dir)?;
+
+    // A redelivery replaces the earlier copy.
+    let mut entries = entries();
+    for entry in entries.iter().filter(|e| e.delivery == delivery) {
+        let _ = fs::remove_file(dir.join(&entry.file_name));
+    }
+    entries.retain(|e| e.delivery != delivery);
+
+    let file_name = format!("{}-{}.{}.json", Utc::now().timestamp(), delivery, event);
+    let tmp = dir.join(format!("{}.tmp", file_name));
+    fs::write(&tmp, body)?;
+    fs::rename(&tmp, dir.join(file_name))?;
+
+    // The new file is the newest, so it counts against the limit but is never dropped.
+    let excess = (entries.len() + 1).saturating_sub(max);
+    for entry in entries.iter().take(excess) {
+        let _ = fs::remove_file(dir.join(&entry.file_name));
+    }
+    Ok(())
+}
+
+/// Looks up an archived delivery by its `X-GitHub-Delivery` ID.
+pub async fn find(delivery: &str) -> Option<ArchivedDelivery> {
+    let delivery = sanitize(delivery);
+    tokio::task::spawn_blocking(move || {
+        let entry = entries()
+            .into_iter()
+            .rev()
+            .find(|e| e.delivery.eq_ignore_ascii_case(&delivery))?;
+        let body = fs::read(archive_dir().join(&entry.file_name)).ok()?;
+        Some(ArchivedDelivery {
+            delivery: entry.delivery,
+            event: entry.event,
+            received_at: entry.received_at,
+            body,
+        })
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// Bearer token for the replay endpoint, if it is enabled.
+pub fn replay_token() -> Option<String> {
+    secrets::current("WEBHOOK_REPLAY_TOKEN")
+}
@@ -0,0 +1,300 @@
+//! Authenticated GitHub API client, shared by every feature that calls the GitHub API (job
+//! logs, re-runs, PR actions, CODEOWNERS and issue lookups).
+//!
+//! Requests are authenticated as a GitHub App installation when one is configured, otherwise
+//! with a personal access token, and otherwise sent anonymously (enough for public
+//! repositories, within GitHub's anonymous rate limit). The App's JWT is signed with the
+//! `openssl` CLI and the installation token it buys is cached until shortly before it expires.
+//!
+//! Rate limits are handled here too: the quota from each response's headers is remembered, and
+//! once it is used up requests fail straight away until it resets instead of each being
+//! rejected by GitHub. Short secondary rate limits (`Retry-After` of a few seconds) are waited
+//! out and the request retried once.
+//!
+//! Environment Variables:
+//! - `GITHUB_TOKEN`: Personal access token
+//! - `GITHUB_APP_ID`, `GITHUB_APP_INSTALLATION_ID`, `GITHUB_APP_PRIVATE_KEY_PATH`: Authenticate
+//!   as this GitHub App installation instead (all three required; takes precedence over
+//!   `GITHUB_TOKEN`)
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use reqwest::{Method, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::{
+    env,
+    io::Write,
+    process::{Command, Stdio},
+    sync::Mutex,
+    time::Duration,
+};
+
+const API_URL: &str = "https://api.github.com";
+/// Accept header for JSON responses.
+pub const JSON: &str = "application/vnd.github+json";
+/// Accept header for raw file contents.
+pub const RAW: &str = "application/vnd.github.raw";
+/// Longest `Retry-After` that is waited out instead of failing the request.
+const MAX_RETRY_WAIT_SECS: u64 = 10;
+/// Installation tokens last an hour; a new one is bought this long before that.
+const TOKEN_RENEW_MARGIN_SECS: i64 = 300;
+
+/// Cached installation token and the Unix timestamp it expires at. An async mutex, so
+/// concurrent requests wait for one token instead of each buying their own.
+static APP_TOKEN: Lazy<tokio::sync::Mutex<Option<(String, i64)>>> = Lazy::new(|| tokio::sync::Mutex::new(None));
+/// The rate limit reported by the latest response.
+static RATE_LIMIT: Lazy<Mutex<Option<RateLimit>>> = Lazy::new(|| Mutex::new(None));
+
+/// The API quota, from GitHub's `X-RateLimit-*` headers.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub remaining: u64,
+    pub limit: u64,
+    /// Unix timestamp the quota resets at.
+    pub reset: i64,
+}
+
+#[derive(Debug, Clone)]
+struct AppConfig {
+    app_id: String,
+    installation_id: String,
+    key_path: String,
+}
+
+fn env_value(key: &str) -> Option<String> {
+    env::var(key).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+fn app_config() -> Option<AppConfig> {
+    Some(AppConfig {
+        app_id: env_value("GITHUB_APP_ID")?,
+        installation_id: env_value("GITHUB_APP_INSTALLATION_ID")?,
+        key_path: env_value("GITHUB_APP_PRIVATE_KEY_PATH")?,
+    })
+}
+
+/// How requests are authenticated, or `None` if they are anonymous.
+pub fn auth_kind() -> Option<&'static str> {
+    if app_config().is_some() {
+        Some("GitHub App")
+    } else if env_value("GITHUB_TOKEN").is_some() {
+        Some("token")
+    } else {
+        None
+    }
+}
+
+/// Whether requests are authenticated; features that write, or read Actions logs, need it.
+pub fn authenticated() -> bool {
+    auth_kind().is_some()
+}
+
+/// The rate limit reported by the latest response, if any was seen.
+pub fn rate_limit() -> Option<RateLimit> {
+    *RATE_LIMIT.lock().unwrap()
+}
+
+/// Unpadded base64url, as JWTs use.
+fn base64url(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    out
+}
+
+/// A JWT identifying the App, signed RS256 with `openssl`.
+fn app_jwt(config: &AppConfig) -> Result<String, String> {
+    let now = Utc::now().timestamp();
+    // Backdated a minute for clock drift, as GitHub recommends; it may live 10 minutes at most.
+    let claims = json!({ "iat": now - 60, "exp": now + 540, "iss": config.app_id });
+    let signing_input = format!(
+        "{}.{}",
+        base64url(br#"{"alg":"RS256","typ":"JWT"}"#),
+        base64url(claims.to_string().as_bytes())
+    );
+
+    let mut child = Command::new("openssl")
+        .args(["dgst", "-sha256", "-sign", &config.key_path])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("cannot run openssl: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(signing_input.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!("openssl failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(format!("{}.{}", signing_input, base64url(&output.stdout)))
+}
+
+/// The installation token, buying a new one when the cached one is about to expire.
+async fn installation_token(config: AppConfig) -> Result<String, String> {
+    let mut cached = APP_TOKEN.lock().await;
+    let now = Utc::now().timestamp();
+    if let Some((token, expires_at)) = cached.as_ref() {
+        if expires_at - TOKEN_RENEW_MARGIN_SECS > now {
+            return Ok(token.clone());
+        }
+    }
+
+    let installation_id = config.installation_id.clone();
+    let jwt = tokio::task::spawn_blocking(move || app_jwt(&config))
+        .await
+        .map_err(|e| e.to_string())??;
+    let url = format!("{}/app/installations/{}/access_tokens", API_URL, installation_id);
+    let res = crate::http::client()
+        .post(&url)
+        .header("Accept", JSON)
+        .bearer_auth(jwt)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let res = checked(res).await.map_err(|e| format!("installation token: {}", e))?;
+    let body: Value = res.json().await.map_err(|e| e.to_string())?;
+
+    let token = body
+        .get("token")
+        .and_then(Value::as_str)
+        .ok_or("installation token missing from GitHub's response")?
+        .to_string();
+    let expires_at = body
+        .get("expires_at")
+        .and_then(Value::as_str)
+        .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+        .map_or(now + 3600, |at| at.timestamp());
+    *cached = Some((token.clone(), expires_at));
+    Ok(token)
+}
+
+async fn auth_token() -> Result<Option<String>, String> {
+    match app_config() {
+        Some(config) => installation_token(config).await.map(Some),
+        None => Ok(env_value("GITHUB_TOKEN")),
+    }
+}
+
+fn header_num<T: std::str::FromStr>(res: &Response, name: &str) -> Option<T> {
+    res.headers().get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+fn record_rate_limit(res: &Response) {
+    let limit = (|| {
+        Some(RateLimit {
+            remaining: header_num(res, "x-ratelimit-remaining")?,
+            limit: header_num(res, "x-ratelimit-limit")?,
+            reset: header_num(res, "x-ratelimit-reset")?,
+        })
+    })();
+    if limit.is_some() {
+        *RATE_LIMIT.lock().unwrap() = limit;
+    }
+}
+
+/// Fails while the quota is used up, until it resets.
+fn check_quota() -> Result<(), String> {
+    match rate_limit() {
+        Some(limit) if limit.remaining == 0 && limit.reset > Utc::now().timestamp() => Err(format!(
+            "GitHub API rate limit of {} requests used up until {}",
+            limit.limit,
+            DateTime::from_timestamp(limit.reset, 0).map_or_else(String::new, |at| at.format("%H:%M:%S UTC").to_string())
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Turns an unsuccessful response into an error carrying GitHub's message.
+async fn checked(res: Response) -> Result<Response, String> {
+    let status = res.status();
+    if status.is_success() {
+        return Ok(res);
+    }
+    let body: Value = res.json().await.unwrap_or(Value::Null);
+    let message = body.get("message").and_then(Value::as_str).unwrap_or_default();
+    Err(format!("GitHub API returned {} {}", status, message).trim().to_string())
+}
+
+fn url(path: &str) -> String {
+    if path.starts_with("https://") {
+        path.to_string()
+    } else {
+        format!("{}{}", API_URL, path)
+    }
+}
+
+/// Sends a request to `path` (e.g. `/repos/owner/name/pulls`, or a full API URL), returning the
+/// response whatever its status.
+pub async fn send(method: Method, path: &str, accept: &str, body: Option<&Value>) -> Result<Response, String> {
+    check_quota()?;
+    let token = auth_token().await?;
+    let url = url(path);
+
+    let mut retried = false;
+    loop {
+        let mut request = crate::http::client()
+            .request(method.clone(), &url)
+            .header("Accept", accept)
+            .header("X-GitHub-Api-Version", "2022-11-28");
+        if let Some(token) = &token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let res = request.send().await.map_err(|e| e.to_string())?;
+        record_rate_limit(&res);
+
+        // Secondary rate limits say how long to back off; short waits are sat out once.
+        let limited = matches!(res.status(), StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS);
+        match header_num::<u64>(&res, "retry-after") {
+            Some(wait) if limited && !retried && wait <= MAX_RETRY_WAIT_SECS => {
+                eprintln!("GitHub API asked to retry {} in {}s", path, wait);
+                tokio::time::sleep(Duration::from_secs(wait)).await;
+                retried = true;
+            }
+            _ => {
+                if limited {
+                    check_quota()?;
+                }
+                return Ok(res);
+            }
+        }
+    }
+}
+
+/// GETs `path` and parses the JSON response.
+pub async fn get_json<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let res = checked(send(Method::GET, path, JSON, None).await?).await?;
+    res.json().await.map_err(|e| e.to_string())
+}
+
+/// GETs `path` as text, e.g. raw file contents or a job log.
+pub async fn get_text(path: &str, accept: &str) -> Result<String, String> {
+    let res = checked(send(Method::GET, path, accept, None).await?).await?;
+    res.text().await.map_err(|e| e.to_string())
+}
+
+/// Sends a JSON request, returning the JSON response (`Null` when GitHub returns no body).
+pub async fn request_json(method: Method, path: &str, body: Option<Value>) -> Result<Value, String> {
+    let res = checked(send(method, path, JSON, body.as_ref()).await?).await?;
+    let text = res.text().await.map_err(|e| e.to_string())?;
+    Ok(serde_json::from_str(&text).unwrap_or(Value::Null))
+}
+
+/// Runs a GraphQL query, returning its `data` or the first error.
+pub async fn graphql(query: &str, variables: Value) -> Result<Value, String> {
+    let body = json!({ "query": query, "variables": variables });
+    let res = request_json(Method::POST, "/graphql", Some(body)).await?;
+    if let Some(error) = res.pointer("/errors/0/message").and_then(Value::as_str) {
+        return Err(error.to_string());
+    }
+    Ok(res.get("data").cloned().unwrap_or(Value::Null))
+}
@@ -0,0 +1,243 @@
+//! Suggested reviewers from the repository's CODEOWNERS file.
+//!
+//! When a PR is opened (or marked ready for review), the bot fetches CODEOWNERS from the PR's
+//! base branch and the list of files the PR touches, and mentions the owners of those paths in
+//! the PR's thread (or the PR channel without threads). Owners are mentioned if they linked
+//! their Discord account with `/link_github`, teams if they are mapped to a Discord role, so
+//! the right people see the PR before a review is formally requested.
+//!
+//! Environment Variables:
+//! - `CODEOWNERS_MENTIONS`: Set to `false` to turn suggestions off (default: on)
+//! - `CODEOWNERS_TEAM_ROLES`: Comma-separated `org/team=role_id` pairs for team owners
+//!
+//! Private repositories need GitHub API credentials (see [`super::client`]).
+
+use reqwest::{Method, StatusCode};
+use serde::Deserialize;
+use std::env;
+
+use super::client;
+use super::mentions::discord_mention_for;
+use super::threads;
+use crate::notify::{self, Delivery, Destination};
+use crate::AppState;
+
+/// Where GitHub looks for CODEOWNERS, in order.
+const LOCATIONS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+/// The files API returns at most 3000 files, 100 per page.
+const MAX_FILE_PAGES: usize = 30;
+
+#[derive(Debug, Deserialize)]
+struct PrFile {
+    filename: String,
+}
+
+/// One CODEOWNERS line: a path pattern and its owners (`@user`, `@org/team` or an email).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    pattern: String,
+    owners: Vec<String>,
+}
+
+/// Whether suggestions are enabled (`CODEOWNERS_MENTIONS`, default: true).
+pub fn enabled() -> bool {
+    env::var("CODEOWNERS_MENTIONS")
+        .map(|v| !v.trim().eq_ignore_ascii_case("false"))
+        .unwrap_or(true)
+}
+
+/// The raw CODEOWNERS file on `branch`, or `None` if the repository has none.
+async fn fetch_codeowners(repo: &str, branch: &str) -> Result<Option<String>, String> {
+    for location in LOCATIONS {
+        let path = format!("/repos/{}/contents/{}?ref={}", repo, location, branch);
+        let res = client::send(Method::GET, &path, client::RAW, None).await?;
+        if res.status() == StatusCode::NOT_FOUND {
+            continue;
+        }
+        if !res.status().is_success() {
+            return Err(format!("GitHub API returned {}", res.status()));
+        }
+        return res.text().await.map(Some).map_err(|e| e.to_string());
+    }
+    Ok(None)
+}
+
+/// Paths of every file a PR touches.
+async fn fetch_pr_files(repo: &str, number: u64) -> Result<Vec<String>, String> {
+    let mut files = Vec::new();
+    for page in 1..=MAX_FILE_PAGES {
+        let path = format!("/repos/{}/pulls/{}/files?per_page=100&page={}", repo, number, page);
+        let batch: Vec<PrFile> = client::get_json(&path).await?;
+        let done = batch.len() < 100;
+        files.extend(batch.into_iter().map(|f| f.filename));
+        if done {
+            break;
+        }
+    }
+    Ok(files)
+}
+
+/// Parses CODEOWNERS rules, skipping comments and blank lines.
+fn parse(content: &str) -> Vec<Rule> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.split_once('#').map_or(line, |(rule, _)| rule).trim();
+            let mut parts = line.split_whitespace();
+            let pattern = parts.next()?.to_string();
+            Some(Rule { pattern, owners: parts.map(str::to_string).collect() })
+        })
+        .collect()
+}
+
+/// Matches one path segment against a segment pattern with `*` and `?` wildcards.
+fn segment_matches(pattern: &str, segment: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let s: Vec<char> = segment.chars().collect();
+    let (mut pi, mut si) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while si < s.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == s[si]) {
+            pi += 1;
+            si += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, si));
+            pi += 1;
+        } else if let Some((star_pi, star_si)) = star {
+            pi = star_pi + 1;
+            si = star_si + 1;
+            star = Some((star_pi, star_si + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// Matches path segments against pattern segments, where `**` spans any number of segments.
+fn segments_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| segments_match(rest, &path[skip..])),
+        Some((first, rest)) => path
+            .split_first()
+            .is_some_and(|(segment, path_rest)| segment_matches(first, segment) && segments_match(rest, path_rest)),
+    }
+}
+
+/// Whether a CODEOWNERS `pattern` (gitignore syntax) covers `path`.
+///
+/// A pattern without a slash (other than a trailing one) matches at any depth; a pattern
+/// that matches a directory covers everything inside it.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let directory_only = pattern.ends_with('/');
+    let trimmed = pattern.trim_end_matches('/');
+    let anchored = trimmed.contains('/');
+    let trimmed = trimmed.trim_start_matches('/');
+    if trimmed.is_empty() {
+        return false;
+    }
+
+    let mut pattern_segments: Vec<&str> = trimmed.split('/').collect();
+    if !anchored {
+        pattern_segments.insert(0, "**");
+    }
+    let path_segments: Vec<&str> = path.split('/').collect();
+
+    // Try the path itself, then each parent directory.
+    (1..=path_segments.len()).rev().any(|len| {
+        let is_file = len == path_segments.len();
+        !(directory_only && is_file) && segments_match(&pattern_segments, &path_segments[..len])
+    })
+}
+
+/// Owners of `path`: the last matching rule wins, as on GitHub.
+fn owners_of<'a>(rules: &'a [Rule], path: &str) -> &'a [String] {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| pattern_matches(&rule.pattern, path))
+        .map(|rule| rule.owners.as_slice())
+        .unwrap_or_default()
+}
+
+/// Discord role per `org/team`, from `CODEOWNERS_TEAM_ROLES`.
+fn team_role(team: &str) -> Option<u64> {
+    env::var("CODEOWNERS_TEAM_ROLES")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|entry| entry.split_once('='))
+        .find(|(name, _)| name.trim().trim_start_matches('@').eq_ignore_ascii_case(team))
+        .and_then(|(_, role)| role.trim().parse().ok())
+}
+
+/// Owners of `files`, excluding `author`, as Discord mentions where linked and inline code
+/// otherwise. Email owners are skipped.
+fn suggested_reviewers(rules: &[Rule], files: &[String], author: &str) -> Vec<String> {
+    let mut owners: Vec<&str> = files
+        .iter()
+        .flat_map(|file| owners_of(rules, file))
+        .filter_map(|owner| owner.strip_prefix('@'))
+        .filter(|owner| !owner.eq_ignore_ascii_case(author))
+        .collect();
+    owners.sort_unstable_by_key(|owner| owner.to_lowercase());
+    owners.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+
+    owners
+        .into_iter()
+        .map(|owner| {
+            let mention = if owner.contains('/') {
+                team_role(owner).map(|role| format!("<@&{}>", role))
+            } else {
+                discord_mention_for(owner)
+            };
+            mention.unwrap_or_else(|| format!("`@{}`", owner))
+        })
+        .collect()
+}
+
+/// Mentions the code owners of the paths a PR touches in the PR's thread.
+///
+/// `parent` is the PR channel, used when threads are disabled or the thread can't be created.
+pub async fn suggest_reviewers(
+    state: &AppState,
+    parent: u64,
+    repo: &str,
+    number: u64,
+    title: &str,
+    base: &str,
+    author: &str,
+) {
+    let rules = match fetch_codeowners(repo, base).await {
+        Ok(Some(content)) => parse(&content),
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("Failed to fetch CODEOWNERS for {}: {}", repo, e);
+            return;
+        }
+    };
+    let files = match fetch_pr_files(repo, number).await {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("Failed to list files of {}#{}: {}", repo, number, e);
+            return;
+        }
+    };
+
+    let reviewers = suggested_reviewers(&rules, &files, author);
+    if reviewers.is_empty() {
+        return;
+    }
+    let message = format!("👀 Suggested reviewers (CODEOWNERS): {}", reviewers.join(" "));
+
+    let channel_id = if threads::enabled() {
+        threads::pr_thread(state, parent, repo, number, title).await.unwrap_or(parent)
+    } else {
+        parent
+    };
+    let delivery = notify::send(state, "pull_request", Destination::Channel(channel_id), message).await;
+    if let Delivery::Failed(e) = delivery {
+        eprintln!("Failed to post suggested reviewers for {}#{}: {}", repo, number, e);
+    }
+}
@@ -0,0 +1,90 @@
+//! Deduplication of redelivered webhooks.
+//!
+//! GitHub occasionally delivers the same event twice, and "Redeliver" in the webhook settings
+//! resends it with the same `X-GitHub-Delivery` ID. The most recent delivery IDs are kept in a
+//! bounded, least-recently-seen-first list so a repeated ID is not posted to Discord again.
+//! Deliveries whose handler failed are forgotten, so a manual redelivery can retry them.
+//!
+//! Environment Variables:
+//! - `GITHUB_DELIVERY_CACHE_SIZE`: Number of delivery IDs remembered (default 1000)
+
+use once_cell::sync::Lazy;
+use std::{
+    collections::{HashSet, VecDeque},
+    env,
+    sync::Mutex,
+};
+
+const DEFAULT_CAPACITY: usize = 1000;
+
+#[derive(Default)]
+struct SeenDeliveries {
+    /// Oldest first.
+    order: VecDeque<String>,
+    ids: HashSet<String>,
+}
+
+static SEEN: Lazy<Mutex<SeenDeliveries>> = Lazy::new(|| Mutex::new(SeenDeliveries::default()));
+
+fn capacity() -> usize {
+    env::var("GITHUB_DELIVERY_CACHE_SIZE")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_CAPACITY)
+}
+
+/// Records `delivery` as seen. Returns `false` if it was already seen, i.e. a duplicate.
+pub fn first_sighting(delivery: &str) -> bool {
+    let mut seen = SEEN.lock().unwrap();
+    if seen.ids.contains(delivery) {
+        // Refresh it so a burst of redeliveries doesn't let it fall out of the cache.
+        seen.order.retain(|id| id != delivery);
+        seen.order.push_back(delivery.to_string());
+        return false;
+    }
+
+    seen.ids.insert(delivery.to_string());
+    seen.order.push_back(delivery.to_string());
+
+    let capacity = capacity().max(1);
+    while seen.order.len() > capacity {
+        if let Some(oldest) = seen.order.pop_front() {
+            seen.ids.remove(&oldest);
+        }
+    }
+    true
+}
+
+/// Forgets `delivery`, so a later redelivery is processed again.
+pub fn forget(delivery: &str) {
+    let mut seen = SEEN.lock().unwrap();
+    if seen.ids.remove(delivery) {
+        seen.order.retain(|id| id != delivery);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{first_sighting, forget};
+
+    #[test]
+    fn repeated_delivery_is_a_duplicate() {
+        assert!(first_sighting("deliveries-test-repeat"));
+        assert!(!first_sighting("deliveries-test-repeat"));
+        assert!(!first_sighting("deliveries-test-repeat"));
+    }
+
+    #[test]
+    fn forgotten_delivery_is_processed_again() {
+        assert!(first_sighting("deliveries-test-forget"));
+        forget("deliveries-test-forget");
+        assert!(first_sighting("deliveries-test-forget"));
+        assert!(!first_sighting("deliveries-test-forget"));
+    }
+
+    #[test]
+    fn distinct_deliveries_are_first_sightings() {
+        assert!(first_sighting("deliveries-test-a"));
+        assert!(first_sighting("deliveries-test-b"));
+    }
+}
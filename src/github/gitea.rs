@@ -0,0 +1,96 @@
+//! Compatibility with Gitea and Forgejo webhooks.
+//!
+//! Self-hosted forges can point their webhooks at the same `/webhook/github-webhook` endpoint.
+//! Their payloads mostly mirror GitHub's, so they run through the same handlers; this module
+//! covers the differences:
+//! - the event type and delivery ID come from `X-Forgejo-*` / `X-Gitea-*` headers
+//! - the signature is a bare hex HMAC-SHA256 in `X-Forgejo-Signature` / `X-Gitea-Signature`,
+//!   checked against the same secrets as GitHub's (see [`super::signature`])
+//! - reviews arrive as `pull_request_approved`, `pull_request_rejected`, and
+//!   `pull_request_comment` events and are rewritten into GitHub's `pull_request_review` shape
+
+use axum::http::HeaderMap;
+use serde_json::{json, Value};
+
+/// The forge that sent a delivery, judged by its headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Forge {
+    GitHub,
+    Gitea,
+    Forgejo,
+}
+
+impl Forge {
+    pub fn detect(headers: &HeaderMap) -> Self {
+        if headers.contains_key("X-Forgejo-Event") {
+            Forge::Forgejo
+        } else if headers.contains_key("X-Gitea-Event") {
+            Forge::Gitea
+        } else {
+            Forge::GitHub
+        }
+    }
+
+    fn header(self, headers: &HeaderMap, suffix: &str) -> Option<String> {
+        let names: &[&str] = match self {
+            Forge::GitHub => &["X-GitHub-"],
+            // Forgejo also sends the `X-Gitea-*` headers; prefer its own.
+            Forge::Forgejo => &["X-Forgejo-", "X-Gitea-"],
+            Forge::Gitea => &["X-Gitea-"],
+        };
+        names.iter().find_map(|prefix| {
+            headers
+                .get(format!("{}{}", prefix, suffix))
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        })
+    }
+
+    /// The event type, e.g. `pull_request`.
+    pub fn event(self, headers: &HeaderMap) -> Option<String> {
+        self.header(headers, "Event")
+    }
+
+    /// The delivery's unique ID.
+    pub fn delivery(self, headers: &HeaderMap) -> Option<String> {
+        self.header(headers, "Delivery")
+    }
+}
+
+/// The decoded `X-Forgejo-Signature` / `X-Gitea-Signature` header, if present.
+pub fn signature(headers: &HeaderMap) -> Option<Vec<u8>> {
+    ["X-Forgejo-Signature", "X-Gitea-Signature"].iter().find_map(|name| {
+        headers
+            .get(*name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| hex::decode(v.trim()).ok())
+    })
+}
+
+/// Rewrites a Gitea-only event into the GitHub event the handlers understand.
+///
+/// Returns `None` when the event can be handled as it is.
+pub fn normalize(event: &str, body: &[u8]) -> Result<Option<(String, Vec<u8>)>, String> {
+    let state = match event {
+        "pull_request_approved" => "approved",
+        "pull_request_rejected" => "changes_requested",
+        "pull_request_comment" => "commented",
+        _ => return Ok(None),
+    };
+
+    let payload: Value = serde_json::from_slice(body).map_err(|e| e.to_string())?;
+    let review = json!({
+        "action": "submitted",
+        "review": {
+            // Gitea reviews have no page of their own; link the PR instead.
+            "html_url": payload.pointer("/pull_request/html_url").cloned().unwrap_or_default(),
+            "state": state,
+            "body": payload.pointer("/review/content").cloned().unwrap_or(Value::Null),
+            "user": payload.get("sender").cloned().unwrap_or_default(),
+        },
+        "pull_request": payload.get("pull_request").cloned().unwrap_or_default(),
+        "repository": payload.get("repository").cloned().unwrap_or_default(),
+    });
+    let body = serde_json::to_vec(&review).map_err(|e| e.to_string())?;
+    Ok(Some(("pull_request_review".to_string(), body)))
+}
@@ -0,0 +1,153 @@
+use axum::extract::{Json, State};
+use serde::Deserialize;
+
+use super::{deliver, repo_channel};
+use crate::github::WebhookOutcome;
+use crate::guilds::ChannelKind;
+use crate::ops_events::{self, EventKind};
+use crate::AppState;
+
+/// GitHub Actions suites and runs are already reported by the `workflow_run`/`workflow_job` handlers.
+const ACTIONS_APP_SLUG: &str = "github-actions";
+
+#[derive(Debug, Deserialize)]
+pub struct CheckSuiteEvent {
+    pub action: String,
+    pub check_suite: CheckSuite,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CheckSuite {
+    pub head_branch: Option<String>,
+    pub head_sha: String,
+    pub conclusion: Option<String>,
+    pub app: App,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CheckRunEvent {
+    pub action: String,
+    pub check_run: CheckRun,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CheckRun {
+    pub name: String,
+    pub html_url: String,
+    pub details_url: Option<String>,
+    pub conclusion: Option<String>,
+    pub app: App,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct App {
+    pub name: String,
+    pub slug: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    pub full_name: String,
+    pub html_url: String,
+}
+
+fn is_actions(app: &App) -> bool {
+    app.slug.as_deref() == Some(ACTIONS_APP_SLUG)
+}
+
+fn is_failure(conclusion: &str) -> bool {
+    matches!(conclusion, "failure" | "timed_out" | "action_required" | "startup_failure")
+}
+
+/// Reports completed check suites from external CI apps with their overall result.
+pub async fn handle_check_suite_event(
+    State(state): State<AppState>,
+    Json(payload): Json<CheckSuiteEvent>,
+) -> WebhookOutcome {
+    if payload.action != "completed" {
+        return WebhookOutcome::ignored("check_suite", format!("action `{}` is not `completed`", payload.action));
+    }
+
+    let suite = &payload.check_suite;
+    if is_actions(&suite.app) {
+        return WebhookOutcome::ignored("check_suite", "GitHub Actions suites are reported via workflow_run");
+    }
+
+    let conclusion = suite.conclusion.as_deref().unwrap_or("unknown");
+    let (emoji, summary) = match conclusion {
+        "success" => ("✅", "passed"),
+        c if is_failure(c) => ("❌", "failed"),
+        "neutral" | "skipped" | "cancelled" | "stale" => {
+            return WebhookOutcome::ignored("check_suite", format!("suite concluded with `{}`", conclusion));
+        }
+        _ => ("⚠️", "completed"),
+    };
+
+    let record = format!("{} checks in {}", suite.app.name, payload.repository.full_name);
+    if conclusion == "success" {
+        ops_events::record(EventKind::CiSuccess, record);
+    } else if is_failure(conclusion) {
+        ops_events::record(EventKind::CiFailure, record);
+    }
+
+    let short_sha: String = suite.head_sha.chars().take(7).collect();
+    let branch = suite
+        .head_branch
+        .as_deref()
+        .map(|b| format!(" on `{}`", b))
+        .unwrap_or_default();
+
+    let message = format!(
+        "{} **{}** checks {} for `{}`{} in **{}** (result `{}`):\n{}/commit/{}/checks",
+        emoji,
+        suite.app.name,
+        summary,
+        short_sha,
+        branch,
+        payload.repository.full_name,
+        conclusion,
+        payload.repository.html_url,
+        suite.head_sha
+    );
+
+    match repo_channel("check_suite", &payload.repository.full_name, ChannelKind::Workflows) {
+        Ok(channel_id) => deliver(&state, "check_suite", channel_id, message).await,
+        Err(outcome) => outcome,
+    }
+}
+
+/// Reports individual failed check runs from external CI apps, linking to the check details.
+pub async fn handle_check_run_event(
+    State(state): State<AppState>,
+    Json(payload): Json<CheckRunEvent>,
+) -> WebhookOutcome {
+    if payload.action != "completed" {
+        return WebhookOutcome::ignored("check_run", format!("action `{}` is not `completed`", payload.action));
+    }
+
+    let run = &payload.check_run;
+    if is_actions(&run.app) {
+        return WebhookOutcome::ignored("check_run", "GitHub Actions runs are reported via workflow_job");
+    }
+
+    let conclusion = run.conclusion.as_deref().unwrap_or("unknown");
+    if !is_failure(conclusion) {
+        return WebhookOutcome::ignored("check_run", format!("check concluded with `{}`", conclusion));
+    }
+
+    let message = format!(
+        "❌ Check **{}** ({}) in **{}** concluded with `{}`:\n{}",
+        run.name,
+        run.app.name,
+        payload.repository.full_name,
+        conclusion,
+        run.details_url.as_deref().unwrap_or(&run.html_url)
+    );
+
+    match repo_channel("check_run", &payload.repository.full_name, ChannelKind::Workflows) {
+        Ok(channel_id) => deliver(&state, "check_run", channel_id, message).await,
+        Err(outcome) => outcome,
+    }
+}
@@ -0,0 +1,180 @@
+use axum::extract::{Json, State};
+use serde::Deserialize;
+
+use super::{deliver, quote_excerpt, quote_pr_excerpt, repo_channel};
+use crate::github::mentions::{escape_mentions, resolve_mentions};
+use crate::github::{threads, WebhookOutcome};
+use crate::guilds::ChannelKind;
+use crate::AppState;
+
+/// Longest comment excerpt included in a notification.
+const MAX_COMMENT_CHARS: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct IssueCommentEvent {
+    pub action: String,
+    pub issue: Issue,
+    pub comment: Comment,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Issue {
+    pub number: u64,
+    pub title: String,
+    /// Present when the issue is actually a pull request.
+    pub pull_request: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewCommentEvent {
+    pub action: String,
+    pub pull_request: PullRequest,
+    pub comment: ReviewComment,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PullRequest {
+    pub number: u64,
+    pub title: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Comment {
+    pub html_url: String,
+    pub body: Option<String>,
+    pub user: User,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewComment {
+    pub html_url: String,
+    pub body: Option<String>,
+    pub path: String,
+    pub line: Option<u64>,
+    pub user: User,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    pub full_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct User {
+    pub login: String,
+}
+
+/// Posts a PR comment into the PR's thread when threads are enabled, else into the PR channel.
+async fn deliver_pr_comment(
+    state: &AppState,
+    handler: &'static str,
+    repo: &str,
+    number: u64,
+    title: &str,
+    message: String,
+) -> WebhookOutcome {
+    let pr_channel = match repo_channel(handler, repo, ChannelKind::PullRequests) {
+        Ok(id) => id,
+        Err(outcome) => return outcome,
+    };
+
+    let channel_id = if threads::enabled() {
+        threads::pr_thread(state, pr_channel, repo, number, title)
+            .await
+            .unwrap_or(pr_channel)
+    } else {
+        pr_channel
+    };
+
+    deliver(state, handler, channel_id, message).await
+}
+
+/// Handles `issue_comment` events, which GitHub sends for comments on both issues and PRs.
+pub async fn handle_issue_comment_event(
+    State(state): State<AppState>,
+    Json(payload): Json<IssueCommentEvent>,
+) -> WebhookOutcome {
+    if payload.action != "created" {
+        return WebhookOutcome::ignored("issue_comment", format!("unsupported action `{}`", payload.action));
+    }
+
+    let issue = &payload.issue;
+    let comment = &payload.comment;
+    // Only PR conversations ping linked users; issue text is passed through escaped.
+    let (kind, title, body) = if issue.pull_request.is_some() {
+        ("PR", resolve_mentions(&issue.title), quote_pr_excerpt(comment.body.as_deref(), MAX_COMMENT_CHARS))
+    } else {
+        ("issue", escape_mentions(&issue.title), quote_excerpt(comment.body.as_deref(), MAX_COMMENT_CHARS))
+    };
+
+    let message = format!(
+        "💬 `{}` commented on {} #{} in **{}**:\n**{}**\n{}{}",
+        comment.user.login,
+        kind,
+        issue.number,
+        payload.repository.full_name,
+        title,
+        body,
+        comment.html_url
+    );
+
+    if issue.pull_request.is_some() {
+        deliver_pr_comment(
+            &state,
+            "issue_comment",
+            &payload.repository.full_name,
+            issue.number,
+            &issue.title,
+            message,
+        )
+        .await
+    } else {
+        match repo_channel("issue_comment", &payload.repository.full_name, ChannelKind::Issues) {
+            Ok(channel_id) => deliver(&state, "issue_comment", channel_id, message).await,
+            Err(outcome) => outcome,
+        }
+    }
+}
+
+/// Handles `pull_request_review_comment` events (inline comments on a PR's diff).
+pub async fn handle_review_comment_event(
+    State(state): State<AppState>,
+    Json(payload): Json<ReviewCommentEvent>,
+) -> WebhookOutcome {
+    if payload.action != "created" {
+        return WebhookOutcome::ignored(
+            "pull_request_review_comment",
+            format!("unsupported action `{}`", payload.action),
+        );
+    }
+
+    let pr = &payload.pull_request;
+    let comment = &payload.comment;
+    let location = match comment.line {
+        Some(line) => format!("{}:{}", comment.path, line),
+        None => comment.path.clone(),
+    };
+
+    let message = format!(
+        "💬 `{}` commented on `{}` in PR #{} in **{}**:\n**{}**\n{}{}",
+        comment.user.login,
+        location,
+        pr.number,
+        payload.repository.full_name,
+        resolve_mentions(&pr.title),
+        quote_pr_excerpt(comment.body.as_deref(), MAX_COMMENT_CHARS),
+        comment.html_url
+    );
+
+    deliver_pr_comment(
+        &state,
+        "pull_request_review_comment",
+        &payload.repository.full_name,
+        pr.number,
+        &pr.title,
+        message,
+    )
+    .await
+}
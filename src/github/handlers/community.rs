@@ -0,0 +1,254 @@
+use axum::extract::{Json, State};
+use chrono::{Local, Timelike};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, env, time::Duration};
+use tokio::time::sleep;
+
+use super::{deliver, hold_low_priority, repo_channel};
+use crate::github::WebhookOutcome;
+use crate::guilds::ChannelKind;
+use crate::notify::low_priority;
+use crate::store;
+use crate::tasks::Tasks;
+use crate::AppState;
+
+const DIGEST_FILE: &str = "community_digest.json";
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_secs(600);
+/// Most names listed per repository in a digest line.
+const MAX_LISTED_USERS: usize = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct StarEvent {
+    pub action: String,
+    pub repository: Repository,
+    pub sender: User,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForkEvent {
+    pub forkee: Forkee,
+    pub repository: Repository,
+    pub sender: User,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Forkee {
+    pub full_name: String,
+    pub html_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    pub full_name: String,
+    pub html_url: String,
+    #[serde(default)]
+    pub stargazers_count: u64,
+    #[serde(default)]
+    pub forks_count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct User {
+    pub login: String,
+}
+
+/// Stars and forks waiting for the daily digest, per channel.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Digest {
+    /// Local date (`YYYY-MM-DD`) the last digest was posted.
+    last_posted: Option<String>,
+    #[serde(default)]
+    pending: BTreeMap<u64, Vec<PendingEvent>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingEvent {
+    repo: String,
+    login: String,
+    /// Full name of the fork, for fork events.
+    fork: Option<String>,
+}
+
+/// Whether stars and forks are batched into a daily digest (`COMMUNITY_DIGEST=daily`).
+fn digest_enabled() -> bool {
+    env::var("COMMUNITY_DIGEST").is_ok_and(|v| v.trim().eq_ignore_ascii_case("daily"))
+}
+
+/// Local hour the daily digest is posted at (`COMMUNITY_DIGEST_HOUR`, default 18).
+fn digest_hour() -> u32 {
+    env::var("COMMUNITY_DIGEST_HOUR")
+        .ok()
+        .and_then(|h| h.parse().ok())
+        .filter(|h| *h < 24)
+        .unwrap_or(18)
+}
+
+/// Queues an event for the digest.
+fn queue(channel_id: u64, event: PendingEvent) {
+    let mut digest: Digest = store::load(DIGEST_FILE);
+    digest.pending.entry(channel_id).or_default().push(event);
+    store::save(DIGEST_FILE, &digest);
+}
+
+/// Celebrates a new star, or queues it for the daily digest.
+pub async fn handle_star_event(
+    State(state): State<AppState>,
+    Json(payload): Json<StarEvent>,
+) -> WebhookOutcome {
+    if payload.action != "created" {
+        return WebhookOutcome::ignored("star", format!("unsupported action `{}`", payload.action));
+    }
+
+    let channel_id = match repo_channel("star", &payload.repository.full_name, ChannelKind::Community) {
+        Ok(id) => id,
+        Err(outcome) => return outcome,
+    };
+
+    if digest_enabled() {
+        queue(
+            channel_id,
+            PendingEvent {
+                repo: payload.repository.full_name.clone(),
+                login: payload.sender.login.clone(),
+                fork: None,
+            },
+        );
+        let mut outcome = WebhookOutcome::handled("star");
+        outcome.reason = Some("queued for the daily digest".into());
+        return outcome;
+    }
+
+    if low_priority::enabled("star") {
+        let line = format!(
+            "`{}` starred **{}** ({} stars)",
+            payload.sender.login, payload.repository.full_name, payload.repository.stargazers_count
+        );
+        return hold_low_priority("star", channel_id, "star", line);
+    }
+
+    let message = format!(
+        "⭐ `{}` starred **{}** ({} stars)\n<{}>",
+        payload.sender.login,
+        payload.repository.full_name,
+        payload.repository.stargazers_count,
+        payload.repository.html_url
+    );
+
+    deliver(&state, "star", channel_id, message).await
+}
+
+/// Celebrates a new fork, or queues it for the daily digest.
+pub async fn handle_fork_event(
+    State(state): State<AppState>,
+    Json(payload): Json<ForkEvent>,
+) -> WebhookOutcome {
+    let channel_id = match repo_channel("fork", &payload.repository.full_name, ChannelKind::Community) {
+        Ok(id) => id,
+        Err(outcome) => return outcome,
+    };
+
+    if digest_enabled() {
+        queue(
+            channel_id,
+            PendingEvent {
+                repo: payload.repository.full_name.clone(),
+                login: payload.sender.login.clone(),
+                fork: Some(payload.forkee.full_name.clone()),
+            },
+        );
+        let mut outcome = WebhookOutcome::handled("fork");
+        outcome.reason = Some("queued for the daily digest".into());
+        return outcome;
+    }
+
+    let message = format!(
+        "🍴 `{}` forked **{}** to [{}](<{}>) ({} forks)",
+        payload.sender.login,
+        payload.repository.full_name,
+        payload.forkee.full_name,
+        payload.forkee.html_url,
+        payload.repository.forks_count
+    );
+
+    deliver(&state, "fork", channel_id, message).await
+}
+
+/// One digest message for a channel's pending events.
+fn digest_message(events: &[PendingEvent]) -> String {
+    let mut by_repo: BTreeMap<&str, (Vec<&str>, Vec<&str>)> = BTreeMap::new();
+    for event in events {
+        let (stars, forks) = by_repo.entry(&event.repo).or_default();
+        match &event.fork {
+            Some(_) => forks.push(&event.login),
+            None => stars.push(&event.login),
+        }
+    }
+
+    let list = |users: &[&str]| {
+        let mut names: Vec<String> = users
+            .iter()
+            .take(MAX_LISTED_USERS)
+            .map(|u| format!("`{}`", u))
+            .collect();
+        if users.len() > MAX_LISTED_USERS {
+            names.push(format!("and {} more", users.len() - MAX_LISTED_USERS));
+        }
+        names.join(", ")
+    };
+
+    let mut lines = vec!["🎉 **Today's community activity**".to_string()];
+    for (repo, (stars, forks)) in by_repo {
+        if !stars.is_empty() {
+            lines.push(format!("⭐ **{}**: {} new star(s) from {}", repo, stars.len(), list(&stars)));
+        }
+        if !forks.is_empty() {
+            lines.push(format!("🍴 **{}**: {} new fork(s) by {}", repo, forks.len(), list(&forks)));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Starts the loop that posts the daily community digest, if `COMMUNITY_DIGEST=daily`.
+pub async fn start_community_digest_loop(state: AppState) {
+    if !digest_enabled() {
+        return;
+    }
+    if state.tasks.is_running("community_digest") {
+        println!("Community digest loop already running, reusing it.");
+        return;
+    }
+
+    let tasks: Tasks = state.tasks.clone();
+    tasks.spawn("community_digest", move |beat| {
+        let state = state.clone();
+        async move {
+            loop {
+                beat.tick();
+                let now = Local::now();
+                let today = now.format("%Y-%m-%d").to_string();
+                let mut digest: Digest = store::load(DIGEST_FILE);
+
+                let due = now.hour() >= digest_hour() && digest.last_posted.as_deref() != Some(today.as_str());
+                if due {
+                    for (channel_id, events) in std::mem::take(&mut digest.pending) {
+                        if events.is_empty() {
+                            continue;
+                        }
+                        let message = digest_message(&events);
+                        let outcome = deliver(&state, "community_digest", channel_id, message).await;
+                        if !outcome.handled {
+                            // Keep the events for the next attempt.
+                            digest.pending.insert(channel_id, events);
+                        }
+                    }
+                    if digest.pending.is_empty() {
+                        digest.last_posted = Some(today);
+                    }
+                    store::save(DIGEST_FILE, &digest);
+                }
+
+                sleep(DIGEST_CHECK_INTERVAL).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,155 @@
+use axum::extract::{Json, State};
+use serde::Deserialize;
+
+use super::{deliver, repo_channel};
+use crate::github::mentions::escape_mentions;
+use crate::github::WebhookOutcome;
+use crate::guilds::ChannelKind;
+use crate::ops_events::{self, EventKind};
+use crate::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct DeploymentEvent {
+    pub action: String,
+    pub deployment: Deployment,
+    pub repository: Repository,
+    pub sender: User,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeploymentStatusEvent {
+    pub deployment_status: DeploymentStatus,
+    pub deployment: Deployment,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Deployment {
+    pub environment: String,
+    #[serde(rename = "ref")]
+    pub r#ref: String,
+    pub sha: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeploymentStatus {
+    pub state: String,
+    pub description: Option<String>,
+    pub log_url: Option<String>,
+    pub target_url: Option<String>,
+    pub environment_url: Option<String>,
+    pub creator: User,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    pub full_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct User {
+    pub login: String,
+}
+
+fn short_sha(sha: &str) -> String {
+    sha.chars().take(7).collect()
+}
+
+/// Announces a newly created deployment to an environment.
+pub async fn handle_deployment_event(
+    State(state): State<AppState>,
+    Json(payload): Json<DeploymentEvent>,
+) -> WebhookOutcome {
+    if payload.action != "created" {
+        return WebhookOutcome::ignored("deployment", format!("unsupported action `{}`", payload.action));
+    }
+
+    let deployment = &payload.deployment;
+    let description = deployment
+        .description
+        .as_deref()
+        .filter(|d| !d.trim().is_empty())
+        .map(|d| format!("\n> {}", escape_mentions(d)))
+        .unwrap_or_default();
+
+    let message = format!(
+        "🚚 Deploying **{}** `{}` (`{}`) to **{}** by `{}`{}",
+        payload.repository.full_name,
+        deployment.r#ref,
+        short_sha(&deployment.sha),
+        deployment.environment,
+        payload.sender.login,
+        description
+    );
+
+    match repo_channel("deployment", &payload.repository.full_name, ChannelKind::Deployments) {
+        Ok(channel_id) => deliver(&state, "deployment", channel_id, message).await,
+        Err(outcome) => outcome,
+    }
+}
+
+/// Announces deployment state changes (in progress, success, failure, ...) with the log URL.
+pub async fn handle_deployment_status_event(
+    State(state): State<AppState>,
+    Json(payload): Json<DeploymentStatusEvent>,
+) -> WebhookOutcome {
+    let status = &payload.deployment_status;
+    let deployment = &payload.deployment;
+
+    let (emoji, what) = match status.state.as_str() {
+        "success" => ("✅", "succeeded"),
+        "failure" => ("❌", "failed"),
+        "error" => ("💥", "errored"),
+        "in_progress" => ("⏳", "is in progress"),
+        "inactive" => {
+            return WebhookOutcome::ignored("deployment_status", "deployment superseded (inactive)");
+        }
+        _ => ("ℹ️", "was updated"),
+    };
+
+    let summary = format!(
+        "{} `{}` to {}",
+        payload.repository.full_name, deployment.r#ref, deployment.environment
+    );
+    let link = status.log_url.clone().or_else(|| status.target_url.clone());
+    match status.state.as_str() {
+        "success" => ops_events::record_with_link(EventKind::Deployment, summary, link),
+        "failure" | "error" => ops_events::record_with_link(
+            EventKind::Incident,
+            format!("Deployment failed: {}", summary),
+            link,
+        ),
+        _ => {}
+    }
+
+    let mut message = format!(
+        "{} Deployment of **{}** `{}` (`{}`) to **{}** {} (`{}`)",
+        emoji,
+        payload.repository.full_name,
+        deployment.r#ref,
+        short_sha(&deployment.sha),
+        deployment.environment,
+        what,
+        status.creator.login
+    );
+    if let Some(description) = status.description.as_deref().filter(|d| !d.trim().is_empty()) {
+        message.push_str(&format!("\n> {}", escape_mentions(description)));
+    }
+    if let Some(url) = status.environment_url.as_deref().filter(|u| !u.is_empty()) {
+        message.push_str(&format!("\nEnvironment: {}", url));
+    }
+    if let Some(url) = status
+        .log_url
+        .as_deref()
+        .or(status.target_url.as_deref())
+        .filter(|u| !u.is_empty())
+    {
+        message.push_str(&format!("\nLogs: {}", url));
+    }
+
+    match repo_channel("deployment_status", &payload.repository.full_name, ChannelKind::Deployments) {
+        Ok(channel_id) => deliver(&state, "deployment_status", channel_id, message).await,
+        Err(outcome) => outcome,
+    }
+}
@@ -0,0 +1,153 @@
+use axum::extract::{Json, State};
+use serde::Deserialize;
+
+use super::{deliver_to_repo, quote_excerpt};
+use crate::github::mentions::escape_mentions;
+use crate::github::WebhookOutcome;
+use crate::guilds::ChannelKind;
+use crate::AppState;
+
+/// Longest discussion or comment excerpt included in a notification.
+const MAX_BODY_CHARS: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct DiscussionEvent {
+    pub action: String,
+    pub discussion: Discussion,
+    /// The comment marked as the answer, for `answered` events.
+    pub answer: Option<Comment>,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiscussionCommentEvent {
+    pub action: String,
+    pub comment: Comment,
+    pub discussion: Discussion,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Discussion {
+    pub number: u64,
+    pub title: String,
+    pub html_url: String,
+    pub body: Option<String>,
+    pub user: User,
+    pub category: Category,
+    /// Set once a comment has been marked as the answer (Q&A categories only).
+    pub answer_html_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Category {
+    pub name: String,
+    pub emoji: Option<String>,
+    /// `true` for Q&A categories, whose discussions can be marked answered.
+    #[serde(default)]
+    pub is_answerable: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Comment {
+    pub html_url: String,
+    pub body: Option<String>,
+    pub user: User,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    pub full_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct User {
+    pub login: String,
+}
+
+/// Reports new discussions, and Q&A discussions being answered.
+pub async fn handle_discussion_event(
+    State(state): State<AppState>,
+    Json(payload): Json<DiscussionEvent>,
+) -> WebhookOutcome {
+    let discussion = &payload.discussion;
+    let repo = &payload.repository.full_name;
+
+    let message = match payload.action.as_str() {
+        "created" => {
+            let headline = if discussion.category.is_answerable {
+                format!("❓ New question #{} in **{}** by `{}`", discussion.number, repo, discussion.user.login)
+            } else {
+                format!("🗨️ New discussion #{} in **{}** by `{}`", discussion.number, repo, discussion.user.login)
+            };
+            format!(
+                "{} ({}):\n**{}**\n{}{}",
+                headline,
+                category_label(&discussion.category),
+                escape_mentions(&discussion.title),
+                quote_excerpt(discussion.body.as_deref(), MAX_BODY_CHARS),
+                discussion.html_url
+            )
+        }
+        "answered" => {
+            let (answerer, url) = match &payload.answer {
+                Some(answer) => (answer.user.login.as_str(), answer.html_url.as_str()),
+                None => ("someone", discussion.answer_html_url.as_deref().unwrap_or(&discussion.html_url)),
+            };
+            format!(
+                "✅ Question #{} in **{}** answered by `{}`:\n**{}**\n{}",
+                discussion.number,
+                repo,
+                answerer,
+                escape_mentions(&discussion.title),
+                url
+            )
+        }
+        other => return WebhookOutcome::ignored("discussion", format!("unsupported action `{}`", other)),
+    };
+
+    deliver_to_repo(&state, "discussion", repo, ChannelKind::Discussions, message).await
+}
+
+/// Reports replies on discussions, flagging those on questions that are still unanswered.
+pub async fn handle_discussion_comment_event(
+    State(state): State<AppState>,
+    Json(payload): Json<DiscussionCommentEvent>,
+) -> WebhookOutcome {
+    if payload.action != "created" {
+        return WebhookOutcome::ignored(
+            "discussion_comment",
+            format!("unsupported action `{}`", payload.action),
+        );
+    }
+
+    let discussion = &payload.discussion;
+    let comment = &payload.comment;
+    let repo = &payload.repository.full_name;
+
+    let unanswered = if discussion.category.is_answerable && discussion.answer_html_url.is_none() {
+        " (unanswered)"
+    } else {
+        ""
+    };
+    let message = format!(
+        "💬 `{}` replied to discussion #{}{} in **{}**:\n**{}**\n{}{}",
+        comment.user.login,
+        discussion.number,
+        unanswered,
+        repo,
+        escape_mentions(&discussion.title),
+        quote_excerpt(comment.body.as_deref(), MAX_BODY_CHARS),
+        comment.html_url
+    );
+
+    deliver_to_repo(&state, "discussion_comment", repo, ChannelKind::Discussions, message).await
+}
+
+fn category_label(category: &Category) -> String {
+    match category.emoji.as_deref() {
+        // GitHub sends category emoji as `:shortcode:`, which Discord renders the same way.
+        Some(emoji) if !emoji.is_empty() => format!("{} {}", emoji, category.name),
+        _ => category.name.clone(),
+    }
+}
@@ -0,0 +1,97 @@
+use axum::extract::{Json, State};
+use serde::Deserialize;
+
+use super::deliver_to_repo;
+use crate::github::mentions::escape_mentions;
+use crate::github::WebhookOutcome;
+use crate::guilds::ChannelKind;
+use crate::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct IssuesEvent {
+    pub action: String,
+    pub issue: Issue,
+    /// The label that was added, for `labeled` events.
+    pub label: Option<Label>,
+    pub repository: Repository,
+    pub sender: User,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Issue {
+    pub number: u64,
+    pub title: String,
+    pub html_url: String,
+    pub user: User,
+    #[serde(default)]
+    pub labels: Vec<Label>,
+    pub state_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Label {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    pub full_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct User {
+    pub login: String,
+}
+
+pub async fn handle_issues_event(
+    State(state): State<AppState>,
+    Json(payload): Json<IssuesEvent>,
+) -> WebhookOutcome {
+    let issue = &payload.issue;
+
+    let headline = match payload.action.as_str() {
+        "opened" => format!("🐛 New issue #{} in **{}** by `{}`", issue.number, payload.repository.full_name, issue.user.login),
+        "closed" => format!(
+            "✅ Issue #{} closed{} in **{}** by `{}`",
+            issue.number,
+            match issue.state_reason.as_deref() {
+                Some("not_planned") => " as not planned",
+                _ => "",
+            },
+            payload.repository.full_name,
+            payload.sender.login
+        ),
+        "labeled" => format!(
+            "🏷️ Issue #{} in **{}** labeled `{}` by `{}`",
+            issue.number,
+            payload.repository.full_name,
+            payload.label.as_ref().map(|l| l.name.as_str()).unwrap_or("unknown"),
+            payload.sender.login
+        ),
+        other => return WebhookOutcome::ignored("issues", format!("unsupported action `{}`", other)),
+    };
+
+    let labels = if issue.labels.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "\nLabels: {}",
+            issue
+                .labels
+                .iter()
+                .map(|l| format!("`{}`", l.name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    };
+
+    let message = format!(
+        "{}:\n**{}**{}\n{}",
+        headline,
+        escape_mentions(&issue.title),
+        labels,
+        issue.html_url
+    );
+
+    deliver_to_repo(&state, "issues", &payload.repository.full_name, ChannelKind::Issues, message).await
+}
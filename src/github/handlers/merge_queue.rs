@@ -0,0 +1,154 @@
+use axum::extract::{Json, State};
+use serde::Deserialize;
+
+use super::deliver_to_repo;
+use crate::github::mentions::escape_mentions;
+use crate::github::WebhookOutcome;
+use crate::guilds::ChannelKind;
+use crate::AppState;
+
+/// `merge_group` event, sent when GitHub's merge queue starts or discards a queue run.
+#[derive(Debug, Deserialize)]
+pub struct MergeGroupEvent {
+    pub action: String,
+    /// Why the group was destroyed: `merged`, `invalidated`, or `dequeued`.
+    pub reason: Option<String>,
+    pub merge_group: MergeGroup,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergeGroup {
+    pub head_sha: String,
+    /// Temporary queue branch, e.g. `refs/heads/gh-readonly-queue/main/pr-42-<sha>`.
+    pub head_ref: String,
+    pub base_ref: String,
+    pub head_commit: Option<Commit>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Commit {
+    pub message: String,
+}
+
+/// `pull_request` event for the auto-merge and merge queue actions.
+#[derive(Debug, Deserialize)]
+pub struct PullRequestQueueEvent {
+    pub action: String,
+    /// Why the PR left the queue or auto-merge was disabled, e.g. `CI_FAILURE`.
+    pub reason: Option<String>,
+    pub pull_request: PullRequest,
+    pub repository: Repository,
+    pub sender: User,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PullRequest {
+    pub number: u64,
+    pub title: String,
+    pub html_url: String,
+    pub auto_merge: Option<AutoMerge>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AutoMerge {
+    /// `merge`, `squash`, or `rebase`.
+    pub merge_method: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    pub full_name: String,
+    pub html_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct User {
+    pub login: String,
+}
+
+/// Reports merge queue runs that fail. Successful runs are covered by the PR's `closed` event.
+pub async fn handle_merge_group_event(
+    State(state): State<AppState>,
+    Json(payload): Json<MergeGroupEvent>,
+) -> WebhookOutcome {
+    if payload.action != "destroyed" {
+        return WebhookOutcome::ignored("merge_group", format!("unsupported action `{}`", payload.action));
+    }
+    let reason = payload.reason.as_deref().unwrap_or("unknown");
+    if reason == "merged" {
+        return WebhookOutcome::ignored("merge_group", "queue run merged; reported by the PR's closed event");
+    }
+
+    let group = &payload.merge_group;
+    let branch = group.head_ref.trim_start_matches("refs/heads/");
+    let base = group.base_ref.trim_start_matches("refs/heads/");
+    let summary = group
+        .head_commit
+        .as_ref()
+        .and_then(|c| c.message.lines().next())
+        .unwrap_or_default();
+
+    let message = format!(
+        "🚨 Merge queue run for `{}` in **{}** was {} (`{}`)\n{}\n{}/actions?query=branch%3A{}",
+        base,
+        payload.repository.full_name,
+        reason,
+        &group.head_sha[..group.head_sha.len().min(7)],
+        escape_mentions(summary),
+        payload.repository.html_url,
+        branch
+    );
+
+    deliver_to_repo(&state, "merge_group", &payload.repository.full_name, ChannelKind::PullRequests, message).await
+}
+
+/// Reports PRs entering or leaving the merge queue and auto-merge being toggled.
+pub async fn handle_pull_request_queue_event(
+    State(state): State<AppState>,
+    Json(payload): Json<PullRequestQueueEvent>,
+) -> WebhookOutcome {
+    let pr = &payload.pull_request;
+    let repo = &payload.repository.full_name;
+    let reason = payload.reason.as_deref().map(describe_reason);
+
+    let headline = match payload.action.as_str() {
+        "auto_merge_enabled" => format!(
+            "🤖 Auto-merge ({}) enabled on PR #{} in **{}** by `{}`",
+            pr.auto_merge.as_ref().map(|a| a.merge_method.as_str()).unwrap_or("merge"),
+            pr.number,
+            repo,
+            payload.sender.login
+        ),
+        "auto_merge_disabled" => format!(
+            "✋ Auto-merge disabled on PR #{} in **{}**{}",
+            pr.number,
+            repo,
+            reason.map(|r| format!(": {}", r)).unwrap_or_default()
+        ),
+        "enqueued" => format!(
+            "🚂 PR #{} added to the merge queue in **{}** by `{}`",
+            pr.number, repo, payload.sender.login
+        ),
+        // Leaving the queue by merging is reported by the `closed` event.
+        "dequeued" if payload.reason.as_deref().is_some_and(|r| r.eq_ignore_ascii_case("merge")) => {
+            return WebhookOutcome::ignored("pull_request", "dequeued by merging");
+        }
+        "dequeued" => format!(
+            "⚠️ PR #{} was removed from the merge queue in **{}**{}\nChecks: {}/checks",
+            pr.number,
+            repo,
+            reason.map(|r| format!(": {}", r)).unwrap_or_default(),
+            pr.html_url
+        ),
+        other => return WebhookOutcome::ignored("pull_request", format!("unsupported action `{}`", other)),
+    };
+
+    let message = format!("{}\n**{}**\n{}", headline, escape_mentions(&pr.title), pr.html_url);
+    deliver_to_repo(&state, "pull_request", repo, ChannelKind::PullRequests, message).await
+}
+
+/// `CI_FAILURE` -> `ci failure`.
+fn describe_reason(reason: &str) -> String {
+    reason.to_lowercase().replace('_', " ")
+}
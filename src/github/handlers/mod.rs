@@ -1,7 +1,127 @@
+pub mod checks;
+pub mod comments;
+pub mod community;
+pub mod deployments;
+pub mod discussions;
+pub mod issues;
+pub mod merge_queue;
+pub mod planning;
 pub mod pull_requests;
+pub mod push;
+pub mod refs;
+pub mod releases;
+pub mod security;
+pub mod statuses;
+pub mod workflow_jobs;
 pub mod workflow_runs;
 pub mod review_requests;
 
+pub use checks::{handle_check_run_event, handle_check_suite_event};
+pub use comments::{handle_issue_comment_event, handle_review_comment_event};
+pub use community::{handle_fork_event, handle_star_event, start_community_digest_loop};
+pub use deployments::{handle_deployment_event, handle_deployment_status_event};
+pub use discussions::{handle_discussion_comment_event, handle_discussion_event};
+pub use issues::handle_issues_event;
+pub use merge_queue::{handle_merge_group_event, handle_pull_request_queue_event};
+pub use planning::{handle_milestone_event, handle_project_card_event, handle_project_item_event};
 pub use pull_requests::handle_pull_request_event;
+pub use push::handle_push_event;
+pub use refs::{handle_create_event, handle_delete_event};
+pub use releases::handle_release_event;
+pub use security::{handle_dependabot_alert_event, handle_vulnerability_alert_event};
+pub use statuses::handle_status_event;
+pub use workflow_jobs::handle_workflow_job_event;
 pub use workflow_runs::handle_workflow_run_event;
-pub use review_requests::handle_review_requested_event;
+pub use review_requests::{handle_review_requested_event, handle_review_submitted_event};
+
+use crate::guilds::{self, ChannelKind};
+use crate::github::mentions::{escape_mentions, resolve_mentions};
+use crate::github::WebhookOutcome;
+use crate::notify::{self, Destination, Grouping};
+use crate::AppState;
+
+/// Markdown text (issue comment, discussion, ...) as a quoted excerpt of at most `max_chars`
+/// characters with GitHub mentions escaped, or empty if there is none.
+pub fn quote_excerpt(text: Option<&str>, max_chars: usize) -> String {
+    quote(text, max_chars, escape_mentions)
+}
+
+/// Like [`quote_excerpt`], but for PR descriptions and review comments, whose mentions of
+/// linked users ping them.
+pub fn quote_pr_excerpt(text: Option<&str>, max_chars: usize) -> String {
+    quote(text, max_chars, resolve_mentions)
+}
+
+fn quote(text: Option<&str>, max_chars: usize, rewrite: fn(&str) -> String) -> String {
+    let text = text.unwrap_or_default().trim();
+    if text.is_empty() {
+        return String::new();
+    }
+
+    let mut excerpt: String = text.chars().take(max_chars).collect();
+    if text.chars().count() > max_chars {
+        excerpt.push('…');
+    }
+
+    let quoted = rewrite(&excerpt)
+        .lines()
+        .map(|l| format!("> {}", l))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("{}\n", quoted)
+}
+
+/// Channel for `repo`'s notifications of `kind`, or an ignored outcome when the guild the
+/// repository is routed to (or the environment) has none configured.
+pub fn repo_channel(handler: &'static str, repo: &str, kind: ChannelKind) -> Result<u64, WebhookOutcome> {
+    guilds::github_channel(repo, kind).ok_or_else(|| {
+        WebhookOutcome::ignored(handler, format!("no `{}` channel configured for {}", kind.key(), repo))
+    })
+}
+
+/// `<@&role> ` mentioning the dev role for `repo`, or empty if none is configured.
+pub fn dev_mention(repo: &str) -> String {
+    guilds::github_dev_role(repo)
+        .map(|id| format!("<@&{}> ", id))
+        .unwrap_or_default()
+}
+
+/// Posts a notification to `channel_id` through the [`notify`] service.
+pub async fn deliver(
+    state: &AppState,
+    handler: &'static str,
+    channel_id: u64,
+    message: String,
+) -> WebhookOutcome {
+    let delivery = notify::send(state, handler, Destination::Channel(channel_id), message).await;
+    WebhookOutcome::from_delivery(handler, delivery)
+}
+
+/// Posts a notification to `repo`'s channel of `kind`, or ignores it if there is none.
+pub async fn deliver_to_repo(
+    state: &AppState,
+    handler: &'static str,
+    repo: &str,
+    kind: ChannelKind,
+    message: String,
+) -> WebhookOutcome {
+    let delivery = notify::send(state, handler, Destination::Repo(repo.to_string(), kind), message).await;
+    WebhookOutcome::from_delivery(handler, delivery)
+}
+
+/// Posts a notification to `channel_id` that may be summarized with others of `grouping`.
+pub async fn deliver_grouped(
+    state: &AppState,
+    handler: &'static str,
+    channel_id: u64,
+    grouping: Grouping,
+    message: String,
+) -> WebhookOutcome {
+    let delivery = notify::send_grouped(state, handler, Destination::Channel(channel_id), grouping, message).await;
+    WebhookOutcome::from_delivery(handler, delivery)
+}
+
+/// Holds a routine event for `channel_id`'s low-priority digest instead of posting it.
+pub fn hold_low_priority(handler: &'static str, channel_id: u64, category: &str, line: String) -> WebhookOutcome {
+    WebhookOutcome::from_delivery(handler, notify::low_priority::hold(channel_id, category, line))
+}
@@ -0,0 +1,327 @@
+//! Sprint planning changes: milestones and project boards.
+//!
+//! - `milestone`: created, closed, reopened, and due date changes
+//! - `project_card` (classic projects): cards added, moved between columns, and removed
+//! - `projects_v2_item` (organization projects): items added, archived or removed, and field
+//!   changes such as Status or Iteration
+//!
+//! Project payloads carry only IDs, so item titles, column names and project names are looked
+//! up through the GitHub API (see [`crate::github::client`]); events are still posted, with
+//! less detail, when a lookup fails. Org-level project events go to the channel of the item's
+//! repository, or of the organization when the item isn't an issue or PR.
+//!
+//! Environment Variables:
+//! - `PROJECT_FIELDS`: Comma-separated project fields whose changes are posted, e.g.
+//!   `Status,Iteration` (default: all)
+
+use axum::extract::{Json, State};
+use chrono::DateTime;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::env;
+
+use super::{deliver_to_repo, quote_excerpt};
+use crate::github::mentions::escape_mentions;
+use crate::github::{client, WebhookOutcome};
+use crate::guilds::ChannelKind;
+use crate::AppState;
+
+/// Longest milestone description or card note included in a notification.
+const MAX_BODY_CHARS: usize = 300;
+
+#[derive(Debug, Deserialize)]
+pub struct MilestoneEvent {
+    pub action: String,
+    pub milestone: Milestone,
+    /// Previous values, for `edited` events.
+    #[serde(default)]
+    pub changes: Value,
+    pub repository: Repository,
+    pub sender: User,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Milestone {
+    pub title: String,
+    pub description: Option<String>,
+    pub html_url: String,
+    pub due_on: Option<String>,
+    #[serde(default)]
+    pub open_issues: u64,
+    #[serde(default)]
+    pub closed_issues: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProjectCardEvent {
+    pub action: String,
+    pub project_card: ProjectCard,
+    #[serde(default)]
+    pub changes: Value,
+    pub repository: Option<Repository>,
+    pub organization: Option<Organization>,
+    pub sender: User,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProjectCard {
+    pub note: Option<String>,
+    /// API URL of the issue or PR on the card; `None` for notes.
+    pub content_url: Option<String>,
+    pub column_id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProjectItemEvent {
+    pub action: String,
+    pub projects_v2_item: ProjectItem,
+    #[serde(default)]
+    pub changes: Value,
+    pub organization: Organization,
+    pub sender: User,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProjectItem {
+    pub content_node_id: String,
+    /// `Issue`, `PullRequest` or `DraftIssue`.
+    pub content_type: String,
+    pub project_node_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    pub full_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Organization {
+    pub login: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct User {
+    pub login: String,
+}
+
+/// A milestone's RFC 3339 due date as a Discord timestamp.
+fn due_date(due_on: Option<&str>) -> Option<String> {
+    due_on
+        .and_then(|due| DateTime::parse_from_rfc3339(due).ok())
+        .map(|due| format!("<t:{}:D>", due.timestamp()))
+}
+
+/// Reports milestones being created, closed, reopened, or rescheduled.
+pub async fn handle_milestone_event(
+    State(state): State<AppState>,
+    Json(payload): Json<MilestoneEvent>,
+) -> WebhookOutcome {
+    let milestone = &payload.milestone;
+    let repo = &payload.repository.full_name;
+    let due_on = due_date(milestone.due_on.as_deref());
+    let due = due_on.as_ref().map(|due| format!(" (due {})", due)).unwrap_or_default();
+
+    let message = match payload.action.as_str() {
+        "created" => format!(
+            "🏁 New milestone **{}** in **{}** by `{}`{}\n{}{}",
+            escape_mentions(&milestone.title),
+            repo,
+            payload.sender.login,
+            due,
+            quote_excerpt(milestone.description.as_deref(), MAX_BODY_CHARS),
+            milestone.html_url
+        ),
+        "closed" => {
+            let total = milestone.open_issues + milestone.closed_issues;
+            let still_open = match milestone.open_issues {
+                0 => String::new(),
+                open => format!(", {} still open", open),
+            };
+            format!(
+                "✅ Milestone **{}** closed in **{}** by `{}`: {}/{} issues done{}\n{}",
+                escape_mentions(&milestone.title), repo, payload.sender.login, milestone.closed_issues, total, still_open, milestone.html_url
+            )
+        }
+        "opened" => format!(
+            "🔄 Milestone **{}** reopened in **{}** by `{}`{}\n{}",
+            escape_mentions(&milestone.title), repo, payload.sender.login, due, milestone.html_url
+        ),
+        "edited" if payload.changes.get("due_on").is_some() => {
+            let previous = due_date(payload.changes.pointer("/due_on/from").and_then(Value::as_str));
+            let now = due_on.map_or_else(|| "no due date".to_string(), |due| format!("due {}", due));
+            let was = previous.map(|due| format!(", was {}", due)).unwrap_or_default();
+            format!(
+                "📅 Milestone **{}** in **{}** rescheduled by `{}`: {}{}\n{}",
+                escape_mentions(&milestone.title), repo, payload.sender.login, now, was, milestone.html_url
+            )
+        }
+        "edited" => return WebhookOutcome::ignored("milestone", "only due date changes are reported"),
+        other => return WebhookOutcome::ignored("milestone", format!("unsupported action `{}`", other)),
+    };
+
+    deliver_to_repo(&state, "milestone", repo, ChannelKind::Planning, message).await
+}
+
+/// Name of a classic project column, if it can be looked up.
+async fn column_name(column_id: u64) -> Option<String> {
+    let column: Value = client::get_json(&format!("/projects/columns/{}", column_id)).await.ok()?;
+    column.get("name").and_then(Value::as_str).map(|name| format!("**{}**", name))
+}
+
+/// `owner/repo#12` and its web URL, from an issue or PR API URL.
+fn issue_ref(content_url: &str) -> Option<(String, String)> {
+    let path = content_url.strip_prefix("https://api.github.com/repos/")?;
+    let mut parts = path.split('/');
+    let (owner, name, _, number) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    Some((
+        format!("{}/{}#{}", owner, name, number),
+        format!("https://github.com/{}/{}/issues/{}", owner, name, number),
+    ))
+}
+
+/// Reports classic project cards being added, moved between columns, or removed.
+pub async fn handle_project_card_event(
+    State(state): State<AppState>,
+    Json(payload): Json<ProjectCardEvent>,
+) -> WebhookOutcome {
+    let card = &payload.project_card;
+    let from_column = payload.changes.pointer("/column_id/from").and_then(Value::as_u64);
+    let verb = match payload.action.as_str() {
+        "created" => "added",
+        "moved" if from_column.is_some() => "moved",
+        "moved" => return WebhookOutcome::ignored("project_card", "reordered within its column"),
+        "deleted" => "removed",
+        other => return WebhookOutcome::ignored("project_card", format!("unsupported action `{}`", other)),
+    };
+
+    let (item, repo) = match card.content_url.as_deref().and_then(issue_ref) {
+        Some((label, url)) => {
+            let repo = label.split('#').next().unwrap_or_default().to_string();
+            (format!("[{}](<{}>)", label, url), Some(repo))
+        }
+        None => {
+            let note = card.note.as_deref().unwrap_or_default().lines().next().unwrap_or_default();
+            (format!("note “{}”", note.chars().take(MAX_BODY_CHARS).collect::<String>()), None)
+        }
+    };
+    let repo = match (repo, &payload.repository, &payload.organization) {
+        (Some(repo), _, _) => repo,
+        (None, Some(repository), _) => repository.full_name.clone(),
+        (None, None, Some(org)) => format!("{}/*", org.login),
+        (None, None, None) => return WebhookOutcome::ignored("project_card", "no repository or organization"),
+    };
+
+    let column = column_name(card.column_id).await.unwrap_or_else(|| "a column".to_string());
+    let placement = match (verb, from_column) {
+        ("moved", Some(from)) => {
+            let from = column_name(from).await.unwrap_or_else(|| "another column".to_string());
+            format!("from {} to {}", from, column)
+        }
+        ("removed", _) => format!("from {}", column),
+        _ => format!("to {}", column),
+    };
+    let message = format!("🗂️ `{}` {} {} {} on the project board", payload.sender.login, verb, item, placement);
+
+    deliver_to_repo(&state, "project_card", &repo, ChannelKind::Planning, message).await
+}
+
+/// Whether changes to `field` are reported (`PROJECT_FIELDS`, default: all).
+fn field_reported(field: &str) -> bool {
+    match env::var("PROJECT_FIELDS") {
+        Ok(fields) if !fields.trim().is_empty() => {
+            fields.split(',').any(|f| f.trim().eq_ignore_ascii_case(field))
+        }
+        _ => true,
+    }
+}
+
+/// Renders a project field value: an option or iteration name, a date, text, or a number.
+fn field_value(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::Object(object)) => ["name", "title"]
+            .iter()
+            .find_map(|key| object.get(*key).and_then(Value::as_str))
+            .map(|name| format!("**{}**", name))
+            .unwrap_or_else(|| "a new value".to_string()),
+        Some(Value::String(text)) if !text.is_empty() => format!("**{}**", text),
+        Some(Value::Number(number)) => format!("**{}**", number),
+        _ => "none".to_string(),
+    }
+}
+
+/// An org project item and its project, as `(label, repository, project)`.
+async fn describe_item(item: &ProjectItem) -> (String, Option<String>, String) {
+    let query = "query($item: ID!, $project: ID!) { \
+                 item: node(id: $item) { \
+                 ... on Issue { title url number repository { nameWithOwner } } \
+                 ... on PullRequest { title url number repository { nameWithOwner } } \
+                 ... on DraftIssue { title } } \
+                 project: node(id: $project) { ... on ProjectV2 { title url } } }";
+    let variables = json!({ "item": item.content_node_id, "project": item.project_node_id });
+    let data = client::graphql(query, variables).await.unwrap_or_else(|e| {
+        eprintln!("Project item lookup failed: {}", e);
+        Value::Null
+    });
+
+    let text = |pointer: &str| data.pointer(pointer).and_then(Value::as_str);
+    let repo = text("/item/repository/nameWithOwner").map(str::to_string);
+    let label = match (text("/item/title"), text("/item/url"), &repo) {
+        (Some(title), Some(url), Some(repo)) => {
+            let number = data.pointer("/item/number").and_then(Value::as_u64).unwrap_or_default();
+            format!("[{}#{}](<{}>) **{}**", repo, number, url, title)
+        }
+        (Some(title), _, _) => format!("draft “{}”", title),
+        _ => format!("a {}", item.content_type.replace("PullRequest", "pull request").to_lowercase()),
+    };
+    let project = match (text("/project/title"), text("/project/url")) {
+        (Some(title), Some(url)) => format!("[{}](<{}>)", title, url),
+        (Some(title), None) => format!("**{}**", title),
+        _ => "a project".to_string(),
+    };
+    (label, repo, project)
+}
+
+/// Reports organization project items being added, archived or removed, and field changes.
+pub async fn handle_project_item_event(
+    State(state): State<AppState>,
+    Json(payload): Json<ProjectItemEvent>,
+) -> WebhookOutcome {
+    const HANDLER: &str = "projects_v2_item";
+    let field_change = payload.changes.get("field_value");
+    let field = field_change
+        .and_then(|change| change.get("field_name"))
+        .and_then(Value::as_str)
+        .unwrap_or("a field");
+    match payload.action.as_str() {
+        "created" | "archived" | "deleted" => {}
+        "edited" if field_change.is_none() => return WebhookOutcome::ignored(HANDLER, "no field value changed"),
+        "edited" if !field_reported(field) => {
+            return WebhookOutcome::ignored(HANDLER, format!("`{}` is not in PROJECT_FIELDS", field))
+        }
+        "edited" => {}
+        other => return WebhookOutcome::ignored(HANDLER, format!("unsupported action `{}`", other)),
+    }
+
+    let (item, repo, project) = describe_item(&payload.projects_v2_item).await;
+    let who = &payload.sender.login;
+    let message = match payload.action.as_str() {
+        "created" => format!("🗂️ `{}` added {} to {}", who, item, project),
+        "archived" => format!("🗄️ `{}` archived {} in {}", who, item, project),
+        "deleted" => format!("🗑️ `{}` removed {} from {}", who, item, project),
+        _ => {
+            let change = field_change.cloned().unwrap_or_default();
+            format!(
+                "🗂️ `{}` changed **{}** of {} in {}: {} → {}",
+                who,
+                field,
+                item,
+                project,
+                field_value(change.get("from")),
+                field_value(change.get("to"))
+            )
+        }
+    };
+
+    let repo = repo.unwrap_or_else(|| format!("{}/*", payload.organization.login));
+    deliver_to_repo(&state, HANDLER, &repo, ChannelKind::Planning, message).await
+}
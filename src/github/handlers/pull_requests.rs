@@ -1,12 +1,15 @@
-use axum::{
-    extract::{Json, State},
-    http::StatusCode,
-    response::{IntoResponse, Response},
-};
-use serde::Deserialize;
-use serenity::model::id::ChannelId;
+use axum::extract::{Json, State};
+use serde::{de::IgnoredAny, Deserialize};
 use std::env;
 
+use super::{deliver, deliver_grouped, dev_mention, hold_low_priority, quote_pr_excerpt, repo_channel};
+use crate::bot::components;
+use crate::github::{codeowners, linked_issues};
+use crate::github::mentions::resolve_mentions;
+use crate::github::threads::{self, PrState};
+use crate::github::WebhookOutcome;
+use crate::guilds::ChannelKind;
+use crate::notify::{self, low_priority, Grouping};
 use crate::AppState;
 
 #[derive(Debug, Deserialize)]
@@ -15,14 +18,76 @@ pub struct PullRequestEvent {
     pub pull_request: PullRequest,
     pub repository: Repository,
     pub sender: Sender,
+    /// Previous values of edited fields, for `edited` events.
+    pub changes: Option<Changes>,
+    /// The label added or removed, for `labeled`/`unlabeled` events.
+    pub label: Option<Label>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Label {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Changes {
+    /// Present when the title was edited; the previous title itself isn't used.
+    pub title: Option<IgnoredAny>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct PullRequest {
     pub html_url: String,
+    pub number: u64,
     pub title: String,
+    /// `open` or `closed`.
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub body: Option<String>,
     pub head: BranchRef, // source branch
     pub base: BranchRef, // target branch
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub merged: bool,
+    pub merged_by: Option<Sender>,
+    #[serde(default)]
+    pub labels: Vec<Label>,
+    /// The PR's author.
+    pub user: Option<Sender>,
+    #[serde(default)]
+    pub changed_files: u64,
+    #[serde(default)]
+    pub additions: u64,
+    #[serde(default)]
+    pub deletions: u64,
+}
+
+impl PullRequest {
+    /// Size bucket by lines changed, like the usual `size/*` labels.
+    pub fn size_label(&self) -> &'static str {
+        match self.additions + self.deletions {
+            0..=9 => "XS",
+            10..=29 => "S",
+            30..=99 => "M",
+            100..=499 => "L",
+            _ => "XL",
+        }
+    }
+
+    /// Size indicator with diff stats, e.g. "**M** (+84 −12, 3 files)".
+    pub fn size_summary(&self) -> String {
+        let files = if self.changed_files == 1 { "file" } else { "files" };
+        format!(
+            "**{}** (+{} −{}, {} {})",
+            self.size_label(),
+            self.additions,
+            self.deletions,
+            self.changed_files,
+            files
+        )
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -34,6 +99,7 @@ pub struct BranchRef {
 #[derive(Debug, Deserialize)]
 pub struct Repository {
     pub full_name: String,
+    pub default_branch: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -44,46 +110,387 @@ pub struct Sender {
 pub async fn handle_pull_request_event(
     State(state): State<AppState>,
     Json(payload): Json<PullRequestEvent>,
-) -> Response {
-    if payload.action != "opened" {
-        return StatusCode::OK.into_response();
-    }
-
-    let ctx = {
-        let guard = state.discord_ctx.lock().unwrap();
-        match &*guard {
-            Some(ctx) => ctx.clone(),
-            None => {
-                eprintln!("Discord context not initialized yet.");
-                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-            }
+) -> WebhookOutcome {
+    let channel_id = match repo_channel("pull_request", &payload.repository.full_name, ChannelKind::PullRequests) {
+        Ok(id) => id,
+        Err(outcome) => return outcome,
+    };
+
+    if payload.action == "edited" {
+        return handle_edited(&state, &payload).await;
+    }
+
+    if let Some(label) = skip_label(&payload.pull_request) {
+        return WebhookOutcome::ignored("pull_request", format!("PR is labeled `{}`", label));
+    }
+
+    if payload.action == "labeled" {
+        return handle_labeled(&state, channel_id, &payload).await;
+    }
+
+    // Pushes are only summarized for drafts, and only when held for the low-priority digest.
+    if payload.action == "synchronize" {
+        let pr = &payload.pull_request;
+        if !pr.draft || !low_priority::enabled("draft_push") {
+            return WebhookOutcome::ignored("pull_request", "unsupported action `synchronize`");
+        }
+        let line = format!(
+            "`{}` pushed to draft [#{} {}](<{}>) in **{}**",
+            payload.sender.login,
+            pr.number,
+            resolve_mentions(&pr.title),
+            pr.html_url,
+            payload.repository.full_name
+        );
+        return hold_low_priority("pull_request", channel_id, "draft_push", line);
+    }
+
+    // Drafting is only tracked in the PR's thread, not announced in the channel.
+    if payload.action == "converted_to_draft" {
+        if !threads::enabled() {
+            return WebhookOutcome::ignored("pull_request", "PR threads are disabled");
+        }
+        record_transition(&state, channel_id, &payload).await;
+        return WebhookOutcome::handled("pull_request");
+    }
+
+    let escalation = escalation_mentions(&payload.pull_request);
+    let message = match payload.action.as_str() {
+        "opened" => format!("{}{}", escalation, opened_message(&payload)),
+        "closed" => closed_message(&payload),
+        "reopened" | "ready_for_review" => format!("{}{}", escalation, actionable_message(&payload)),
+        other => {
+            return WebhookOutcome::ignored("pull_request", format!("unsupported action `{}`", other));
         }
     };
 
-    let channel_id: u64 = env::var("DISCORD_PR_CHANNEL_ID")
-        .expect("DISCORD_PR_CHANNEL_ID not set")
-        .parse()
-        .unwrap();
+    // Bursts of PRs from one author (e.g. 15 Dependabot bumps) may be summarized together;
+    // escalated PRs are always announced on their own.
+    let outcome = if payload.action == "opened" && escalation.is_empty() {
+        deliver_grouped(&state, "pull_request", channel_id, opened_grouping(&payload), message).await
+    } else {
+        deliver(&state, "pull_request", channel_id, message).await
+    };
+
+    if threads::enabled() {
+        record_transition(&state, channel_id, &payload).await;
+    }
+
+    if payload.action == "closed" && payload.pull_request.merged {
+        announce_closed_issues(&state, channel_id, &payload);
+    }
+
+    let ready = (payload.action == "opened" && !payload.pull_request.draft) || payload.action == "ready_for_review";
+    if ready && codeowners::enabled() {
+        suggest_reviewers(&state, channel_id, &payload);
+    }
+    if ready && components::pr_actions_enabled() {
+        post_pr_actions(&state, channel_id, &payload);
+    }
+
+    outcome
+}
+
+/// Lists the issues a merged PR closed. Runs in the background so GitHub API lookups don't
+/// hold up the webhook response.
+fn announce_closed_issues(state: &AppState, channel_id: u64, payload: &PullRequestEvent) {
+    let pr = &payload.pull_request;
+    let repo = &payload.repository;
+
+    // GitHub only closes linked issues for merges into the default branch.
+    if repo.default_branch.as_deref().is_some_and(|branch| branch != pr.base.r#ref) {
+        return;
+    }
+    let issues = linked_issues::closing_references(pr.body.as_deref().unwrap_or_default(), &repo.full_name);
+    if issues.is_empty() {
+        return;
+    }
+
+    let (state, repo, number, title) = (state.clone(), repo.full_name.clone(), pr.number, pr.title.clone());
+    tokio::spawn(async move {
+        linked_issues::announce(&state, channel_id, &repo, number, &title, issues).await;
+    });
+}
+
+/// Mentions the CODEOWNERS of the paths a PR touches. Runs in the background so GitHub API
+/// lookups don't hold up the webhook response.
+fn suggest_reviewers(state: &AppState, channel_id: u64, payload: &PullRequestEvent) {
+    let pr = &payload.pull_request;
+    let author = pr.user.as_ref().unwrap_or(&payload.sender).login.clone();
+    let (state, repo, number, title, base) = (
+        state.clone(),
+        payload.repository.full_name.clone(),
+        pr.number,
+        pr.title.clone(),
+        pr.base.r#ref.clone(),
+    );
+    tokio::spawn(async move {
+        codeowners::suggest_reviewers(&state, channel_id, &repo, number, &title, &base, &author).await;
+    });
+}
 
-    let role_id: u64 = env::var("DISCORD_DEV_ROLE_ID")
-        .expect("DISCORD_DEV_ROLE_ID not set")
-        .parse()
-        .unwrap();
+/// Posts the approve/merge buttons under the notification, in the PR's thread if threads are
+/// on. Runs in the background so creating the thread doesn't hold up the webhook response.
+fn post_pr_actions(state: &AppState, parent: u64, payload: &PullRequestEvent) {
+    let ctx = match notify::discord_ctx(state) {
+        Some(ctx) => ctx,
+        None => return,
+    };
+    let pr = &payload.pull_request;
+    let (state, repo, number, title, url) = (
+        state.clone(),
+        payload.repository.full_name.clone(),
+        pr.number,
+        pr.title.clone(),
+        pr.html_url.clone(),
+    );
+    tokio::spawn(async move {
+        let channel_id = if threads::enabled() {
+            threads::pr_thread(&state, parent, &repo, number, &title).await.unwrap_or(parent)
+        } else {
+            parent
+        };
+        components::post_pr_actions(&ctx, channel_id, &repo, number, &title, &url).await;
+    });
+}
+
+/// Parses a comma-separated label list, lowercased.
+fn label_list(key: &str) -> Vec<String> {
+    env::var(key)
+        .unwrap_or_default()
+        .split(',')
+        .map(|l| l.trim().to_lowercase())
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
+/// The first label on `pr` listed in `PR_SKIP_LABELS`, if any.
+fn skip_label(pr: &PullRequest) -> Option<&str> {
+    let skipped = label_list("PR_SKIP_LABELS");
+    pr.labels
+        .iter()
+        .map(|l| l.name.as_str())
+        .find(|name| skipped.contains(&name.to_lowercase()))
+}
+
+/// Roles to ping per label, from `PR_ESCALATE_LABELS` (`label=role_id,...`).
+fn escalation_roles() -> Vec<(String, u64)> {
+    label_list("PR_ESCALATE_LABELS")
+        .iter()
+        .filter_map(|entry| {
+            let (label, role) = entry.split_once('=')?;
+            Some((label.trim().to_string(), role.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// Escalation role mentions for the labels on `pr`, or empty if none match.
+fn escalation_mentions(pr: &PullRequest) -> String {
+    let labels: Vec<String> = pr.labels.iter().map(|l| l.name.to_lowercase()).collect();
+    let mut roles: Vec<u64> = escalation_roles()
+        .into_iter()
+        .filter(|(label, _)| labels.contains(label))
+        .map(|(_, role)| role)
+        .collect();
+    roles.sort_unstable();
+    roles.dedup();
+
+    roles.iter().map(|r| format!("<@&{}> ", r)).collect()
+}
+
+/// Pings the escalation role when an escalation label is added to an open PR.
+async fn handle_labeled(
+    state: &AppState,
+    channel_id: u64,
+    payload: &PullRequestEvent,
+) -> WebhookOutcome {
+    let pr = &payload.pull_request;
+    let label = payload.label.as_ref().map(|l| l.name.as_str()).unwrap_or_default();
+    let role = escalation_roles()
+        .into_iter()
+        .find(|(escalated, _)| escalated.eq_ignore_ascii_case(label))
+        .map(|(_, role)| role);
+
+    let role = match role {
+        Some(role) if pr.state == "open" => role,
+        _ => return WebhookOutcome::ignored("pull_request", format!("label `{}` is not escalated", label)),
+    };
 
     let message = format!(
-        "<@&{}> New PR in **{}** by `{}`:\n**{}**\n`{}` → `{}`\n{}",
-        role_id,
+        "<@&{}> 🚨 PR #{} in **{}** labeled `{}` by `{}`:\n**{}**\n`{}` → `{}`\n{}",
+        role,
+        pr.number,
         payload.repository.full_name,
+        label,
         payload.sender.login,
-        payload.pull_request.title,
+        resolve_mentions(&pr.title),
+        pr.head.r#ref,
+        pr.base.r#ref,
+        pr.html_url
+    );
+
+    deliver(state, "pull_request", channel_id, message).await
+}
+
+/// Renames the PR's thread when its title was edited.
+async fn handle_edited(state: &AppState, payload: &PullRequestEvent) -> WebhookOutcome {
+    let title_changed = payload.changes.as_ref().and_then(|c| c.title.as_ref()).is_some();
+    if !title_changed {
+        return WebhookOutcome::ignored("pull_request", "edit did not change the title");
+    }
+    if !threads::enabled() {
+        return WebhookOutcome::ignored("pull_request", "PR threads are disabled");
+    }
+
+    let pr = &payload.pull_request;
+    if threads::rename_for_title(state, &payload.repository.full_name, pr.number, &pr.title).await {
+        WebhookOutcome::handled("pull_request")
+    } else {
+        WebhookOutcome::ignored("pull_request", "no thread to rename")
+    }
+}
+
+/// Posts this event into the PR's thread and updates the thread's status emoji.
+async fn record_transition(state: &AppState, channel_id: u64, payload: &PullRequestEvent) {
+    let pr = &payload.pull_request;
+    threads::record_transition(
+        state,
+        channel_id,
+        &payload.repository.full_name,
+        pr.number,
+        &pr.title,
+        pr_state(payload),
+        &transition_note(payload),
+    )
+    .await;
+}
+
+/// The PR's state after this event.
+fn pr_state(payload: &PullRequestEvent) -> PrState {
+    let pr = &payload.pull_request;
+    if pr.merged {
+        PrState::Merged
+    } else if payload.action == "closed" {
+        PrState::Closed
+    } else if pr.draft {
+        PrState::Draft
+    } else {
+        PrState::Open
+    }
+}
+
+/// One-line description of the transition, posted into the PR's thread.
+fn transition_note(payload: &PullRequestEvent) -> String {
+    let pr = &payload.pull_request;
+    let by = &payload.sender.login;
+
+    match payload.action.as_str() {
+        "opened" if pr.draft => format!("📝 Opened as a draft by `{}`", by),
+        "opened" => format!("🟢 Opened by `{}`", by),
+        "reopened" => format!("🔄 Reopened by `{}`", by),
+        "ready_for_review" => format!("👀 Marked ready for review by `{}`", by),
+        "converted_to_draft" => format!("📝 Converted to draft by `{}`", by),
+        "closed" if pr.merged => format!(
+            "🟣 Merged into `{}` by `{}`",
+            pr.base.r#ref,
+            pr.merged_by.as_ref().map(|u| u.login.as_str()).unwrap_or(by)
+        ),
+        "closed" => format!("⚫ Closed without merging by `{}`", by),
+        other => format!("ℹ️ `{}` by `{}`", other, by),
+    }
+}
+
+/// Longest PR description excerpt included in a notification.
+const MAX_BODY_CHARS: usize = 600;
+
+/// Message for a newly opened PR, pinging the dev role.
+fn opened_message(payload: &PullRequestEvent) -> String {
+    format!(
+        "{}New PR in **{}** by `{}`:\n**{}**\n`{}` → `{}` · 📏 {}\n{}{}",
+        dev_mention(&payload.repository.full_name),
+        payload.repository.full_name,
+        payload.sender.login,
+        resolve_mentions(&payload.pull_request.title),
         payload.pull_request.head.r#ref,
         payload.pull_request.base.r#ref,
+        payload.pull_request.size_summary(),
+        quote_pr_excerpt(payload.pull_request.body.as_deref(), MAX_BODY_CHARS),
         payload.pull_request.html_url
-    );
+    )
+}
 
-    let _ = ChannelId(channel_id)
-        .send_message(&ctx.http, |m| m.content(message))
-        .await;
+/// Digest group for PRs opened by the same author in a repository.
+fn opened_grouping(payload: &PullRequestEvent) -> Grouping {
+    let repo = &payload.repository.full_name;
+    let pr = &payload.pull_request;
+    Grouping {
+        key: format!("pr-opened:{}:{}", repo, payload.sender.login),
+        title: format!("PRs opened in **{}** by `{}`", repo, payload.sender.login),
+        line: format!(
+            "[#{}]({}) {} `{}`",
+            pr.number,
+            pr.html_url,
+            resolve_mentions(&pr.title),
+            pr.size_label()
+        ),
+    }
+}
+
+/// Message for a closed PR, distinguishing merged from abandoned.
+fn closed_message(payload: &PullRequestEvent) -> String {
+    let pr = &payload.pull_request;
 
-    StatusCode::OK.into_response()
+    if pr.merged {
+        let merger = pr
+            .merged_by
+            .as_ref()
+            .map(|u| u.login.as_str())
+            .unwrap_or(payload.sender.login.as_str());
+
+        format!(
+            "🟣 PR #{} merged into `{}` in **{}** by `{}`:\n**{}**\n{}",
+            pr.number,
+            pr.base.r#ref,
+            payload.repository.full_name,
+            merger,
+            resolve_mentions(&pr.title),
+            pr.html_url
+        )
+    } else {
+        format!(
+            "⚫ PR #{} closed without merging in **{}** by `{}`:\n**{}**\n`{}` → `{}`\n{}",
+            pr.number,
+            payload.repository.full_name,
+            payload.sender.login,
+            resolve_mentions(&pr.title),
+            pr.head.r#ref,
+            pr.base.r#ref,
+            pr.html_url
+        )
+    }
+}
+
+/// Message for a PR that became actionable again (reopened, or draft marked ready), pinging the dev role.
+fn actionable_message(payload: &PullRequestEvent) -> String {
+    let pr = &payload.pull_request;
+    let (emoji, what) = if payload.action == "reopened" {
+        ("🔄", "reopened")
+    } else {
+        ("👀", "is ready for review")
+    };
+
+    format!(
+        "{}{} PR #{} {} in **{}** by `{}`:\n**{}**\n`{}` → `{}`\n{}{}",
+        dev_mention(&payload.repository.full_name),
+        emoji,
+        pr.number,
+        what,
+        payload.repository.full_name,
+        payload.sender.login,
+        resolve_mentions(&pr.title),
+        pr.head.r#ref,
+        pr.base.r#ref,
+        quote_pr_excerpt(pr.body.as_deref(), MAX_BODY_CHARS),
+        pr.html_url
+    )
 }
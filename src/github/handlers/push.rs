@@ -0,0 +1,163 @@
+use axum::extract::{Json, State};
+use serde::Deserialize;
+use std::env;
+
+use super::refs::is_protected;
+use super::{deliver, dev_mention, repo_channel};
+use crate::github::mentions::escape_mentions;
+use crate::github::WebhookOutcome;
+use crate::guilds::ChannelKind;
+use crate::AppState;
+
+/// Maximum number of commits listed individually in a push notification.
+const MAX_LISTED_COMMITS: usize = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct PushEvent {
+    #[serde(rename = "ref")]
+    pub r#ref: String,
+    /// Head commit before and after the push.
+    #[serde(default)]
+    pub before: String,
+    #[serde(default)]
+    pub after: String,
+    /// Gitea and Forgejo call it `compare_url`.
+    #[serde(alias = "compare_url")]
+    pub compare: String,
+    #[serde(default)]
+    pub forced: bool,
+    #[serde(default)]
+    pub deleted: bool,
+    pub commits: Vec<Commit>,
+    pub pusher: Pusher,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Commit {
+    pub id: String,
+    pub message: String,
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Pusher {
+    /// Gitea and Forgejo send the pusher as a full user with a `login`.
+    #[serde(alias = "login")]
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    pub full_name: String,
+}
+
+/// Branches to notify for, from `PUSH_NOTIFY_BRANCHES` (empty = all branches).
+fn notify_branches() -> Vec<String> {
+    env::var("PUSH_NOTIFY_BRANCHES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|b| b.trim().to_string())
+        .filter(|b| !b.is_empty())
+        .collect()
+}
+
+/// `DISCORD_OPS_ROLE_ID` (or the repo's dev role), for force-pushes to protected branches.
+fn ops_mention(repo: &str) -> String {
+    match env::var("DISCORD_OPS_ROLE_ID").ok().and_then(|v| v.trim().parse::<u64>().ok()) {
+        Some(role) => format!("<@&{}> ", role),
+        None => dev_mention(repo),
+    }
+}
+
+fn short_sha(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
+
+/// Warns about a force-push to a protected branch in the alerts channel (or the pushes
+/// channel), whatever `PUSH_NOTIFY_BRANCHES` says: rewritten history there has broken grading
+/// data before.
+async fn warn_force_push(state: &AppState, payload: &PushEvent, branch: &str) -> WebhookOutcome {
+    let repo = &payload.repository.full_name;
+    let channel_id = match repo_channel("force_push", repo, ChannelKind::Alerts)
+        .or_else(|_| repo_channel("force_push", repo, ChannelKind::Pushes))
+    {
+        Ok(id) => id,
+        Err(outcome) => return outcome,
+    };
+
+    println!("Force-push to protected branch {} of {} by {}", branch, repo, payload.pusher.name);
+    let message = format!(
+        "{}🚨 **Force-push to protected branch** `{}` in **{}** by `{}`\n\
+         History was rewritten: `{}` → `{}` ({} commit(s) in the new push). Check that nothing \
+         built on the old commits (grading runs, deployed builds) was lost.\n[Compare changes](<{}>)",
+        ops_mention(repo),
+        branch,
+        repo,
+        payload.pusher.name,
+        short_sha(&payload.before),
+        short_sha(&payload.after),
+        payload.commits.len(),
+        payload.compare
+    );
+    deliver(state, "force_push", channel_id, message).await
+}
+
+pub async fn handle_push_event(
+    State(state): State<AppState>,
+    Json(payload): Json<PushEvent>,
+) -> WebhookOutcome {
+    let branch = match payload.r#ref.strip_prefix("refs/heads/") {
+        Some(branch) => branch,
+        None => return WebhookOutcome::ignored("push", format!("`{}` is not a branch", payload.r#ref)),
+    };
+
+    // A force-push may add no commits at all (e.g. a reset), so check before filtering those out.
+    if payload.forced && !payload.deleted && is_protected(&payload.repository.full_name, branch).await {
+        return warn_force_push(&state, &payload, branch).await;
+    }
+
+    if payload.deleted || payload.commits.is_empty() {
+        return WebhookOutcome::ignored("push", "no commits pushed");
+    }
+
+    let branches = notify_branches();
+    if !branches.is_empty() && !branches.iter().any(|b| b == branch) {
+        return WebhookOutcome::ignored("push", format!("filtered branch `{}`", branch));
+    }
+
+    let channel_id = match repo_channel("push", &payload.repository.full_name, ChannelKind::Pushes) {
+        Ok(id) => id,
+        Err(outcome) => return outcome,
+    };
+
+    let mut lines: Vec<String> = payload
+        .commits
+        .iter()
+        .take(MAX_LISTED_COMMITS)
+        .map(|c| {
+            format!(
+                "- [`{}`](<{}>) {}",
+                short_sha(&c.id),
+                c.url,
+                escape_mentions(c.message.lines().next().unwrap_or_default())
+            )
+        })
+        .collect();
+    if payload.commits.len() > MAX_LISTED_COMMITS {
+        lines.push(format!("- …and {} more", payload.commits.len() - MAX_LISTED_COMMITS));
+    }
+
+    let message = format!(
+        "📦 `{}` {}pushed {} commit(s) to `{}` in **{}**:\n{}\n[Compare changes](<{}>)",
+        payload.pusher.name,
+        if payload.forced { "force-" } else { "" },
+        payload.commits.len(),
+        branch,
+        payload.repository.full_name,
+        lines.join("\n"),
+        payload.compare
+    );
+
+    deliver(&state, "push", channel_id, message).await
+}
@@ -0,0 +1,157 @@
+//! `create` and `delete` events: branches and tags being created or deleted.
+//!
+//! Posted to the pushes channel. Whether a deleted branch was protected is looked up on GitHub
+//! (its branch protection rules and rulesets, which still apply to the name after the branch
+//! is gone) and from `PROTECTED_BRANCHES`, which also covers forges the lookup can't reach.
+//!
+//! Environment Variables:
+//! - `REF_EVENTS_TAGS_AND_PROTECTED_ONLY`: Only report tags and deletions of protected branches
+//!   (default: false)
+//! - `PROTECTED_BRANCHES`: Comma-separated branch globs treated as protected in addition to
+//!   GitHub's rules, e.g. `main,release/*` (also used to flag force-pushes, see `push`)
+
+use axum::extract::{Json, State};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::env;
+
+use super::workflow_runs::glob_match;
+use super::{deliver, repo_channel};
+use crate::github::{client, WebhookOutcome};
+use crate::guilds::ChannelKind;
+use crate::AppState;
+
+/// Payload of both `create` and `delete` events.
+#[derive(Debug, Deserialize)]
+pub struct RefEvent {
+    /// Short name of the branch or tag, e.g. `release/1.4` or `v1.4.0`.
+    #[serde(rename = "ref")]
+    pub r#ref: String,
+    /// `branch` or `tag`.
+    pub ref_type: String,
+    pub sender: User,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct User {
+    pub login: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    pub full_name: String,
+    pub html_url: String,
+}
+
+fn tags_and_protected_only() -> bool {
+    env::var("REF_EVENTS_TAGS_AND_PROTECTED_ONLY").is_ok_and(|v| v.trim().eq_ignore_ascii_case("true"))
+}
+
+fn configured_protected(branch: &str) -> bool {
+    env::var("PROTECTED_BRANCHES")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .any(|pattern| !pattern.is_empty() && glob_match(pattern, branch))
+}
+
+/// Whether `branch` of `repo` is covered by a branch protection rule or a ruleset on GitHub.
+async fn github_protected(repo: &str, branch: &str) -> bool {
+    if !client::authenticated() {
+        return false;
+    }
+    let Some((owner, name)) = repo.split_once('/') else {
+        return false;
+    };
+
+    let query = "query($owner: String!, $name: String!) { repository(owner: $owner, name: $name) { \
+                 branchProtectionRules(first: 100) { nodes { pattern } } } }";
+    match client::graphql(query, json!({ "owner": owner, "name": name })).await {
+        Ok(data) => {
+            let rules = data["repository"]["branchProtectionRules"]["nodes"].as_array().cloned();
+            let matched = rules
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|rule| rule["pattern"].as_str())
+                .any(|pattern| glob_match(pattern, branch));
+            if matched {
+                return true;
+            }
+        }
+        Err(e) => eprintln!("Failed to look up branch protection rules of {}: {}", repo, e),
+    }
+
+    // Rulesets are evaluated by name, so this works for a branch that no longer exists.
+    match client::get_json::<Vec<Value>>(&format!("/repos/{}/rules/branches/{}", repo, branch)).await {
+        Ok(rules) => !rules.is_empty(),
+        Err(e) => {
+            eprintln!("Failed to look up the rulesets of {} for `{}`: {}", repo, branch, e);
+            false
+        }
+    }
+}
+
+/// Whether `branch` of `repo` is protected, per `PROTECTED_BRANCHES` or GitHub.
+pub(crate) async fn is_protected(repo: &str, branch: &str) -> bool {
+    configured_protected(branch) || github_protected(repo, branch).await
+}
+
+pub async fn handle_create_event(State(state): State<AppState>, Json(payload): Json<RefEvent>) -> WebhookOutcome {
+    let repo = &payload.repository.full_name;
+    let emoji = match payload.ref_type.as_str() {
+        "tag" => "🏷️",
+        "branch" if tags_and_protected_only() => {
+            return WebhookOutcome::ignored("create", "only tags and protected branch deletions are reported");
+        }
+        "branch" => "🌿",
+        other => return WebhookOutcome::ignored("create", format!("ref type `{}`", other)),
+    };
+
+    let channel_id = match repo_channel("create", repo, ChannelKind::Pushes) {
+        Ok(id) => id,
+        Err(outcome) => return outcome,
+    };
+
+    let message = format!(
+        "{} `{}` created {} [`{}`](<{}/tree/{}>) in **{}**",
+        emoji,
+        payload.sender.login,
+        payload.ref_type,
+        payload.r#ref,
+        payload.repository.html_url,
+        payload.r#ref,
+        repo
+    );
+    deliver(&state, "create", channel_id, message).await
+}
+
+pub async fn handle_delete_event(State(state): State<AppState>, Json(payload): Json<RefEvent>) -> WebhookOutcome {
+    let repo = &payload.repository.full_name;
+    let protected = match payload.ref_type.as_str() {
+        "tag" => false,
+        "branch" => is_protected(repo, &payload.r#ref).await,
+        other => return WebhookOutcome::ignored("delete", format!("ref type `{}`", other)),
+    };
+    if payload.ref_type == "branch" && !protected && tags_and_protected_only() {
+        return WebhookOutcome::ignored("delete", format!("`{}` is not a protected branch", payload.r#ref));
+    }
+
+    let channel_id = match repo_channel("delete", repo, ChannelKind::Pushes) {
+        Ok(id) => id,
+        Err(outcome) => return outcome,
+    };
+
+    let message = if protected {
+        format!(
+            "⚠️ `{}` deleted protected branch `{}` in **{}**",
+            payload.sender.login, payload.r#ref, repo
+        )
+    } else {
+        format!(
+            "🗑️ `{}` deleted {} `{}` in **{}**",
+            payload.sender.login, payload.ref_type, payload.r#ref, repo
+        )
+    };
+    deliver(&state, "delete", channel_id, message).await
+}
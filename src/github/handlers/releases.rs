@@ -0,0 +1,94 @@
+use axum::extract::{Json, State};
+use serde::Deserialize;
+
+use super::{deliver, repo_channel};
+use crate::github::mentions::escape_mentions;
+use crate::github::WebhookOutcome;
+use crate::guilds::ChannelKind;
+use crate::AppState;
+
+/// Discord's message length limit.
+const MAX_MESSAGE_CHARS: usize = 2000;
+
+#[derive(Debug, Deserialize)]
+pub struct ReleaseEvent {
+    pub action: String,
+    pub release: Release,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Release {
+    pub html_url: String,
+    pub tag_name: String,
+    pub name: Option<String>,
+    pub body: Option<String>,
+    #[serde(default)]
+    pub prerelease: bool,
+    pub author: User,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    pub full_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct User {
+    pub login: String,
+}
+
+pub async fn handle_release_event(
+    State(state): State<AppState>,
+    Json(payload): Json<ReleaseEvent>,
+) -> WebhookOutcome {
+    if payload.action != "published" {
+        return WebhookOutcome::ignored("release", format!("action `{}` is not `published`", payload.action));
+    }
+
+    let channel_id = match repo_channel("release", &payload.repository.full_name, ChannelKind::Announcements) {
+        Ok(id) => id,
+        Err(outcome) => return outcome,
+    };
+
+    let release = &payload.release;
+    let name = release
+        .name
+        .as_deref()
+        .filter(|n| !n.trim().is_empty())
+        .unwrap_or(&release.tag_name);
+
+    let header = format!(
+        "🚀 **{}** {} released{} by `{}`: **{}**\n",
+        payload.repository.full_name,
+        release.tag_name,
+        if release.prerelease { " (pre-release)" } else { "" },
+        release.author.login,
+        name
+    );
+    let footer = format!("\n{}", release.html_url);
+
+    let notes = escape_mentions(release.body.as_deref().unwrap_or_default().trim());
+    let budget = MAX_MESSAGE_CHARS.saturating_sub(header.chars().count() + footer.chars().count() + 2);
+
+    let message = format!("{}{}{}", header, truncate_notes(&notes, budget), footer);
+
+    deliver(&state, "release", channel_id, message).await
+}
+
+/// Cuts release notes to `max_chars`, preferring a line boundary, and marks the cut.
+fn truncate_notes(notes: &str, max_chars: usize) -> String {
+    if notes.chars().count() <= max_chars {
+        return notes.to_string();
+    }
+
+    let marker = "\n… (see full notes on GitHub)";
+    let keep = max_chars.saturating_sub(marker.chars().count());
+    let cut: String = notes.chars().take(keep).collect();
+    let cut = match cut.rfind('\n') {
+        Some(pos) if pos > keep / 2 => cut[..pos].to_string(),
+        _ => cut,
+    };
+
+    format!("{}{}", cut, marker)
+}
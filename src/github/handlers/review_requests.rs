@@ -1,14 +1,15 @@
-use axum::{
-    extract::{Json, State},
-    http::StatusCode,
-    response::{IntoResponse, Response},
-};
+use axum::extract::{Json, State};
 use serde::Deserialize;
-use serenity::model::id::ChannelId;
-use std::env;
 
+use super::{deliver, quote_pr_excerpt, repo_channel};
+use crate::github::mentions::{discord_mention_for, resolve_mentions};
+use crate::github::WebhookOutcome;
+use crate::guilds::ChannelKind;
 use crate::AppState;
 
+/// Longest review summary excerpt included in a notification.
+const MAX_REVIEW_CHARS: usize = 500;
+
 #[derive(Debug, Deserialize)]
 pub struct PullRequestReviewRequestedEvent {
     pub action: String,
@@ -18,12 +19,35 @@ pub struct PullRequestReviewRequestedEvent {
     pub sender: User,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct PullRequestReviewEvent {
+    pub action: String,
+    pub review: Review,
+    pub pull_request: ReviewedPullRequest,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Review {
+    pub html_url: String,
+    pub state: String,
+    pub body: Option<String>,
+    pub user: User,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct PullRequest {
     pub html_url: String,
     pub title: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ReviewedPullRequest {
+    pub number: u64,
+    pub title: String,
+    pub user: User,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Repository {
     pub full_name: String,
@@ -34,36 +58,19 @@ pub struct User {
     pub login: String,
 }
 
-/// Tries to map a GitHub username to a Discord mention via env var like GITHUB_NOTIFY_username
-fn discord_mention_for_github_user(username: &str) -> Option<String> {
-    let key = format!("GITHUB_NOTIFY_{}", username);
-    env::var(key).ok()
-}
-
 pub async fn handle_review_requested_event(
     State(state): State<AppState>,
     Json(payload): Json<PullRequestReviewRequestedEvent>,
-) -> Response {
+) -> WebhookOutcome {
     if payload.action != "review_requested" {
-        return StatusCode::OK.into_response();
+        return WebhookOutcome::ignored("review_requested", format!("unsupported action `{}`", payload.action));
     }
 
-    let ctx = {
-        let guard = state.discord_ctx.lock().unwrap();
-        match &*guard {
-            Some(ctx) => ctx.clone(),
-            None => {
-                eprintln!("Discord context not initialized yet.");
-                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-            }
-        }
+    let channel_id = match repo_channel("review_requested", &payload.repository.full_name, ChannelKind::Reviews) {
+        Ok(id) => id,
+        Err(outcome) => return outcome,
     };
 
-    let channel_id: u64 = env::var("DISCORD_REVIEW_CHANNEL_ID")
-        .expect("DISCORD_REVIEW_CHANNEL_ID not set")
-        .parse()
-        .unwrap();
-
     let requester = payload.sender.login;
     let reviewer_login = payload
         .requested_reviewer
@@ -71,7 +78,7 @@ pub async fn handle_review_requested_event(
         .map(|r| r.login.clone())
         .unwrap_or_else(|| "(unknown)".to_string());
 
-    let reviewer_display = discord_mention_for_github_user(&reviewer_login)
+    let reviewer_display = discord_mention_for(&reviewer_login)
         .unwrap_or_else(|| format!("`{}`", reviewer_login));
 
     let message = format!(
@@ -79,13 +86,62 @@ pub async fn handle_review_requested_event(
         requester,
         reviewer_display,
         payload.repository.full_name,
-        payload.pull_request.title,
+        resolve_mentions(&payload.pull_request.title),
         payload.pull_request.html_url
     );
 
-    let _ = ChannelId(channel_id)
-        .send_message(&ctx.http, |m| m.content(message))
-        .await;
+    deliver(&state, "review_requested", channel_id, message).await
+}
+
+/// Announces a submitted review with its verdict, mentioning the PR author.
+pub async fn handle_review_submitted_event(
+    State(state): State<AppState>,
+    Json(payload): Json<PullRequestReviewEvent>,
+) -> WebhookOutcome {
+    if payload.action != "submitted" {
+        return WebhookOutcome::ignored(
+            "pull_request_review",
+            format!("unsupported action `{}`", payload.action),
+        );
+    }
+
+    let review = &payload.review;
+    let pr = &payload.pull_request;
+
+    let (emoji, verdict) = match review.state.to_lowercase().as_str() {
+        "approved" => ("✅", "approved"),
+        "changes_requested" => ("🔴", "requested changes on"),
+        "commented" => ("💬", "reviewed"),
+        other => {
+            return WebhookOutcome::ignored(
+                "pull_request_review",
+                format!("unsupported review state `{}`", other),
+            );
+        }
+    };
 
-    StatusCode::OK.into_response()
+    // A plain comment review without a summary is followed by its inline comments anyway.
+    if verdict == "reviewed" && review.body.as_deref().unwrap_or_default().trim().is_empty() {
+        return WebhookOutcome::ignored("pull_request_review", "comment review without a summary");
+    }
+
+    let author = discord_mention_for(&pr.user.login).unwrap_or_else(|| format!("`{}`", pr.user.login));
+
+    let message = format!(
+        "{} `{}` {} PR #{} by {} in **{}**:\n**{}**\n{}{}",
+        emoji,
+        review.user.login,
+        verdict,
+        pr.number,
+        author,
+        payload.repository.full_name,
+        resolve_mentions(&pr.title),
+        quote_pr_excerpt(review.body.as_deref(), MAX_REVIEW_CHARS),
+        review.html_url
+    );
+
+    match repo_channel("pull_request_review", &payload.repository.full_name, ChannelKind::Reviews) {
+        Ok(channel_id) => deliver(&state, "pull_request_review", channel_id, message).await,
+        Err(outcome) => outcome,
+    }
 }
@@ -0,0 +1,231 @@
+use axum::extract::{Json, State};
+use serde::Deserialize;
+use std::env;
+
+use super::{deliver, dev_mention, repo_channel};
+use crate::github::WebhookOutcome;
+use crate::guilds::ChannelKind;
+use crate::AppState;
+
+/// `dependabot_alert` event, sent when Dependabot opens, fixes, or dismisses an alert.
+#[derive(Debug, Deserialize)]
+pub struct DependabotAlertEvent {
+    pub action: String,
+    pub alert: DependabotAlert,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DependabotAlert {
+    pub number: u64,
+    pub html_url: String,
+    pub dependency: Dependency,
+    pub security_advisory: Advisory,
+    pub security_vulnerability: Vulnerability,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Dependency {
+    pub package: Package,
+    pub manifest_path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Package {
+    pub ecosystem: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Advisory {
+    pub ghsa_id: String,
+    pub cve_id: Option<String>,
+    pub summary: String,
+    pub severity: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Vulnerability {
+    pub vulnerable_version_range: String,
+    pub first_patched_version: Option<PatchedVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PatchedVersion {
+    pub identifier: String,
+}
+
+/// Legacy `repository_vulnerability_alert` event, still sent by older GitHub Enterprise servers.
+#[derive(Debug, Deserialize)]
+pub struct VulnerabilityAlertEvent {
+    pub action: String,
+    pub alert: VulnerabilityAlert,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VulnerabilityAlert {
+    pub affected_package_name: String,
+    pub affected_range: String,
+    pub fixed_in: Option<String>,
+    pub severity: String,
+    pub ghsa_id: Option<String>,
+    pub external_identifier: Option<String>,
+    pub external_reference: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    pub full_name: String,
+    pub html_url: String,
+}
+
+/// Reports Dependabot alerts with their severity, affected package, and fix version.
+pub async fn handle_dependabot_alert_event(
+    State(state): State<AppState>,
+    Json(payload): Json<DependabotAlertEvent>,
+) -> WebhookOutcome {
+    let verb = match payload.action.as_str() {
+        "created" => "opened",
+        "reopened" | "auto_reopened" => "reopened",
+        "reintroduced" => "reintroduced",
+        "fixed" => "fixed",
+        "dismissed" | "auto_dismissed" => "dismissed",
+        other => return WebhookOutcome::ignored("dependabot_alert", format!("unsupported action `{}`", other)),
+    };
+    let repo = &payload.repository.full_name;
+
+    let channel_id = match repo_channel("dependabot_alert", repo, ChannelKind::Security) {
+        Ok(id) => id,
+        Err(outcome) => return outcome,
+    };
+
+    let alert = &payload.alert;
+    let advisory = &alert.security_advisory;
+    let opened = matches!(verb, "opened" | "reopened" | "reintroduced");
+    let mention = if opened { severity_mention(repo, &advisory.severity) } else { String::new() };
+    let identifier = advisory.cve_id.as_deref().unwrap_or(&advisory.ghsa_id);
+
+    let mut message = format!(
+        "{}{} **Dependabot alert [#{}](<{}>) {}** in **{}**: {} ({})\n\
+         {} severity · `{}` ({}) `{}`",
+        mention,
+        severity_emoji(&advisory.severity, opened),
+        alert.number,
+        alert.html_url,
+        verb,
+        repo,
+        advisory.summary,
+        identifier,
+        capitalize(&advisory.severity),
+        alert.dependency.package.name,
+        alert.dependency.package.ecosystem,
+        alert.security_vulnerability.vulnerable_version_range,
+    );
+    if let Some(path) = alert.dependency.manifest_path.as_deref() {
+        message.push_str(&format!(" in `{}`", path));
+    }
+    if opened {
+        match &alert.security_vulnerability.first_patched_version {
+            Some(patched) => message.push_str(&format!("\nFixed in `{}`", patched.identifier)),
+            None => message.push_str("\nNo patched version available yet"),
+        }
+    }
+
+    deliver(&state, "dependabot_alert", channel_id, message).await
+}
+
+/// Reports legacy vulnerability alerts the same way as Dependabot alerts.
+pub async fn handle_vulnerability_alert_event(
+    State(state): State<AppState>,
+    Json(payload): Json<VulnerabilityAlertEvent>,
+) -> WebhookOutcome {
+    let verb = match payload.action.as_str() {
+        "create" => "opened",
+        "reopen" => "reopened",
+        "resolve" => "fixed",
+        "dismiss" => "dismissed",
+        other => {
+            return WebhookOutcome::ignored(
+                "repository_vulnerability_alert",
+                format!("unsupported action `{}`", other),
+            )
+        }
+    };
+    let repo = &payload.repository.full_name;
+
+    let channel_id = match repo_channel("repository_vulnerability_alert", repo, ChannelKind::Security) {
+        Ok(id) => id,
+        Err(outcome) => return outcome,
+    };
+
+    let alert = &payload.alert;
+    let opened = matches!(verb, "opened" | "reopened");
+    let mention = if opened { severity_mention(repo, &alert.severity) } else { String::new() };
+    let identifier = alert
+        .external_identifier
+        .as_deref()
+        .or(alert.ghsa_id.as_deref())
+        .unwrap_or("advisory");
+    let link = alert
+        .external_reference
+        .clone()
+        .unwrap_or_else(|| format!("{}/security", payload.repository.html_url));
+
+    let mut message = format!(
+        "{}{} **Vulnerability alert {}** in **{}**: [{}](<{}>)\n{} severity · `{}` `{}`",
+        mention,
+        severity_emoji(&alert.severity, opened),
+        verb,
+        repo,
+        identifier,
+        link,
+        capitalize(&alert.severity),
+        alert.affected_package_name,
+        alert.affected_range,
+    );
+    if opened {
+        match alert.fixed_in.as_deref() {
+            Some(fixed) => message.push_str(&format!("\nFixed in `{}`", fixed)),
+            None => message.push_str("\nNo patched version available yet"),
+        }
+    }
+
+    deliver(&state, "repository_vulnerability_alert", channel_id, message).await
+}
+
+/// Whether `severity` warrants pinging someone (GitHub uses `moderate` for medium).
+fn is_urgent(severity: &str) -> bool {
+    matches!(severity.to_lowercase().as_str(), "high" | "critical")
+}
+
+/// `DISCORD_SECURITY_ROLE_ID` (or the repo's dev role) for high and critical alerts.
+fn severity_mention(repo: &str, severity: &str) -> String {
+    if !is_urgent(severity) {
+        return String::new();
+    }
+    match env::var("DISCORD_SECURITY_ROLE_ID").ok().and_then(|v| v.trim().parse::<u64>().ok()) {
+        Some(role) => format!("<@&{}> ", role),
+        None => dev_mention(repo),
+    }
+}
+
+fn severity_emoji(severity: &str, opened: bool) -> &'static str {
+    if !opened {
+        return "✅";
+    }
+    match severity.to_lowercase().as_str() {
+        "critical" => "🚨",
+        "high" => "🔴",
+        "medium" | "moderate" => "🟠",
+        _ => "🟡",
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,92 @@
+use axum::extract::{Json, State};
+use serde::Deserialize;
+
+use super::deliver_to_repo;
+use crate::github::mentions::escape_mentions;
+use crate::github::WebhookOutcome;
+use crate::guilds::ChannelKind;
+use crate::ops_events::{self, EventKind};
+use crate::AppState;
+
+/// `status` event, sent when an external system (CI, deployment gate, ...) sets a commit status.
+#[derive(Debug, Deserialize)]
+pub struct StatusEvent {
+    pub sha: String,
+    pub state: String,
+    pub context: String,
+    pub description: Option<String>,
+    pub target_url: Option<String>,
+    #[serde(default)]
+    pub branches: Vec<Branch>,
+    pub commit: Commit,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Branch {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Commit {
+    pub html_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    pub full_name: String,
+}
+
+/// Reports settled commit statuses with their context, state, and target URL.
+pub async fn handle_status_event(
+    State(state): State<AppState>,
+    Json(payload): Json<StatusEvent>,
+) -> WebhookOutcome {
+    let (emoji, what) = match payload.state.as_str() {
+        "success" => ("✅", "passed"),
+        "failure" => ("❌", "failed"),
+        "error" => ("💥", "errored"),
+        other => return WebhookOutcome::ignored("status", format!("state `{}` is not final", other)),
+    };
+
+    let record = format!("{} in {}", payload.context, payload.repository.full_name);
+    match payload.state.as_str() {
+        "success" => ops_events::record(EventKind::CiSuccess, record),
+        _ => ops_events::record(EventKind::CiFailure, record),
+    }
+
+    let short_sha: String = payload.sha.chars().take(7).collect();
+    // A commit can sit on several branches; only list a few.
+    let branches = match payload.branches.len() {
+        0 => String::new(),
+        n => {
+            let names: Vec<String> = payload
+                .branches
+                .iter()
+                .take(3)
+                .map(|b| format!("`{}`", b.name))
+                .collect();
+            let more = if n > 3 { format!(" +{} more", n - 3) } else { String::new() };
+            format!(" on {}{}", names.join(", "), more)
+        }
+    };
+
+    let mut message = format!(
+        "{} **{}** {} for [`{}`](<{}>){} in **{}**",
+        emoji,
+        payload.context,
+        what,
+        short_sha,
+        payload.commit.html_url,
+        branches,
+        payload.repository.full_name
+    );
+    if let Some(description) = payload.description.as_deref().filter(|d| !d.trim().is_empty()) {
+        message.push_str(&format!("\n> {}", escape_mentions(description)));
+    }
+    if let Some(url) = payload.target_url.as_deref().filter(|u| !u.is_empty()) {
+        message.push_str(&format!("\n{}", url));
+    }
+
+    deliver_to_repo(&state, "status", &payload.repository.full_name, ChannelKind::Workflows, message).await
+}
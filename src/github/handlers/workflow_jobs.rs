@@ -0,0 +1,89 @@
+use axum::extract::{Json, State};
+use serde::Deserialize;
+
+use super::{deliver, repo_channel};
+use crate::github::WebhookOutcome;
+use crate::guilds::ChannelKind;
+use crate::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct WorkflowJobEvent {
+    pub action: String,
+    pub workflow_job: WorkflowJob,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WorkflowJob {
+    pub html_url: String,
+    pub name: String,
+    pub workflow_name: Option<String>,
+    pub head_branch: Option<String>,
+    pub conclusion: Option<String>,
+    #[serde(default)]
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Step {
+    pub name: String,
+    pub number: u64,
+    pub conclusion: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    pub full_name: String,
+}
+
+/// Reports individual failed jobs, naming the step that broke.
+pub async fn handle_workflow_job_event(
+    State(state): State<AppState>,
+    Json(payload): Json<WorkflowJobEvent>,
+) -> WebhookOutcome {
+    if payload.action != "completed" {
+        return WebhookOutcome::ignored("workflow_job", format!("action `{}` is not `completed`", payload.action));
+    }
+
+    let job = &payload.workflow_job;
+    let conclusion = job.conclusion.as_deref().unwrap_or("unknown");
+    if !matches!(conclusion, "failure" | "timed_out") {
+        return WebhookOutcome::ignored("workflow_job", format!("job concluded with `{}`", conclusion));
+    }
+
+    let channel_id = match repo_channel("workflow_job", &payload.repository.full_name, ChannelKind::Workflows) {
+        Ok(id) => id,
+        Err(outcome) => return outcome,
+    };
+
+    let failed_step = job
+        .steps
+        .iter()
+        .find(|s| matches!(s.conclusion.as_deref(), Some("failure" | "timed_out")))
+        .map(|s| format!("step {} `{}`", s.number, s.name))
+        .unwrap_or_else(|| "unknown step".to_string());
+
+    let workflow = job
+        .workflow_name
+        .as_deref()
+        .map(|w| format!(" ({})", w))
+        .unwrap_or_default();
+    let branch = job
+        .head_branch
+        .as_deref()
+        .map(|b| format!(" on `{}`", b))
+        .unwrap_or_default();
+
+    let message = format!(
+        "❌ Job **{}**{} in **{}**{} {} at {}:\n{}",
+        job.name,
+        workflow,
+        payload.repository.full_name,
+        branch,
+        if conclusion == "timed_out" { "timed out" } else { "failed" },
+        failed_step,
+        job.html_url
+    );
+
+    deliver(&state, "workflow_job", channel_id, message).await
+}
@@ -1,14 +1,34 @@
-use axum::{
-    extract::{Json, State},
-    http::StatusCode,
-    response::{IntoResponse, Response},
-};
-use serde::Deserialize;
-use serenity::model::id::ChannelId;
-use std::env;
+use axum::extract::{Json, State};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serenity::builder::CreateComponents;
+use std::{collections::BTreeMap, env, time::Duration};
+use tokio::sync::Mutex;
 
+use super::{deliver, dev_mention, hold_low_priority, repo_channel};
+use crate::bot::components;
+use crate::duration::format_duration;
+use crate::github::mentions::escape_mentions;
+use crate::github::{job_logs, WebhookOutcome};
+use crate::guilds::ChannelKind;
+use crate::ops_events::{self, EventKind};
+use crate::notify::{self, low_priority, Outgoing};
+use crate::store;
 use crate::AppState;
 
+const MESSAGES_FILE: &str = "workflow_messages.json";
+
+/// Latest status message per `repo|workflow|branch`, edited in place when the next run completes.
+static LATEST_MESSAGES: Lazy<Mutex<BTreeMap<String, PostedMessage>>> =
+    Lazy::new(|| Mutex::new(store::load(MESSAGES_FILE)));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PostedMessage {
+    channel_id: u64,
+    message_id: u64,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct WorkflowRunEvent {
     pub action: String,
@@ -18,54 +38,341 @@ pub struct WorkflowRunEvent {
 
 #[derive(Debug, Deserialize)]
 pub struct WorkflowRun {
+    pub id: u64,
     pub html_url: String,
     pub name: String,
+    pub head_branch: Option<String>,
     pub status: Option<String>,
     pub conclusion: Option<String>,
+    /// When the current attempt started (RFC 3339).
+    pub run_started_at: Option<String>,
+    /// When the run last changed, i.e. when it completed for `completed` events (RFC 3339).
+    pub updated_at: Option<String>,
+    /// 1 for the first attempt, incremented on each re-run.
+    pub run_attempt: Option<u64>,
+}
+
+impl WorkflowRun {
+    /// How long the current attempt took, if both timestamps are present.
+    fn elapsed(&self) -> Option<Duration> {
+        let started = DateTime::parse_from_rfc3339(self.run_started_at.as_deref()?).ok()?;
+        let finished = DateTime::parse_from_rfc3339(self.updated_at.as_deref()?).ok()?;
+        (finished - started).to_std().ok()
+    }
+
+    /// " in 4m 12s (attempt 2)", with whichever parts are known.
+    fn timing_note(&self) -> String {
+        let mut note = String::new();
+        if let Some(elapsed) = self.elapsed() {
+            note.push_str(&format!(" in {}", format_duration(elapsed)));
+        }
+        if let Some(attempt) = self.run_attempt.filter(|a| *a > 1) {
+            note.push_str(&format!(" (attempt {})", attempt));
+        }
+        note
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Repository {
     pub full_name: String,
+    pub default_branch: Option<String>,
+}
+
+/// Branch patterns to notify for, from `WORKFLOW_NOTIFY_BRANCHES` (empty = all branches).
+fn notify_branches() -> Vec<String> {
+    env::var("WORKFLOW_NOTIFY_BRANCHES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|b| b.trim().to_string())
+        .filter(|b| !b.is_empty())
+        .collect()
+}
+
+/// Whether runs on `branch` are notified under the `WORKFLOW_NOTIFY_BRANCHES` `patterns`.
+fn branch_notified(patterns: &[String], branch: &str) -> bool {
+    patterns.is_empty() || patterns.iter().any(|pattern| glob_match(pattern, branch))
+}
+
+/// Whether to edit the previous message for a workflow+branch instead of posting a new one
+/// (`WORKFLOW_EDIT_IN_PLACE`, default: true).
+fn edit_in_place() -> bool {
+    env::var("WORKFLOW_EDIT_IN_PLACE")
+        .map(|v| !v.eq_ignore_ascii_case("false"))
+        .unwrap_or(true)
+}
+
+/// Whether successful runs are posted (`WORKFLOW_SUCCESS_NOTIFY`: `quiet`, the default, or `off`).
+fn notify_successes() -> bool {
+    !env::var("WORKFLOW_SUCCESS_NOTIFY")
+        .map(|v| v.trim().eq_ignore_ascii_case("off"))
+        .unwrap_or(false)
+}
+
+/// Conclusions that ping the dev role.
+fn is_failure(conclusion: &str) -> bool {
+    matches!(conclusion, "failure" | "timed_out")
+}
+
+/// Edits the latest message for `key` if there is one, otherwise posts a new message and
+/// remembers it. Falls back to a normal delivery when the gateway is down.
+///
+/// Edits don't notify anyone, so with `ping` set a new message is always posted. A new message
+/// gets a re-run button with the `rerun` custom ID; an edited one loses its button.
+async fn post_or_edit(
+    state: &AppState,
+    key: String,
+    channel_id: u64,
+    message: String,
+    ping: bool,
+    rerun: Option<String>,
+) -> WebhookOutcome {
+    let ctx = match notify::discord_ctx(state) {
+        Some(ctx) => ctx,
+        None => return deliver(state, "workflow_run", channel_id, message).await,
+    };
+
+    // Not held across the Discord calls, so one slow send doesn't stall every other run.
+    let priority = notify::priority_for("workflow_run");
+    let previous = LATEST_MESSAGES.lock().await.get(&key).cloned();
+
+    if let Some(posted) = previous.filter(|p| !ping && p.channel_id == channel_id) {
+        let edit = Outgoing::text(&message).components(CreateComponents::default());
+        let edited = notify::edit(&ctx, posted.channel_id, posted.message_id, priority, edit).await;
+        match edited {
+            Ok(_) => {
+                let mut outcome = WebhookOutcome::handled("workflow_run");
+                outcome.reason = Some("edited previous run message".into());
+                return outcome;
+            }
+            // Deleted or otherwise uneditable: post a fresh message below.
+            Err(e) => eprintln!("Failed to edit workflow message for {}: {}", key, e),
+        }
+    }
+
+    let mut outgoing = Outgoing::text(&message);
+    if let Some(custom_id) = &rerun {
+        let mut buttons = CreateComponents::default();
+        components::add_rerun_button(&mut buttons, custom_id);
+        outgoing = outgoing.components(buttons);
+    }
+    let sent = notify::post(&ctx, channel_id, priority, outgoing).await;
+    match sent {
+        Ok(sent) => {
+            LATEST_MESSAGES.lock().await.insert(
+                key,
+                PostedMessage {
+                    channel_id,
+                    message_id: sent.id.0,
+                },
+            );
+            // Saves whatever is current, so a slow save never overwrites a newer one.
+            let _ = tokio::task::spawn_blocking(|| {
+                store::save(MESSAGES_FILE, &*LATEST_MESSAGES.blocking_lock());
+            })
+            .await;
+            WebhookOutcome::handled("workflow_run")
+        }
+        Err(e) => {
+            eprintln!("Failed to send workflow_run notification: {}", e);
+            deliver(state, "workflow_run", channel_id, message).await
+        }
+    }
+}
+
+/// Matches `name` against a glob `pattern`, where `*` matches any run of characters
+/// (including `/`) and `?` matches exactly one.
+pub(crate) fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut pi, mut ni) = (0, 0);
+    // Position of the last `*` and the name index it was tried at, for backtracking.
+    let mut star: Option<(usize, usize)> = None;
+
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ni));
+            pi += 1;
+        } else if let Some((star_pi, star_ni)) = star {
+            pi = star_pi + 1;
+            ni = star_ni + 1;
+            star = Some((star_pi, star_ni + 1));
+        } else {
+            return false;
+        }
+    }
+
+    p[pi..].iter().all(|c| *c == '*')
 }
 
 pub async fn handle_workflow_run_event(
     State(state): State<AppState>,
     Json(payload): Json<WorkflowRunEvent>,
-) -> Response {
+) -> WebhookOutcome {
     // Only notify on completed workflow runs
     if payload.action != "completed" {
-        return StatusCode::OK.into_response();
+        return WebhookOutcome::ignored("workflow_run", format!("action `{}` is not `completed`", payload.action));
     }
 
-    let ctx = {
-        let guard = state.discord_ctx.lock().unwrap();
-        match &*guard {
-            Some(ctx) => ctx.clone(),
-            None => {
-                eprintln!("Discord context not initialized yet.");
-                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-            }
-        }
+    let conclusion = payload.workflow_run.conclusion.as_deref().unwrap_or("unknown");
+    let summary = format!("{} in {}", payload.workflow_run.name, payload.repository.full_name);
+    match conclusion {
+        "success" => ops_events::record(EventKind::CiSuccess, summary),
+        "failure" | "timed_out" | "startup_failure" => ops_events::record(EventKind::CiFailure, summary),
+        _ => {}
+    }
+
+    // Filtered runs still count towards the weekly CI pass rate above.
+    let head_branch = payload.workflow_run.head_branch.as_deref().unwrap_or_default();
+    if !branch_notified(&notify_branches(), head_branch) {
+        return WebhookOutcome::ignored(
+            "workflow_run",
+            format!("branch `{}` is not in WORKFLOW_NOTIFY_BRANCHES", head_branch),
+        );
+    }
+
+    if conclusion == "success" && !notify_successes() {
+        return WebhookOutcome::ignored("workflow_run", "successful runs are suppressed by WORKFLOW_SUCCESS_NOTIFY");
+    }
+
+    let channel_id = match repo_channel("workflow_run", &payload.repository.full_name, ChannelKind::Workflows) {
+        Ok(id) => id,
+        Err(outcome) => return outcome,
     };
 
-    let channel_id: u64 = env::var("DISCORD_WORKFLOW_CHANNEL_ID")
-        .expect("DISCORD_WORKFLOW_CHANNEL_ID not set")
-        .parse()
-        .unwrap();
+    // Only failures mention the dev role, so they stand out from routine runs.
+    let ping = is_failure(conclusion);
+    let prefix = if ping {
+        format!("{}❌ ", dev_mention(&payload.repository.full_name))
+    } else {
+        String::new()
+    };
+
+    let branch = payload.workflow_run.head_branch.as_deref().unwrap_or("unknown");
+    let key = format!(
+        "{}|{}|{}",
+        payload.repository.full_name, payload.workflow_run.name, branch
+    );
+
+    // Workflow and branch names come from the repository, so they must never ping.
+    let (name, shown_branch) = (escape_mentions(&payload.workflow_run.name), escape_mentions(branch));
+
+    // Routine passes on feature branches may be summarized in the low-priority digest, unless
+    // they would update a posted (failed) run's message in place.
+    let default_branch = payload.repository.default_branch.as_deref();
+    let on_default_branch = match default_branch {
+        Some(default) => branch == default,
+        None => matches!(branch, "main" | "master"),
+    };
+    if conclusion == "success" && !on_default_branch && low_priority::enabled("ci_success") {
+        let has_posted = edit_in_place() && LATEST_MESSAGES.lock().await.contains_key(&key);
+        if !has_posted {
+            let line = format!(
+                "**{}** passed on `{}` in **{}** (<{}>)",
+                name, shown_branch, payload.repository.full_name, payload.workflow_run.html_url
+            );
+            return hold_low_priority("workflow_run", channel_id, "ci_success", line);
+        }
+    }
 
     let message = format!(
-        "Workflow run **{}** in **{}** completed with status `{}` and result `{}`:\n{}",
-        payload.workflow_run.name,
+        "{}Workflow run **{}** in **{}** on `{}` completed with status `{}` and result `{}`{} (<t:{}:R>):\n{}",
+        prefix,
+        name,
         payload.repository.full_name,
+        shown_branch,
         payload.workflow_run.status.as_deref().unwrap_or("unknown"),
-        payload.workflow_run.conclusion.as_deref().unwrap_or("unknown"),
+        conclusion,
+        payload.workflow_run.timing_note(),
+        Utc::now().timestamp(),
         payload.workflow_run.html_url
     );
 
-    let _ = ChannelId(channel_id)
-        .send_message(&ctx.http, |m| m.content(message))
-        .await;
+    let rerun = if ping && components::rerun_enabled() {
+        components::rerun_custom_id(&payload.repository.full_name, payload.workflow_run.id)
+    } else {
+        None
+    };
+
+    // The button needs a direct send; failures are never edited, so post_or_edit only posts.
+    let outcome = if edit_in_place() || rerun.is_some() {
+        post_or_edit(&state, key, channel_id, message, ping, rerun).await
+    } else {
+        deliver(&state, "workflow_run", channel_id, message).await
+    };
+
+    if ping && job_logs::enabled() {
+        post_failed_job_logs(&state, channel_id, &payload);
+    }
+
+    outcome
+}
+
+/// Posts the failed jobs' log tails below the failure message. Runs in the background so
+/// GitHub API lookups don't hold up the webhook response.
+fn post_failed_job_logs(state: &AppState, channel_id: u64, payload: &WorkflowRunEvent) {
+    let (state, repo, run_id) = (state.clone(), payload.repository.full_name.clone(), payload.workflow_run.id);
+    tokio::spawn(async move {
+        job_logs::post_failed_job_logs(&state, channel_id, &repo, run_id).await;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{branch_notified, glob_match};
+
+    fn patterns(list: &[&str]) -> Vec<String> {
+        list.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn release_glob_matches_release_branches() {
+        let patterns = patterns(&["release/*"]);
+        assert!(branch_notified(&patterns, "release/1.2"));
+        assert!(branch_notified(&patterns, "release/2024/q3"));
+        assert!(!branch_notified(&patterns, "release"));
+        assert!(!branch_notified(&patterns, "pre-release/1.2"));
+    }
+
+    #[test]
+    fn literal_branch_matches_only_itself() {
+        let patterns = patterns(&["main"]);
+        assert!(branch_notified(&patterns, "main"));
+        assert!(!branch_notified(&patterns, "main-old"));
+        assert!(!branch_notified(&patterns, "feature/main"));
+    }
+
+    #[test]
+    fn unlisted_branches_are_filtered_out() {
+        let patterns = patterns(&["main", "release/*"]);
+        assert!(!branch_notified(&patterns, "feature/x"));
+    }
+
+    #[test]
+    fn runs_without_a_head_branch_only_match_a_bare_star() {
+        assert!(!branch_notified(&patterns(&["main", "release/*"]), ""));
+        assert!(branch_notified(&patterns(&["*"]), ""));
+        assert!(branch_notified(&[], ""));
+    }
 
-    StatusCode::OK.into_response()
+    #[test]
+    fn no_patterns_notify_every_branch() {
+        assert!(branch_notified(&[], "feature/x"));
+    }
+
+    #[test]
+    fn glob_match_backtracks() {
+        assert!(glob_match("release/*-rc*", "release/1.0-beta-rc2"));
+        assert!(!glob_match("release/*-rc*", "release/1.0-beta"));
+    }
+
+    #[test]
+    fn glob_match_question_mark_is_one_char() {
+        assert!(glob_match("hotfix-?", "hotfix-1"));
+        assert!(!glob_match("hotfix-?", "hotfix-"));
+        assert!(!glob_match("hotfix-?", "hotfix-12"));
+    }
 }
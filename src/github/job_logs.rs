@@ -0,0 +1,174 @@
+//! Log tails of failed jobs, posted after a workflow failure message.
+//!
+//! When a workflow run fails, the bot lists the run's failed jobs through the GitHub API,
+//! downloads each one's log and posts the last lines, so the error is visible without
+//! clicking through to GitHub. Short tails are posted as a code block, longer ones as a
+//! `.log` attachment.
+//!
+//! Job logs require authentication even for public repositories, so this only runs with
+//! GitHub API credentials (see [`super::client`]) that can read Actions.
+//!
+//! Environment Variables:
+//! - `WORKFLOW_FAILURE_LOGS`: Set to `false` to stop posting log tails (default: on)
+//! - `WORKFLOW_FAILURE_LOG_LINES`: Lines per failed job (default: 100)
+
+use serde::Deserialize;
+use std::env;
+
+use super::client;
+use super::mentions::escape_mentions;
+use crate::notify::{self, Delivery, Destination, Outgoing};
+use crate::AppState;
+
+const DEFAULT_LINES: usize = 100;
+/// Failed jobs whose logs are posted per run; a matrix can fail dozens at once.
+const MAX_JOBS: usize = 3;
+/// Tails longer than this are attached instead of inlined.
+const MAX_INLINE_CHARS: usize = 1800;
+
+#[derive(Debug, Deserialize)]
+struct JobList {
+    jobs: Vec<Job>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Job {
+    id: u64,
+    name: String,
+    conclusion: Option<String>,
+}
+
+/// Whether log tails are posted: enabled (the default) and the API client is authenticated.
+pub fn enabled() -> bool {
+    let on = env::var("WORKFLOW_FAILURE_LOGS")
+        .map(|v| !v.trim().eq_ignore_ascii_case("false"))
+        .unwrap_or(true);
+    on && client::authenticated()
+}
+
+fn line_count() -> usize {
+    env::var("WORKFLOW_FAILURE_LOG_LINES")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_LINES)
+}
+
+/// The latest attempt's failed jobs of a run.
+async fn failed_jobs(repo: &str, run_id: u64) -> Result<Vec<Job>, String> {
+    let path = format!("/repos/{}/actions/runs/{}/jobs?filter=latest&per_page=100", repo, run_id);
+    let list: JobList = client::get_json(&path).await?;
+    Ok(list
+        .jobs
+        .into_iter()
+        .filter(|job| matches!(job.conclusion.as_deref(), Some("failure" | "timed_out")))
+        .collect())
+}
+
+/// Removes ANSI colour codes from a log line.
+fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // Skip `ESC [ ... <letter>`.
+            for next in chars.by_ref() {
+                if next.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// The last `lines` lines of a job log, without GitHub's per-line timestamps.
+fn tail(log: &str, lines: usize) -> String {
+    let all: Vec<&str> = log.lines().collect();
+    all[all.len().saturating_sub(lines)..]
+        .iter()
+        .map(|line| {
+            // Lines start with an RFC 3339 timestamp, e.g. `2024-05-01T10:00:00.1234567Z `.
+            let line = match line.split_once(' ') {
+                Some((stamp, rest)) if chrono::DateTime::parse_from_rfc3339(stamp).is_ok() => rest,
+                _ => line,
+            };
+            strip_ansi(line)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+async fn fetch_tail(repo: &str, job_id: u64, lines: usize) -> Result<String, String> {
+    // Redirects to a short-lived download URL, which reqwest follows.
+    let path = format!("/repos/{}/actions/jobs/{}/logs", repo, job_id);
+    let log = client::get_text(&path, client::JSON).await?;
+    Ok(tail(&log, lines))
+}
+
+/// Keeps a log from closing the code block it is shown in.
+fn escape_fences(text: &str) -> String {
+    text.replace("```", "`\u{200b}``")
+}
+
+/// The last `max_chars` characters of `text`, starting at a line boundary.
+fn last_chars(text: &str, max_chars: usize) -> &str {
+    let count = text.chars().count();
+    if count <= max_chars {
+        return text;
+    }
+    let start = text.char_indices().nth(count - max_chars).map_or(0, |(i, _)| i);
+    let cut = &text[start..];
+    cut.split_once('\n').map_or(cut, |(_, rest)| rest)
+}
+
+/// Posts one job's log tail, inline when short and as an attachment otherwise.
+async fn post_tail(state: &AppState, channel_id: u64, repo: &str, job: &Job, tail: String) {
+    let heading = format!("📜 Last lines of failed job **{}** in **{}**:", escape_mentions(&job.name), repo);
+    let fits = tail.chars().count() <= MAX_INLINE_CHARS;
+
+    if !fits {
+        if let Some(ctx) = notify::discord_ctx(state) {
+            let filename = format!("{}.log", job.name.replace(|c: char| !c.is_ascii_alphanumeric(), "-"));
+            let message = Outgoing::text(&heading).file(filename, tail.clone().into_bytes());
+            let priority = notify::priority_for("workflow_run");
+            match notify::post(&ctx, channel_id, priority, message).await {
+                Ok(_) => return,
+                Err(e) => eprintln!("Failed to attach log of job {} in {}: {}", job.id, repo, e),
+            }
+        }
+    }
+
+    // Without the gateway (or if the upload failed), inline as much of the end as fits.
+    let message = format!(
+        "{}\n```\n{}\n```",
+        heading,
+        escape_fences(last_chars(&tail, MAX_INLINE_CHARS))
+    );
+    let delivery = notify::send(state, "workflow_run", Destination::Channel(channel_id), message).await;
+    if let Delivery::Failed(e) = delivery {
+        eprintln!("Failed to post log of job {} in {}: {}", job.id, repo, e);
+    }
+}
+
+/// Posts the log tails of a failed run's failed jobs to `channel_id`.
+pub async fn post_failed_job_logs(state: &AppState, channel_id: u64, repo: &str, run_id: u64) {
+    let jobs = match failed_jobs(repo, run_id).await {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            eprintln!("Failed to list jobs of run {} in {}: {}", run_id, repo, e);
+            return;
+        }
+    };
+
+    let lines = line_count();
+    for job in jobs.iter().take(MAX_JOBS) {
+        match fetch_tail(repo, job.id, lines).await {
+            Ok(tail) if !tail.trim().is_empty() => post_tail(state, channel_id, repo, job, tail).await,
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to download log of job {} in {}: {}", job.id, repo, e),
+        }
+    }
+}
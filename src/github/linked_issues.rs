@@ -0,0 +1,148 @@
+//! Follow-ups for issues closed by a merged PR.
+//!
+//! GitHub closes the issues a PR references with a closing keyword ("Fixes #12",
+//! "Closes owner/repo#3", "Resolves <issue URL>") once it is merged into the default branch.
+//! The bot lists those issues in the PR's thread (or the PR channel without threads) and
+//! mentions each issue's reporter if they linked their Discord account with `/link_github`.
+//!
+//! Issue reporters are looked up through the GitHub API, which needs credentials for private
+//! repositories (see [`super::client`]).
+
+use serde::Deserialize;
+
+use super::client;
+use super::mentions::{discord_mention_for, escape_mentions};
+use super::threads;
+use crate::notify::{self, Delivery, Destination};
+use crate::AppState;
+
+/// GitHub's closing keywords.
+const KEYWORDS: &[&str] = &[
+    "close", "closes", "closed", "fix", "fixes", "fixed", "resolve", "resolves", "resolved",
+];
+
+/// An issue referenced with a closing keyword.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssueRef {
+    pub repo: String,
+    pub number: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Issue {
+    title: String,
+    html_url: String,
+    user: User,
+    /// Present when the "issue" is actually a pull request.
+    pull_request: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    login: String,
+}
+
+/// Issues `body` closes, in order of mention. References without an owner are in `repo`.
+pub fn closing_references(body: &str, repo: &str) -> Vec<IssueRef> {
+    let words: Vec<&str> = body.split_whitespace().collect();
+    let mut refs: Vec<IssueRef> = Vec::new();
+
+    for pair in words.windows(2) {
+        let keyword = pair[0].trim_end_matches(':').to_lowercase();
+        if !KEYWORDS.contains(&keyword.as_str()) {
+            continue;
+        }
+        if let Some(issue) = parse_reference(pair[1], repo) {
+            if !refs.contains(&issue) {
+                refs.push(issue);
+            }
+        }
+    }
+    refs
+}
+
+/// Parses `#12`, `owner/repo#12`, or `https://github.com/owner/repo/issues/12`.
+fn parse_reference(word: &str, repo: &str) -> Option<IssueRef> {
+    let word = word.trim_end_matches(['.', ',', ';', ':', ')', '!']);
+
+    if let Some(path) = word.strip_prefix("https://github.com/") {
+        let mut parts = path.split('/');
+        let (owner, name, kind, number) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        if kind != "issues" {
+            return None;
+        }
+        return Some(IssueRef { repo: format!("{}/{}", owner, name), number: number.parse().ok()? });
+    }
+
+    let (target, number) = word.split_once('#')?;
+    let target = if target.is_empty() {
+        repo.to_string()
+    } else if target.matches('/').count() == 1 {
+        target.to_string()
+    } else {
+        return None;
+    };
+    Some(IssueRef { repo: target, number: number.parse().ok()? })
+}
+
+/// Looks up an issue through the GitHub API.
+async fn fetch_issue(issue: &IssueRef) -> Result<Issue, String> {
+    client::get_json(&format!("/repos/{}/issues/{}", issue.repo, issue.number)).await
+}
+
+/// Lists the issues a merged PR closed in the PR's thread, mentioning linked reporters.
+///
+/// `parent` is the PR channel, used when threads are disabled or the thread can't be created.
+pub async fn announce(
+    state: &AppState,
+    parent: u64,
+    repo: &str,
+    number: u64,
+    title: &str,
+    issues: Vec<IssueRef>,
+) {
+    let mut lines = Vec::new();
+
+    for issue in &issues {
+        let label = if issue.repo.eq_ignore_ascii_case(repo) {
+            format!("#{}", issue.number)
+        } else {
+            format!("{}#{}", issue.repo, issue.number)
+        };
+
+        match fetch_issue(issue).await {
+            Ok(found) if found.pull_request.is_some() => continue,
+            Ok(found) => {
+                // Mentioning a linked reporter pings them that their issue was fixed.
+                let reporter = discord_mention_for(&found.user.login)
+                    .unwrap_or_else(|| format!("`{}`", found.user.login));
+                lines.push(format!(
+                    "- [{}]({}) {} (reported by {})",
+                    label,
+                    found.html_url,
+                    escape_mentions(&found.title),
+                    reporter
+                ));
+            }
+            Err(e) => {
+                eprintln!("Failed to look up {} closed by {}#{}: {}", label, repo, number, e);
+                lines.push(format!("- {}", label));
+            }
+        }
+    }
+    if lines.is_empty() {
+        return;
+    }
+
+    let message = format!("🔗 Merging PR #{} closed:\n{}", number, lines.join("\n"));
+
+    let channel_id = if threads::enabled() {
+        threads::pr_thread(state, parent, repo, number, title).await.unwrap_or(parent)
+    } else {
+        parent
+    };
+    let delivery = notify::send(state, "pull_request", Destination::Channel(channel_id), message).await;
+    if let Delivery::Failed(e) = delivery {
+        eprintln!("Failed to post closed issues for {}#{}: {}", repo, number, e);
+    }
+}
@@ -0,0 +1,240 @@
+//! GitHub → Discord mention resolution.
+//!
+//! Teammates link their GitHub username to their Discord account with `/link_github`;
+//! the mapping is persisted in `github_links.json`. `@username` mentions in PR titles,
+//! bodies and comments are rewritten to the linked Discord mention so the person
+//! actually gets pinged; unlinked mentions are left as inline code. Other text passed
+//! through from GitHub and other services (commit messages, deployment descriptions, alert
+//! titles) only goes through [`escape_mentions`], which never pings anyone.
+//!
+//! Legacy `GITHUB_NOTIFY_<username>=<@id>` entries are imported once, when the store
+//! doesn't exist yet.
+
+use once_cell::sync::Lazy;
+use std::{collections::BTreeMap, env, sync::Mutex};
+
+use crate::store;
+
+const LINKS_FILE: &str = "github_links.json";
+const NOTIFY_PREFIX: &str = "GITHUB_NOTIFY_";
+/// GitHub usernames are at most 39 characters.
+const MAX_LOGIN_LEN: usize = 39;
+
+/// Discord user ID per lowercased GitHub username.
+static LINKS: Lazy<Mutex<BTreeMap<String, u64>>> = Lazy::new(|| Mutex::new(load_links()));
+
+fn load_links() -> BTreeMap<String, u64> {
+    if store::data_path(LINKS_FILE).exists() {
+        return store::load(LINKS_FILE);
+    }
+
+    let imported: BTreeMap<String, u64> = env::vars()
+        .filter_map(|(key, mention)| {
+            let login = key.strip_prefix(NOTIFY_PREFIX)?.to_lowercase();
+            let id = mention
+                .trim()
+                .trim_start_matches("<@")
+                .trim_start_matches('!')
+                .trim_end_matches('>')
+                .parse()
+                .ok()?;
+            Some((login, id))
+        })
+        .collect();
+
+    if !imported.is_empty() {
+        println!("Imported {} GITHUB_NOTIFY_ mapping(s) into {}.", imported.len(), LINKS_FILE);
+        store::save(LINKS_FILE, &imported);
+    }
+    imported
+}
+
+/// Returns `true` if `login` looks like a valid GitHub username.
+pub fn is_valid_login(login: &str) -> bool {
+    !login.is_empty()
+        && login.len() <= MAX_LOGIN_LEN
+        && !login.starts_with('-')
+        && !login.ends_with('-')
+        && login.chars().all(is_login_char)
+}
+
+/// Discord user linked to a GitHub username (case-insensitive, like GitHub itself).
+pub fn linked_user(login: &str) -> Option<u64> {
+    LINKS.lock().unwrap().get(&login.to_lowercase()).copied()
+}
+
+/// Links `login` to `user_id`, returning the previously linked user if it changed hands.
+pub fn link(login: &str, user_id: u64) -> Option<u64> {
+    let mut links = LINKS.lock().unwrap();
+    let previous = links.insert(login.to_lowercase(), user_id);
+    store::save(LINKS_FILE, &*links);
+    previous.filter(|id| *id != user_id)
+}
+
+/// Removes every GitHub username linked to `user_id`, returning them.
+pub fn unlink(user_id: u64) -> Vec<String> {
+    let mut links = LINKS.lock().unwrap();
+    let removed: Vec<String> = links
+        .iter()
+        .filter(|(_, id)| **id == user_id)
+        .map(|(login, _)| login.clone())
+        .collect();
+    for login in &removed {
+        links.remove(login);
+    }
+    if !removed.is_empty() {
+        store::save(LINKS_FILE, &*links);
+    }
+    removed
+}
+
+/// Maps a GitHub username to its Discord mention.
+pub fn discord_mention_for(login: &str) -> Option<String> {
+    linked_user(login).map(|id| format!("<@{}>", id))
+}
+
+fn is_login_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-'
+}
+
+/// Rewrites `@username` mentions in `text` to Discord mentions.
+///
+/// Mapped users become their Discord mention; unmapped ones are wrapped in backticks so
+/// they can't accidentally match `@everyone`/`@here`. Email addresses and `@` inside
+/// words are left untouched, except that `@everyone`/`@here` and raw `<@id>`/`<@&id>`
+/// mentions are broken up with a zero-width space so they never ping.
+pub fn resolve_mentions(text: &str) -> String {
+    rewrite_mentions(text, true)
+}
+
+/// Escapes mentions in `text` like [`resolve_mentions`], but without pinging linked users:
+/// every `@username` becomes inline code. Use this for third-party text that isn't a PR
+/// title, body or review comment.
+pub fn escape_mentions(text: &str) -> String {
+    rewrite_mentions(text, false)
+}
+
+fn rewrite_mentions(text: &str, resolve: bool) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i] != '@' {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+
+        let raw_mention = i > 0
+            && chars[i - 1] == '<'
+            && chars.get(i + 1).is_some_and(|c| matches!(c, '&' | '!') || c.is_ascii_digit());
+        if raw_mention {
+            out.push_str("@\u{200b}");
+            i += 1;
+            continue;
+        }
+        let preceded_by_word = i > 0 && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '`');
+        if preceded_by_word {
+            push_at(&mut out, &chars[i + 1..]);
+            i += 1;
+            continue;
+        }
+
+        let start = i + 1;
+        let mut end = start;
+        while end < chars.len() && is_login_char(chars[end]) {
+            end += 1;
+        }
+        // Trailing hyphens aren't part of a username (e.g. "@alice-").
+        while end > start && chars[end - 1] == '-' {
+            end -= 1;
+        }
+
+        let login: String = chars[start..end].iter().collect();
+        if login.is_empty() || login.starts_with('-') || login.len() > MAX_LOGIN_LEN {
+            push_at(&mut out, &chars[start..]);
+            i += 1;
+            continue;
+        }
+
+        match discord_mention_for(&login).filter(|_| resolve) {
+            Some(mention) => out.push_str(&mention),
+            None => out.push_str(&format!("`@{}`", login)),
+        }
+        i = end;
+    }
+
+    out
+}
+
+/// Pushes an `@` left as text, breaking it up if `rest` would make it `@everyone`/`@here`.
+fn push_at(out: &mut String, rest: &[char]) {
+    let starts_with = |word: &str| {
+        word.chars()
+            .enumerate()
+            .all(|(i, c)| rest.get(i).is_some_and(|r| r.eq_ignore_ascii_case(&c)))
+    };
+    out.push('@');
+    if starts_with("everyone") || starts_with("here") {
+        out.push('\u{200b}');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlinked_mentions_become_inline_code() {
+        assert_eq!(resolve_mentions("thanks @octo-cat!"), "thanks `@octo-cat`!");
+        assert_eq!(resolve_mentions("@alice- and @bob"), "`@alice`- and `@bob`");
+    }
+
+    #[test]
+    fn everyone_and_here_never_ping() {
+        assert_eq!(resolve_mentions("@everyone look"), "`@everyone` look");
+        assert_eq!(resolve_mentions("ping @here"), "ping `@here`");
+    }
+
+    #[test]
+    fn linked_mentions_become_discord_mentions() {
+        LINKS.lock().unwrap().insert("mentions-test-user".into(), 42);
+        assert_eq!(resolve_mentions("by @Mentions-Test-User"), "by <@42>");
+    }
+
+    #[test]
+    fn escaping_never_pings_linked_users() {
+        LINKS.lock().unwrap().insert("mentions-test-escaped".into(), 43);
+        assert_eq!(escape_mentions("by @mentions-test-escaped"), "by `@mentions-test-escaped`");
+        assert_eq!(escape_mentions("<@43> @everyone"), "<@\u{200b}43> `@everyone`");
+    }
+
+    #[test]
+    fn emails_code_and_bare_at_signs_are_left_alone() {
+        assert_eq!(resolve_mentions("mail dev@example.com"), "mail dev@example.com");
+        assert_eq!(resolve_mentions("`@decorator`"), "`@decorator`");
+        assert_eq!(resolve_mentions("a @ b, @-x"), "a @ b, @-x");
+    }
+
+    #[test]
+    fn overlong_logins_are_not_mentions() {
+        let long = format!("@{}", "a".repeat(MAX_LOGIN_LEN + 1));
+        assert_eq!(resolve_mentions(&long), long);
+    }
+
+    #[test]
+    fn raw_discord_mentions_are_broken_up() {
+        assert_eq!(resolve_mentions("<@&123> ping"), "<@\u{200b}&123> ping");
+        assert_eq!(resolve_mentions("<@!123>"), "<@\u{200b}!123>");
+        assert_eq!(resolve_mentions("<@123>"), "<@\u{200b}123>");
+    }
+
+    #[test]
+    fn everyone_inside_words_or_overlong_never_pings() {
+        assert_eq!(resolve_mentions("x@everyone"), "x@\u{200b}everyone");
+        assert_eq!(resolve_mentions("hi@HERE"), "hi@\u{200b}HERE");
+        let long = format!("@everyone{}", "e".repeat(MAX_LOGIN_LEN));
+        assert_eq!(resolve_mentions(&long), format!("@\u{200b}{}", &long[1..]));
+    }
+}
@@ -1,51 +1,358 @@
+mod archive;
+pub(crate) mod client;
+pub(crate) mod codeowners;
+mod deliveries;
+mod gitea;
 mod handlers;
+pub(crate) mod job_logs;
+pub(crate) mod linked_issues;
+pub(crate) mod mentions;
+mod outcome;
+mod payload;
+mod review_queue;
+pub(crate) mod signature;
+pub(crate) mod sources;
+mod stale_reviews;
+pub(crate) mod threads;
 
 use axum::{
-    extract::{Json, State},
-    http::{HeaderMap, HeaderValue, StatusCode},
+    body::{Body, Bytes},
+    extract::{Json, Path, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     routing::post,
     Router,
 };
+use crate::observer;
+use crate::secrets;
 use crate::AppState;
-use handlers::{handle_pull_request_event, handle_review_requested_event, handle_workflow_run_event};
+use handlers::{
+    handle_check_run_event, handle_check_suite_event, handle_create_event, handle_delete_event,
+    handle_dependabot_alert_event, handle_deployment_event, handle_deployment_status_event,
+    handle_discussion_comment_event,
+    handle_discussion_event, handle_fork_event, handle_issue_comment_event, handle_issues_event,
+    handle_merge_group_event, handle_milestone_event, handle_project_card_event,
+    handle_project_item_event, handle_pull_request_event, handle_pull_request_queue_event,
+    handle_push_event, handle_release_event, handle_review_comment_event,
+    handle_review_requested_event, handle_review_submitted_event, handle_star_event,
+    handle_status_event, handle_vulnerability_alert_event, handle_workflow_job_event,
+    handle_workflow_run_event,
+};
+pub use handlers::start_community_digest_loop;
+pub use review_queue::start_review_queue_loop;
+pub use stale_reviews::start_stale_review_loop;
+pub use outcome::WebhookOutcome;
+use gitea::Forge;
+use payload::Envelope;
+use signature::Verification;
+use std::time::Duration;
+
+/// How long a delivery waits for its handler before it is acknowledged anyway. GitHub gives up
+/// on deliveries after 10 seconds, and queued Discord messages may wait out rate limits.
+const RESPONSE_DEADLINE: Duration = Duration::from_secs(5);
 
 pub fn routes(shared_state: AppState) -> Router {
-    Router::new().route("/github-webhook", post(dispatch_event).with_state(shared_state))
+    Router::new()
+        .route("/github-webhook", post(dispatch_event))
+        .route("/replay/:delivery", post(replay_endpoint))
+        .with_state(shared_state)
 }
 
 /// Main entry point for the GitHub webhook route.
-/// In future this can match `X-GitHub-Event` header to dispatch different handlers.
+///
+/// Reads the body within the size cap, verifies the delivery signature, skips duplicate
+/// deliveries, dispatches on the `X-GitHub-Event` header, and always answers with a
+/// [`WebhookOutcome`], so GitHub's delivery log shows which handler ran, which secret matched,
+/// and why an event was ignored. Handlers still running after [`RESPONSE_DEADLINE`] finish
+/// in the background and the delivery is acknowledged with [`WebhookOutcome::accepted`].
+///
+/// Gitea and Forgejo deliveries are accepted too (see [`gitea`]).
 async fn dispatch_event(
     headers: HeaderMap,
-    state: State<AppState>,
-    payload: Json<serde_json::Value>,
-) -> Response {
-    match headers.get("X-GitHub-Event") {
-        Some(event_type) if event_type == HeaderValue::from_static("pull_request") => {
-            let action = payload
-                .get("action")
-                .and_then(|a| a.as_str())
-                .unwrap_or_default();
-
-            match action {
-                "opened" => match serde_json::from_value(payload.0) {
-                    Ok(data) => handle_pull_request_event(State(state.0.clone()), Json(data)).await,
-                    Err(_) => StatusCode::BAD_REQUEST.into_response(),
-                },
-                "review_requested" => match serde_json::from_value(payload.0) {
-                    Ok(data) => handle_review_requested_event(State(state.0.clone()), Json(data)).await,
-                    Err(_) => StatusCode::BAD_REQUEST.into_response(),
-                },
-                _ => StatusCode::OK.into_response(),
+    State(state): State<AppState>,
+    body: Body,
+) -> WebhookOutcome {
+    let forge = Forge::detect(&headers);
+    let event = forge.event(&headers).unwrap_or_default();
+    let delivery = forge.delivery(&headers).unwrap_or_else(|| "unknown".to_string());
+
+    let body = match payload::read(&headers, body).await {
+        Ok(body) => body,
+        Err(outcome) => {
+            let reason = outcome.reason.as_deref().unwrap_or_default();
+            eprintln!("Rejected delivery {} ({}): {}", delivery, event, reason);
+            return outcome;
+        }
+    };
+
+    let envelope = match Envelope::parse(&body) {
+        Ok(envelope) => envelope,
+        Err(e) => return WebhookOutcome::bad_request("dispatch", format!("invalid JSON body: {}", e)),
+    };
+
+    let credential = match signature::verify(&headers, &body, envelope.repo()) {
+        Verification::Disabled => None,
+        Verification::Matched(label) => {
+            println!("Delivery {} ({}) verified with secret `{}`", delivery, event, label);
+            Some(label)
+        }
+        Verification::Rejected(reason) => {
+            eprintln!("Rejected delivery {} ({}): {}", delivery, event, reason);
+            return WebhookOutcome::unauthorized(reason);
+        }
+    };
+
+    let (event, body) = match gitea::normalize(&event, &body) {
+        Ok(Some((event, body))) => (event, Bytes::from(body)),
+        Ok(None) => (event, body),
+        Err(e) => return WebhookOutcome::bad_request("dispatch", format!("invalid JSON body: {}", e)),
+    };
+
+    // Only deliveries that carry an ID can be deduplicated.
+    let tracked = delivery != "unknown";
+    if tracked && !deliveries::first_sighting(&delivery) {
+        println!("Skipping duplicate delivery {} ({})", delivery, event);
+        return WebhookOutcome::ignored("dispatch", format!("duplicate delivery {}", delivery))
+            .with_credential(credential);
+    }
+
+    if tracked {
+        archive::record(&delivery, &event, body.clone());
+    }
+
+    let action = envelope.action.clone();
+    let forgotten = delivery.clone();
+    let handling = tokio::spawn(async move {
+        let outcome = route_event(&event, action.as_deref(), state, &body).await;
+        if tracked && outcome.status.is_server_error() {
+            deliveries::forget(&forgotten);
+        }
+        if !outcome.status.is_success() {
+            let reason = outcome.reason.as_deref().unwrap_or_default();
+            eprintln!("Delivery {} ({}) failed: {}", forgotten, event, reason);
+        }
+        outcome
+    });
+
+    match tokio::time::timeout(RESPONSE_DEADLINE, handling).await {
+        Ok(Ok(outcome)) => outcome.with_credential(credential),
+        Ok(Err(e)) => {
+            if tracked {
+                deliveries::forget(&delivery);
             }
+            WebhookOutcome::failed("dispatch", format!("handler crashed: {}", e)).with_credential(credential)
+        }
+        Err(_) => {
+            println!("Delivery {} still being handled; acknowledged early", delivery);
+            WebhookOutcome::accepted("dispatch").with_credential(credential)
         }
-        Some(event_type) if event_type == HeaderValue::from_static("workflow_run") => {
-            match serde_json::from_value(payload.0) {
-                Ok(data) => handle_workflow_run_event(State(state.0.clone()), Json(data)).await,
-                Err(_) => StatusCode::BAD_REQUEST.into_response(),
+    }
+}
+
+/// Runs an archived delivery through the handlers again, or `None` if it isn't archived.
+///
+/// Replays skip signature checks (the payload was verified when it arrived) and deduplication.
+pub async fn replay(state: AppState, delivery: &str) -> Option<(String, WebhookOutcome)> {
+    let archived = archive::find(delivery).await?;
+    println!(
+        "Replaying delivery {} ({}, received at {})",
+        archived.delivery, archived.event, archived.received_at
+    );
+    let envelope = match Envelope::parse(&archived.body) {
+        Ok(envelope) => envelope,
+        Err(e) => return Some((archived.event, WebhookOutcome::bad_request("dispatch", e.to_string()))),
+    };
+    let outcome = route_event(&archived.event, envelope.action.as_deref(), state, &archived.body).await;
+    Some((archived.event, outcome))
+}
+
+/// `POST /webhook/replay/<delivery_id>`: replays an archived delivery (bearer token required).
+async fn replay_endpoint(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(delivery): Path<String>,
+) -> Response {
+    let authorization = headers.get(AUTHORIZATION).and_then(|v| v.to_str().ok());
+    if let Some(refusal) = replay_refusal(archive::replay_token(), authorization, observer::enabled()) {
+        return refusal;
+    }
+
+    match replay(state, &delivery).await {
+        Some((_, outcome)) => outcome.into_response(),
+        None => (StatusCode::NOT_FOUND, format!("delivery {} is not archived", delivery)).into_response(),
+    }
+}
+
+/// The response refusing a replay request, or `None` if it may run. The endpoint is hidden
+/// without a `token`, and observer mode refuses replays, since they post and edit messages again.
+fn replay_refusal(token: Option<String>, authorization: Option<&str>, observer: bool) -> Option<Response> {
+    let token = match token {
+        Some(token) => token,
+        None => return Some(StatusCode::NOT_FOUND.into_response()),
+    };
+
+    let authorized = authorization
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|t| secrets::accepts("WEBHOOK_REPLAY_TOKEN", &token, t));
+    if !authorized {
+        return Some(StatusCode::UNAUTHORIZED.into_response());
+    }
+    observer.then(|| (StatusCode::FORBIDDEN, observer::refusal("replay webhook deliveries")).into_response())
+}
+
+/// Routes a verified payload to the handler for its event type, deserializing it straight from
+/// the raw body into the handler's event type. `action` is the envelope's, already parsed by
+/// the caller.
+async fn route_event(event: &str, action: Option<&str>, state: AppState, body: &[u8]) -> WebhookOutcome {
+    match event {
+        "pull_request" => {
+            match action.unwrap_or_default() {
+                "opened" | "closed" | "reopened" | "ready_for_review" | "converted_to_draft" | "edited"
+                | "labeled" | "synchronize" => {
+                    match serde_json::from_slice(body) {
+                        Ok(data) => handle_pull_request_event(State(state), Json(data)).await,
+                        Err(e) => WebhookOutcome::bad_request("pull_request", e.to_string()),
+                    }
+                }
+                "auto_merge_enabled" | "auto_merge_disabled" | "enqueued" | "dequeued" => {
+                    match serde_json::from_slice(body) {
+                        Ok(data) => handle_pull_request_queue_event(State(state), Json(data)).await,
+                        Err(e) => WebhookOutcome::bad_request("pull_request", e.to_string()),
+                    }
+                }
+                "review_requested" => match serde_json::from_slice(body) {
+                    Ok(data) => handle_review_requested_event(State(state), Json(data)).await,
+                    Err(e) => WebhookOutcome::bad_request("review_requested", e.to_string()),
+                },
+                other => WebhookOutcome::ignored("pull_request", format!("unsupported action `{}`", other)),
             }
         }
-        _ => StatusCode::NOT_IMPLEMENTED.into_response(),
+        "check_run" => match serde_json::from_slice(body) {
+            Ok(data) => handle_check_run_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("check_run", e.to_string()),
+        },
+        "check_suite" => match serde_json::from_slice(body) {
+            Ok(data) => handle_check_suite_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("check_suite", e.to_string()),
+        },
+        "create" => match serde_json::from_slice(body) {
+            Ok(data) => handle_create_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("create", e.to_string()),
+        },
+        "delete" => match serde_json::from_slice(body) {
+            Ok(data) => handle_delete_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("delete", e.to_string()),
+        },
+        "dependabot_alert" => match serde_json::from_slice(body) {
+            Ok(data) => handle_dependabot_alert_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("dependabot_alert", e.to_string()),
+        },
+        "deployment" => match serde_json::from_slice(body) {
+            Ok(data) => handle_deployment_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("deployment", e.to_string()),
+        },
+        "deployment_status" => match serde_json::from_slice(body) {
+            Ok(data) => handle_deployment_status_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("deployment_status", e.to_string()),
+        },
+        "issues" => match serde_json::from_slice(body) {
+            Ok(data) => handle_issues_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("issues", e.to_string()),
+        },
+        "issue_comment" => match serde_json::from_slice(body) {
+            Ok(data) => handle_issue_comment_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("issue_comment", e.to_string()),
+        },
+        "merge_group" => match serde_json::from_slice(body) {
+            Ok(data) => handle_merge_group_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("merge_group", e.to_string()),
+        },
+        "milestone" => match serde_json::from_slice(body) {
+            Ok(data) => handle_milestone_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("milestone", e.to_string()),
+        },
+        "project_card" => match serde_json::from_slice(body) {
+            Ok(data) => handle_project_card_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("project_card", e.to_string()),
+        },
+        "projects_v2_item" => match serde_json::from_slice(body) {
+            Ok(data) => handle_project_item_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("projects_v2_item", e.to_string()),
+        },
+        "pull_request_review" => match serde_json::from_slice(body) {
+            Ok(data) => handle_review_submitted_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("pull_request_review", e.to_string()),
+        },
+        "pull_request_review_comment" => match serde_json::from_slice(body) {
+            Ok(data) => handle_review_comment_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("pull_request_review_comment", e.to_string()),
+        },
+        "push" => match serde_json::from_slice(body) {
+            Ok(data) => handle_push_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("push", e.to_string()),
+        },
+        "discussion" => match serde_json::from_slice(body) {
+            Ok(data) => handle_discussion_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("discussion", e.to_string()),
+        },
+        "discussion_comment" => match serde_json::from_slice(body) {
+            Ok(data) => handle_discussion_comment_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("discussion_comment", e.to_string()),
+        },
+        "fork" => match serde_json::from_slice(body) {
+            Ok(data) => handle_fork_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("fork", e.to_string()),
+        },
+        "release" => match serde_json::from_slice(body) {
+            Ok(data) => handle_release_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("release", e.to_string()),
+        },
+        "repository_vulnerability_alert" => match serde_json::from_slice(body) {
+            Ok(data) => handle_vulnerability_alert_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("repository_vulnerability_alert", e.to_string()),
+        },
+        "star" => match serde_json::from_slice(body) {
+            Ok(data) => handle_star_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("star", e.to_string()),
+        },
+        "status" => match serde_json::from_slice(body) {
+            Ok(data) => handle_status_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("status", e.to_string()),
+        },
+        "workflow_job" => match serde_json::from_slice(body) {
+            Ok(data) => handle_workflow_job_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("workflow_job", e.to_string()),
+        },
+        // GitHub's legacy `watch` event is sent for stars too; `star` covers it.
+        "watch" => WebhookOutcome::ignored("watch", "stars are reported via the `star` event"),
+        "workflow_run" => match serde_json::from_slice(body) {
+            Ok(data) => handle_workflow_run_event(State(state), Json(data)).await,
+            Err(e) => WebhookOutcome::bad_request("workflow_run", e.to_string()),
+        },
+        other => WebhookOutcome::unsupported(other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::replay_refusal;
+    use axum::http::StatusCode;
+
+    fn status(token: Option<&str>, authorization: Option<&str>, observer: bool) -> Option<StatusCode> {
+        replay_refusal(token.map(str::to_string), authorization, observer).map(|response| response.status())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn replay_needs_the_token() {
+        assert_eq!(status(None, Some("Bearer t0ken"), false), Some(StatusCode::NOT_FOUND));
+        assert_eq!(status(Some("t0ken"), None, false), Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(status(Some("t0ken"), Some("Bearer wrong"), false), Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(status(Some("t0ken"), Some("Bearer t0ken"), false), None);
+    }
+
+    #[test]
+    fn observer_mode_refuses_replays() {
+        assert_eq!(status(Some("t0ken"), Some("Bearer t0ken"), true), Some(StatusCode::FORBIDDEN));
+        assert_eq!(status(Some("t0ken"), Some("Bearer wrong"), true), Some(StatusCode::UNAUTHORIZED));
+    }
+}
@@ -0,0 +1,199 @@
+//! Structured result returned for every GitHub webhook delivery.
+//!
+//! GitHub's "Recent Deliveries" log shows the status, headers, and body of each
+//! response, so every delivery reports whether it was handled, by which handler,
+//! and why it was ignored (unsupported event, filtered branch, duplicate, ...).
+
+use axum::{
+    http::{HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+use crate::notify::Delivery;
+
+pub const HANDLED_HEADER: &str = "x-fitchfork-handled";
+pub const HANDLER_HEADER: &str = "x-fitchfork-handler";
+pub const REASON_HEADER: &str = "x-fitchfork-reason";
+pub const CREDENTIAL_HEADER: &str = "x-fitchfork-credential";
+
+/// Outcome of a single webhook delivery, rendered as a small JSON body plus headers.
+#[derive(Debug, Serialize)]
+pub struct WebhookOutcome {
+    #[serde(skip)]
+    pub status: StatusCode,
+    pub handled: bool,
+    pub handler: Option<&'static str>,
+    pub reason: Option<String>,
+    /// Label of the webhook secret that verified the delivery, if verification is enabled.
+    pub credential: Option<String>,
+}
+
+impl WebhookOutcome {
+    /// The event was processed and a notification was posted.
+    pub fn handled(handler: &'static str) -> Self {
+        Self {
+            status: StatusCode::OK,
+            handled: true,
+            handler: Some(handler),
+            reason: None,
+            credential: None,
+        }
+    }
+
+    /// The event was understood but intentionally skipped (filtered action, branch, duplicate, ...).
+    pub fn ignored(handler: &'static str, reason: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::OK,
+            handled: false,
+            handler: Some(handler),
+            reason: Some(reason.into()),
+            credential: None,
+        }
+    }
+
+    /// No handler exists for the `X-GitHub-Event` type.
+    pub fn unsupported(event: &str) -> Self {
+        let reason = if event.is_empty() {
+            "missing X-GitHub-Event header".to_string()
+        } else {
+            format!("unsupported event `{}`", event)
+        };
+
+        Self {
+            status: StatusCode::NOT_IMPLEMENTED,
+            handled: false,
+            handler: None,
+            reason: Some(reason),
+            credential: None,
+        }
+    }
+
+    /// The payload could not be deserialized for the selected handler.
+    pub fn bad_request(handler: &'static str, reason: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            handled: false,
+            handler: Some(handler),
+            reason: Some(reason.into()),
+            credential: None,
+        }
+    }
+
+    /// The delivery's signature could not be verified against any configured secret.
+    pub fn unauthorized(reason: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::UNAUTHORIZED,
+            handled: false,
+            handler: None,
+            reason: Some(reason.into()),
+            credential: None,
+        }
+    }
+
+    /// The request body is larger than the configured limit.
+    pub fn too_large(reason: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+            handled: false,
+            handler: None,
+            reason: Some(reason.into()),
+            credential: None,
+        }
+    }
+
+    /// Outcome of a notification sent through the [`crate::notify`] service.
+    pub fn from_delivery(handler: &'static str, delivery: Delivery) -> Self {
+        match delivery {
+            Delivery::Sent => Self::handled(handler),
+            Delivery::Fallback => {
+                let mut outcome = Self::handled(handler);
+                outcome.reason = Some("delivered via fallback webhook".into());
+                outcome
+            }
+            Delivery::Queued => Self::queued(handler),
+            Delivery::Grouped => {
+                let mut outcome = Self::handled(handler);
+                outcome.reason = Some("grouped into a digest".into());
+                outcome
+            }
+            Delivery::Held => {
+                let mut outcome = Self::handled(handler);
+                outcome.reason = Some("held for the low-priority digest".into());
+                outcome
+            }
+            Delivery::Unroutable(reason) => Self::ignored(handler, reason),
+            Delivery::Failed(reason) => Self::failed(handler, reason),
+        }
+    }
+
+    /// Tags the outcome with the label of the secret that verified the delivery.
+    pub fn with_credential(mut self, label: Option<String>) -> Self {
+        self.credential = label;
+        self
+    }
+
+    /// Discord is not connected yet; the notification is queued until it is.
+    pub fn queued(handler: &'static str) -> Self {
+        Self {
+            status: StatusCode::ACCEPTED,
+            handled: true,
+            handler: Some(handler),
+            reason: Some("queued until the Discord gateway connects".into()),
+            credential: None,
+        }
+    }
+
+    /// The handler is still delivering the event to Discord when the response is due.
+    pub fn accepted(handler: &'static str) -> Self {
+        Self {
+            status: StatusCode::ACCEPTED,
+            handled: true,
+            handler: Some(handler),
+            reason: Some("still delivering to Discord".into()),
+            credential: None,
+        }
+    }
+
+    /// The handler accepted the event but could not deliver it to Discord.
+    pub fn failed(handler: &'static str, reason: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            handled: false,
+            handler: Some(handler),
+            reason: Some(reason.into()),
+            credential: None,
+        }
+    }
+}
+
+impl IntoResponse for WebhookOutcome {
+    fn into_response(self) -> Response {
+        let status = self.status;
+        let handled = self.handled;
+        let handler = self.handler;
+        let reason = self.reason.clone();
+        let credential = self.credential.clone();
+
+        let mut response = (status, Json(self)).into_response();
+        let headers = response.headers_mut();
+
+        headers.insert(
+            HeaderName::from_static(HANDLED_HEADER),
+            HeaderValue::from_static(if handled { "true" } else { "false" }),
+        );
+        if let Some(handler) = handler {
+            headers.insert(HeaderName::from_static(HANDLER_HEADER), HeaderValue::from_static(handler));
+        }
+        // Reasons may contain payload data; drop the header rather than fail if it isn't valid ASCII.
+        if let Some(value) = reason.and_then(|r| HeaderValue::from_str(&r).ok()) {
+            headers.insert(HeaderName::from_static(REASON_HEADER), value);
+        }
+        if let Some(value) = credential.and_then(|c| HeaderValue::from_str(&c).ok()) {
+            headers.insert(HeaderName::from_static(CREDENTIAL_HEADER), value);
+        }
+
+        response
+    }
+}
@@ -0,0 +1,127 @@
+//! Reading webhook request bodies within a size cap.
+//!
+//! Push events with many commits can be megabytes, so the body is never turned into a
+//! `serde_json::Value` up front: only the few envelope fields needed for routing and signature
+//! verification are read from it, and each handler deserializes its own event type directly
+//! from the raw bytes, skipping everything it doesn't use.
+//!
+//! Environment Variables:
+//! - `GITHUB_WEBHOOK_MAX_BYTES`: Largest accepted request body; larger deliveries are answered
+//!   with 413 (default 26214400, GitHub's own 25 MB payload cap)
+
+use axum::{
+    body::{self, Body, Bytes},
+    http::{header::CONTENT_LENGTH, HeaderMap},
+};
+use serde::Deserialize;
+use std::env;
+
+use super::WebhookOutcome;
+
+const DEFAULT_MAX_BYTES: usize = 25 * 1024 * 1024;
+
+/// Fields shared by every GitHub event that are needed before a handler is picked.
+#[derive(Debug, Default, Deserialize)]
+pub struct Envelope {
+    pub action: Option<String>,
+    pub repository: Option<EnvelopeRepository>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EnvelopeRepository {
+    pub full_name: String,
+}
+
+impl Envelope {
+    /// Reads the envelope fields, ignoring the rest of the payload.
+    pub fn parse(body: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(body)
+    }
+
+    pub fn repo(&self) -> Option<&str> {
+        self.repository.as_ref().map(|r| r.full_name.as_str())
+    }
+}
+
+pub fn max_bytes() -> usize {
+    env::var("GITHUB_WEBHOOK_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|max| *max > 0)
+        .unwrap_or(DEFAULT_MAX_BYTES)
+}
+
+/// Reads the request body, or a 413 outcome if it is larger than `GITHUB_WEBHOOK_MAX_BYTES`.
+pub async fn read(headers: &HeaderMap, body: Body) -> Result<Bytes, WebhookOutcome> {
+    read_within(headers, body, max_bytes()).await
+}
+
+/// Same as [`read`], with an explicit limit of `max` bytes.
+async fn read_within(headers: &HeaderMap, body: Body, max: usize) -> Result<Bytes, WebhookOutcome> {
+    // GitHub always sends Content-Length, so oversized deliveries are refused before reading.
+    let declared = headers
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    if let Some(length) = declared.filter(|length| *length > max) {
+        return Err(WebhookOutcome::too_large(format!(
+            "body of {} bytes exceeds the {} byte limit",
+            length, max
+        )));
+    }
+
+    // Reading stops as soon as the limit is passed; the only other failure is the sender
+    // going away mid-body, in which case nobody reads the response.
+    body::to_bytes(body, max)
+        .await
+        .map_err(|e| WebhookOutcome::too_large(format!("body exceeds the {} byte limit: {}", max, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    const MAX: usize = 64;
+
+    fn headers(length: Option<usize>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(length) = length {
+            headers.insert(CONTENT_LENGTH, length.into());
+        }
+        headers
+    }
+
+    async fn read_bytes(declared: Option<usize>, length: usize) -> Result<Bytes, WebhookOutcome> {
+        read_within(&headers(declared), Body::from(vec![b'x'; length]), MAX).await
+    }
+
+    #[tokio::test]
+    async fn read_accepts_a_body_at_the_limit() {
+        assert_eq!(read_bytes(Some(MAX), MAX).await.unwrap().len(), MAX);
+        assert_eq!(read_bytes(None, MAX).await.unwrap().len(), MAX);
+    }
+
+    #[tokio::test]
+    async fn read_refuses_a_declared_oversized_body() {
+        let outcome = read_bytes(Some(MAX + 1), MAX + 1).await.unwrap_err();
+        assert_eq!(outcome.status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn read_cuts_off_an_undeclared_or_understated_body() {
+        for declared in [None, Some(1)] {
+            let outcome = read_bytes(declared, MAX + 1).await.unwrap_err();
+            assert_eq!(outcome.status, StatusCode::PAYLOAD_TOO_LARGE);
+        }
+    }
+
+    #[test]
+    fn envelope_reads_only_routing_fields() {
+        let body = br#"{"action":"opened","repository":{"full_name":"a/b","id":1},"x":[1]}"#;
+        let envelope = Envelope::parse(body).unwrap();
+        assert_eq!(envelope.action.as_deref(), Some("opened"));
+        assert_eq!(envelope.repo(), Some("a/b"));
+        assert_eq!(Envelope::parse(b"{}").unwrap().repo(), None);
+    }
+}
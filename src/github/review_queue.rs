@@ -0,0 +1,230 @@
+//! Morning review-queue report for standup.
+//!
+//! On the configured weekdays, posts every open pull request of each repository in
+//! `REVIEW_QUEUE_REPOS` to its reviews channel, oldest first, with its age, requested reviewers,
+//! review decision, and CI state, so reviews can be triaged without opening GitHub. Reviewers
+//! are named by GitHub login rather than mentioned, so the daily report pings nobody (see
+//! [`super::stale_reviews`] for reminders that do).
+//!
+//! Environment Variables:
+//! - `REVIEW_QUEUE_REPOS`: Comma-separated `owner/repo`s to report on (default:
+//!   `STALE_REVIEW_REPOS`)
+//! - `REVIEW_QUEUE_HOUR`: Local hour to post at, 0-23 (default: 9)
+//! - `REVIEW_QUEUE_DAYS`: Comma-separated weekdays to post on (default: `mon,tue,wed,thu,fri`)
+
+use chrono::{DateTime, Datelike, Local, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{env, time::Duration};
+use tokio::time::sleep;
+
+use super::client;
+use super::handlers::deliver;
+use super::stale_reviews::reviewer_name;
+use crate::guilds::{self, ChannelKind};
+use crate::store;
+use crate::tasks::Tasks;
+use crate::AppState;
+
+const STATE_FILE: &str = "review_queue.json";
+const CHECK_INTERVAL: Duration = Duration::from_secs(600);
+/// Room left below Discord's 2000-character limit per report message.
+const MAX_MESSAGE_CHARS: usize = 1900;
+const WEEKDAYS: &[Weekday] = &[Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri];
+
+const QUERY: &str = "query($owner: String!, $name: String!) {
+  repository(owner: $owner, name: $name) {
+    pullRequests(states: OPEN, first: 100, orderBy: {field: CREATED_AT, direction: ASC}) {
+      totalCount
+      nodes {
+        number title url isDraft createdAt reviewDecision
+        author { login }
+        reviewRequests(first: 20) {
+          nodes { requestedReviewer { ... on User { login } ... on Team { combinedSlug } } }
+        }
+        commits(last: 1) { nodes { commit { statusCheckRollup { state } } } }
+      }
+    }
+  }
+}";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct QueueState {
+    /// Local date (`YYYY-MM-DD`) the report was last posted.
+    last_posted: Option<String>,
+}
+
+fn repos() -> Vec<String> {
+    env::var("REVIEW_QUEUE_REPOS")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .or_else(|| env::var("STALE_REVIEW_REPOS").ok())
+        .unwrap_or_default()
+        .split(',')
+        .map(|r| r.trim().to_string())
+        .filter(|r| r.contains('/'))
+        .collect()
+}
+
+fn report_hour() -> u32 {
+    env::var("REVIEW_QUEUE_HOUR")
+        .ok()
+        .and_then(|h| h.trim().parse().ok())
+        .filter(|h| *h < 24)
+        .unwrap_or(9)
+}
+
+fn report_days() -> Vec<Weekday> {
+    let days: Vec<Weekday> = env::var("REVIEW_QUEUE_DAYS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|d| d.trim().parse().ok())
+        .collect();
+    if days.is_empty() {
+        WEEKDAYS.to_vec()
+    } else {
+        days
+    }
+}
+
+fn ci_state(pr: &Value) -> &'static str {
+    match pr["commits"]["nodes"][0]["commit"]["statusCheckRollup"]["state"].as_str() {
+        Some("SUCCESS") => "CI ✅",
+        Some("FAILURE") | Some("ERROR") => "CI ❌",
+        Some("PENDING") | Some("EXPECTED") => "CI ⏳",
+        _ => "no CI",
+    }
+}
+
+fn review_state(pr: &Value) -> Option<&'static str> {
+    match pr["reviewDecision"].as_str() {
+        Some("APPROVED") => Some("approved"),
+        Some("CHANGES_REQUESTED") => Some("changes requested"),
+        _ => None,
+    }
+}
+
+/// One line per pull request.
+fn describe(pr: &Value) -> String {
+    let opened = pr["createdAt"]
+        .as_str()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| format!("opened <t:{}:R>", t.timestamp()))
+        .unwrap_or_else(|| "opened at an unknown time".to_string());
+
+    let reviewers: Vec<String> = pr["reviewRequests"]["nodes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|request| reviewer_name(&request["requestedReviewer"]))
+        .map(|reviewer| format!("`{}`", reviewer))
+        .collect();
+    let reviewers = if reviewers.is_empty() {
+        "no reviewers requested".to_string()
+    } else {
+        format!("waiting on {}", reviewers.join(", "))
+    };
+
+    let author = format!("by `{}`", pr["author"]["login"].as_str().unwrap_or("ghost"));
+    let mut parts = vec![opened, author, reviewers];
+    parts.extend(review_state(pr).map(str::to_string));
+    parts.push(ci_state(pr).to_string());
+
+    format!(
+        "- {}[#{} {}](<{}>): {}",
+        if pr["isDraft"].as_bool().unwrap_or(false) { "📝 " } else { "" },
+        pr["number"].as_u64().unwrap_or_default(),
+        pr["title"].as_str().unwrap_or_default(),
+        pr["url"].as_str().unwrap_or_default(),
+        parts.join(" · ")
+    )
+}
+
+/// `repo`'s report, split into messages under Discord's length limit.
+async fn report_messages(repo: &str) -> Result<Vec<String>, String> {
+    let (owner, name) = repo.split_once('/').ok_or_else(|| format!("`{}` is not owner/repo", repo))?;
+    let data = client::graphql(QUERY, json!({ "owner": owner, "name": name })).await?;
+    let pull_requests = &data["repository"]["pullRequests"];
+    let prs = pull_requests["nodes"].as_array().cloned().unwrap_or_default();
+    let total = pull_requests["totalCount"].as_u64().unwrap_or(prs.len() as u64);
+
+    let mut messages = vec![format!("📋 **Review queue for {}** ({} open)", repo, total)];
+    if prs.is_empty() {
+        messages[0].push_str("\nNo open pull requests. 🎉");
+        return Ok(messages);
+    }
+    let mut lines: Vec<String> = prs.iter().map(describe).collect();
+    if total > prs.len() as u64 {
+        lines.push(format!("- …and {} newer", total - prs.len() as u64));
+    }
+    for line in lines {
+        let current = messages.last_mut().unwrap();
+        if current.len() + line.len() + 1 > MAX_MESSAGE_CHARS {
+            messages.push(line);
+        } else {
+            current.push('\n');
+            current.push_str(&line);
+        }
+    }
+    Ok(messages)
+}
+
+/// Posts the report for every repository.
+async fn post_report(state: &AppState) {
+    for repo in repos() {
+        let channel_id = match guilds::github_channel(&repo, ChannelKind::Reviews) {
+            Some(id) => id,
+            None => {
+                eprintln!("No review channel configured for {}, skipping its review queue", repo);
+                continue;
+            }
+        };
+        match report_messages(&repo).await {
+            Ok(messages) => {
+                for message in messages {
+                    deliver(state, "review_queue", channel_id, message).await;
+                }
+            }
+            Err(e) => eprintln!("Failed to build the review queue of {}: {}", repo, e),
+        }
+    }
+}
+
+/// Spawns the background task that posts the review queue on the configured mornings.
+pub async fn start_review_queue_loop(state: AppState) {
+    if repos().is_empty() {
+        return;
+    }
+    if !client::authenticated() {
+        eprintln!("REVIEW_QUEUE_REPOS is set but no GitHub credentials are configured, no review queue.");
+        return;
+    }
+    if state.tasks.is_running("review_queue") {
+        println!("Review queue loop already running, reusing it.");
+        return;
+    }
+
+    let tasks: Tasks = state.tasks.clone();
+    tasks.spawn("review_queue", move |beat| {
+        let state = state.clone();
+        async move {
+            loop {
+                beat.tick();
+                let now = Local::now();
+                let today = now.format("%Y-%m-%d").to_string();
+                let mut queue: QueueState = store::load(STATE_FILE);
+
+                let due = report_days().contains(&now.weekday())
+                    && now.hour() >= report_hour()
+                    && queue.last_posted.as_deref() != Some(today.as_str());
+                if due {
+                    post_report(&state).await;
+                    queue.last_posted = Some(today);
+                    store::save(STATE_FILE, &queue);
+                }
+
+                sleep(CHECK_INTERVAL).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,356 @@
+//! Verification of GitHub's `X-Hub-Signature-256` header against one or more shared secrets.
+//! Gitea and Forgejo deliveries are checked the same way using their own signature header.
+//!
+//! Several secrets can be configured at once (one per repository, per environment, or an
+//! old and a new secret during rotation). Each candidate is tried in turn and the label of
+//! the matching secret is reported back so the delivery can be tagged with it.
+//!
+//! Environment Variables:
+//! - `GITHUB_WEBHOOK_SECRET`: Secret with the label `default`
+//! - `GITHUB_WEBHOOK_SECRET_<LABEL>`: Additional secrets, labelled by the suffix
+//! - `GITHUB_WEBHOOK_REPO_SECRETS`: Optional per-repo selection, e.g.
+//!   `fitch-fork/backend=backend|backend_next,fitch-fork/frontend=frontend`
+//! - `GITHUB_SOURCE_<NAME>_SECRET`: A webhook source's secret, labelled `<name>` and only
+//!   accepted for the source's repositories (see [`super::sources`])
+//!
+//! A secret rotated with `/rotate-secret` keeps accepting its old value during the grace
+//! period (see [`crate::secrets`]).
+//!
+//! If no secret is configured, verification is disabled and every delivery is accepted.
+
+use axum::http::HeaderMap;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::env;
+
+use super::{gitea, sources};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const SECRET_ENV: &str = "GITHUB_WEBHOOK_SECRET";
+const SECRET_PREFIX: &str = "GITHUB_WEBHOOK_SECRET_";
+const REPO_SECRETS_ENV: &str = "GITHUB_WEBHOOK_REPO_SECRETS";
+
+/// A configured webhook secret and the label it is reported under.
+#[derive(Debug, Clone)]
+pub struct WebhookSecret {
+    pub label: String,
+    pub secret: String,
+}
+
+/// Result of checking a delivery's signature.
+#[derive(Debug)]
+pub enum Verification {
+    /// No secrets are configured, so deliveries are not verified.
+    Disabled,
+    /// The signature matched the secret with this label.
+    Matched(String),
+    /// The delivery must be rejected, with the reason why.
+    Rejected(String),
+}
+
+/// Loads every configured secret from the environment, `default` first.
+pub fn configured_secrets() -> Vec<WebhookSecret> {
+    let mut secrets = Vec::new();
+
+    if let Some(secret) = crate::secrets::current(SECRET_ENV) {
+        secrets.push(WebhookSecret { label: "default".into(), secret });
+    }
+
+    let mut labelled: Vec<WebhookSecret> = env::vars()
+        .filter_map(|(key, _)| {
+            let label = key.strip_prefix(SECRET_PREFIX)?;
+            if label.is_empty() {
+                return None;
+            }
+            let secret = crate::secrets::current(&key)?;
+            Some(WebhookSecret { label: label.to_lowercase(), secret })
+        })
+        .collect();
+    labelled.sort_by(|a, b| a.label.cmp(&b.label));
+    secrets.extend(labelled);
+
+    secrets.extend(
+        sources::all()
+            .into_iter()
+            .map(|source| WebhookSecret { label: source.name, secret: source.secret }),
+    );
+
+    // Old values of rotated secrets stay valid, under the same label, until their grace
+    // period ends.
+    let retiring: Vec<WebhookSecret> = crate::secrets::retiring()
+        .into_iter()
+        .filter_map(|(name, secret)| Some(WebhookSecret { label: label_for(&name)?, secret }))
+        .filter(|old| secrets.iter().any(|s| s.label == old.label))
+        .collect();
+    secrets.extend(retiring);
+
+    secrets
+}
+
+/// The label a secret variable is reported under, or `None` if it isn't a webhook secret.
+pub(crate) fn label_for(name: &str) -> Option<String> {
+    if name == SECRET_ENV {
+        return Some("default".to_string());
+    }
+    name.strip_prefix(SECRET_PREFIX)
+        .or_else(|| name.strip_prefix("GITHUB_SOURCE_")?.strip_suffix("_SECRET"))
+        .filter(|label| !label.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Which secrets each repository's deliveries may be signed with.
+#[derive(Debug, Clone, Default)]
+pub struct SecretRouting {
+    /// `GITHUB_WEBHOOK_REPO_SECRETS`, e.g. `owner/repo=label|label,...`.
+    pub repo_secrets: String,
+    pub sources: Vec<sources::Source>,
+}
+
+impl SecretRouting {
+    pub fn from_env() -> Self {
+        Self {
+            repo_secrets: env::var(REPO_SECRETS_ENV).unwrap_or_default(),
+            sources: sources::all(),
+        }
+    }
+
+    /// Returns the secret labels allowed for `repo`, if the repo has an explicit selection or
+    /// belongs to a webhook source.
+    fn labels_for_repo(&self, repo: &str) -> Option<Vec<String>> {
+        self.explicit_labels(repo).or_else(|| {
+            let source = self.sources.iter().find(|source| source.claims_repo(repo))?;
+            Some(vec![source.name.clone()])
+        })
+    }
+
+    fn explicit_labels(&self, repo: &str) -> Option<Vec<String>> {
+        self.repo_secrets.split(',').find_map(|entry| {
+            let (name, labels) = entry.trim().split_once('=')?;
+            if !name.trim().eq_ignore_ascii_case(repo) {
+                return None;
+            }
+            Some(
+                labels
+                    .split('|')
+                    .map(|l| l.trim().to_lowercase())
+                    .filter(|l| !l.is_empty())
+                    .collect(),
+            )
+        })
+    }
+}
+
+/// Repositories whose deliveries are signed with the secret labelled `label`, as far as the
+/// configuration says: a source's repositories, or those selecting it explicitly.
+pub(crate) fn repos_for_label(label: &str) -> Vec<String> {
+    if let Some(source) = sources::all().into_iter().find(|source| source.name == label) {
+        return source.repos;
+    }
+    env::var(REPO_SECRETS_ENV)
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|entry| entry.trim().split_once('='))
+        .filter(|(_, labels)| labels.split('|').any(|l| l.trim().eq_ignore_ascii_case(label)))
+        .map(|(repo, _)| repo.trim().to_string())
+        .collect()
+}
+
+/// Checks `body` against `X-Hub-Signature-256` using every secret eligible for `repo`.
+pub fn verify(headers: &HeaderMap, body: &[u8], repo: Option<&str>) -> Verification {
+    verify_with(&configured_secrets(), &SecretRouting::from_env(), headers, body, repo)
+}
+
+/// Same as [`verify`], but against an explicit list of candidate secrets and routing.
+pub fn verify_with(
+    secrets: &[WebhookSecret],
+    routing: &SecretRouting,
+    headers: &HeaderMap,
+    body: &[u8],
+    repo: Option<&str>,
+) -> Verification {
+    if secrets.is_empty() {
+        return Verification::Disabled;
+    }
+
+    // A source's secret is only valid for its own repositories.
+    let allowed = repo.and_then(|repo| routing.labels_for_repo(repo));
+    let candidates: Vec<&WebhookSecret> = secrets
+        .iter()
+        .filter(|s| allowed.as_ref().is_none_or(|labels| labels.contains(&s.label)))
+        .filter(|s| {
+            let source = routing.sources.iter().find(|source| source.name == s.label);
+            source.is_none_or(|source| repo.is_some_and(|repo| source.claims_repo(repo)))
+        })
+        .collect();
+
+    if candidates.is_empty() {
+        return Verification::Rejected(format!(
+            "no webhook secret configured for repository `{}`",
+            repo.unwrap_or_default()
+        ));
+    }
+
+    let signature = match headers
+        .get("X-Hub-Signature-256")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("sha256="))
+        .and_then(|hex_sig| hex::decode(hex_sig).ok())
+        .or_else(|| gitea::signature(headers))
+    {
+        Some(sig) => sig,
+        None => return Verification::Rejected("missing or malformed signature header".into()),
+    };
+
+    for candidate in candidates {
+        let mut mac = HmacSha256::new_from_slice(candidate.secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(body);
+        if mac.verify_slice(&signature).is_ok() {
+            return Verification::Matched(candidate.label.clone());
+        }
+    }
+
+    Verification::Rejected("signature did not match any configured secret".into())
+}
+
+/// Values from `.env.example` that were never replaced.
+const PLACEHOLDERS: &[&str] = &["your_webhook_secret_here", "another_secret", "rotated_secret", "change_me"];
+
+/// Checks the secret configuration for mistakes that would reject every delivery (for
+/// `/selftest`): placeholder secrets, and per-repo selections naming labels that don't exist.
+/// Returns `Ok(None)` when verification is disabled.
+pub fn self_check() -> Result<Option<String>, String> {
+    let secrets = configured_secrets();
+    if secrets.is_empty() {
+        return Ok(None);
+    }
+
+    let placeholders: Vec<&str> = secrets
+        .iter()
+        .filter(|s| PLACEHOLDERS.contains(&s.secret.as_str()))
+        .map(|s| s.label.as_str())
+        .collect();
+    if !placeholders.is_empty() {
+        return Err(format!("placeholder secret for `{}`", placeholders.join("`, `")));
+    }
+
+    let mapping = env::var(REPO_SECRETS_ENV).unwrap_or_default();
+    let unknown: Vec<String> = mapping
+        .split(',')
+        .filter_map(|entry| entry.split_once('=').map(|(_, labels)| labels))
+        .flat_map(|labels| labels.split('|'))
+        .map(|label| label.trim().to_lowercase())
+        .filter(|label| !label.is_empty() && !secrets.iter().any(|s| s.label == *label))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(format!("{} names unknown secret `{}`", REPO_SECRETS_ENV, unknown.join("`, `")));
+    }
+
+    let labels: Vec<&str> = secrets.iter().map(|s| s.label.as_str()).collect();
+    Ok(Some(format!("{} secret(s): {}", labels.len(), labels.join(", "))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &[u8] = br#"{"action":"opened"}"#;
+
+    fn secret(label: &str, secret: &str) -> WebhookSecret {
+        WebhookSecret { label: label.into(), secret: secret.into() }
+    }
+
+    fn signed_with(secret: &str) -> HeaderMap {
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(BODY);
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Hub-Signature-256", signature.parse().unwrap());
+        headers
+    }
+
+    fn matched(verification: Verification) -> Option<String> {
+        match verification {
+            Verification::Matched(label) => Some(label),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn no_secrets_disables_verification() {
+        let verification = verify_with(&[], &SecretRouting::default(), &HeaderMap::new(), BODY, None);
+        assert!(matches!(verification, Verification::Disabled));
+    }
+
+    #[test]
+    fn reports_the_matching_label() {
+        let secrets = [secret("default", "first"), secret("next", "second")];
+        let first = matched(verify_with(&secrets, &SecretRouting::default(), &signed_with("first"), BODY, None));
+        assert_eq!(first.as_deref(), Some("default"));
+        let second = matched(verify_with(&secrets, &SecretRouting::default(), &signed_with("second"), BODY, None));
+        assert_eq!(second.as_deref(), Some("next"));
+    }
+
+    #[test]
+    fn rejects_unknown_secret_and_tampered_body() {
+        let secrets = [secret("default", "first")];
+        let verification = verify_with(&secrets, &SecretRouting::default(), &signed_with("other"), BODY, None);
+        assert!(matches!(verification, Verification::Rejected(_)));
+        let verification = verify_with(&secrets, &SecretRouting::default(), &signed_with("first"), b"{}", None);
+        assert!(matches!(verification, Verification::Rejected(_)));
+    }
+
+    #[test]
+    fn rejects_missing_or_malformed_header() {
+        let secrets = [secret("default", "first")];
+        assert!(matches!(verify_with(&secrets, &SecretRouting::default(), &HeaderMap::new(), BODY, None), Verification::Rejected(_)));
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Hub-Signature-256", "sha256=not-hex".parse().unwrap());
+        assert!(matches!(verify_with(&secrets, &SecretRouting::default(), &headers, BODY, None), Verification::Rejected(_)));
+    }
+
+    #[test]
+    fn repo_selection_limits_the_candidates() {
+        let routing = SecretRouting {
+            repo_secrets: "fitch-fork/backend=backend|backend_next".into(),
+            ..SecretRouting::default()
+        };
+        let secrets = [
+            secret("default", "first"),
+            secret("backend", "second"),
+            secret("backend_next", "third"),
+        ];
+        let verify = |signed: &str, repo: &str| {
+            verify_with(&secrets, &routing, &signed_with(signed), BODY, Some(repo))
+        };
+
+        assert_eq!(matched(verify("third", "fitch-fork/backend")).as_deref(), Some("backend_next"));
+        assert!(matches!(verify("first", "fitch-fork/backend"), Verification::Rejected(_)));
+        // Repositories without a selection accept every secret.
+        assert_eq!(matched(verify("first", "fitch-fork/frontend")).as_deref(), Some("default"));
+    }
+
+    #[test]
+    fn source_secret_only_accepted_for_its_repositories() {
+        let routing = SecretRouting {
+            sources: vec![sources::Source {
+                name: "partner".into(),
+                secret: "source-secret".into(),
+                repos: vec!["partner-org/*".into()],
+            }],
+            ..SecretRouting::default()
+        };
+        let secrets = [secret("default", "first"), secret("partner", "source-secret")];
+        let headers = signed_with("source-secret");
+        let verify = |repo| verify_with(&secrets, &routing, &headers, BODY, repo);
+
+        assert_eq!(matched(verify(Some("partner-org/app"))).as_deref(), Some("partner"));
+        // Elsewhere, and without a repository, only the other secrets are candidates.
+        assert!(matches!(verify(Some("fitch-fork/backend")), Verification::Rejected(_)));
+        assert!(matches!(verify(None), Verification::Rejected(_)));
+        // The source's repositories only accept the source's secret.
+        let other = verify_with(&secrets, &routing, &signed_with("first"), BODY, Some("partner-org/app"));
+        assert!(matches!(other, Verification::Rejected(_)));
+    }
+}
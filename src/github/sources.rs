@@ -0,0 +1,88 @@
+//! Webhook sources: several GitHub organizations or accounts served by one bot instance.
+//!
+//! A source is a set of repositories (e.g. everything in `fitch-fork/*`, or a few personal
+//! forks) with its own webhook secret and its own notification channels. The source's secret
+//! is only accepted for its repositories, and only its secret is accepted for them, so a
+//! personal fork's secret can't be used to post as the organization. Notifications for its
+//! repositories go to its channels instead of the global ones.
+//!
+//! Guild records that claim a repository (see [`crate::guilds`]) still take precedence for
+//! routing.
+//!
+//! Environment Variables:
+//! - `GITHUB_SOURCE_<NAME>_SECRET`: The source's webhook secret
+//! - `GITHUB_SOURCE_<NAME>_REPOS`: Comma-separated repositories (`owner/name` or `owner/*`)
+//! - `GITHUB_SOURCE_<NAME>_CHANNEL_ID`: Channel for all of the source's notifications
+//! - `GITHUB_SOURCE_<NAME>_<KIND>_CHANNEL_ID`: Channel for one kind, overriding the above;
+//!   `<KIND>` is a channel key such as `PR`, `WORKFLOW`, or `PUSH`
+//! - `GITHUB_SOURCE_<NAME>_DEV_ROLE_ID`: Role mentioned instead of `DISCORD_DEV_ROLE_ID`
+
+use std::env;
+
+use crate::guilds::{self, ChannelKind};
+
+const PREFIX: &str = "GITHUB_SOURCE_";
+
+/// A configured webhook source.
+#[derive(Debug, Clone)]
+pub struct Source {
+    /// Lowercased `<NAME>`, also the label its secret is reported under.
+    pub name: String,
+    pub secret: String,
+    pub repos: Vec<String>,
+}
+
+impl Source {
+    fn var(&self, suffix: &str) -> Option<String> {
+        env::var(format!("{}{}_{}", PREFIX, self.name.to_uppercase(), suffix))
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    }
+
+    pub fn claims_repo(&self, repo: &str) -> bool {
+        self.repos.iter().any(|pattern| guilds::repo_matches(pattern, repo))
+    }
+
+    /// The source's channel for `kind`, falling back to its default channel.
+    pub fn channel(&self, kind: ChannelKind) -> Option<u64> {
+        self.var(&format!("{}_CHANNEL_ID", kind.key().to_uppercase()))
+            .or_else(|| self.var("CHANNEL_ID"))
+            .and_then(|v| v.parse().ok())
+    }
+
+    pub fn dev_role(&self) -> Option<u64> {
+        self.var("DEV_ROLE_ID").and_then(|v| v.parse().ok())
+    }
+}
+
+/// Every source with both a secret and repositories, sorted by name.
+pub fn all() -> Vec<Source> {
+    let mut sources: Vec<Source> = env::vars()
+        .filter_map(|(key, _)| {
+            let name = key.strip_prefix(PREFIX)?.strip_suffix("_SECRET")?;
+            if name.is_empty() {
+                return None;
+            }
+            let secret = crate::secrets::current(&key)?;
+            let repos: Vec<String> = env::var(format!("{}{}_REPOS", PREFIX, name))
+                .unwrap_or_default()
+                .split(',')
+                .map(|r| r.trim().to_string())
+                .filter(|r| !r.is_empty())
+                .collect();
+            if repos.is_empty() {
+                eprintln!("Ignoring webhook source `{}`: {}{}_REPOS is not set", name, PREFIX, name);
+                return None;
+            }
+            Some(Source { name: name.to_lowercase(), secret, repos })
+        })
+        .collect();
+    sources.sort_by(|a, b| a.name.cmp(&b.name));
+    sources
+}
+
+/// The first source (by name) whose repositories include `repo`.
+pub fn for_repo(repo: &str) -> Option<Source> {
+    all().into_iter().find(|source| source.claims_repo(repo))
+}
@@ -0,0 +1,247 @@
+//! Daily reminders about pull requests waiting too long for review.
+//!
+//! Once a day, every repository in `STALE_REVIEW_REPOS` is queried through the GitHub API for
+//! open pull requests with review requests older than `STALE_REVIEW_DAYS`. Each repository with
+//! any gets one reminder in its reviews channel, listing the pull requests and mentioning the
+//! requested reviewers linked with `/link_github` (others are named by GitHub login). Draft pull
+//! requests are skipped. A review request's age is the time since it was last (re-)requested.
+//!
+//! Environment Variables:
+//! - `STALE_REVIEW_REPOS`: Comma-separated `owner/repo`s to check (none by default)
+//! - `STALE_REVIEW_DAYS`: Days a review request may wait before it is reminded about (default: 3)
+//! - `STALE_REVIEW_HOUR`: Local hour to post the reminders at, 0-23 (default: 9)
+
+use chrono::{DateTime, Local, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{collections::HashMap, env, time::Duration};
+use tokio::time::sleep;
+
+use super::client;
+use super::handlers::deliver;
+use super::mentions::discord_mention_for;
+use crate::guilds::{self, ChannelKind};
+use crate::store;
+use crate::tasks::Tasks;
+use crate::AppState;
+
+const STATE_FILE: &str = "stale_reviews.json";
+const CHECK_INTERVAL: Duration = Duration::from_secs(600);
+const DEFAULT_DAYS: i64 = 3;
+/// Room left below Discord's 2000-character limit per reminder message.
+const MAX_MESSAGE_CHARS: usize = 1900;
+
+const QUERY: &str = "query($owner: String!, $name: String!) {
+  repository(owner: $owner, name: $name) {
+    pullRequests(states: OPEN, first: 100, orderBy: {field: CREATED_AT, direction: ASC}) {
+      nodes {
+        number title url isDraft
+        reviewRequests(first: 20) {
+          nodes { requestedReviewer { ... on User { login } ... on Team { combinedSlug } } }
+        }
+        timelineItems(itemTypes: [REVIEW_REQUESTED_EVENT], last: 50) {
+          nodes {
+            ... on ReviewRequestedEvent {
+              createdAt
+              requestedReviewer { ... on User { login } ... on Team { combinedSlug } }
+            }
+          }
+        }
+      }
+    }
+  }
+}";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ReminderState {
+    /// Local date (`YYYY-MM-DD`) the reminders were last posted.
+    last_posted: Option<String>,
+}
+
+/// A pull request with review requests older than the threshold.
+struct StalePr {
+    number: u64,
+    title: String,
+    url: String,
+    /// Stale reviewers as `(GitHub login or org/team, requested at)`, oldest first.
+    reviewers: Vec<(String, i64)>,
+}
+
+fn repos() -> Vec<String> {
+    env::var("STALE_REVIEW_REPOS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|r| r.trim().to_string())
+        .filter(|r| r.contains('/'))
+        .collect()
+}
+
+fn stale_days() -> i64 {
+    env::var("STALE_REVIEW_DAYS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|d| *d > 0)
+        .unwrap_or(DEFAULT_DAYS)
+}
+
+fn reminder_hour() -> u32 {
+    env::var("STALE_REVIEW_HOUR")
+        .ok()
+        .and_then(|h| h.trim().parse().ok())
+        .filter(|h| *h < 24)
+        .unwrap_or(9)
+}
+
+/// A requested reviewer's GitHub login, or `org/team` for a team.
+pub(super) fn reviewer_name(reviewer: &Value) -> Option<String> {
+    reviewer["login"]
+        .as_str()
+        .or_else(|| reviewer["combinedSlug"].as_str())
+        .map(str::to_string)
+}
+
+/// `repo`'s open pull requests with review requests made before `cutoff`.
+async fn stale_prs(repo: &str, cutoff: i64) -> Result<Vec<StalePr>, String> {
+    let (owner, name) = repo.split_once('/').ok_or_else(|| format!("`{}` is not owner/repo", repo))?;
+    let data = client::graphql(QUERY, json!({ "owner": owner, "name": name })).await?;
+    let nodes = data["repository"]["pullRequests"]["nodes"].as_array().cloned().unwrap_or_default();
+
+    let mut stale = Vec::new();
+    for pr in nodes.iter().filter(|pr| !pr["isDraft"].as_bool().unwrap_or(false)) {
+        // When each reviewer was last requested; re-requesting restarts the clock.
+        let mut requested_at: HashMap<String, i64> = HashMap::new();
+        for event in pr["timelineItems"]["nodes"].as_array().into_iter().flatten() {
+            let at = event["createdAt"].as_str().and_then(|t| DateTime::parse_from_rfc3339(t).ok());
+            if let (Some(reviewer), Some(at)) = (reviewer_name(&event["requestedReviewer"]), at) {
+                requested_at.insert(reviewer, at.timestamp());
+            }
+        }
+
+        let mut reviewers: Vec<(String, i64)> = pr["reviewRequests"]["nodes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|request| reviewer_name(&request["requestedReviewer"]))
+            .filter_map(|reviewer| {
+                let at = *requested_at.get(&reviewer)?;
+                (at < cutoff).then_some((reviewer, at))
+            })
+            .collect();
+        if reviewers.is_empty() {
+            continue;
+        }
+        reviewers.sort_by_key(|(_, at)| *at);
+
+        stale.push(StalePr {
+            number: pr["number"].as_u64().unwrap_or_default(),
+            title: pr["title"].as_str().unwrap_or_default().to_string(),
+            url: pr["url"].as_str().unwrap_or_default().to_string(),
+            reviewers,
+        });
+    }
+    Ok(stale)
+}
+
+fn describe(pr: &StalePr) -> String {
+    let reviewers = pr
+        .reviewers
+        .iter()
+        .map(|(reviewer, _)| match discord_mention_for(reviewer) {
+            Some(mention) => mention,
+            None => format!("`{}`", reviewer),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let oldest = pr.reviewers[0].1;
+    format!(
+        "- [#{} {}](<{}>): requested <t:{}:R> from {}",
+        pr.number, pr.title, pr.url, oldest, reviewers
+    )
+}
+
+/// The reminder for `repo`, split into messages under Discord's length limit.
+fn reminder_messages(repo: &str, prs: &[StalePr], days: i64) -> Vec<String> {
+    let mut messages = vec![format!(
+        "⏰ **Waiting for review in {}** (requested over {} day(s) ago):",
+        repo, days
+    )];
+    for line in prs.iter().map(describe) {
+        let current = messages.last_mut().unwrap();
+        if current.len() + line.len() + 1 > MAX_MESSAGE_CHARS {
+            messages.push(line);
+        } else {
+            current.push('\n');
+            current.push_str(&line);
+        }
+    }
+    messages
+}
+
+/// Posts the reminders for every repository.
+async fn post_reminders(state: &AppState) {
+    let days = stale_days();
+    let cutoff = Utc::now().timestamp() - days * 86_400;
+
+    for repo in repos() {
+        let channel_id = match guilds::github_channel(&repo, ChannelKind::Reviews) {
+            Some(id) => id,
+            None => {
+                eprintln!("No review channel configured for {}, skipping its review reminders", repo);
+                continue;
+            }
+        };
+        let prs = match stale_prs(&repo, cutoff).await {
+            Ok(prs) => prs,
+            Err(e) => {
+                eprintln!("Failed to check {} for stale review requests: {}", repo, e);
+                continue;
+            }
+        };
+        if prs.is_empty() {
+            continue;
+        }
+
+        println!("Reminding about {} pull request(s) waiting for review in {}", prs.len(), repo);
+        for message in reminder_messages(&repo, &prs, days) {
+            deliver(state, "stale_review", channel_id, message).await;
+        }
+    }
+}
+
+/// Spawns the background task that posts the review reminders once a day.
+pub async fn start_stale_review_loop(state: AppState) {
+    if repos().is_empty() {
+        return;
+    }
+    if !client::authenticated() {
+        eprintln!("STALE_REVIEW_REPOS is set but no GitHub credentials are configured, no review reminders.");
+        return;
+    }
+    if state.tasks.is_running("stale_reviews") {
+        println!("Stale review loop already running, reusing it.");
+        return;
+    }
+
+    let tasks: Tasks = state.tasks.clone();
+    tasks.spawn("stale_reviews", move |beat| {
+        let state = state.clone();
+        async move {
+            loop {
+                beat.tick();
+                let now = Local::now();
+                let today = now.format("%Y-%m-%d").to_string();
+                let mut reminders: ReminderState = store::load(STATE_FILE);
+
+                let due = now.hour() >= reminder_hour() && reminders.last_posted.as_deref() != Some(today.as_str());
+                // Repositories that failed aren't retried the same day, so nobody is pinged twice.
+                if due {
+                    post_reminders(&state).await;
+                    reminders.last_posted = Some(today);
+                    store::save(STATE_FILE, &reminders);
+                }
+
+                sleep(CHECK_INTERVAL).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,248 @@
+//! Per-PR discussion threads in the PR channel.
+//!
+//! When enabled, each PR gets a public thread under `DISCORD_PR_CHANNEL_ID`. Comments and
+//! state transitions (draft, ready for review, merged, ...) are posted into it, and the
+//! thread name carries a status emoji, e.g. `🟢 #123 Fix marking race`. Threads are renamed
+//! when the PR's title is edited so they stay findable as PRs evolve.
+//! Threads are persisted in `pr_threads.json` so they survive restarts.
+//!
+//! Environment Variables:
+//! - `PR_COMMENT_THREADS`: Set to `true` to post PR comments and transitions into per-PR threads
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serenity::{model::id::ChannelId, prelude::Context};
+use std::{collections::BTreeMap, env};
+use tokio::sync::Mutex;
+
+use crate::notify::{self, Outgoing, Priority};
+use crate::store;
+use crate::AppState;
+
+const THREADS_FILE: &str = "pr_threads.json";
+/// Discord's limit on thread names.
+const MAX_THREAD_NAME_CHARS: usize = 100;
+/// Auto-archive after a week of inactivity; posting into the thread unarchives it.
+const AUTO_ARCHIVE_MINUTES: u16 = 10080;
+
+/// Lifecycle state of a PR, shown as the thread's emoji prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrState {
+    #[default]
+    Open,
+    Draft,
+    Merged,
+    Closed,
+}
+
+impl PrState {
+    pub fn emoji(self) -> &'static str {
+        match self {
+            PrState::Open => "🟢",
+            PrState::Draft => "📝",
+            PrState::Merged => "🟣",
+            PrState::Closed => "⚫",
+        }
+    }
+}
+
+/// A PR's thread and the state and title its name currently reflects.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PrThread {
+    thread_id: u64,
+    #[serde(default)]
+    state: PrState,
+    #[serde(default)]
+    title: String,
+}
+
+/// `owner/repo#number` → thread. Held across thread creation so concurrent deliveries for
+/// the same PR don't create duplicate threads.
+static PR_THREADS: Lazy<Mutex<BTreeMap<String, PrThread>>> =
+    Lazy::new(|| Mutex::new(store::load(THREADS_FILE)));
+
+pub fn enabled() -> bool {
+    env::var("PR_COMMENT_THREADS")
+        .map(|v| v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+fn thread_key(repo: &str, number: u64) -> String {
+    format!("{}#{}", repo, number)
+}
+
+/// IDs of every tracked PR thread.
+pub async fn thread_ids() -> Vec<u64> {
+    PR_THREADS.lock().await.values().map(|t| t.thread_id).collect()
+}
+
+/// A tracked PR thread, as listed by [`tracked_threads`].
+pub struct TrackedThread {
+    /// `owner/repo#number`.
+    pub key: String,
+    pub title: String,
+    pub state: PrState,
+    pub thread_id: u64,
+}
+
+/// Every tracked PR thread, by repository and number.
+pub async fn tracked_threads() -> Vec<TrackedThread> {
+    PR_THREADS
+        .lock()
+        .await
+        .iter()
+        .map(|(key, thread)| TrackedThread {
+            key: key.clone(),
+            title: thread.title.clone(),
+            state: thread.state,
+            thread_id: thread.thread_id,
+        })
+        .collect()
+}
+
+/// Thread name in the form `<emoji> #<number> <title>`, cutting long titles at a word boundary.
+fn thread_name(pr_state: PrState, number: u64, title: &str) -> String {
+    let prefix = format!("{} #{} ", pr_state.emoji(), number);
+    let budget = MAX_THREAD_NAME_CHARS - prefix.chars().count();
+    let title = title.trim();
+
+    if title.chars().count() <= budget {
+        return format!("{}{}", prefix, title);
+    }
+
+    let cut: String = title.chars().take(budget - 1).collect();
+    let cut = match cut.rfind(' ') {
+        Some(pos) if pos > cut.len() / 2 => &cut[..pos],
+        _ => cut.as_str(),
+    };
+    format!("{}{}…", prefix, cut.trim_end())
+}
+
+async fn rename_thread(ctx: &Context, thread_id: u64, name: String, key: &str) {
+    if let Err(e) = ChannelId(thread_id).edit_thread(&ctx.http, |t| t.name(name)).await {
+        eprintln!("Failed to rename thread for {}: {e:?}", key);
+    }
+}
+
+/// Creates the thread for a PR in `parent`, starting from a short starter message.
+async fn create_thread(ctx: &Context, parent: u64, key: &str, name: &str) -> Option<u64> {
+    let parent = ChannelId(parent);
+
+    let starter = format!("🧵 Discussion for **{}**", key);
+    let starter = match notify::post(ctx, parent.0, Priority::Normal, Outgoing::text(starter)).await {
+        Ok(message) => message,
+        Err(e) => {
+            eprintln!("Failed to post thread starter for {}: {}", key, e);
+            return None;
+        }
+    };
+
+    match parent
+        .create_public_thread(&ctx.http, starter.id, |t| {
+            t.name(name).auto_archive_duration(AUTO_ARCHIVE_MINUTES)
+        })
+        .await
+    {
+        Ok(thread) => Some(thread.id.0),
+        Err(e) => {
+            eprintln!("Failed to create thread for {}: {e:?}", key);
+            None
+        }
+    }
+}
+
+/// Returns the thread for a PR, creating it in `parent` if it doesn't exist yet.
+///
+/// Returns `None` when the gateway is unavailable or the thread can't be created, in which
+/// case callers should post to the parent channel instead.
+pub async fn pr_thread(state: &AppState, parent: u64, repo: &str, number: u64, title: &str) -> Option<u64> {
+    let key = thread_key(repo, number);
+    let mut threads = PR_THREADS.lock().await;
+    if let Some(thread) = threads.get(&key) {
+        return Some(thread.thread_id);
+    }
+
+    let ctx = notify::discord_ctx(state)?;
+    let thread_id = create_thread(&ctx, parent, &key, &thread_name(PrState::Open, number, title)).await?;
+
+    threads.insert(
+        key,
+        PrThread {
+            thread_id,
+            state: PrState::Open,
+            title: title.to_string(),
+        },
+    );
+    store::save(THREADS_FILE, &*threads);
+    Some(thread_id)
+}
+
+/// Posts a state transition into the PR's thread (creating it if needed) and renames the
+/// thread when its status emoji changes.
+pub async fn record_transition(
+    state: &AppState,
+    parent: u64,
+    repo: &str,
+    number: u64,
+    title: &str,
+    pr_state: PrState,
+    note: &str,
+) {
+    let ctx = match notify::discord_ctx(state) {
+        Some(ctx) => ctx,
+        None => return,
+    };
+    let key = thread_key(repo, number);
+    let mut threads = PR_THREADS.lock().await;
+
+    let (thread_id, previous) = match threads.get(&key) {
+        Some(thread) => (thread.thread_id, Some(thread_name(thread.state, number, &thread.title))),
+        None => match create_thread(&ctx, parent, &key, &thread_name(pr_state, number, title)).await {
+            Some(id) => (id, None),
+            None => return,
+        },
+    };
+
+    if let Err(e) = notify::post(&ctx, thread_id, Priority::Normal, Outgoing::text(note)).await {
+        eprintln!("Failed to post transition into thread for {}: {}", key, e);
+    }
+
+    let name = thread_name(pr_state, number, title);
+    if previous.is_some_and(|p| p != name) {
+        rename_thread(&ctx, thread_id, name, &key).await;
+    }
+
+    threads.insert(
+        key,
+        PrThread {
+            thread_id,
+            state: pr_state,
+            title: title.to_string(),
+        },
+    );
+    store::save(THREADS_FILE, &*threads);
+}
+
+/// Renames a PR's existing thread after its title was edited. PRs without a thread are ignored.
+pub async fn rename_for_title(state: &AppState, repo: &str, number: u64, title: &str) -> bool {
+    let ctx = match notify::discord_ctx(state) {
+        Some(ctx) => ctx,
+        None => return false,
+    };
+    let key = thread_key(repo, number);
+    let mut threads = PR_THREADS.lock().await;
+
+    let thread = match threads.get_mut(&key) {
+        Some(thread) => thread,
+        None => return false,
+    };
+    if thread.title == title {
+        return false;
+    }
+
+    rename_thread(&ctx, thread.thread_id, thread_name(thread.state, number, title), &key).await;
+    thread.title = title.to_string();
+    store::save(THREADS_FILE, &*threads);
+    true
+}
@@ -0,0 +1,386 @@
+//! Persisted per-guild configuration.
+//!
+//! Each guild the bot serves gets a record in `guilds.json` with its own notification
+//! channels, roles, and allowlists, so one instance can serve the production guild and a
+//! sandbox guild with different settings.
+//!
+//! GitHub events are routed to the first guild whose `repos` patterns match the event's
+//! repository, then to the webhook source that claims it (see [`crate::github::sources`]).
+//! Repositories neither claims, and guilds without a record, fall back to the global
+//! environment variables (`DISCORD_PR_CHANNEL_ID`, `DISCORD_DEV_ROLE_ID`, ...). The operators'
+//! guild (`DISCORD_GUILD_ID`) is checked first; other guilds may only claim repositories in
+//! `DISCORD_CLAIMABLE_REPOS`, so none can take production's notifications away.
+//!
+//! File and watch allowlists are deny-by-default: a guild may only use the aliases its record
+//! lists, except the operators' guild, which may use every alias until it restricts itself.
+//!
+//! A guild's notifications only use the global fallback and mirror webhooks
+//! (`DISCORD_FALLBACK_WEBHOOK_URL`, `NOTIFY_MIRROR_WEBHOOK_URLS`) in the operators' guild;
+//! other guilds use the ones in their record, if any (see [`guild_for_channel`]).
+//!
+//! The records are loaded once and kept in memory; every change is saved under the same lock,
+//! so concurrent edits (e.g. the setup wizard and `/guild-config`) can't overwrite each other.
+//!
+//! Environment Variables:
+//! - `DISCORD_GUILD_ID`: The operators' (production) guild
+//! - `DISCORD_CLAIMABLE_REPOS`: Comma-separated repositories (`owner/name` or `owner/*`) other
+//!   guilds may claim (default: none)
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, env, sync::Mutex};
+
+use crate::github::sources;
+use crate::routing;
+use crate::store;
+
+const GUILDS_FILE: &str = "guilds.json";
+
+static CONFIGS: Lazy<Mutex<GuildConfigs>> = Lazy::new(|| Mutex::new(store::load(GUILDS_FILE)));
+
+/// Notification channels a guild can configure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelKind {
+    Status,
+    Alerts,
+    PullRequests,
+    Reviews,
+    Workflows,
+    Issues,
+    Pushes,
+    Announcements,
+    Deployments,
+    Community,
+    Security,
+    Discussions,
+    Planning,
+}
+
+impl ChannelKind {
+    pub const ALL: &'static [ChannelKind] = &[
+        ChannelKind::Status,
+        ChannelKind::Alerts,
+        ChannelKind::PullRequests,
+        ChannelKind::Reviews,
+        ChannelKind::Workflows,
+        ChannelKind::Issues,
+        ChannelKind::Pushes,
+        ChannelKind::Announcements,
+        ChannelKind::Deployments,
+        ChannelKind::Community,
+        ChannelKind::Security,
+        ChannelKind::Discussions,
+        ChannelKind::Planning,
+    ];
+
+    /// Key used in `guilds.json` and slash command choices.
+    pub fn key(self) -> &'static str {
+        match self {
+            ChannelKind::Status => "status",
+            ChannelKind::Alerts => "alerts",
+            ChannelKind::PullRequests => "pr",
+            ChannelKind::Reviews => "review",
+            ChannelKind::Workflows => "workflow",
+            ChannelKind::Issues => "issues",
+            ChannelKind::Pushes => "push",
+            ChannelKind::Announcements => "announcements",
+            ChannelKind::Deployments => "deploy",
+            ChannelKind::Community => "community",
+            ChannelKind::Security => "security",
+            ChannelKind::Discussions => "discussions",
+            ChannelKind::Planning => "planning",
+        }
+    }
+
+    /// Global environment variable used when no guild record applies.
+    pub fn env_var(self) -> &'static str {
+        match self {
+            ChannelKind::Status => "DISCORD_STATUS_CHANNEL_ID",
+            ChannelKind::Alerts => "PERMISSION_ALERT_CHANNEL_ID",
+            ChannelKind::PullRequests => "DISCORD_PR_CHANNEL_ID",
+            ChannelKind::Reviews => "DISCORD_REVIEW_CHANNEL_ID",
+            ChannelKind::Workflows => "DISCORD_WORKFLOW_CHANNEL_ID",
+            ChannelKind::Issues => "DISCORD_ISSUES_CHANNEL_ID",
+            ChannelKind::Pushes => "DISCORD_PUSH_CHANNEL_ID",
+            ChannelKind::Announcements => "DISCORD_ANNOUNCEMENTS_CHANNEL_ID",
+            ChannelKind::Deployments => "DISCORD_DEPLOY_CHANNEL_ID",
+            ChannelKind::Community => "DISCORD_COMMUNITY_CHANNEL_ID",
+            ChannelKind::Security => "DISCORD_SECURITY_CHANNEL_ID",
+            ChannelKind::Discussions => "DISCORD_DISCUSSIONS_CHANNEL_ID",
+            ChannelKind::Planning => "DISCORD_PLANNING_CHANNEL_ID",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|kind| kind.key() == key)
+    }
+}
+
+/// Settings for a single guild.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GuildConfig {
+    /// Notification channel IDs keyed by [`ChannelKind::key`].
+    #[serde(default)]
+    pub channels: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub admin_role_ids: Vec<u64>,
+    /// Role mentioned in PR and workflow failure notifications.
+    #[serde(default)]
+    pub dev_role_id: Option<u64>,
+    /// GitHub repositories routed to this guild (`owner/name`, or `owner/*` for a whole org).
+    #[serde(default)]
+    pub repos: Vec<String>,
+    /// `FETCH_FILE_` aliases usable in this guild (`None` = none, or every allowlisted file in
+    /// the operators' guild).
+    #[serde(default)]
+    pub file_aliases: Option<Vec<String>>,
+    /// `WATCH_COMMAND_` aliases usable in this guild (`None` = none, or every allowlisted
+    /// command in the operators' guild).
+    #[serde(default)]
+    pub watch_aliases: Option<Vec<String>>,
+    /// Webhook posting this guild's notifications while the gateway is down.
+    #[serde(default)]
+    pub fallback_webhook_url: Option<String>,
+    /// Webhooks receiving a copy of this guild's notifications.
+    #[serde(default)]
+    pub mirror_webhook_urls: Vec<String>,
+    /// Whether the setup wizard has been completed (or skipped for a pre-existing guild).
+    #[serde(default)]
+    pub onboarded: bool,
+}
+
+impl GuildConfig {
+    pub fn channel(&self, kind: ChannelKind) -> Option<u64> {
+        self.channels.get(kind.key()).copied()
+    }
+
+    pub fn set_channel(&mut self, kind: ChannelKind, channel_id: Option<u64>) {
+        match channel_id {
+            Some(id) => self.channels.insert(kind.key().to_string(), id),
+            None => self.channels.remove(kind.key()),
+        };
+    }
+
+    fn claims_repo(&self, repo: &str) -> bool {
+        self.repos.iter().any(|pattern| repo_matches(pattern, repo))
+    }
+}
+
+/// Whether `repo` matches `pattern` (`owner/name`, or `owner/*` for a whole org).
+pub fn repo_matches(pattern: &str, repo: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(owner) => repo
+            .split_once('/')
+            .is_some_and(|(repo_owner, _)| repo_owner.eq_ignore_ascii_case(owner)),
+        None => pattern.eq_ignore_ascii_case(repo),
+    }
+}
+
+/// Guild configurations persisted in `guilds.json`, keyed by guild ID.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct GuildConfigs {
+    #[serde(default)]
+    pub guilds: BTreeMap<u64, GuildConfig>,
+}
+
+impl GuildConfigs {
+    /// Runs `f` on every guild's record.
+    pub fn read<T>(f: impl FnOnce(&GuildConfigs) -> T) -> T {
+        f(&CONFIGS.lock().unwrap())
+    }
+
+    /// Runs `f` on every guild's record and saves the result.
+    pub fn update<T>(f: impl FnOnce(&mut GuildConfigs) -> T) -> T {
+        let mut configs = CONFIGS.lock().unwrap();
+        let result = f(&mut configs);
+        store::save(GUILDS_FILE, &*configs);
+        result
+    }
+
+    /// The guild GitHub events for `repo` are routed to, if any guild claims it.
+    pub fn guild_for_repo(&self, repo: &str) -> Option<(u64, &GuildConfig)> {
+        let home = home_guild();
+        let home_claim = home
+            .and_then(|id| Some((id, self.guilds.get(&id)?)))
+            .filter(|(_, config)| config.claims_repo(repo));
+        home_claim.or_else(|| {
+            if !is_claimable(repo) {
+                return None;
+            }
+            self.guilds
+                .iter()
+                .find(|(id, config)| Some(**id) != home && config.claims_repo(repo))
+                .map(|(id, config)| (*id, config))
+        })
+    }
+}
+
+fn env_id(key: &str) -> Option<u64> {
+    env::var(key).ok().and_then(|v| v.trim().parse().ok())
+}
+
+/// The operators' guild (`DISCORD_GUILD_ID`).
+pub fn home_guild() -> Option<u64> {
+    env_id("DISCORD_GUILD_ID")
+}
+
+fn claimable_repos() -> Vec<String> {
+    env::var("DISCORD_CLAIMABLE_REPOS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+        .collect()
+}
+
+pub fn is_claimable(repo: &str) -> bool {
+    claimable_repos().iter().any(|pattern| repo_matches(pattern, repo))
+}
+
+/// Whether a guild other than the operators' may claim `pattern`, i.e. every repository it
+/// matches is in `DISCORD_CLAIMABLE_REPOS`.
+pub fn pattern_claimable(pattern: &str) -> bool {
+    claimable_repos().iter().any(|allowed| pattern_covers(allowed, pattern))
+}
+
+/// Whether every repository matching `pattern` also matches `allowed`.
+fn pattern_covers(allowed: &str, pattern: &str) -> bool {
+    match allowed.strip_suffix("/*") {
+        Some(owner) => pattern
+            .split_once('/')
+            .is_some_and(|(pattern_owner, _)| pattern_owner.eq_ignore_ascii_case(owner)),
+        None => !pattern.ends_with("/*") && allowed.eq_ignore_ascii_case(pattern),
+    }
+}
+
+/// The record for `guild_id`, if the guild has one.
+pub fn for_guild(guild_id: u64) -> Option<GuildConfig> {
+    GuildConfigs::read(|configs| configs.guilds.get(&guild_id).cloned())
+}
+
+/// Whether `alias` from the `FETCH_FILE_` allowlist may be used in `guild_id`.
+pub fn file_alias_allowed(guild_id: Option<u64>, alias: &str) -> bool {
+    alias_allowed(guild_id, alias, |config| config.file_aliases.as_ref())
+}
+
+/// Whether `alias` from the `WATCH_COMMAND_` allowlist may be used in `guild_id`.
+pub fn watch_alias_allowed(guild_id: Option<u64>, alias: &str) -> bool {
+    alias_allowed(guild_id, alias, |config| config.watch_aliases.as_ref())
+}
+
+fn alias_allowed(
+    guild_id: Option<u64>,
+    alias: &str,
+    list: impl Fn(&GuildConfig) -> Option<&Vec<String>>,
+) -> bool {
+    let guild_id = match guild_id {
+        Some(id) => id,
+        None => return false,
+    };
+    let aliases = GuildConfigs::read(|configs| configs.guilds.get(&guild_id).and_then(|c| list(c).cloned()));
+    match aliases {
+        Some(aliases) => aliases.iter().any(|a| a.eq_ignore_ascii_case(alias)),
+        None => Some(guild_id) == home_guild(),
+    }
+}
+
+/// Channel for `repo`'s notifications of `kind`.
+///
+/// A provisioned module routing `repo` (see [`crate::routing`]) comes first, as the most
+/// specific route; kinds it has no channel for fall through to the guild, source or global
+/// channel. A guild or webhook source that claims `repo` but has no such channel gets nothing,
+/// rather than leaking its events into the globally configured channel.
+pub fn github_channel(repo: &str, kind: ChannelKind) -> Option<u64> {
+    if let Some(channel) = routing::module_channel(repo, kind) {
+        return Some(channel);
+    }
+    if let Some(channel) = GuildConfigs::read(|configs| Some(configs.guild_for_repo(repo)?.1.channel(kind))) {
+        return channel;
+    }
+    match sources::for_repo(repo) {
+        Some(source) => source.channel(kind),
+        None => env_id(kind.env_var()),
+    }
+}
+
+/// The guild that claims `repo` in `guilds.json`, if any.
+pub fn owning_guild(repo: &str) -> Option<u64> {
+    GuildConfigs::read(|configs| configs.guild_for_repo(repo).map(|(id, _)| id))
+}
+
+/// The guild `channel_id` belongs to, if it is one of a guild's configured channels or a
+/// provisioned module's. Other channels (from the environment) are the operators'.
+pub fn guild_for_channel(channel_id: u64) -> Option<u64> {
+    let configured = GuildConfigs::read(|configs| {
+        configs
+            .guilds
+            .iter()
+            .find(|(_, config)| config.channels.values().any(|id| *id == channel_id))
+            .map(|(id, _)| *id)
+    });
+    configured.or_else(|| routing::module_guild(channel_id))
+}
+
+/// Whether `guild_id` is a guild other than the operators'.
+pub fn is_foreign(guild_id: Option<u64>) -> bool {
+    guild_id.is_some_and(|id| Some(id) != home_guild())
+}
+
+/// Role to mention for `repo`'s notifications (`DISCORD_DEV_ROLE_ID` if no guild or webhook
+/// source claims it).
+pub fn github_dev_role(repo: &str) -> Option<u64> {
+    if let Some(role) = GuildConfigs::read(|configs| Some(configs.guild_for_repo(repo)?.1.dev_role_id)) {
+        return role;
+    }
+    match sources::for_repo(repo) {
+        Some(source) => source.dev_role(),
+        None => env_id("DISCORD_DEV_ROLE_ID"),
+    }
+}
+
+/// Every guild's channel of `kind`, excluding the globally configured one.
+pub fn guild_channels(kind: ChannelKind) -> Vec<u64> {
+    let global = env_id(kind.env_var());
+    let mut ids: Vec<u64> = GuildConfigs::read(|configs| {
+        configs
+            .guilds
+            .values()
+            .filter_map(|config| config.channel(kind))
+            .filter(|id| Some(*id) != global)
+            .collect()
+    });
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pattern_covers, repo_matches};
+
+    #[test]
+    fn repo_matches_exact_name_ignoring_case() {
+        assert!(repo_matches("fitch-fork/backend", "fitch-fork/backend"));
+        assert!(repo_matches("Fitch-Fork/Backend", "fitch-fork/backend"));
+        assert!(!repo_matches("fitch-fork/backend", "fitch-fork/frontend"));
+        assert!(!repo_matches("fitch-fork/backend", "other/backend"));
+    }
+
+    #[test]
+    fn repo_matches_whole_org() {
+        assert!(repo_matches("fitch-fork/*", "fitch-fork/backend"));
+        assert!(repo_matches("FITCH-FORK/*", "fitch-fork/frontend"));
+        assert!(!repo_matches("fitch-fork/*", "fitch-forks/backend"));
+        assert!(!repo_matches("fitch-fork/*", "fitch-fork"));
+    }
+
+    #[test]
+    fn claims_must_stay_within_the_allowed_pattern() {
+        assert!(pattern_covers("alice/*", "alice/*"));
+        assert!(pattern_covers("alice/*", "Alice/sandbox"));
+        assert!(pattern_covers("alice/sandbox", "alice/sandbox"));
+        assert!(!pattern_covers("alice/sandbox", "alice/*"));
+        assert!(!pattern_covers("alice/*", "fitch-fork/*"));
+        assert!(!pattern_covers("alice/sandbox", "fitch-fork/backend"));
+    }
+}
@@ -0,0 +1,18 @@
+//! Shared outbound HTTP client for talking to services other than Discord
+//! (external monitors, webhook URLs, third-party APIs).
+
+use once_cell::sync::Lazy;
+use std::time::Duration;
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .user_agent(concat!("fitchfork-discord-bot/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .expect("Failed to build HTTP client")
+});
+
+/// Returns the process-wide HTTP client (connection pooled, 10s timeout).
+pub fn client() -> &'static reqwest::Client {
+    &CLIENT
+}
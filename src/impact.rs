@@ -0,0 +1,91 @@
+//! Current user impact, appended to API-down and marking-stalled alerts.
+//!
+//! When the API goes down (uptime monitor), its 5xx rate spikes, or the synthetic submission
+//! probe finds marking stalled, the alert includes how many students are affected right now:
+//! active sessions, submissions waiting to be marked, and the next assignment deadline. The
+//! figures come from a FitchFork stats endpoint; if it can't be reached the alert says so
+//! instead of waiting on it.
+//!
+//! Environment Variables:
+//! - `IMPACT_STATS_URL`: JSON endpoint with the figures; impact is not reported when unset
+//! - `IMPACT_STATS_TOKEN`: Optional bearer token for the endpoint
+//! - `IMPACT_SESSIONS_FIELD`, `IMPACT_PENDING_FIELD`, `IMPACT_DEADLINE_FIELD`: JSON pointers to
+//!   the figures (defaults: `/data/active_sessions`, `/data/pending_submissions`,
+//!   `/data/next_deadline`). The deadline may be an RFC 3339 string or a unix timestamp.
+
+use chrono::DateTime;
+use serde_json::Value;
+use std::{env, time::Duration};
+
+/// Alerts shouldn't wait long on a service that may be down too.
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+fn pointer(key: &str, default: &str) -> String {
+    env::var(key)
+        .ok()
+        .map(|p| p.trim().to_string())
+        .filter(|p| p.starts_with('/'))
+        .unwrap_or_else(|| default.to_string())
+}
+
+/// Unix timestamp of a deadline given as RFC 3339 or seconds.
+fn deadline_timestamp(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) => n.as_i64(),
+        Value::String(s) => DateTime::parse_from_rfc3339(s).ok().map(|t| t.timestamp()),
+        _ => None,
+    }
+}
+
+async fn fetch_stats(url: &str) -> Result<Value, String> {
+    let mut request = crate::http::client().get(url).timeout(TIMEOUT);
+    if let Some(token) = env::var("IMPACT_STATS_TOKEN").ok().filter(|t| !t.is_empty()) {
+        request = request.bearer_auth(token);
+    }
+    let res = request.send().await.map_err(|e| e.to_string())?;
+    if !res.status().is_success() {
+        return Err(format!("stats endpoint returned {}", res.status()));
+    }
+    res.json().await.map_err(|e| e.to_string())
+}
+
+/// A one-line impact summary to append to an alert, or `None` if impact isn't configured.
+pub async fn summary() -> Option<String> {
+    let url = env::var("IMPACT_STATS_URL").ok().map(|u| u.trim().to_string()).filter(|u| !u.is_empty())?;
+
+    let stats = match fetch_stats(&url).await {
+        Ok(stats) => stats,
+        Err(e) => {
+            eprintln!("Failed to fetch impact stats: {}", e);
+            return Some("👥 Impact: unavailable (stats endpoint unreachable)".to_string());
+        }
+    };
+
+    let count = |key: &str, default: &str| stats.pointer(&pointer(key, default)).and_then(Value::as_u64);
+    let mut parts = Vec::new();
+    if let Some(sessions) = count("IMPACT_SESSIONS_FIELD", "/data/active_sessions") {
+        parts.push(format!("{} active sessions", sessions));
+    }
+    if let Some(pending) = count("IMPACT_PENDING_FIELD", "/data/pending_submissions") {
+        parts.push(format!("{} submissions pending", pending));
+    }
+    if let Some(deadline) = stats
+        .pointer(&pointer("IMPACT_DEADLINE_FIELD", "/data/next_deadline"))
+        .and_then(deadline_timestamp)
+    {
+        parts.push(format!("next deadline <t:{}:R>", deadline));
+    }
+
+    if parts.is_empty() {
+        return Some("👥 Impact: unavailable (no figures in the stats response)".to_string());
+    }
+    Some(format!("👥 Impact: {}", parts.join(", ")))
+}
+
+/// `message` with the impact summary on a new line, if impact is configured.
+pub async fn append(message: String) -> String {
+    match summary().await {
+        Some(impact) => format!("{}\n{}", message, impact),
+        None => message,
+    }
+}
@@ -0,0 +1,148 @@
+//! Jenkins build notifications, for jobs that haven't moved to GitHub Actions yet.
+//!
+//! Point the Jenkins Notification plugin (JSON format, HTTP) at
+//! `POST /webhook/jenkins?token=<JENKINS_WEBHOOK_TOKEN>`. Completed builds are posted to the
+//! workflow channel with their result, duration, and console link. Builds whose SCM URL is a
+//! GitHub repository go to that repository's workflow channel, like GitHub Actions runs.
+//!
+//! Environment Variables:
+//! - `JENKINS_WEBHOOK_TOKEN`: Token required in the `token` query parameter
+//! - `JENKINS_CHANNEL_ID`: Channel for builds not tied to a GitHub repository
+//!   (default: `DISCORD_WORKFLOW_CHANNEL_ID`)
+
+use axum::{
+    extract::{Json, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use serde::Deserialize;
+use std::{env, time::Duration};
+
+use crate::duration::format_duration;
+use crate::github::WebhookOutcome;
+use crate::guilds::ChannelKind;
+use crate::notify::{self, Destination};
+use crate::ops_events::{self, EventKind};
+use crate::secrets;
+use crate::AppState;
+
+const HANDLER: &str = "jenkins";
+
+pub fn routes(shared_state: AppState) -> Router {
+    Router::new()
+        .route("/jenkins", post(handle_jenkins_webhook))
+        .with_state(shared_state)
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+/// Notification plugin payload.
+#[derive(Debug, Deserialize)]
+pub struct JenkinsEvent {
+    /// Job name.
+    pub name: String,
+    pub build: Build,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Build {
+    pub number: u64,
+    /// `STARTED`, `COMPLETED`, or `FINALIZED`.
+    pub phase: String,
+    /// `SUCCESS`, `UNSTABLE`, `FAILURE`, `NOT_BUILT`, or `ABORTED`; absent while running.
+    pub status: Option<String>,
+    pub full_url: Option<String>,
+    /// Milliseconds.
+    pub duration: Option<u64>,
+    pub scm: Option<Scm>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Scm {
+    pub url: Option<String>,
+    pub branch: Option<String>,
+    pub commit: Option<String>,
+}
+
+async fn handle_jenkins_webhook(
+    State(state): State<AppState>,
+    Query(query): Query<TokenQuery>,
+    Json(payload): Json<JenkinsEvent>,
+) -> Response {
+    let token = match secrets::current("JENKINS_WEBHOOK_TOKEN") {
+        Some(token) => token,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    if !query.token.as_deref().is_some_and(|t| secrets::accepts("JENKINS_WEBHOOK_TOKEN", &token, t)) {
+        eprintln!("Rejected Jenkins notification for `{}`: missing or invalid token", payload.name);
+        return WebhookOutcome::unauthorized("missing or invalid token").into_response();
+    }
+
+    handle_build(&state, payload).await.into_response()
+}
+
+async fn handle_build(state: &AppState, payload: JenkinsEvent) -> WebhookOutcome {
+    let build = &payload.build;
+    // The plugin reports a build several times; COMPLETED is the first with the result.
+    if !build.phase.eq_ignore_ascii_case("completed") {
+        return WebhookOutcome::ignored(HANDLER, format!("phase `{}` is not `COMPLETED`", build.phase));
+    }
+
+    let status = build.status.as_deref().unwrap_or("UNKNOWN").to_uppercase();
+    let summary = format!("Jenkins {} #{}", payload.name, build.number);
+    match status.as_str() {
+        "SUCCESS" => ops_events::record(EventKind::CiSuccess, summary),
+        "FAILURE" | "UNSTABLE" => ops_events::record(EventKind::CiFailure, summary),
+        _ => {}
+    }
+
+    let emoji = match status.as_str() {
+        "SUCCESS" => "✅",
+        "UNSTABLE" => "⚠️",
+        "ABORTED" | "NOT_BUILT" => "⏹️",
+        _ => "❌",
+    };
+    let scm = build.scm.as_ref();
+    let mut message = format!("{} Jenkins **{}** #{}: `{}`", emoji, payload.name, build.number, status);
+    if let Some(duration) = build.duration.filter(|ms| *ms > 0) {
+        message.push_str(&format!(" in {}", format_duration(Duration::from_millis(duration))));
+    }
+    if let Some(branch) = scm.and_then(|s| s.branch.as_deref()) {
+        let commit: String = scm.and_then(|s| s.commit.as_deref()).unwrap_or_default().chars().take(7).collect();
+        message.push_str(&format!("\nBranch `{}` @ `{}`", branch, commit));
+    }
+    if let Some(url) = &build.full_url {
+        message.push_str(&format!("\nConsole: {}console", url));
+    }
+
+    let destination = match scm.and_then(|s| s.url.as_deref()).and_then(github_repo) {
+        Some(repo) => Destination::Repo(repo, ChannelKind::Workflows),
+        None => match channel() {
+            Some(id) => Destination::Channel(id),
+            None => return WebhookOutcome::ignored(HANDLER, "no Jenkins or workflow channel configured"),
+        },
+    };
+    WebhookOutcome::from_delivery(HANDLER, notify::send(state, HANDLER, destination, message).await)
+}
+
+/// `JENKINS_CHANNEL_ID`, falling back to the global workflow channel.
+fn channel() -> Option<u64> {
+    ["JENKINS_CHANNEL_ID", ChannelKind::Workflows.env_var()]
+        .iter()
+        .find_map(|key| env::var(key).ok().and_then(|v| v.trim().parse().ok()))
+}
+
+/// `owner/repo` for a GitHub clone URL (`https://github.com/owner/repo.git`,
+/// `git@github.com:owner/repo.git`).
+fn github_repo(url: &str) -> Option<String> {
+    let path = url
+        .strip_prefix("https://github.com/")
+        .or_else(|| url.strip_prefix("git@github.com:"))?;
+    let path = path.trim_end_matches('/').trim_end_matches(".git");
+    (path.matches('/').count() == 1).then(|| path.to_string())
+}
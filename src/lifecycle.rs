@@ -0,0 +1,170 @@
+//! Process lifecycle bookkeeping across restarts.
+//!
+//! `lifecycle.json` records when the bot started, when it was last known alive, and whether
+//! it shut down cleanly. On the next start this tells a deploy (version changed) apart from
+//! a plain restart or a crash (no clean-shutdown marker), and how long the bot was down.
+//!
+//! Recent start times are kept too: more than `CRASH_LOOP_MAX_RESTARTS` starts within
+//! `CRASH_LOOP_WINDOW_MINS` means the bot is crash-looping, and callers back off work that
+//! would otherwise hammer the Discord API on every restart.
+//!
+//! Environment Variables:
+//! - `BOT_VERSION`: Version string to report, e.g. a git SHA set by the deploy script
+//!   (default: the crate version)
+//! - `CRASH_LOOP_MAX_RESTARTS`: Restarts tolerated within the window (default: 5)
+//! - `CRASH_LOOP_WINDOW_MINS`: Crash-loop detection window in minutes (default: 10)
+//! - `CRASH_LOOP_BACKOFF_SECS`: How long to delay the Discord login while crash-looping (default: 300)
+
+use chrono::Utc;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    sync::atomic::{AtomicBool, AtomicI64, Ordering},
+    time::Duration,
+};
+
+use crate::store;
+
+const LIFECYCLE_FILE: &str = "lifecycle.json";
+
+const DEFAULT_MAX_RESTARTS: usize = 5;
+const DEFAULT_WINDOW_MINS: i64 = 10;
+const DEFAULT_BACKOFF_SECS: u64 = 300;
+
+static PREVIOUS_RUN: OnceCell<PreviousRun> = OnceCell::new();
+static CRASH_LOOPING: AtomicBool = AtomicBool::new(false);
+static STARTED_AT: AtomicI64 = AtomicI64::new(0);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LifecycleState {
+    version: Option<String>,
+    started_at: Option<i64>,
+    last_alive_at: Option<i64>,
+    /// Set on graceful shutdown and cleared on start, so its absence means a crash.
+    clean_shutdown_at: Option<i64>,
+    /// Start times within the crash-loop window, oldest first.
+    #[serde(default)]
+    recent_starts: Vec<i64>,
+}
+
+/// Why the bot is (re)starting.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RestartReason {
+    FirstStart,
+    Deploy { from: String },
+    Restart,
+    Crash,
+}
+
+/// What is known about the run before this one.
+#[derive(Debug, Clone)]
+pub struct PreviousRun {
+    pub reason: RestartReason,
+    /// When the previous run stopped: its clean shutdown, or when it was last seen alive.
+    pub stopped_at: Option<i64>,
+}
+
+/// The version this process reports.
+pub fn version() -> String {
+    env::var("BOT_VERSION")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| env!("CARGO_PKG_VERSION").to_string())
+}
+
+fn max_restarts() -> usize {
+    env::var("CRASH_LOOP_MAX_RESTARTS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_RESTARTS)
+}
+
+/// Crash-loop detection window in seconds.
+fn window_secs() -> i64 {
+    env::var("CRASH_LOOP_WINDOW_MINS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|m| *m > 0)
+        .unwrap_or(DEFAULT_WINDOW_MINS)
+        * 60
+}
+
+/// Records this process start. Call once, before anything else touches the lifecycle state.
+pub fn record_start() {
+    let mut state: LifecycleState = store::load(LIFECYCLE_FILE);
+    let current = version();
+
+    let reason = match (&state.version, state.clean_shutdown_at) {
+        (None, _) => RestartReason::FirstStart,
+        (Some(previous), _) if *previous != current => RestartReason::Deploy {
+            from: previous.clone(),
+        },
+        (Some(_), Some(_)) => RestartReason::Restart,
+        (Some(_), None) => RestartReason::Crash,
+    };
+    let stopped_at = state.clean_shutdown_at.or(state.last_alive_at);
+    let _ = PREVIOUS_RUN.set(PreviousRun { reason, stopped_at });
+
+    let now = Utc::now().timestamp();
+    let window = window_secs();
+    state.recent_starts.retain(|t| now - t < window);
+    state.recent_starts.push(now);
+    // The first start in the window isn't a restart.
+    let restarts = state.recent_starts.len() - 1;
+    CRASH_LOOPING.store(restarts > max_restarts(), Ordering::SeqCst);
+
+    state.version = Some(current);
+    state.started_at = Some(now);
+    STARTED_AT.store(now, Ordering::SeqCst);
+    state.last_alive_at = Some(now);
+    state.clean_shutdown_at = None;
+    store::save(LIFECYCLE_FILE, &state);
+}
+
+/// Unix timestamp this process started at, once [`record_start`] has been called.
+pub fn started_at() -> i64 {
+    STARTED_AT.load(Ordering::SeqCst)
+}
+
+/// Information about the previous run, once [`record_start`] has been called.
+pub fn previous_run() -> Option<&'static PreviousRun> {
+    PREVIOUS_RUN.get()
+}
+
+/// Returns `true` if this process started as part of a crash loop.
+pub fn crash_looping() -> bool {
+    CRASH_LOOPING.load(Ordering::SeqCst)
+}
+
+/// How long to delay the Discord login while crash-looping.
+pub fn crash_loop_backoff() -> Duration {
+    Duration::from_secs(
+        env::var("CRASH_LOOP_BACKOFF_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_BACKOFF_SECS),
+    )
+}
+
+/// Restarts recorded within the crash-loop window, and the window length in minutes.
+pub fn recent_restarts() -> (usize, i64) {
+    let state: LifecycleState = store::load(LIFECYCLE_FILE);
+    (state.recent_starts.len().saturating_sub(1), window_secs() / 60)
+}
+
+/// Notes that the bot is still alive, bounding the downtime estimate after a crash.
+pub fn touch() {
+    let mut state: LifecycleState = store::load(LIFECYCLE_FILE);
+    state.last_alive_at = Some(Utc::now().timestamp());
+    store::save(LIFECYCLE_FILE, &state);
+}
+
+/// Writes the clean-shutdown marker.
+pub fn record_clean_shutdown() {
+    let mut state: LifecycleState = store::load(LIFECYCLE_FILE);
+    let now = Utc::now().timestamp();
+    state.last_alive_at = Some(now);
+    state.clean_shutdown_at = Some(now);
+    store::save(LIFECYCLE_FILE, &state);
+}
@@ -1,16 +1,45 @@
+mod alerts;
 mod bot;
 mod github;
 mod commands;
+mod custom_webhook;
+mod duration;
+mod fallback;
+mod files;
+mod freeze;
+mod guilds;
+mod http;
+mod impact;
+mod jenkins;
+mod lifecycle;
+mod notify;
+mod observer;
+mod ops_events;
+mod rate_limit;
+mod routing;
+mod secrets;
+mod sentry;
+mod server;
+mod store;
+mod tasks;
+mod uptime;
 
-use std::{env, net::SocketAddr, sync::{Arc, Mutex}};
-use axum::{Router};
+use std::{env, future::IntoFuture, net::SocketAddr, sync::{atomic::AtomicBool, Arc, Mutex}, time::Duration};
+use axum::{middleware, Router};
 use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
 use tower_http::cors::CorsLayer;
 use dotenvy::dotenv;
+use server::Shutdown;
+use tasks::Tasks;
 
 #[derive(Clone)]
 pub struct AppState {
     pub discord_ctx: Arc<Mutex<Option<serenity::prelude::Context>>>,
+    /// Whether the gateway connection is currently up (tracked from shard stage updates).
+    pub gateway_connected: Arc<AtomicBool>,
+    pub shutdown: Shutdown,
+    /// Background loops, reused across gateway reconnects.
+    pub tasks: Tasks,
 }
 
 #[tokio::main]
@@ -24,15 +53,42 @@ async fn main() {
         .parse::<u16>()
         .expect("PORT must be a valid number");
     let token = env::var("DISCORD_TOKEN").expect("DISCORD_TOKEN must be set");
+    let drain_timeout_secs: u64 = env::var("SHUTDOWN_DRAIN_TIMEOUT_SECS")
+        .unwrap_or_else(|_| "30".into())
+        .parse()
+        .unwrap_or(30);
+
+    // Note how the previous run ended before anything else runs.
+    lifecycle::record_start();
+    if lifecycle::crash_looping() {
+        warn_crash_loop();
+    }
+    if observer::enabled() {
+        println!("Observer mode is on: state-changing commands are disabled");
+    }
 
     // Shared bot/app state
+    let shutdown = Shutdown::new();
     let shared_state = AppState {
         discord_ctx: Arc::new(Mutex::new(None)),
+        gateway_connected: Arc::new(AtomicBool::new(false)),
+        shutdown: shutdown.clone(),
+        tasks: Tasks::default(),
     };
 
+    // Trigger graceful shutdown on SIGINT/SIGTERM
+    tokio::spawn(server::listen_for_signals(shutdown.clone()));
+
     // Start Discord bot in background
     let bot_state = shared_state.clone();
-    tokio::spawn(async move {
+    let bot_task = tokio::spawn(async move {
+        // Don't hammer the Discord API with logins while crash-looping.
+        if lifecycle::crash_looping() {
+            tokio::select! {
+                _ = tokio::time::sleep(lifecycle::crash_loop_backoff()) => {}
+                _ = bot_state.shutdown.wait() => return,
+            }
+        }
         bot::start(token, bot_state).await;
     });
 
@@ -41,19 +97,66 @@ async fn main() {
         .expose_headers([CONTENT_DISPOSITION, CONTENT_TYPE]);
 
     let app = Router::new()
-        .nest("/webhook", github::routes(shared_state.clone()))
+        .nest(
+            "/webhook",
+            github::routes(shared_state.clone())
+                .merge(alerts::routes(shared_state.clone()))
+                .merge(custom_webhook::routes(shared_state.clone()))
+                .merge(jenkins::routes(shared_state.clone()))
+                .merge(sentry::routes(shared_state.clone()))
+                .merge(uptime::routes(shared_state.clone()))
+                .layer(middleware::from_fn(rate_limit::limit)),
+        )
+        .nest("/status", bot::status_routes())
+        .nest("/files", files::routes())
+        .merge(bot::schedule_routes())
         .layer(cors);
 
     let addr: SocketAddr = format!("{}:{}", host, port)
         .parse()
         .expect("Invalid address");
+
+    let listener = server::bind_listener(addr).expect("Failed to bind to address");
     println!("Listening on http://{}", addr);
 
-    let listener = tokio::net::TcpListener::bind(&addr)
-        .await
-        .expect("Failed to bind to address");
+    let graceful = shutdown.clone();
+    let server = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async move { graceful.wait().await })
+        .into_future();
+
+    // Stop waiting for in-flight webhook requests once the drain timeout has passed.
+    let drain_deadline = async {
+        shutdown.wait().await;
+        tokio::time::sleep(Duration::from_secs(drain_timeout_secs)).await;
+    };
 
-    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
-        .await
-        .expect("Server crashed");
+    tokio::select! {
+        result = server => result.expect("Server crashed"),
+        _ = drain_deadline => eprintln!("Timed out draining in-flight requests after {}s", drain_timeout_secs),
+    }
+
+    // Give the Discord client a moment to close its shards cleanly.
+    let _ = tokio::time::timeout(Duration::from_secs(10), bot_task).await;
+    shared_state.tasks.abort_all();
+    lifecycle::record_clean_shutdown();
+    println!("Shutdown complete");
+}
+
+/// Posts a crash-loop warning through the fallback webhook, which works without a gateway.
+fn warn_crash_loop() {
+    let (restarts, window_mins) = lifecycle::recent_restarts();
+    let warning = format!(
+        "🚨 **Crash loop detected**: the bot restarted {} times in the last {} minutes. \
+         Delaying the Discord login by {} and skipping command registration.",
+        restarts,
+        window_mins,
+        duration::format_duration(lifecycle::crash_loop_backoff())
+    );
+    eprintln!("{}", warning);
+
+    tokio::spawn(async move {
+        if let Err(e) = fallback::post(&warning).await {
+            eprintln!("Failed to post crash-loop warning: {}", e);
+        }
+    });
 }
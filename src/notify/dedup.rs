@@ -0,0 +1,193 @@
+//! Duplicate alert suppression.
+//!
+//! Every alert has a key naming the condition it reports (e.g. `kernel: OOM kills` or
+//! `api: error rate`). An alert is posted the first time its key fires, and again only once
+//! the window has passed since it was last posted or when it fires at a higher [`Severity`]
+//! than the one posted. Flapping conditions therefore post once per window instead of on
+//! every flap, and the recovery message of a suppressed episode is suppressed with it.
+//!
+//! A key can also be silenced for a while with `/alerts silence`: it is then never posted, but
+//! still tracked, so `/alerts list` shows what was held back.
+//!
+//! State is kept in `alert_dedup.json` and `alert_silences.json` so a restart doesn't re-post
+//! everything, and is shown by `/alerts list`.
+//!
+//! Environment Variables:
+//! - `ALERT_DEDUP_WINDOW_MINS`: Minimum time between two posts of the same alert; 0 disables
+//!   suppression (default: 60)
+
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, env, sync::Mutex};
+
+use crate::store;
+
+const STATE_FILE: &str = "alert_dedup.json";
+const SILENCE_FILE: &str = "alert_silences.json";
+const DEFAULT_WINDOW_MINS: i64 = 60;
+
+static STATE: Lazy<Mutex<HashMap<String, Entry>>> = Lazy::new(|| Mutex::new(store::load(STATE_FILE)));
+/// Silenced keys (or key prefixes) and the Unix timestamp each silence ends at.
+static SILENCES: Lazy<Mutex<HashMap<String, i64>>> = Lazy::new(|| Mutex::new(store::load(SILENCE_FILE)));
+
+/// How serious an alert is; a higher severity is posted even inside the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+/// What is known about one alert key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// Severity of the last post.
+    pub severity: Severity,
+    /// Unix timestamp the current episode started at.
+    pub since: i64,
+    /// Unix timestamp of the last post, 0 if it was never posted.
+    pub last_posted: i64,
+    /// Whether the condition is currently firing.
+    pub active: bool,
+    /// Whether the current episode was posted, so its recovery should be too.
+    pub notified: bool,
+    /// Posts suppressed (as duplicates or silenced) since the last one.
+    pub suppressed: u32,
+}
+
+/// The suppression window in seconds (0 when suppression is disabled).
+pub fn window_secs() -> i64 {
+    env::var("ALERT_DEDUP_WINDOW_MINS")
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .filter(|mins| *mins >= 0)
+        .unwrap_or(DEFAULT_WINDOW_MINS)
+        * 60
+}
+
+/// Drops resolved alerts whose window has passed; they would post again anyway.
+fn prune(state: &mut HashMap<String, Entry>, now: i64, window: i64) {
+    state.retain(|_, entry| entry.active || now - entry.last_posted < window);
+}
+
+/// Records that `key` fired at `severity`, returning whether the alert should be posted.
+pub fn fire(key: &str, severity: Severity) -> bool {
+    let now = Utc::now().timestamp();
+    let window = window_secs();
+    let silenced = silenced_until(key).is_some();
+    let mut state = STATE.lock().unwrap();
+    prune(&mut state, now, window);
+
+    let entry = state.entry(key.to_string()).or_insert(Entry {
+        severity,
+        since: now,
+        last_posted: 0,
+        active: false,
+        notified: false,
+        suppressed: 0,
+    });
+    if !entry.active {
+        entry.active = true;
+        entry.since = now;
+        entry.notified = false;
+    }
+    let due = window == 0 || entry.last_posted == 0 || now - entry.last_posted >= window;
+    let post = !silenced && (due || severity > entry.severity);
+    if post {
+        entry.severity = severity;
+        entry.last_posted = now;
+        entry.notified = true;
+        entry.suppressed = 0;
+    } else {
+        entry.suppressed += 1;
+        let why = if silenced { "silenced" } else { "duplicate" };
+        println!("Suppressed {} alert `{}` ({} since the last post)", why, key, entry.suppressed);
+    }
+
+    store::save(STATE_FILE, &*state);
+    post
+}
+
+/// Records a one-off event (an OOM kill, a detected leak) under `key`, returning whether it
+/// should be posted. Events have no recovery, so they never stay active.
+pub fn event(key: &str, severity: Severity) -> bool {
+    let post = fire(key, severity);
+    let mut state = STATE.lock().unwrap();
+    if let Some(entry) = state.get_mut(key) {
+        entry.active = false;
+    }
+    store::save(STATE_FILE, &*state);
+    post
+}
+
+/// Records that `key` recovered, returning whether the recovery should be posted: only when
+/// the alert it closes was.
+pub fn resolve(key: &str) -> bool {
+    let mut state = STATE.lock().unwrap();
+    let post = match state.get_mut(key) {
+        Some(entry) if entry.active => {
+            entry.active = false;
+            entry.notified
+        }
+        Some(_) => false,
+        // Unknown (e.g. state file lost); say it recovered rather than stay silent.
+        None => true,
+    };
+    store::save(STATE_FILE, &*state);
+    post
+}
+
+/// Alerts that are firing or had posts suppressed within the window, most recent first.
+pub fn active() -> Vec<(String, Entry)> {
+    let state = STATE.lock().unwrap();
+    let mut active: Vec<(String, Entry)> = state
+        .iter()
+        .filter(|(_, entry)| entry.active || entry.suppressed > 0)
+        .map(|(key, entry)| (key.clone(), entry.clone()))
+        .collect();
+    active.sort_by_key(|b| std::cmp::Reverse(b.1.since));
+    active
+}
+
+/// Silences `key`, and every key it is a prefix of, until the Unix timestamp `until`.
+pub fn silence(key: &str, until: i64) {
+    let mut silences = SILENCES.lock().unwrap();
+    silences.insert(key.to_string(), until);
+    store::save(SILENCE_FILE, &*silences);
+}
+
+/// When the silence covering `key` ends, if it is silenced.
+pub fn silenced_until(key: &str) -> Option<i64> {
+    let now = Utc::now().timestamp();
+    SILENCES
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(prefix, until)| key.starts_with(prefix.as_str()) && **until > now)
+        .map(|(_, until)| *until)
+        .max()
+}
+
+/// Silences that haven't ended yet, ending soonest first.
+pub fn silences() -> Vec<(String, i64)> {
+    let now = Utc::now().timestamp();
+    let mut silences = SILENCES.lock().unwrap();
+    let before = silences.len();
+    silences.retain(|_, until| *until > now);
+    if silences.len() != before {
+        store::save(SILENCE_FILE, &*silences);
+    }
+    let mut list: Vec<(String, i64)> = silences.iter().map(|(key, until)| (key.clone(), *until)).collect();
+    list.sort_by_key(|(_, until)| *until);
+    list
+}
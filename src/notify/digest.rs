@@ -0,0 +1,123 @@
+//! Grouping of noisy event bursts into a single digest message.
+//!
+//! When enabled, notifications that belong to a group (e.g. "PRs opened by `dependabot[bot]`
+//! in a repository") are held for a short window. If enough of them arrive, one summary is
+//! posted with the individual items in a spoiler that expands on click; otherwise they are
+//! posted individually as usual.
+//!
+//! Environment Variables:
+//! - `NOTIFY_DIGEST_WINDOW_SECS`: How long to collect a group (default: unset, grouping disabled)
+//! - `NOTIFY_DIGEST_MIN_EVENTS`: Events needed for a digest instead of individual messages (default 3)
+
+use once_cell::sync::Lazy;
+use std::{collections::HashMap, env, sync::Mutex, time::Duration};
+
+use super::{send, Delivery, Destination};
+use crate::AppState;
+
+const DEFAULT_MIN_EVENTS: usize = 3;
+/// Leave room for the summary line within Discord's 2000 character limit.
+const MAX_LIST_CHARS: usize = 1800;
+
+/// Identifies which notifications may be summarized together.
+#[derive(Debug, Clone)]
+pub struct Grouping {
+    /// Events with the same key (per channel) are grouped.
+    pub key: String,
+    /// Summary headline, e.g. "pull requests opened by `dependabot[bot]` in **org/repo**".
+    pub title: String,
+    /// One-line version of this event for the digest list.
+    pub line: String,
+}
+
+struct PendingGroup {
+    title: String,
+    /// `(line, full message)` per event, in arrival order.
+    entries: Vec<(String, String)>,
+}
+
+static GROUPS: Lazy<Mutex<HashMap<(u64, String), PendingGroup>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// The collection window, or `None` if grouping is disabled.
+pub fn window() -> Option<Duration> {
+    env::var("NOTIFY_DIGEST_WINDOW_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs)
+}
+
+fn min_events() -> usize {
+    env::var("NOTIFY_DIGEST_MIN_EVENTS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_MIN_EVENTS)
+        .max(2)
+}
+
+/// Adds a notification to its group, starting the group's window if it is the first one.
+pub fn add(
+    state: &AppState,
+    handler: &'static str,
+    channel_id: u64,
+    window: Duration,
+    grouping: Grouping,
+    message: String,
+) {
+    let key = (channel_id, grouping.key);
+    let first = {
+        let mut groups = GROUPS.lock().unwrap();
+        let group = groups.entry(key.clone()).or_insert_with(|| PendingGroup {
+            title: grouping.title,
+            entries: Vec::new(),
+        });
+        group.entries.push((grouping.line, message));
+        group.entries.len() == 1
+    };
+    if !first {
+        return;
+    }
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(window).await;
+        let group = match GROUPS.lock().unwrap().remove(&key) {
+            Some(group) => group,
+            None => return,
+        };
+        flush(&state, handler, channel_id, group).await;
+    });
+}
+
+async fn flush(state: &AppState, handler: &'static str, channel_id: u64, group: PendingGroup) {
+    if group.entries.len() < min_events() {
+        for (_, message) in group.entries {
+            report(handler, send(state, handler, Destination::Channel(channel_id), message).await);
+        }
+        return;
+    }
+
+    let count = group.entries.len();
+    let mut list = String::new();
+    let mut listed = 0;
+    for (line, _) in &group.entries {
+        if list.chars().count() + line.chars().count() + 3 > MAX_LIST_CHARS {
+            break;
+        }
+        list.push_str(&format!("- {}\n", line));
+        listed += 1;
+    }
+    if listed < count {
+        list.push_str(&format!("…and {} more\n", count - listed));
+    }
+
+    let message = format!("🗂️ **{} {}**\n||{}||", count, group.title, list.trim_end());
+    println!("Posting a digest of {} {} notifications.", count, handler);
+    report(handler, send(state, handler, Destination::Channel(channel_id), message).await);
+}
+
+fn report(handler: &str, delivery: Delivery) {
+    if let Delivery::Failed(reason) | Delivery::Unroutable(reason) = delivery {
+        eprintln!("Failed to post grouped {} notification: {}", handler, reason);
+    }
+}
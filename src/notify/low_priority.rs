@@ -0,0 +1,185 @@
+//! Digest of low-priority events, posted every few hours instead of one message per event.
+//!
+//! Routine activity (new stars, pushes to draft PRs, successful CI on feature branches) can be
+//! held per channel and summarized in one message per period, grouped by category. A period
+//! starts with the first held event. Held events are persisted, so a restart doesn't lose them.
+//!
+//! Environment Variables:
+//! - `LOW_PRIORITY_DIGEST`: Comma-separated categories to hold: `star`, `draft_push`,
+//!   `ci_success` (default: none, everything is posted as it happens)
+//! - `LOW_PRIORITY_DIGEST_HOURS`: How often the digest is posted (default: 6)
+
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, env, sync::Mutex, time::Duration};
+use tokio::time::sleep;
+
+use super::{send, Delivery, Destination};
+use crate::store;
+use crate::tasks::Tasks;
+use crate::AppState;
+
+const DIGEST_FILE: &str = "low_priority_digest.json";
+const CHECK_INTERVAL: Duration = Duration::from_secs(600);
+const DEFAULT_HOURS: i64 = 6;
+/// Lines listed per category before the rest are counted.
+const MAX_LINES: usize = 10;
+/// Leave room for the headline within Discord's 2000 character limit.
+const MAX_CHARS: usize = 1900;
+
+/// Categories in the order they appear in the digest, with their headings.
+const CATEGORIES: &[(&str, &str)] = &[
+    ("star", "⭐ Stars"),
+    ("draft_push", "✏️ Pushes to draft PRs"),
+    ("ci_success", "✅ Successful CI on feature branches"),
+];
+
+/// Serializes reads and writes of the digest file.
+static LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Digest {
+    /// Unix timestamp of the first event held in the current period.
+    period_start: Option<i64>,
+    /// Held events per channel.
+    #[serde(default)]
+    pending: BTreeMap<u64, Vec<HeldEvent>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HeldEvent {
+    category: String,
+    line: String,
+}
+
+fn categories() -> Vec<String> {
+    env::var("LOW_PRIORITY_DIGEST")
+        .unwrap_or_default()
+        .split(',')
+        .map(|c| c.trim().to_lowercase())
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
+fn period_hours() -> i64 {
+    env::var("LOW_PRIORITY_DIGEST_HOURS")
+        .ok()
+        .and_then(|h| h.trim().parse().ok())
+        .filter(|h| *h > 0)
+        .unwrap_or(DEFAULT_HOURS)
+}
+
+/// Whether events of `category` are held for the digest.
+pub fn enabled(category: &str) -> bool {
+    categories().iter().any(|c| c == category)
+}
+
+/// Holds a one-line summary of an event for `channel_id`'s next digest.
+pub fn hold(channel_id: u64, category: &str, line: String) -> Delivery {
+    let _guard = LOCK.lock().unwrap();
+    let mut digest: Digest = store::load(DIGEST_FILE);
+    digest.period_start.get_or_insert_with(|| Utc::now().timestamp());
+    digest.pending.entry(channel_id).or_default().push(HeldEvent {
+        category: category.to_string(),
+        line,
+    });
+    store::save(DIGEST_FILE, &digest);
+    Delivery::Held
+}
+
+/// One digest message for a channel's held events.
+fn digest_message(events: &[HeldEvent], hours: i64) -> String {
+    let mut message = format!("🗒️ **Low-priority activity (last {}h)**", hours);
+
+    for (category, heading) in CATEGORIES {
+        let lines: Vec<&str> = events
+            .iter()
+            .filter(|e| e.category == *category)
+            .map(|e| e.line.as_str())
+            .collect();
+        if lines.is_empty() {
+            continue;
+        }
+
+        let mut section = format!("\n**{} ({})**", heading, lines.len());
+        for line in lines.iter().take(MAX_LINES) {
+            section.push_str(&format!("\n- {}", line));
+        }
+        if lines.len() > MAX_LINES {
+            section.push_str(&format!("\n…and {} more", lines.len() - MAX_LINES));
+        }
+        message.push_str(&section);
+    }
+
+    if message.chars().count() > MAX_CHARS {
+        message = message.chars().take(MAX_CHARS).collect();
+        message.push('…');
+    }
+    message
+}
+
+/// Takes every held event if the period is over, starting a new period.
+fn take_due(hours: i64) -> Option<BTreeMap<u64, Vec<HeldEvent>>> {
+    let _guard = LOCK.lock().unwrap();
+    let mut digest: Digest = store::load(DIGEST_FILE);
+    let due = digest
+        .period_start
+        .is_some_and(|start| Utc::now().timestamp() - start >= hours * 3600);
+    if !due {
+        return None;
+    }
+    let pending = std::mem::take(&mut digest.pending);
+    digest.period_start = None;
+    store::save(DIGEST_FILE, &digest);
+    Some(pending)
+}
+
+/// Puts events that couldn't be posted back, ahead of newer ones, to retry on the next check.
+fn requeue(channel_id: u64, mut events: Vec<HeldEvent>, hours: i64) {
+    let _guard = LOCK.lock().unwrap();
+    let mut digest: Digest = store::load(DIGEST_FILE);
+    let held = digest.pending.entry(channel_id).or_default();
+    events.append(held);
+    *held = events;
+    digest.period_start = Some(Utc::now().timestamp() - hours * 3600);
+    store::save(DIGEST_FILE, &digest);
+}
+
+/// Starts the loop that posts the digest once a period has passed since the first held event,
+/// if any category is held.
+pub async fn start_low_priority_digest_loop(state: AppState) {
+    if categories().is_empty() {
+        return;
+    }
+    if state.tasks.is_running("low_priority_digest") {
+        println!("Low-priority digest loop already running, reusing it.");
+        return;
+    }
+
+    let tasks: Tasks = state.tasks.clone();
+    tasks.spawn("low_priority_digest", move |beat| {
+        let state = state.clone();
+        async move {
+            loop {
+                beat.tick();
+                let hours = period_hours();
+
+                for (channel_id, events) in take_due(hours).unwrap_or_default() {
+                    if events.is_empty() {
+                        continue;
+                    }
+                    let message = digest_message(&events, hours);
+                    let delivery =
+                        send(&state, "low_priority_digest", Destination::Channel(channel_id), message).await;
+                    if !matches!(delivery, Delivery::Sent | Delivery::Fallback | Delivery::Queued) {
+                        eprintln!("Failed to post low-priority digest to {}: {:?}", channel_id, delivery);
+                        requeue(channel_id, events, hours);
+                    }
+                }
+
+                sleep(CHECK_INTERVAL).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,337 @@
+//! Outbound notification service.
+//!
+//! Every GitHub notification goes through [`send`], which resolves its [`Destination`],
+//! applies the handler's template, posts it through the bot's prioritized [`queue`] (retrying
+//! transient failures), falls back to the fallback webhook or the pending queue while the
+//! gateway is down, and mirrors a copy to any extra webhook sinks.
+//!
+//! The bot's own messages (status updates, reports, alerts, buttons) go through the same queue
+//! with an explicit priority: [`post_text`] for plain text, which also falls back to the fallback
+//! webhook, and [`post`] / [`edit`] for messages with embeds, components or files.
+//!
+//! Environment Variables:
+//! - `NOTIFY_RETRIES`: Extra attempts for a failed Discord send (default 2)
+//! - `NOTIFY_TEMPLATE_<HANDLER>`: Template for a handler's messages, e.g.
+//!   `NOTIFY_TEMPLATE_PUSH="[ci] {message}"`; `{message}` and `{handler}` are substituted
+//! - `NOTIFY_PRIORITY_<HANDLER>`: `critical`, `normal`, or `low`, overriding the handler's
+//!   default priority (see [`priority_for`])
+//! - `NOTIFY_DIGEST_WINDOW_SECS` / `NOTIFY_DIGEST_MIN_EVENTS`: Grouping of event bursts sent
+//!   with [`send_grouped`] (see [`digest`])
+//! - `LOW_PRIORITY_DIGEST` / `LOW_PRIORITY_DIGEST_HOURS`: Routine events held for a periodic
+//!   summary (see [`low_priority`])
+//! - `ALERT_DEDUP_WINDOW_MINS`: Minimum time between two posts of the same alert (see
+//!   [`dedup`])
+//! - `NOTIFY_MIRROR_WEBHOOK_URLS`: Comma-separated webhook URLs that receive a copy of every
+//!   notification for the operators' guild (Discord or Slack-compatible `{"content"}`/`{"text"}`
+//!   webhooks); other guilds use the `mirror_webhook_urls` in their `guilds.json` record
+
+pub mod dedup;
+pub mod digest;
+pub mod low_priority;
+pub mod pending;
+pub mod queue;
+
+use serde_json::json;
+use serenity::{model::channel::Message, prelude::*};
+use std::{env, future::Future, sync::atomic::Ordering, time::Duration};
+
+use crate::fallback;
+use crate::guilds::{self, ChannelKind};
+use crate::AppState;
+
+pub use digest::Grouping;
+pub use low_priority::start_low_priority_digest_loop;
+pub use pending::flush as flush_pending;
+pub use queue::{Outgoing, Priority, SendError};
+
+const DEFAULT_RETRIES: u32 = 2;
+
+/// Where a notification should be posted.
+#[derive(Debug, Clone)]
+pub enum Destination {
+    /// The channel of `kind` for a GitHub repository (see [`guilds::github_channel`]).
+    Repo(String, ChannelKind),
+    /// A specific channel or thread.
+    Channel(u64),
+}
+
+impl Destination {
+    /// The channel ID, or `None` if no channel is configured for it.
+    pub fn resolve(&self) -> Option<u64> {
+        match self {
+            Destination::Repo(repo, kind) => guilds::github_channel(repo, *kind),
+            Destination::Channel(id) => Some(*id),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Destination::Repo(repo, kind) => format!("no `{}` channel configured for {}", kind.key(), repo),
+            Destination::Channel(id) => format!("channel {}", id),
+        }
+    }
+}
+
+/// How a notification was (or wasn't) delivered.
+#[derive(Debug)]
+pub enum Delivery {
+    /// Posted by the bot.
+    Sent,
+    /// Posted through the fallback webhook.
+    Fallback,
+    /// Held until the gateway connects.
+    Queued,
+    /// Held to be posted with others of its group (see [`send_grouped`]).
+    Grouped,
+    /// Held for the periodic low-priority digest (see [`low_priority`]).
+    Held,
+    /// The destination has no channel configured.
+    Unroutable(String),
+    Failed(String),
+}
+
+/// Discord context, if the gateway is up.
+pub fn discord_ctx(state: &AppState) -> Option<Context> {
+    if !state.gateway_connected.load(Ordering::SeqCst) {
+        return None;
+    }
+    state.discord_ctx.lock().unwrap().clone()
+}
+
+/// Sends `content` from `handler` to `destination`.
+pub async fn send(state: &AppState, handler: &'static str, destination: Destination, content: String) -> Delivery {
+    let channel_id = match destination.resolve() {
+        Some(id) => id,
+        None => return Delivery::Unroutable(destination.describe()),
+    };
+    let message = apply_template(handler, content);
+    let guild_id = guilds::guild_for_channel(channel_id);
+    mirror(handler, guild_id, &message);
+
+    match discord_ctx(state) {
+        Some(ctx) => match send_with_retries(&ctx, channel_id, priority_for(handler), &message).await {
+            Ok(()) => return Delivery::Sent,
+            Err(e) => {
+                eprintln!("Failed to send {} notification: {}", handler, e);
+                if !fallback::is_configured_for(guild_id) {
+                    return Delivery::Failed(format!("Discord send failed: {}", e));
+                }
+            }
+        },
+        None => {
+            if !fallback::is_configured_for(guild_id) {
+                eprintln!("Discord gateway unavailable, queueing {} notification.", handler);
+                pending::push(handler, channel_id, message);
+                return Delivery::Queued;
+            }
+            eprintln!("Discord gateway unavailable, using fallback for {} notification.", handler);
+        }
+    }
+
+    match fallback::post_for(guild_id, &message).await {
+        Ok(()) => Delivery::Fallback,
+        // Still deliverable once the gateway is back, unless the bot itself failed to send.
+        Err(e) if !state.gateway_connected.load(Ordering::SeqCst) => {
+            eprintln!("Fallback delivery of {} notification failed, queueing it: {}", handler, e);
+            pending::push(handler, channel_id, message);
+            Delivery::Queued
+        }
+        Err(e) => {
+            eprintln!("Fallback delivery of {} notification failed: {}", handler, e);
+            Delivery::Failed(e)
+        }
+    }
+}
+
+/// Like [`send`], but when digests are enabled the notification is held with others of the
+/// same `grouping` and a burst of them is posted as one summary.
+pub async fn send_grouped(
+    state: &AppState,
+    handler: &'static str,
+    destination: Destination,
+    grouping: Grouping,
+    content: String,
+) -> Delivery {
+    let window = match digest::window() {
+        Some(window) => window,
+        None => return send(state, handler, destination, content).await,
+    };
+    let channel_id = match destination.resolve() {
+        Some(id) => id,
+        None => return Delivery::Unroutable(destination.describe()),
+    };
+    digest::add(state, handler, channel_id, window, grouping, content);
+    Delivery::Grouped
+}
+
+/// Posts plain `content` from one of the bot's own features to `channel_id`, through the fallback
+/// webhook if the bot can't send it.
+pub async fn post_text(ctx: &Context, channel_id: u64, priority: Priority, content: String) -> Delivery {
+    let error = match send_with_retries(ctx, channel_id, priority, &content).await {
+        Ok(()) => return Delivery::Sent,
+        Err(e) => e,
+    };
+    let guild_id = guilds::guild_for_channel(channel_id);
+    if !fallback::is_configured_for(guild_id) {
+        return Delivery::Failed(format!("Discord send failed: {}", error));
+    }
+    eprintln!("Failed to send to channel {}, using the fallback webhook: {}", channel_id, error);
+    match fallback::post_for(guild_id, &content).await {
+        Ok(()) => Delivery::Fallback,
+        Err(e) => Delivery::Failed(e),
+    }
+}
+
+/// Posts a message with embeds, components or a file to `channel_id`, returning it so it can
+/// be edited later.
+pub async fn post(ctx: &Context, channel_id: u64, priority: Priority, message: Outgoing) -> Result<Message, SendError> {
+    with_retries(|| queue::send_message(ctx.http.clone(), channel_id, priority, message.clone())).await
+}
+
+/// Replaces message `message_id` in `channel_id` with `message`.
+pub async fn edit(
+    ctx: &Context,
+    channel_id: u64,
+    message_id: u64,
+    priority: Priority,
+    message: Outgoing,
+) -> Result<Message, SendError> {
+    with_retries(|| queue::edit_message(ctx.http.clone(), channel_id, message_id, priority, message.clone())).await
+}
+
+async fn send_with_retries(
+    ctx: &Context,
+    channel_id: u64,
+    priority: Priority,
+    message: &str,
+) -> Result<(), String> {
+    with_retries(|| queue::send(ctx.http.clone(), channel_id, priority, message.to_string()))
+        .await
+        .map_err(|e| e.message)
+}
+
+/// Runs `attempt` until it succeeds, fails permanently, or `NOTIFY_RETRIES` retries are used up.
+async fn with_retries<T, F, Fut>(mut attempt: F) -> Result<T, SendError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, SendError>>,
+{
+    let retries = env::var("NOTIFY_RETRIES")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_RETRIES);
+
+    let mut tries = 0;
+    loop {
+        match attempt().await {
+            Ok(result) => return Ok(result),
+            Err(e) if tries < retries && e.transient => {
+                tries += 1;
+                tokio::time::sleep(Duration::from_secs(1 << tries)).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Priority of `handler`'s notifications: security alerts and force-pushes to protected
+/// branches skip the queue, chatty CI and activity events may be batched. `NOTIFY_PRIORITY_<HANDLER>` overrides the default.
+pub fn priority_for(handler: &str) -> Priority {
+    let key = format!("NOTIFY_PRIORITY_{}", handler.to_uppercase());
+    if let Some(priority) = env::var(key).ok().as_deref().and_then(Priority::from_key) {
+        return priority;
+    }
+    match handler {
+        "dependabot_alert" | "repository_vulnerability_alert" | "force_push" => Priority::Critical,
+        "workflow_run" | "workflow_job" | "check_run" | "check_suite" | "status" | "push" | "star"
+        | "fork" => Priority::Low,
+        _ => Priority::Normal,
+    }
+}
+
+/// Renders `NOTIFY_TEMPLATE_<HANDLER>` around `content`, if one is configured.
+fn apply_template(handler: &str, content: String) -> String {
+    let key = format!("NOTIFY_TEMPLATE_{}", handler.to_uppercase());
+    match env::var(key).ok().filter(|t| t.contains("{message}")) {
+        Some(template) => template
+            .replace("\\n", "\n")
+            .replace("{handler}", handler)
+            .replace("{message}", &content),
+        None => content,
+    }
+}
+
+/// The configured `NOTIFY_MIRROR_WEBHOOK_URLS`.
+fn mirror_urls() -> Vec<String> {
+    env::var("NOTIFY_MIRROR_WEBHOOK_URLS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|u| u.trim().to_string())
+        .filter(|u| !u.is_empty())
+        .collect()
+}
+
+/// Mirror webhooks for `guild_id`'s notifications: the guild's own, or the configured
+/// `NOTIFY_MIRROR_WEBHOOK_URLS` for the operators' guild.
+fn mirror_urls_for(guild_id: Option<u64>) -> Vec<String> {
+    if !guilds::is_foreign(guild_id) {
+        return mirror_urls();
+    }
+    guild_id
+        .and_then(guilds::for_guild)
+        .map(|config| config.mirror_webhook_urls)
+        .unwrap_or_default()
+}
+
+/// Posts a copy of `message` to every mirror webhook of `guild_id` in the background.
+fn mirror(handler: &'static str, guild_id: Option<u64>, message: &str) {
+    let urls = mirror_urls_for(guild_id);
+    if urls.is_empty() {
+        return;
+    }
+
+    // Discord webhooks read `content`, Slack-compatible ones read `text`. Only explicit
+    // mentions may ping, like queued messages.
+    let body = json!({
+        "content": message,
+        "text": message,
+        "allowed_mentions": queue::allowed_mentions_json(message),
+    });
+    tokio::spawn(async move {
+        for url in urls {
+            let result = crate::http::client().post(&url).json(&body).send().await;
+            match result {
+                Ok(res) if res.status().is_success() => {}
+                Ok(res) => eprintln!("Mirror of {} notification returned {}", handler, res.status()),
+                Err(e) => eprintln!("Mirror of {} notification failed: {}", handler, e),
+            }
+        }
+    });
+}
+
+/// Checks each mirror webhook without posting a message (for `/selftest`), keyed by host so
+/// the URL's token isn't shown. Discord webhooks are looked up; other (Slack-compatible)
+/// webhooks are sent an empty payload, which a live webhook rejects with 400.
+pub async fn check_mirrors() -> Vec<(String, Result<String, String>)> {
+    let mut results = Vec::new();
+    for url in mirror_urls() {
+        let host = reqwest::Url::parse(&url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_else(|| "invalid URL".to_string());
+        let discord = url.contains("/api/webhooks/");
+        let request = if discord {
+            crate::http::client().get(&url)
+        } else {
+            crate::http::client().post(&url).json(&json!({}))
+        };
+        let result = match request.send().await {
+            Ok(res) if discord && res.status().is_success() => Ok("webhook is valid".to_string()),
+            Ok(res) if !discord && res.status().as_u16() == 400 => Ok("webhook is reachable".to_string()),
+            Ok(res) => Err(format!("returned {}", res.status())),
+            Err(e) => Err(format!("request failed: {}", e)),
+        };
+        results.push((host, result));
+    }
+    results
+}
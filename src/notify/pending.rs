@@ -0,0 +1,60 @@
+//! Notifications held back until the Discord gateway is connected.
+//!
+//! A webhook can arrive before `ready` has stored the Discord context (right after startup)
+//! or while the gateway is reconnecting. If the fallback webhook can't take it either, the
+//! notification is queued here and posted in order once `ready` or `resume` fires.
+//!
+//! Environment Variables:
+//! - `PENDING_NOTIFICATIONS_MAX`: Most notifications held (default 200, oldest dropped first)
+
+use once_cell::sync::Lazy;
+use serenity::prelude::*;
+use std::{collections::VecDeque, env, sync::Mutex};
+
+use super::queue;
+
+const DEFAULT_MAX_PENDING: usize = 200;
+
+struct PendingNotification {
+    handler: &'static str,
+    channel_id: u64,
+    message: String,
+}
+
+static PENDING: Lazy<Mutex<VecDeque<PendingNotification>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+fn max_pending() -> usize {
+    env::var("PENDING_NOTIFICATIONS_MAX")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_MAX_PENDING)
+}
+
+/// Queues a notification for delivery once the gateway is connected.
+pub fn push(handler: &'static str, channel_id: u64, message: String) {
+    let mut pending = PENDING.lock().unwrap();
+    pending.push_back(PendingNotification { handler, channel_id, message });
+
+    let max = max_pending();
+    while pending.len() > max {
+        if let Some(dropped) = pending.pop_front() {
+            eprintln!("Pending notification queue full, dropped a {} notification.", dropped.handler);
+        }
+    }
+}
+
+/// Posts every queued notification, oldest first.
+pub async fn flush(ctx: &Context) {
+    let queued: Vec<PendingNotification> = PENDING.lock().unwrap().drain(..).collect();
+    if queued.is_empty() {
+        return;
+    }
+
+    println!("Posting {} notification(s) received while Discord was unavailable.", queued.len());
+    for notification in queued {
+        let priority = super::priority_for(notification.handler);
+        if let Err(e) = queue::send(ctx.http.clone(), notification.channel_id, priority, notification.message).await {
+            eprintln!("Failed to post queued {} notification: {}", notification.handler, e);
+        }
+    }
+}
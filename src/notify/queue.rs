@@ -0,0 +1,543 @@
+//! Prioritized sender for outgoing Discord messages.
+//!
+//! Messages (and edits of earlier ones) are handed to a single dispatcher task instead of being
+//! posted by whichever handler or background loop produced them. The dispatcher always starts
+//! the most urgent queued message next, so a security alert isn't stuck behind a burst of CI
+//! notifications. Each channel sends one message at a time, so serenity's rate limiter only
+//! ever sees a steady stream per channel, while a rate-limited channel doesn't hold up the
+//! others.
+//!
+//! Low-priority text messages wait briefly so bursts (e.g. 20 workflow events at once) can be
+//! coalesced into as few messages as Discord's length limit allows; other messages are sent
+//! meanwhile. Messages with embeds, components or files ([`Outgoing`]) are always sent on
+//! their own.
+//!
+//! Only the users and roles mentioned explicitly (`<@id>`, `<@&id>`) in a message may be
+//! pinged; `@everyone` and `@here` never are. Handlers break up mentions in text they pass on
+//! from GitHub and other senders, so what's left are the mentions the bot wrote itself.
+//!
+//! Environment Variables:
+//! - `NOTIFY_COALESCE_MS`: How long low-priority messages wait for others to join them
+//!   (default 1500, 0 disables coalescing)
+
+use once_cell::sync::Lazy;
+use serenity::{
+    builder::{CreateAllowedMentions, CreateComponents, CreateEmbed, CreateMessage, EditMessage},
+    http::Http,
+    model::channel::{AttachmentType, Message},
+    model::id::{ChannelId, MessageId},
+    prelude::*,
+};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{BinaryHeap, HashSet},
+    env,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+
+const DEFAULT_COALESCE_MS: u64 = 1500;
+/// Discord's message length limit.
+const MAX_MESSAGE_CHARS: usize = 2000;
+/// Discord accepts at most this many users (and roles) in `allowed_mentions`.
+const MAX_ALLOWED_MENTIONS: usize = 100;
+
+/// How urgently a message should be sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Chatty notifications (CI results, pushes, stars) that may be batched.
+    Low,
+    Normal,
+    /// Alerts that should skip ahead of everything else.
+    Critical,
+}
+
+impl Priority {
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key.trim().to_lowercase().as_str() {
+            "low" => Some(Priority::Low),
+            "normal" => Some(Priority::Normal),
+            "critical" => Some(Priority::Critical),
+            _ => None,
+        }
+    }
+}
+
+/// Why a queued message could not be sent.
+#[derive(Debug, Clone)]
+pub struct SendError {
+    /// Network errors and Discord 5xx responses are worth retrying; 4xx (missing access,
+    /// unknown channel, ...) will fail the same way again.
+    pub transient: bool,
+    pub message: String,
+}
+
+impl SendError {
+    fn closed(message: &str) -> Self {
+        Self { transient: false, message: message.to_string() }
+    }
+
+    fn from_serenity(error: &SerenityError) -> Self {
+        let transient = match error {
+            SerenityError::Http(http) => http.status_code().is_none_or(|status| status.is_server_error()),
+            _ => false,
+        };
+        Self { transient, message: error.to_string() }
+    }
+}
+
+impl std::fmt::Display for SendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+type SendResult = Result<(), SendError>;
+
+/// A message with more than plain text, built up front so it can be queued and retried.
+#[derive(Debug, Clone, Default)]
+pub struct Outgoing {
+    content: Option<String>,
+    embeds: Vec<CreateEmbed>,
+    /// `Some` replaces the message's components; an empty set removes them.
+    components: Option<CreateComponents>,
+    /// Attachment as `(filename, data)`. Ignored by edits.
+    file: Option<(String, Vec<u8>)>,
+}
+
+impl Outgoing {
+    pub fn text(content: impl Into<String>) -> Self {
+        Self {
+            content: Some(content.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn embed(mut self, embed: CreateEmbed) -> Self {
+        self.embeds.push(embed);
+        self
+    }
+
+    pub fn components(mut self, components: CreateComponents) -> Self {
+        self.components = Some(components);
+        self
+    }
+
+    pub fn file(mut self, filename: impl Into<String>, data: Vec<u8>) -> Self {
+        self.file = Some((filename.into(), data));
+        self
+    }
+
+    fn fill_create<'a, 'b>(&self, m: &'b mut CreateMessage<'a>) -> &'b mut CreateMessage<'a> {
+        if let Some(content) = &self.content {
+            m.content(content);
+        }
+        m.allowed_mentions(|am| allow_mentions(am, self.content.as_deref().unwrap_or_default()));
+        if !self.embeds.is_empty() {
+            m.set_embeds(self.embeds.clone());
+        }
+        if let Some(components) = &self.components {
+            m.set_components(components.clone());
+        }
+        if let Some((filename, data)) = &self.file {
+            m.add_file(AttachmentType::Bytes {
+                data: Cow::Owned(data.clone()),
+                filename: filename.clone(),
+            });
+        }
+        m
+    }
+
+    fn fill_edit<'a, 'b>(&self, m: &'b mut EditMessage<'a>) -> &'b mut EditMessage<'a> {
+        if let Some(content) = &self.content {
+            m.content(content);
+        }
+        m.allowed_mentions(|am| allow_mentions(am, self.content.as_deref().unwrap_or_default()));
+        if !self.embeds.is_empty() {
+            m.set_embeds(self.embeds.clone());
+        }
+        if let Some(components) = &self.components {
+            m.set_components(components.clone());
+        }
+        m
+    }
+}
+
+/// User and role IDs mentioned explicitly in `content`, as `(users, roles)`.
+fn mentioned_ids(content: &str) -> (Vec<u64>, Vec<u64>) {
+    let (mut users, mut roles) = (Vec::new(), Vec::new());
+    for (i, _) in content.match_indices("<@") {
+        let rest = &content[i + 2..];
+        let (ids, rest) = match rest.strip_prefix('&') {
+            Some(rest) => (&mut roles, rest),
+            None => (&mut users, rest.strip_prefix('!').unwrap_or(rest)),
+        };
+        let Some((id, _)) = rest.split_once('>') else { continue };
+        if let Ok(id) = id.parse::<u64>() {
+            if !ids.contains(&id) && ids.len() < MAX_ALLOWED_MENTIONS {
+                ids.push(id);
+            }
+        }
+    }
+    (users, roles)
+}
+
+/// Lets only the users and roles mentioned explicitly in `content` be pinged.
+fn allow_mentions<'a>(am: &'a mut CreateAllowedMentions, content: &str) -> &'a mut CreateAllowedMentions {
+    let (users, roles) = mentioned_ids(content);
+    am.empty_parse().users(users).roles(roles)
+}
+
+/// [`allow_mentions`] as the `allowed_mentions` object of a raw webhook payload.
+pub fn allowed_mentions_json(content: &str) -> serde_json::Value {
+    let (users, roles) = mentioned_ids(content);
+    let ids = |ids: Vec<u64>| ids.iter().map(u64::to_string).collect::<Vec<_>>();
+    serde_json::json!({ "parse": [], "users": ids(users), "roles": ids(roles) })
+}
+
+/// What a queued job sends.
+enum Payload {
+    /// Plain text, which low-priority messages may be coalesced with.
+    Text(String),
+    Message(Outgoing),
+    /// Replaces the message with this ID.
+    Edit(u64, Outgoing),
+}
+
+struct Job {
+    http: Arc<Http>,
+    channel_id: u64,
+    priority: Priority,
+    /// Arrival order, so equal priorities are sent first-in first-out.
+    seq: u64,
+    /// When the job may be sent; low-priority text waits for others to join it until then.
+    ready_at: Instant,
+    payload: Payload,
+    reply: oneshot::Sender<Result<Message, SendError>>,
+}
+
+impl Job {
+    /// The message text, if this job may be coalesced with others.
+    fn text(&self) -> Option<&str> {
+        match &self.payload {
+            Payload::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    fn coalescible(&self) -> bool {
+        self.priority == Priority::Low && self.text().is_some()
+    }
+}
+
+impl PartialEq for Job {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Job {}
+
+impl PartialOrd for Job {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Job {
+    /// Higher priority first, then lower sequence number.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+static SENDER: Lazy<mpsc::UnboundedSender<Job>> = Lazy::new(|| {
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(run(rx));
+    tx
+});
+
+static SEQ: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// Queues `message` for `channel_id` and waits until it has been sent (or failed).
+pub async fn send(http: Arc<Http>, channel_id: u64, priority: Priority, message: String) -> SendResult {
+    enqueue(http, channel_id, priority, Payload::Text(message)).await.map(|_| ())
+}
+
+/// Queues a message with embeds, components or a file and waits until it has been sent.
+pub async fn send_message(
+    http: Arc<Http>,
+    channel_id: u64,
+    priority: Priority,
+    message: Outgoing,
+) -> Result<Message, SendError> {
+    enqueue(http, channel_id, priority, Payload::Message(message)).await
+}
+
+/// Queues an edit of message `message_id` in `channel_id` and waits until it is done.
+pub async fn edit_message(
+    http: Arc<Http>,
+    channel_id: u64,
+    message_id: u64,
+    priority: Priority,
+    message: Outgoing,
+) -> Result<Message, SendError> {
+    enqueue(http, channel_id, priority, Payload::Edit(message_id, message)).await
+}
+
+async fn enqueue(
+    http: Arc<Http>,
+    channel_id: u64,
+    priority: Priority,
+    payload: Payload,
+) -> Result<Message, SendError> {
+    let (reply, result) = oneshot::channel();
+    let mut job = Job {
+        http,
+        channel_id,
+        priority,
+        seq: SEQ.fetch_add(1, std::sync::atomic::Ordering::SeqCst),
+        ready_at: Instant::now(),
+        payload,
+        reply,
+    };
+    if job.coalescible() {
+        job.ready_at += coalesce_window();
+    }
+    SENDER.send(job).map_err(|_| SendError::closed("message queue is closed"))?;
+    result
+        .await
+        .unwrap_or_else(|_| Err(SendError::closed("message queue dropped the message")))
+}
+
+fn coalesce_window() -> Duration {
+    let ms = env::var("NOTIFY_COALESCE_MS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_COALESCE_MS);
+    Duration::from_millis(ms)
+}
+
+async fn run(mut rx: mpsc::UnboundedReceiver<Job>) {
+    let mut heap = BinaryHeap::new();
+    // Channels with a send in flight; their next message waits until it's done.
+    let mut busy = HashSet::new();
+    let (done_tx, mut done_rx) = mpsc::unbounded_channel();
+
+    loop {
+        let now = Instant::now();
+        while let Some(first) = take_next(&mut heap, &busy, now) {
+            let mut batch = vec![first];
+            if batch[0].coalescible() && !coalesce_window().is_zero() {
+                batch.extend(take_batch(&mut heap, &batch[0]));
+            }
+            busy.insert(batch[0].channel_id);
+            let done = done_tx.clone();
+            tokio::spawn(async move {
+                let channel_id = batch[0].channel_id;
+                send_batch(batch).await;
+                let _ = done.send(channel_id);
+            });
+        }
+
+        // Wake up when the next waiting low-priority message for an idle channel is due.
+        let wake = heap
+            .iter()
+            .filter(|job| !busy.contains(&job.channel_id))
+            .map(|job| job.ready_at)
+            .min();
+        tokio::select! {
+            job = rx.recv() => match job {
+                Some(job) => heap.push(job),
+                None => return,
+            },
+            Some(channel_id) = done_rx.recv() => {
+                busy.remove(&channel_id);
+            }
+            _ = tokio::time::sleep_until(wake.unwrap_or(now)), if wake.is_some() => {}
+        }
+    }
+}
+
+/// Removes the most urgent job that may be sent at `now`: its channel is idle and its
+/// coalescing window has passed.
+fn take_next(heap: &mut BinaryHeap<Job>, busy: &HashSet<u64>, now: Instant) -> Option<Job> {
+    let ready = |job: &Job| !busy.contains(&job.channel_id) && job.ready_at <= now;
+    if heap.peek().is_some_and(ready) {
+        return heap.pop();
+    }
+
+    // Sorted ascending, so the last ready job is the most urgent one.
+    let mut jobs = std::mem::take(heap).into_sorted_vec();
+    let job = jobs.iter().rposition(ready).map(|i| jobs.remove(i));
+    heap.extend(jobs);
+    job
+}
+
+/// Sends a job, or a batch of coalesced low-priority texts, and tells every caller how it went.
+async fn send_batch(batch: Vec<Job>) {
+    let first = &batch[0];
+    let channel = ChannelId(first.channel_id);
+    let result = match &first.payload {
+        Payload::Text(_) => {
+            let message = Outgoing::text(batch.iter().filter_map(Job::text).collect::<Vec<_>>().join("\n\n"));
+            channel.send_message(&first.http, |m| message.fill_create(m)).await
+        }
+        Payload::Message(message) => channel.send_message(&first.http, |m| message.fill_create(m)).await,
+        Payload::Edit(id, message) => {
+            channel.edit_message(&first.http, MessageId(*id), |m| message.fill_edit(m)).await
+        }
+    }
+    .map_err(|e| SendError::from_serenity(&e));
+
+    if batch.len() > 1 {
+        println!("Coalesced {} low-priority messages for channel {}.", batch.len(), first.channel_id);
+    }
+    for job in batch {
+        let _ = job.reply.send(result.clone());
+    }
+}
+
+/// Removes low-priority text jobs for the same channel that fit in one message with `first`.
+/// Stops at the first low-priority job for the channel that can't join, so the channel's
+/// messages keep their order.
+fn take_batch(heap: &mut BinaryHeap<Job>, first: &Job) -> Vec<Job> {
+    let mut length = first.text().unwrap_or_default().chars().count();
+    let mut batch = Vec::new();
+    let mut rest = Vec::new();
+
+    let mut jobs = std::mem::take(heap).into_sorted_vec();
+    // Sorted ascending; walk from the most urgent so FIFO order is kept within the batch.
+    while let Some(job) = jobs.pop() {
+        if job.priority != Priority::Low || job.channel_id != first.channel_id {
+            rest.push(job);
+            continue;
+        }
+        let added = job.text().map(|text| text.chars().count() + 2);
+        match added {
+            Some(added) if length + added <= MAX_MESSAGE_CHARS => {
+                length += added;
+                batch.push(job);
+            }
+            _ => {
+                rest.push(job);
+                break;
+            }
+        }
+    }
+
+    rest.extend(jobs);
+    heap.extend(rest);
+    batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(channel_id: u64, priority: Priority, seq: u64, payload: Payload) -> Job {
+        let (reply, _) = oneshot::channel();
+        Job {
+            http: Arc::new(Http::new("")),
+            channel_id,
+            priority,
+            seq,
+            ready_at: Instant::now(),
+            payload,
+            reply,
+        }
+    }
+
+    fn text(channel_id: u64, priority: Priority, seq: u64, text: &str) -> Job {
+        job(channel_id, priority, seq, Payload::Text(text.to_string()))
+    }
+
+    fn texts(jobs: &[Job]) -> Vec<&str> {
+        jobs.iter().filter_map(Job::text).collect()
+    }
+
+    #[test]
+    fn most_urgent_first_then_first_in_first_out() {
+        let mut heap = BinaryHeap::new();
+        heap.push(text(1, Priority::Low, 0, "low 1"));
+        heap.push(text(1, Priority::Normal, 1, "normal 1"));
+        heap.push(text(1, Priority::Low, 2, "low 2"));
+        heap.push(text(1, Priority::Critical, 3, "critical"));
+        heap.push(text(1, Priority::Normal, 4, "normal 2"));
+
+        let order: Vec<String> = std::iter::from_fn(|| heap.pop())
+            .filter_map(|job| job.text().map(str::to_string))
+            .collect();
+        assert_eq!(order, ["critical", "normal 1", "normal 2", "low 1", "low 2"]);
+    }
+
+    #[test]
+    fn take_next_skips_busy_channels_and_waiting_text() {
+        let mut heap = BinaryHeap::new();
+        heap.push(text(1, Priority::Critical, 1, "rate limited"));
+        heap.push(text(2, Priority::Critical, 2, "alert"));
+        heap.push(text(1, Priority::Normal, 3, "normal"));
+        let now = Instant::now();
+        let mut waiting = text(3, Priority::Low, 0, "waiting");
+        waiting.ready_at = now + Duration::from_secs(1);
+        heap.push(waiting);
+
+        let busy = HashSet::from([1]);
+        assert_eq!(take_next(&mut heap, &busy, now).unwrap().text(), Some("alert"));
+        assert!(take_next(&mut heap, &busy, now).is_none());
+        assert_eq!(heap.len(), 3);
+        let later = now + Duration::from_secs(1);
+        assert_eq!(take_next(&mut heap, &busy, later).unwrap().text(), Some("waiting"));
+    }
+
+    #[test]
+    fn take_batch_coalesces_low_priority_text_for_the_same_channel() {
+        let first = text(1, Priority::Low, 0, "first");
+        let mut heap = BinaryHeap::new();
+        heap.push(text(1, Priority::Low, 1, "a"));
+        heap.push(text(2, Priority::Low, 2, "other channel"));
+        heap.push(text(1, Priority::Normal, 3, "normal"));
+        heap.push(text(1, Priority::Low, 4, "b"));
+        heap.push(job(1, Priority::Low, 5, Payload::Message(Outgoing::text("embed"))));
+        heap.push(text(1, Priority::Low, 6, "after the embed"));
+
+        let batch = take_batch(&mut heap, &first);
+        assert_eq!(texts(&batch), ["a", "b"]);
+
+        let mut rest: Vec<u64> = heap.into_iter().map(|job| job.seq).collect();
+        rest.sort_unstable();
+        assert_eq!(rest, [2, 3, 5, 6]);
+    }
+
+    #[test]
+    fn take_batch_stops_at_the_message_length_limit() {
+        let first = text(1, Priority::Low, 0, &"x".repeat(1000));
+        let mut heap = BinaryHeap::new();
+        heap.push(text(1, Priority::Low, 1, &"y".repeat(900)));
+        heap.push(text(1, Priority::Low, 2, &"z".repeat(200)));
+        heap.push(text(1, Priority::Low, 3, "short"));
+
+        let batch = take_batch(&mut heap, &first);
+        let seqs: Vec<u64> = batch.iter().map(|job| job.seq).collect();
+        assert_eq!(seqs, [1]);
+        assert_eq!(heap.len(), 2);
+    }
+
+    #[test]
+    fn only_explicit_mentions_may_ping() {
+        let content = "<@&7> <@42> <@!43> <@42> @everyone @here <@\u{200b}&8> <@&x>";
+        assert_eq!(mentioned_ids(content), (vec![42, 43], vec![7]));
+        assert_eq!(mentioned_ids("no mentions @everyone"), (vec![], vec![]));
+    }
+
+    #[test]
+    fn webhook_payloads_allow_the_same_mentions() {
+        let allowed = allowed_mentions_json("<@&7> <@42> @everyone");
+        assert_eq!(allowed, serde_json::json!({ "parse": [], "users": ["42"], "roles": ["7"] }));
+    }
+}
@@ -0,0 +1,54 @@
+//! Read-only observer mode, for demo instances and training new operators.
+//!
+//! With `OBSERVER_MODE=true` every command and button that changes something (restarts,
+//! deploys, reboots, purges, freezes, merges, configuration, ...) is answered with an
+//! explanation instead of running. Commands that only read, listed in [`READ_ONLY`], keep
+//! working, as do status updates, alerts, and GitHub notifications.
+//!
+//! Environment Variables:
+//! - `OBSERVER_MODE`: Set to `true` to disable state-changing commands (default: false)
+
+use std::env;
+
+/// Commands that only read, as `(command, subcommand)`. A `None` subcommand covers the whole
+/// command; anything not listed is treated as state-changing.
+pub const READ_ONLY: &[(&str, Option<&str>)] = &[
+    ("alerts", Some("list")),
+    ("botstats", None),
+    ("fetch-file", None),
+    ("find", None),
+    ("guild-config", Some("show")),
+    ("health", None),
+    ("permissions", None),
+    ("schedule", None),
+    ("selftest", None),
+    ("show-file", None),
+    ("smoke-test", None),
+    ("status", None),
+    ("tail_logs", None),
+    ("tasks", None),
+    ("uptime", None),
+    ("usage", None),
+    ("watch", Some("list")),
+];
+
+/// Whether observer mode is on.
+pub fn enabled() -> bool {
+    env::var("OBSERVER_MODE").is_ok_and(|v| v.trim().eq_ignore_ascii_case("true"))
+}
+
+/// Whether `command` (or its `subcommand`) may run in observer mode.
+pub fn allows(command: &str, subcommand: Option<&str>) -> bool {
+    READ_ONLY
+        .iter()
+        .any(|(name, sub)| *name == command && (sub.is_none() || *sub == subcommand))
+}
+
+/// The reply to an attempt to `action` in observer mode, e.g. "restart `api`".
+pub fn refusal(action: &str) -> String {
+    format!(
+        "👀 This bot runs in **observer mode**, so it won't {}. Status, logs, and GitHub \
+         notifications still work; state-changing commands are disabled on this instance.",
+        action
+    )
+}
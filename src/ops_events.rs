@@ -0,0 +1,77 @@
+//! Rolling journal of operational events (deployments, incidents, alerts, CI results and
+//! maintainer actions taken from Discord).
+//!
+//! Features record notable events here as they happen; reports such as the weekly
+//! operations summary read them back. Entries older than the retention window are
+//! dropped on write.
+
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::store;
+
+const EVENTS_FILE: &str = "ops_events.json";
+const RETENTION_SECS: i64 = 60 * 86_400;
+
+static EVENTS: Lazy<Mutex<Vec<OpsEvent>>> = Lazy::new(|| Mutex::new(store::load(EVENTS_FILE)));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Deployment,
+    Incident,
+    Alert,
+    CiSuccess,
+    CiFailure,
+    /// A PR approved or merged, or a workflow re-run, through the bot's buttons.
+    MaintainerAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpsEvent {
+    /// Unix timestamp (seconds).
+    pub timestamp: i64,
+    pub kind: EventKind,
+    pub summary: String,
+    /// Where to read more, e.g. a deployment's logs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+/// Records an event with the current time.
+pub fn record(kind: EventKind, summary: impl Into<String>) {
+    record_with_link(kind, summary, None);
+}
+
+/// Records an event with the current time and a link to its details.
+pub fn record_with_link(kind: EventKind, summary: impl Into<String>, link: Option<String>) {
+    let now = Utc::now().timestamp();
+    let mut events = EVENTS.lock().unwrap();
+
+    events.retain(|e| now - e.timestamp < RETENTION_SECS);
+    events.push(OpsEvent {
+        timestamp: now,
+        kind,
+        summary: summary.into(),
+        link,
+    });
+    store::save(EVENTS_FILE, &*events);
+}
+
+/// Returns every event recorded in `[from, to)`.
+pub fn between(from: i64, to: i64) -> Vec<OpsEvent> {
+    EVENTS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|e| e.timestamp >= from && e.timestamp < to)
+        .cloned()
+        .collect()
+}
+
+/// Returns every event still in the journal, oldest first.
+pub fn all() -> Vec<OpsEvent> {
+    EVENTS.lock().unwrap().clone()
+}
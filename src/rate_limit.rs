@@ -0,0 +1,194 @@
+//! Per-source-IP rate limiting of the webhook endpoints.
+//!
+//! Every source IP gets a token bucket: it may send a burst of requests at once, after which
+//! it is limited to a steady rate. Requests over the limit are answered with
+//! `429 Too Many Requests` and a `Retry-After` header before their body is read, so a
+//! misconfigured or malicious sender can't flood Discord through the bot. Rejected GitHub
+//! deliveries can be redelivered from the webhook's delivery log once the sender slows down.
+//!
+//! Behind a reverse proxy every request comes from the proxy; list it in
+//! `WEBHOOK_TRUSTED_PROXIES` to limit by the `X-Forwarded-For` client address instead.
+//!
+//! Environment Variables:
+//! - `WEBHOOK_RATE_LIMIT_PER_MIN`: Sustained requests per minute per IP; 0 disables the limit
+//!   (default: 120)
+//! - `WEBHOOK_RATE_LIMIT_BURST`: Requests an IP may send at once (default: 60)
+//! - `WEBHOOK_TRUSTED_PROXIES`: Comma-separated proxy IPs whose `X-Forwarded-For` is trusted
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::{header::RETRY_AFTER, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    env,
+    net::{IpAddr, SocketAddr},
+    sync::Mutex,
+    time::Instant,
+};
+
+const DEFAULT_PER_MIN: f64 = 120.0;
+const DEFAULT_BURST: f64 = 60.0;
+/// Buckets tracked before idle ones are dropped.
+const MAX_TRACKED: usize = 10_000;
+
+static BUCKETS: Lazy<Mutex<HashMap<IpAddr, Bucket>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// Requests rejected since the IP was last let through, to log each episode once.
+    rejected: u64,
+}
+
+fn env_f64(key: &str, default: f64) -> f64 {
+    env::var(key)
+        .ok()
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|v| *v >= 0.0)
+        .unwrap_or(default)
+}
+
+fn trusted_proxies() -> Vec<IpAddr> {
+    env::var("WEBHOOK_TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|ip| ip.trim().parse().ok())
+        .collect()
+}
+
+/// The client's IP: the peer, or the nearest untrusted `X-Forwarded-For` hop when the peer
+/// is a trusted proxy.
+fn client_ip(peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+    let proxies = trusted_proxies();
+    if !proxies.contains(&peer) {
+        return peer;
+    }
+    headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|hop| hop.trim().parse::<IpAddr>().ok())
+        .rev()
+        .find(|hop| !proxies.contains(hop))
+        .unwrap_or(peer)
+}
+
+/// Shrinks `buckets` once it holds `max` of them. Full buckets belong to IPs that have been
+/// quiet long enough to start over and go first; if that isn't enough (a flood from many
+/// IPs), the least recently seen tenth are dropped too.
+fn prune(buckets: &mut HashMap<IpAddr, Bucket>, max: usize, now: Instant, per_sec: f64, burst: f64) {
+    if buckets.len() < max {
+        return;
+    }
+    buckets.retain(|_, b| b.tokens + now.duration_since(b.updated).as_secs_f64() * per_sec < burst);
+
+    if buckets.len() >= max {
+        let keep = max - max.div_ceil(10);
+        let mut seen: Vec<Instant> = buckets.values().map(|b| b.updated).collect();
+        let evict = seen.len() - keep;
+        let (_, cutoff, _) = seen.select_nth_unstable(evict - 1);
+        let cutoff = *cutoff;
+        buckets.retain(|_, b| b.updated > cutoff);
+    }
+}
+
+/// Takes a token from `ip`'s bucket, returning the seconds until one is available if empty.
+fn take(ip: IpAddr, per_min: f64, burst: f64) -> Result<(), u64> {
+    let now = Instant::now();
+    let per_sec = per_min / 60.0;
+    let mut buckets = BUCKETS.lock().unwrap();
+    prune(&mut buckets, MAX_TRACKED, now, per_sec, burst);
+
+    let bucket = buckets
+        .entry(ip)
+        .or_insert(Bucket { tokens: burst, updated: now, rejected: 0 });
+    let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+    bucket.tokens = (bucket.tokens + elapsed * per_sec).min(burst);
+    bucket.updated = now;
+
+    if bucket.tokens >= 1.0 {
+        bucket.tokens -= 1.0;
+        if bucket.rejected > 0 {
+            println!("Webhook rate limit lifted for {} after {} rejected requests", ip, bucket.rejected);
+            bucket.rejected = 0;
+        }
+        return Ok(());
+    }
+
+    bucket.rejected += 1;
+    if bucket.rejected == 1 {
+        eprintln!("Webhook rate limit hit by {}, answering 429 until it slows down", ip);
+    }
+    Err(((1.0 - bucket.tokens) / per_sec).ceil().max(1.0) as u64)
+}
+
+/// Middleware answering `429 Too Many Requests` to IPs over the limit.
+pub async fn limit(ConnectInfo(peer): ConnectInfo<SocketAddr>, request: Request, next: Next) -> Response {
+    let per_min = env_f64("WEBHOOK_RATE_LIMIT_PER_MIN", DEFAULT_PER_MIN);
+    if per_min == 0.0 {
+        return next.run(request).await;
+    }
+    let burst = env_f64("WEBHOOK_RATE_LIMIT_BURST", DEFAULT_BURST).max(1.0);
+
+    let ip = client_ip(peer.ip(), request.headers());
+    match take(ip, per_min, burst) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, retry_after.to_string())],
+            "Too many webhook requests, slow down.",
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::from([192, 0, 2, last])
+    }
+
+    #[test]
+    fn take_allows_a_burst_then_rejects() {
+        for _ in 0..3 {
+            assert_eq!(take(ip(1), 60.0, 3.0), Ok(()));
+        }
+        assert_eq!(take(ip(1), 60.0, 3.0), Err(1));
+    }
+
+    #[test]
+    fn take_reports_the_wait_for_the_next_token() {
+        assert_eq!(take(ip(2), 6.0, 1.0), Ok(()));
+        assert_eq!(take(ip(2), 6.0, 1.0), Err(10));
+    }
+
+    #[test]
+    fn prune_drops_the_least_recently_seen_during_a_flood() {
+        let start = Instant::now();
+        let mut buckets: HashMap<IpAddr, Bucket> = (0..20u8)
+            .map(|last| {
+                let updated = start + std::time::Duration::from_millis(last as u64);
+                (ip(last), Bucket { tokens: 0.0, updated, rejected: 0 })
+            })
+            .collect();
+
+        prune(&mut buckets, 20, start, 60.0, 3.0);
+        assert_eq!(buckets.len(), 18);
+        assert!(!buckets.contains_key(&ip(0)) && !buckets.contains_key(&ip(1)));
+        assert!(buckets.contains_key(&ip(2)));
+    }
+
+    #[test]
+    fn take_keeps_a_bucket_per_ip() {
+        assert_eq!(take(ip(3), 60.0, 1.0), Ok(()));
+        assert!(take(ip(3), 60.0, 1.0).is_err());
+        assert_eq!(take(ip(4), 60.0, 1.0), Ok(()));
+    }
+}
@@ -0,0 +1,132 @@
+//! Persisted routing configuration for per-module Discord channels.
+//!
+//! Each provisioned FitchFork module (e.g. `cos301`) gets its own category with
+//! announcements, CI, and alerts channels. Their IDs are stored in `routing.json`, keyed by
+//! guild and module code, so each guild can provision its own `cos301`.
+//!
+//! A module provisioned with `repos` receives those repositories' GitHub notifications in its
+//! channels (see [`crate::guilds::github_channel`]): releases and announcements in
+//! `announcements`, CI, pushes, and deployments in `ci`, and alerts and security advisories in
+//! `alerts`. Its other events (PRs, reviews, issues, ...) go where they would without the
+//! module. Modules outside the operators' guild may only route repositories in
+//! `DISCORD_CLAIMABLE_REPOS`, like guilds.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Mutex};
+
+use crate::guilds::{self, ChannelKind};
+use crate::store;
+
+const ROUTING_FILE: &str = "routing.json";
+
+static ROUTING: Lazy<Mutex<RoutingConfig>> = Lazy::new(|| Mutex::new(store::load(ROUTING_FILE)));
+
+/// Channel IDs provisioned for a single module.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModuleChannels {
+    pub guild_id: u64,
+    pub category_id: u64,
+    pub announcements_id: u64,
+    pub ci_id: u64,
+    pub alerts_id: u64,
+    /// GitHub repositories routed to the module (`owner/name`, or `owner/*` for a whole org).
+    #[serde(default)]
+    pub repos: Vec<String>,
+}
+
+impl ModuleChannels {
+    /// The module's channel for `kind`, if it has one.
+    fn channel(&self, kind: ChannelKind) -> Option<u64> {
+        match kind {
+            ChannelKind::Announcements => Some(self.announcements_id),
+            ChannelKind::Workflows | ChannelKind::Pushes | ChannelKind::Deployments => Some(self.ci_id),
+            ChannelKind::Alerts | ChannelKind::Security => Some(self.alerts_id),
+            _ => None,
+        }
+    }
+
+    fn claims_repo(&self, repo: &str) -> bool {
+        self.repos.iter().any(|pattern| guilds::repo_matches(pattern, repo))
+            && (Some(self.guild_id) == guilds::home_guild() || guilds::is_claimable(repo))
+    }
+}
+
+/// Routing configuration persisted in `routing.json`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RoutingConfig {
+    /// Modules keyed by `<guild_id>/<code>`; entries from before modules were per guild are
+    /// keyed by the bare code.
+    #[serde(default)]
+    pub modules: BTreeMap<String, ModuleChannels>,
+}
+
+impl RoutingConfig {
+    /// Runs `f` on the routing configuration.
+    pub fn read<T>(f: impl FnOnce(&RoutingConfig) -> T) -> T {
+        f(&ROUTING.lock().unwrap())
+    }
+
+    /// Runs `f` on the routing configuration and saves the result.
+    pub fn update<T>(f: impl FnOnce(&mut RoutingConfig) -> T) -> T {
+        let mut routing = ROUTING.lock().unwrap();
+        let result = f(&mut routing);
+        store::save(ROUTING_FILE, &*routing);
+        result
+    }
+
+    /// The key module `code` of `guild_id` is stored under.
+    pub fn key(guild_id: u64, code: &str) -> String {
+        format!("{}/{}", guild_id, code)
+    }
+
+    /// Module `code` of `guild_id`, if it has been provisioned.
+    pub fn module(&self, guild_id: u64, code: &str) -> Option<&ModuleChannels> {
+        self.modules
+            .get(&Self::key(guild_id, code))
+            .or_else(|| self.modules.get(code).filter(|module| module.guild_id == guild_id))
+    }
+}
+
+/// The channel of the first module routing `repo` for `kind`, if a module routes `repo` and
+/// has a channel for `kind`.
+pub fn module_channel(repo: &str, kind: ChannelKind) -> Option<u64> {
+    RoutingConfig::read(|routing| routing.modules.values().find(|module| module.claims_repo(repo))?.channel(kind))
+}
+
+/// The guild of the module that provisioned `channel_id`, if any.
+pub fn module_guild(channel_id: u64) -> Option<u64> {
+    RoutingConfig::read(|routing| {
+        routing
+            .modules
+            .values()
+            .find(|m| [m.category_id, m.announcements_id, m.ci_id, m.alerts_id].contains(&channel_id))
+            .map(|m| m.guild_id)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_routed_repos_still_deliver_pull_requests() {
+        std::env::set_var("DISCORD_CLAIMABLE_REPOS", "routing-test/*");
+        std::env::set_var("DISCORD_PR_CHANNEL_ID", "555");
+        ROUTING.lock().unwrap().modules.insert(
+            RoutingConfig::key(1, "cos999"),
+            ModuleChannels {
+                guild_id: 1,
+                category_id: 10,
+                announcements_id: 11,
+                ci_id: 12,
+                alerts_id: 13,
+                repos: vec!["routing-test/*".to_string()],
+            },
+        );
+
+        assert_eq!(module_channel("routing-test/backend", ChannelKind::Workflows), Some(12));
+        assert_eq!(module_channel("routing-test/backend", ChannelKind::PullRequests), None);
+        assert_eq!(guilds::github_channel("routing-test/backend", ChannelKind::PullRequests), Some(555));
+    }
+}
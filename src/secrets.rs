@@ -0,0 +1,272 @@
+//! Rotation of the secrets and tokens the bot issues to senders (`/rotate-secret`).
+//!
+//! Rotating generates a new random value that takes effect immediately and is persisted in
+//! `rotated_secrets.json`, which overrides the environment (`.env`) from then on, including
+//! after a restart. The running bot keeps rotated values in memory rather than changing its
+//! environment, so rotatable secrets are read through [`current`]. The old value keeps being
+//! accepted for a grace period so the sender can be switched over without dropping
+//! deliveries, and is removed once it ends. Rotating again before then doesn't cut that grace
+//! period short: every old value keeps its own.
+//!
+//! Only values the bot checks can be rotated: webhook signing secrets and the tokens of its
+//! endpoints. Tokens issued by other services (`GITHUB_TOKEN`, `DISCORD_TOKEN`, Sentry's
+//! client secret) must be rotated there.
+//!
+//! Every endpoint guarded by a secret or token, rotatable or not, refuses all requests while
+//! it is unset, so setting it is what enables the endpoint.
+//!
+//! Environment Variables:
+//! - `SECRET_ROTATION_GRACE_HOURS`: How long an old value stays valid by default (default: 24)
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, OpenOptions},
+    io::Write,
+    sync::Mutex,
+};
+
+use crate::store;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const STATE_FILE: &str = "rotated_secrets.json";
+const DEFAULT_GRACE_HOURS: i64 = 24;
+/// Endpoint tokens the bot checks (besides per-webhook `CUSTOM_WEBHOOK_<NAME>_TOKEN`s).
+const TOKENS: &[&str] = &[
+    "ALERT_WEBHOOK_TOKEN",
+    "CUSTOM_WEBHOOK_TOKEN",
+    "JENKINS_WEBHOOK_TOKEN",
+    "SCHEDULE_FEED_TOKEN",
+    "STATUS_API_TOKEN",
+    "UPTIME_WEBHOOK_TOKEN",
+    "WEBHOOK_REPLAY_TOKEN",
+];
+
+static STATE: Lazy<Mutex<HashMap<String, Rotation>>> = Lazy::new(|| Mutex::new(load()));
+
+/// A rotated secret.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rotation {
+    pub value: String,
+    /// Values replaced by earlier rotations that are still in their grace period.
+    #[serde(default)]
+    pub retiring: Vec<Retiring>,
+    /// The single old value kept before rotations tracked several; moved into `retiring`.
+    #[serde(default, skip_serializing)]
+    previous: Option<String>,
+    #[serde(default, skip_serializing)]
+    previous_until: i64,
+    pub rotated_at: i64,
+    pub rotated_by: String,
+}
+
+/// An old value of a secret, accepted until `until`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Retiring {
+    pub value: String,
+    pub until: i64,
+}
+
+impl Rotation {
+    /// Old values whose grace period hasn't ended at `now`.
+    fn valid_retiring(&self, now: i64) -> impl Iterator<Item = &Retiring> {
+        self.retiring.iter().filter(move |old| old.until > now)
+    }
+}
+
+fn load() -> HashMap<String, Rotation> {
+    let mut state: HashMap<String, Rotation> = store::load(STATE_FILE);
+    for rotation in state.values_mut() {
+        if let Some(value) = rotation.previous.take() {
+            rotation.retiring.push(Retiring { value, until: rotation.previous_until });
+        }
+    }
+    state
+}
+
+/// What a secret is used for, which decides the steps shown after rotating it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// A GitHub webhook signing secret.
+    WebhookSecret,
+    /// A token senders present to one of the bot's endpoints.
+    EndpointToken,
+}
+
+/// What `name` is, or `None` if the bot can't rotate it.
+pub fn kind(name: &str) -> Option<Kind> {
+    let source_secret = name
+        .strip_prefix("GITHUB_SOURCE_")
+        .and_then(|rest| rest.strip_suffix("_SECRET"))
+        .is_some_and(|source| !source.is_empty());
+    let custom_token = name
+        .strip_prefix("CUSTOM_WEBHOOK_")
+        .and_then(|rest| rest.strip_suffix("_TOKEN"))
+        .is_some_and(|webhook| !webhook.is_empty());
+
+    if name == "GITHUB_WEBHOOK_SECRET" || name.starts_with("GITHUB_WEBHOOK_SECRET_") || source_secret {
+        Some(Kind::WebhookSecret)
+    } else if TOKENS.contains(&name) || custom_token {
+        Some(Kind::EndpointToken)
+    } else {
+        None
+    }
+}
+
+/// Names of every configured secret the bot can rotate, sorted.
+pub fn rotatable() -> Vec<String> {
+    let mut names: Vec<String> = env::vars()
+        .filter(|(name, value)| !value.is_empty() && kind(name).is_some())
+        .map(|(name, _)| name)
+        .collect();
+    names.sort();
+    names
+}
+
+/// The default grace period, in seconds.
+pub fn default_grace_secs() -> i64 {
+    env::var("SECRET_ROTATION_GRACE_HOURS")
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .filter(|hours| *hours >= 0)
+        .unwrap_or(DEFAULT_GRACE_HOURS)
+        * 3600
+}
+
+/// The current value of `name`: its latest rotation, or the environment if it was never
+/// rotated. `None` if it is unset or empty.
+pub fn current(name: &str) -> Option<String> {
+    let rotated = STATE.lock().unwrap().get(name).map(|rotation| rotation.value.clone());
+    rotated.or_else(|| env::var(name).ok()).filter(|v| !v.is_empty())
+}
+
+/// 32 random bytes from the OS, hex-encoded.
+fn generate() -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("cannot get random bytes: {}", e))?;
+    Ok(hex::encode(bytes))
+}
+
+/// Saves the state like [`store::save`], but creates the file readable by the bot's user
+/// only, since it holds live secrets.
+fn save(state: &HashMap<String, Rotation>) {
+    let path = store::data_path(STATE_FILE);
+    let tmp = path.with_extension("tmp");
+
+    let write = |json: String| -> std::io::Result<()> {
+        // A leftover temp file would keep its old permissions, so start from a fresh one.
+        let _ = fs::remove_file(&tmp);
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(&tmp)?.write_all(json.as_bytes())?;
+        fs::rename(&tmp, &path)
+    };
+    let result = serde_json::to_string_pretty(state)
+        .map_err(|e| e.to_string())
+        .and_then(|json| write(json).map_err(|e| e.to_string()));
+
+    if let Err(e) = result {
+        eprintln!("Failed to save {}: {}", path.display(), e);
+    }
+}
+
+/// Replaces `name` with a new random value, keeping the current one valid for `grace_secs`.
+/// Returns the new value.
+pub fn rotate(name: &str, grace_secs: i64, by: &str) -> Result<String, String> {
+    if kind(name).is_none() {
+        return Err(format!("`{}` is not a secret the bot can rotate", name));
+    }
+    let current = current(name).ok_or_else(|| format!("`{}` is not set", name))?;
+
+    let value = generate()?;
+    let now = Utc::now().timestamp();
+    let mut state = STATE.lock().unwrap();
+    let mut retiring: Vec<Retiring> = state
+        .get(name)
+        .map(|rotation| rotation.valid_retiring(now).cloned().collect())
+        .unwrap_or_default();
+    if grace_secs > 0 {
+        retiring.push(Retiring { value: current, until: now + grace_secs });
+    }
+    state.insert(
+        name.to_string(),
+        Rotation {
+            value: value.clone(),
+            retiring,
+            previous: None,
+            previous_until: 0,
+            rotated_at: now,
+            rotated_by: by.to_string(),
+        },
+    );
+    save(&state);
+    Ok(value)
+}
+
+/// The old values of `name` still in their grace period.
+pub fn previous(name: &str) -> Vec<String> {
+    let now = Utc::now().timestamp();
+    let state = STATE.lock().unwrap();
+    state
+        .get(name)
+        .map(|rotation| rotation.valid_retiring(now).map(|old| old.value.clone()).collect())
+        .unwrap_or_default()
+}
+
+/// Old values still in their grace period, as `(name, old value)`.
+pub fn retiring() -> Vec<(String, String)> {
+    let now = Utc::now().timestamp();
+    STATE
+        .lock()
+        .unwrap()
+        .iter()
+        .flat_map(|(name, rotation)| rotation.valid_retiring(now).map(|old| (name.clone(), old.value.clone())))
+        .collect()
+}
+
+/// Whether `presented` matches `name`'s `current` value, or its old value during the grace
+/// period.
+pub fn accepts(name: &str, current: &str, presented: &str) -> bool {
+    let old_matches = previous(name).iter().fold(false, |matched, old| matched | same(old, presented));
+    same(current, presented) | old_matches
+}
+
+/// Compares in constant time, like webhook signatures are checked: both values are run through
+/// an HMAC so neither how much of `presented` matches nor its length shows in the timing.
+fn same(expected: &str, presented: &str) -> bool {
+    let digest = |value: &str| {
+        let mut mac = HmacSha256::new_from_slice(expected.as_bytes()).expect("HMAC accepts keys of any length");
+        mac.update(value.as_bytes());
+        mac
+    };
+    digest(presented).verify_slice(&digest(expected).finalize().into_bytes()).is_ok()
+}
+
+/// Forgets `name`'s old values whose grace period has ended. Returns whether any were removed.
+pub fn remove_expired(name: &str) -> bool {
+    let now = Utc::now().timestamp();
+    let mut state = STATE.lock().unwrap();
+    let removed = match state.get_mut(name) {
+        Some(rotation) => {
+            let before = rotation.retiring.len();
+            rotation.retiring.retain(|old| old.until > now);
+            rotation.retiring.len() != before
+        }
+        None => false,
+    };
+    if removed {
+        save(&state);
+    }
+    removed
+}
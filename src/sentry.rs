@@ -0,0 +1,244 @@
+//! Sentry issue notifications.
+//!
+//! Add an internal integration in Sentry with the webhook URL `POST /webhook/sentry`, the
+//! `issue` resource subscribed and/or alert rule actions enabled. New and regressed issues are
+//! posted with their title, culprit, and event count. Regressions in production also ping the
+//! dev role.
+//!
+//! Sentry names the payload in `Sentry-Hook-Resource`:
+//! - `issue`: `created` for new issues, `unresolved` with substatus `regressed` for regressions
+//! - `event_alert`: an alert rule fired; rules whose name mentions "regress" count as regressions
+//!
+//! Environment Variables:
+//! - `SENTRY_CLIENT_SECRET`: The integration's client secret, used to verify
+//!   `Sentry-Hook-Signature`
+//! - `SENTRY_CHANNEL_ID`: Channel for Sentry issues (default: `DISCORD_STATUS_CHANNEL_ID`)
+//! - `SENTRY_PRODUCTION_ENVIRONMENTS`: Comma-separated environments whose regressions ping the
+//!   dev role (default `production`). Issue webhooks carry no environment and always ping.
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::env;
+
+use crate::github::mentions::escape_mentions;
+use crate::github::WebhookOutcome;
+use crate::notify::{self, Destination};
+use crate::ops_events::{self, EventKind};
+use crate::AppState;
+
+const HANDLER: &str = "sentry";
+
+pub fn routes(shared_state: AppState) -> Router {
+    Router::new()
+        .route("/sentry", post(handle_sentry_webhook))
+        .with_state(shared_state)
+}
+
+#[derive(Debug, Deserialize)]
+struct IssuePayload {
+    action: String,
+    data: IssueData,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueData {
+    issue: Issue,
+}
+
+#[derive(Debug, Deserialize)]
+struct Issue {
+    title: String,
+    culprit: Option<String>,
+    /// Sentry sends the event count as a string.
+    count: Option<serde_json::Value>,
+    #[serde(alias = "permalink")]
+    web_url: Option<String>,
+    substatus: Option<String>,
+    project: Option<Project>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Project {
+    slug: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AlertPayload {
+    data: AlertData,
+}
+
+#[derive(Debug, Deserialize)]
+struct AlertData {
+    event: AlertEvent,
+    triggered_rule: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AlertEvent {
+    title: String,
+    culprit: Option<String>,
+    web_url: Option<String>,
+    environment: Option<String>,
+    project: Option<serde_json::Value>,
+}
+
+/// What happened, normalized across both payload types.
+struct Notification {
+    regression: bool,
+    title: String,
+    culprit: Option<String>,
+    count: Option<String>,
+    url: Option<String>,
+    project: Option<String>,
+    environment: Option<String>,
+    rule: Option<String>,
+}
+
+async fn handle_sentry_webhook(State(state): State<AppState>, headers: HeaderMap, body: Bytes) -> Response {
+    let secret = match env::var("SENTRY_CLIENT_SECRET").ok().filter(|s| !s.is_empty()) {
+        Some(secret) => secret,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    if !signature_matches(&secret, &headers, &body) {
+        eprintln!("Rejected Sentry webhook: missing or invalid Sentry-Hook-Signature");
+        return WebhookOutcome::unauthorized("missing or invalid Sentry-Hook-Signature").into_response();
+    }
+
+    let resource = headers
+        .get("Sentry-Hook-Resource")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let notification = match resource {
+        "issue" => match serde_json::from_slice::<IssuePayload>(&body) {
+            Ok(payload) => match from_issue(payload) {
+                Ok(notification) => notification,
+                Err(outcome) => return outcome.into_response(),
+            },
+            Err(e) => return WebhookOutcome::bad_request(HANDLER, e.to_string()).into_response(),
+        },
+        "event_alert" => match serde_json::from_slice::<AlertPayload>(&body) {
+            Ok(payload) => from_alert(payload),
+            Err(e) => return WebhookOutcome::bad_request(HANDLER, e.to_string()).into_response(),
+        },
+        // Sentry sends `installation` when the integration is added; nothing to post.
+        other => {
+            return WebhookOutcome::ignored(HANDLER, format!("unsupported resource `{}`", other)).into_response();
+        }
+    };
+
+    notify_issue(&state, notification).await.into_response()
+}
+
+/// Verifies the hex HMAC-SHA256 of the body in `Sentry-Hook-Signature`.
+fn signature_matches(secret: &str, headers: &HeaderMap, body: &[u8]) -> bool {
+    let signature = match headers
+        .get("Sentry-Hook-Signature")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| hex::decode(v.trim()).ok())
+    {
+        Some(signature) => signature,
+        None => return false,
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+fn from_issue(payload: IssuePayload) -> Result<Notification, WebhookOutcome> {
+    let issue = payload.data.issue;
+    let regression = match payload.action.as_str() {
+        "created" => false,
+        "unresolved" if issue.substatus.as_deref() == Some("regressed") => true,
+        other => return Err(WebhookOutcome::ignored(HANDLER, format!("issue action `{}` is not reported", other))),
+    };
+
+    Ok(Notification {
+        regression,
+        title: issue.title,
+        culprit: issue.culprit,
+        count: issue.count.map(|c| c.as_str().map(str::to_string).unwrap_or_else(|| c.to_string())),
+        url: issue.web_url,
+        project: issue.project.map(|p| p.slug),
+        environment: None,
+        rule: None,
+    })
+}
+
+fn from_alert(payload: AlertPayload) -> Notification {
+    let event = payload.data.event;
+    let rule = payload.data.triggered_rule;
+    Notification {
+        regression: rule.as_deref().is_some_and(|r| r.to_lowercase().contains("regress")),
+        title: event.title,
+        culprit: event.culprit,
+        count: None,
+        url: event.web_url,
+        // Alert events identify the project by ID or slug depending on the Sentry version.
+        project: event.project.map(|p| p.as_str().map(str::to_string).unwrap_or_else(|| p.to_string())),
+        environment: event.environment,
+        rule,
+    }
+}
+
+fn production_environments() -> Vec<String> {
+    env::var("SENTRY_PRODUCTION_ENVIRONMENTS")
+        .unwrap_or_else(|_| "production".to_string())
+        .split(',')
+        .map(|e| e.trim().to_lowercase())
+        .filter(|e| !e.is_empty())
+        .collect()
+}
+
+async fn notify_issue(state: &AppState, n: Notification) -> WebhookOutcome {
+    let channel_id = match ["SENTRY_CHANNEL_ID", "DISCORD_STATUS_CHANNEL_ID"]
+        .iter()
+        .find_map(|key| env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok()))
+    {
+        Some(id) => id,
+        None => return WebhookOutcome::ignored(HANDLER, "no Sentry or status channel configured"),
+    };
+
+    let in_production = n
+        .environment
+        .as_ref()
+        .is_none_or(|env| production_environments().contains(&env.to_lowercase()));
+    let ping = match env::var("DISCORD_DEV_ROLE_ID").ok().and_then(|v| v.trim().parse::<u64>().ok()) {
+        Some(role) if n.regression && in_production => format!("<@&{}> ", role),
+        _ => String::new(),
+    };
+
+    let (emoji, what) = if n.regression { ("🔁", "Regression") } else { ("🐞", "New issue") };
+    let mut message = format!("{}{} **{}**", ping, emoji, what);
+    if let Some(project) = &n.project {
+        message.push_str(&format!(" in `{}`", escape_mentions(project)));
+    }
+    if let Some(env) = &n.environment {
+        message.push_str(&format!(" ({})", escape_mentions(env)));
+    }
+    message.push_str(&format!(":\n**{}**", escape_mentions(&n.title)));
+    if let Some(culprit) = n.culprit.as_deref().filter(|c| !c.is_empty()) {
+        message.push_str(&format!("\n`{}`", escape_mentions(culprit)));
+    }
+    if let Some(count) = &n.count {
+        message.push_str(&format!("\nEvents: {}", escape_mentions(count)));
+    }
+    if let Some(rule) = &n.rule {
+        message.push_str(&format!("\nAlert rule: {}", escape_mentions(rule)));
+    }
+    if let Some(url) = &n.url {
+        message.push_str(&format!("\n{}", url));
+    }
+
+    ops_events::record(EventKind::Alert, format!("Sentry {}: {}", what.to_lowercase(), n.title));
+    let delivery = notify::send(state, HANDLER, Destination::Channel(channel_id), message).await;
+    WebhookOutcome::from_delivery(HANDLER, delivery)
+}
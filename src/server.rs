@@ -0,0 +1,128 @@
+//! HTTP server lifecycle helpers.
+//!
+//! Includes:
+//! - Listener setup that reuses a systemd-activated socket (`LISTEN_FDS`) when present, or
+//!   binds with `SO_REUSEPORT` so a new process can take over the port while the old one drains
+//! - A cloneable [`Shutdown`] handle, triggered by SIGINT/SIGTERM, that drives axum's graceful
+//!   shutdown and stops the Discord shards
+
+use std::{env, net::SocketAddr, sync::Arc};
+use tokio::{
+    net::{TcpListener, TcpSocket},
+    sync::watch,
+};
+
+/// Cloneable shutdown flag shared by the HTTP server, the bot, and background tasks.
+#[derive(Clone)]
+pub struct Shutdown {
+    tx: Arc<watch::Sender<bool>>,
+    rx: watch::Receiver<bool>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        let (tx, rx) = watch::channel(false);
+        Self { tx: Arc::new(tx), rx }
+    }
+
+    /// Signals every waiter that the process is shutting down.
+    pub fn trigger(&self) {
+        self.tx.send_replace(true);
+    }
+
+    /// Resolves once shutdown has been requested.
+    pub async fn wait(&self) {
+        let mut rx = self.rx.clone();
+        let _ = rx.wait_for(|stopping| *stopping).await;
+    }
+}
+
+/// Waits for SIGINT (Ctrl+C) or SIGTERM and then triggers `shutdown`.
+pub async fn listen_for_signals(shutdown: Shutdown) {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    println!("Shutdown signal received, draining in-flight requests...");
+    shutdown.trigger();
+}
+
+/// Returns the HTTP listener for `addr`.
+///
+/// Prefers a socket handed over by systemd socket activation, so connections queue in the
+/// kernel while the bot restarts. Otherwise binds a fresh socket with `SO_REUSEADDR` and
+/// `SO_REUSEPORT` set.
+pub fn bind_listener(addr: SocketAddr) -> std::io::Result<TcpListener> {
+    if let Some(listener) = activated_listener()? {
+        println!("Using socket-activated listener (LISTEN_FDS)");
+        return Ok(listener);
+    }
+
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    socket.set_reuseaddr(true)?;
+    #[cfg(unix)]
+    socket.set_reuseport(true)?;
+    socket.bind(addr)?;
+    socket.listen(1024)
+}
+
+/// Takes over the first socket passed by systemd (`LISTEN_FDS`/`LISTEN_PID`), if any.
+#[cfg(unix)]
+fn activated_listener() -> std::io::Result<Option<TcpListener>> {
+    use std::os::unix::io::FromRawFd;
+
+    // systemd passes activated sockets starting at file descriptor 3.
+    const SD_LISTEN_FDS_START: i32 = 3;
+
+    let for_this_process = env::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        .is_some_and(|pid| pid == std::process::id());
+    let fd_count = env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|n| n.parse::<i32>().ok())
+        .unwrap_or(0);
+
+    if !for_this_process || fd_count < 1 {
+        return Ok(None);
+    }
+
+    // SAFETY: systemd guarantees fd 3 is an open listening socket owned by this process
+    // when LISTEN_PID matches our pid, and nothing else in the process uses it.
+    let std_listener = unsafe { std::net::TcpListener::from_raw_fd(SD_LISTEN_FDS_START) };
+    std_listener.set_nonblocking(true)?;
+    TcpListener::from_std(std_listener).map(Some)
+}
+
+#[cfg(not(unix))]
+fn activated_listener() -> std::io::Result<Option<TcpListener>> {
+    Ok(None)
+}
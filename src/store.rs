@@ -0,0 +1,37 @@
+//! Small JSON-file persistence helpers for local bot state.
+//!
+//! Files live in `BOT_DATA_DIR` (default: the working directory, like `status_message_id.txt`).
+//! Reads fall back to the type's default on a missing or unreadable file; write failures
+//! are logged to stderr and otherwise ignored, matching the status loop's behaviour.
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::{env, fs, path::PathBuf};
+
+/// Resolves `name` inside the bot's data directory.
+pub fn data_path(name: &str) -> PathBuf {
+    let dir = env::var("BOT_DATA_DIR").unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(dir).join(name)
+}
+
+/// Loads a JSON document, returning `T::default()` if it is missing or invalid.
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> T {
+    fs::read_to_string(data_path(name))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Saves a JSON document, writing to a temporary file first so readers never see a partial write.
+pub fn save<T: Serialize>(name: &str, value: &T) {
+    let path = data_path(name);
+    let tmp = path.with_extension("tmp");
+
+    let result = serde_json::to_string_pretty(value)
+        .map_err(|e| e.to_string())
+        .and_then(|json| fs::write(&tmp, json).map_err(|e| e.to_string()))
+        .and_then(|_| fs::rename(&tmp, &path).map_err(|e| e.to_string()));
+
+    if let Err(e) = result {
+        eprintln!("Failed to save {}: {}", path.display(), e);
+    }
+}
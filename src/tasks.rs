@@ -0,0 +1,187 @@
+//! Supervisor for long-running background tasks.
+//!
+//! Loops like the status updater are started from `ready`, which fires again after every
+//! gateway reconnect. Each loop is registered here under a name, so a reconnect reuses the
+//! running task instead of spawning a duplicate, and shutdown can cancel them all.
+//!
+//! A loop that panics is restarted with exponential backoff. Loops call [`Beat::tick`] once
+//! per iteration so `/tasks` can show when each last ran.
+
+use chrono::Utc;
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    future::Future,
+    sync::atomic::{AtomicI64, AtomicU32, Ordering},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Handed to a task so it can record that it is still making progress.
+#[derive(Clone)]
+pub struct Beat(Arc<AtomicI64>);
+
+impl Beat {
+    /// Records that the task just ran an iteration.
+    pub fn tick(&self) {
+        self.0.store(Utc::now().timestamp(), Ordering::SeqCst);
+    }
+}
+
+struct TaskEntry {
+    supervisor: JoinHandle<()>,
+    started_at: i64,
+    last_beat: Arc<AtomicI64>,
+    restarts: Arc<AtomicU32>,
+    last_panic: Arc<Mutex<Option<String>>>,
+}
+
+impl TaskEntry {
+    fn is_running(&self) -> bool {
+        !self.supervisor.is_finished()
+    }
+}
+
+/// Snapshot of a supervised task for `/tasks`.
+#[derive(Debug, Clone)]
+pub struct TaskStatus {
+    pub name: &'static str,
+    pub running: bool,
+    pub started_at: i64,
+    /// Last [`Beat::tick`], if the task has ticked at all.
+    pub last_beat: Option<i64>,
+    pub restarts: u32,
+    pub last_panic: Option<String>,
+}
+
+/// Aborts the wrapped task when dropped, so cancelling a supervisor also stops its task.
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Named, supervised background tasks.
+#[derive(Clone, Default)]
+pub struct Tasks {
+    entries: Arc<Mutex<BTreeMap<&'static str, TaskEntry>>>,
+}
+
+impl Tasks {
+    /// Returns `true` if a task named `name` is running.
+    pub fn is_running(&self, name: &str) -> bool {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(name)
+            .is_some_and(TaskEntry::is_running)
+    }
+
+    /// Spawns the task built by `factory` under `name`, unless one with that name is still
+    /// running. If the task panics, `factory` is called again after a backoff.
+    ///
+    /// Returns `false` if the running task was reused.
+    pub fn spawn<F, Fut>(&self, name: &'static str, factory: F) -> bool
+    where
+        F: Fn(Beat) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.get(name).is_some_and(TaskEntry::is_running) {
+            return false;
+        }
+
+        let last_beat = Arc::new(AtomicI64::new(0));
+        let restarts = Arc::new(AtomicU32::new(0));
+        let last_panic = Arc::new(Mutex::new(None));
+
+        let beat = Beat(last_beat.clone());
+        let supervisor_restarts = restarts.clone();
+        let supervisor_panic = last_panic.clone();
+        let supervisor = tokio::spawn(async move {
+            let mut backoff = INITIAL_BACKOFF;
+            loop {
+                let started = Instant::now();
+                let mut run = AbortOnDrop(tokio::spawn(factory(beat.clone())));
+
+                let panic = match (&mut run.0).await {
+                    Ok(()) => return,
+                    Err(e) if e.is_panic() => panic_message(e.into_panic()),
+                    Err(_) => return,
+                };
+
+                // A task that ran for a while before panicking starts over with a short backoff.
+                if started.elapsed() > MAX_BACKOFF {
+                    backoff = INITIAL_BACKOFF;
+                }
+                eprintln!(
+                    "Background task `{}` panicked: {}. Restarting in {}s.",
+                    name,
+                    panic,
+                    backoff.as_secs()
+                );
+                *supervisor_panic.lock().unwrap() = Some(panic);
+                supervisor_restarts.fetch_add(1, Ordering::SeqCst);
+
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        });
+
+        entries.insert(
+            name,
+            TaskEntry {
+                supervisor,
+                started_at: Utc::now().timestamp(),
+                last_beat,
+                restarts,
+                last_panic,
+            },
+        );
+        true
+    }
+
+    /// Status of every task that has been spawned, by name.
+    pub fn statuses(&self) -> Vec<TaskStatus> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, entry)| TaskStatus {
+                name,
+                running: entry.is_running(),
+                started_at: entry.started_at,
+                last_beat: Some(entry.last_beat.load(Ordering::SeqCst)).filter(|t| *t > 0),
+                restarts: entry.restarts.load(Ordering::SeqCst),
+                last_panic: entry.last_panic.lock().unwrap().clone(),
+            })
+            .collect()
+    }
+
+    /// Cancels every running task.
+    pub fn abort_all(&self) {
+        for (name, entry) in std::mem::take(&mut *self.entries.lock().unwrap()) {
+            if entry.is_running() {
+                println!("Stopping background task `{}`...", name);
+                entry.supervisor.abort();
+            }
+        }
+    }
+}
+
+/// Extracts the message from a panic payload.
+pub fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
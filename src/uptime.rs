@@ -0,0 +1,173 @@
+//! "Site down" / "site up" notifications from external uptime monitors.
+//!
+//! Point an UptimeRobot webhook alert contact (sent as JSON) or a healthchecks.io webhook
+//! integration at `POST /webhook/uptime?token=<UPTIME_WEBHOOK_TOKEN>`. Both let you choose the
+//! body, so the monitor's own variables are accepted under their usual names:
+//!
+//! - UptimeRobot: `{"monitor": "*monitorFriendlyName*", "status": "*alertTypeFriendlyName*",
+//!   "url": "*monitorURL*", "details": "*alertDetails*", "duration": "*alertDuration*"}`
+//! - healthchecks.io: `{"name": "$NAME", "status": "$STATUS"}`
+//!
+//! The time a monitor went down is remembered, so the recovery message states how long it was
+//! down even when the monitor doesn't send a duration.
+//!
+//! Environment Variables:
+//! - `UPTIME_WEBHOOK_TOKEN`: Token required in the `token` query parameter
+//! - `UPTIME_CHANNEL_ID`: Channel for down/up messages (default: `DISCORD_STATUS_CHANNEL_ID`)
+
+use axum::{
+    extract::{Json, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{collections::BTreeMap, env, sync::Mutex, time::Duration};
+
+use crate::duration::format_duration;
+use crate::github::WebhookOutcome;
+use crate::impact;
+use crate::notify::{self, Destination};
+use crate::ops_events::{self, EventKind};
+use crate::secrets;
+use crate::store;
+use crate::AppState;
+
+const HANDLER: &str = "uptime";
+const DOWN_FILE: &str = "uptime_down.json";
+
+/// When each monitor that is currently down went down (unix timestamp), by monitor name.
+static DOWN_SINCE: Lazy<Mutex<BTreeMap<String, i64>>> = Lazy::new(|| Mutex::new(store::load(DOWN_FILE)));
+
+pub fn routes(shared_state: AppState) -> Router {
+    Router::new()
+        .route("/uptime", post(handle_uptime_webhook))
+        .with_state(shared_state)
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+/// A monitor's transition, normalized across monitors.
+#[derive(Debug)]
+struct Transition {
+    monitor: String,
+    up: bool,
+    url: Option<String>,
+    details: Option<String>,
+    /// How long the monitor was down, if it reported it.
+    duration: Option<Duration>,
+}
+
+/// The first of `keys` present in `body`, as a string.
+fn field(body: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|key| match body.get(*key)? {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    })
+}
+
+impl Transition {
+    fn parse(body: &Value) -> Result<Self, String> {
+        let monitor = field(body, &["monitor", "name", "monitorFriendlyName", "NAME"])
+            .ok_or("missing monitor name")?;
+        let status = field(body, &["status", "alertTypeFriendlyName", "alertType", "STATUS"])
+            .ok_or("missing status")?
+            .to_lowercase();
+        // UptimeRobot's numeric alert types: 1 is down, 2 is up.
+        let up = match status.as_str() {
+            "up" | "2" | "ok" => true,
+            "down" | "1" => false,
+            other => return Err(format!("unknown status `{}`", other)),
+        };
+
+        Ok(Self {
+            monitor,
+            up,
+            url: field(body, &["url", "monitorURL"]),
+            details: field(body, &["details", "alertDetails"]),
+            duration: field(body, &["duration", "alertDuration"])
+                .and_then(|d| d.parse().ok())
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+        })
+    }
+}
+
+async fn handle_uptime_webhook(
+    State(state): State<AppState>,
+    Query(query): Query<TokenQuery>,
+    Json(body): Json<Value>,
+) -> Response {
+    let token = match secrets::current("UPTIME_WEBHOOK_TOKEN") {
+        Some(token) => token,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    if !query.token.as_deref().is_some_and(|t| secrets::accepts("UPTIME_WEBHOOK_TOKEN", &token, t)) {
+        eprintln!("Rejected uptime webhook: missing or invalid token");
+        return WebhookOutcome::unauthorized("missing or invalid token").into_response();
+    }
+
+    match Transition::parse(&body) {
+        Ok(transition) => handle_transition(&state, transition).await.into_response(),
+        Err(e) => WebhookOutcome::bad_request(HANDLER, e).into_response(),
+    }
+}
+
+async fn handle_transition(state: &AppState, t: Transition) -> WebhookOutcome {
+    let channel_id = match ["UPTIME_CHANNEL_ID", "DISCORD_STATUS_CHANNEL_ID"]
+        .iter()
+        .find_map(|key| env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok()))
+    {
+        Some(id) => id,
+        None => return WebhookOutcome::ignored(HANDLER, "no uptime or status channel configured"),
+    };
+
+    let now = Utc::now().timestamp();
+    let mut message = {
+        let mut down = DOWN_SINCE.lock().unwrap();
+        let message = if t.up {
+            let since = down.remove(&t.monitor);
+            // Monitors repeat "up" on every check for some integrations; only report recoveries.
+            if since.is_none() && t.duration.is_none() {
+                return WebhookOutcome::ignored(HANDLER, format!("`{}` was not down", t.monitor));
+            }
+            let downtime = t
+                .duration
+                .or_else(|| since.map(|s| Duration::from_secs((now - s).max(0) as u64)));
+            let mut message = format!("🟢 **{}** is back up", t.monitor);
+            if let Some(downtime) = downtime {
+                message.push_str(&format!(" after {} of downtime", format_duration(downtime)));
+            }
+            message
+        } else {
+            if down.contains_key(&t.monitor) {
+                return WebhookOutcome::ignored(HANDLER, format!("`{}` is already down", t.monitor));
+            }
+            down.insert(t.monitor.clone(), now);
+            ops_events::record(EventKind::Incident, format!("{} down", t.monitor));
+            format!("🔴 **{}** is down", t.monitor)
+        };
+        store::save(DOWN_FILE, &*down);
+        message
+    };
+    if let Some(details) = &t.details {
+        message.push_str(&format!("\n{}", details));
+    }
+    if let Some(url) = &t.url {
+        message.push_str(&format!("\n<{}>", url));
+    }
+    if !t.up {
+        message = impact::append(message).await;
+    }
+
+    let delivery = notify::send(state, HANDLER, Destination::Channel(channel_id), message).await;
+    WebhookOutcome::from_delivery(HANDLER, delivery)
+}